@@ -0,0 +1,20 @@
+-- Your SQL goes here
+
+-- Plain git submission source, submitted directly through the API
+CREATE TABLE "submission_source_git" (
+    "id" BIGSERIAL NOT NULL PRIMARY KEY,
+
+	"url" TEXT NOT NULL, -- URL used to clone the repo (SSH or HTTPS)
+
+	UNIQUE ("url")
+);
+
+-- Additional information about a specific submission from a plain git source
+CREATE TABLE "submission_info_git" (
+    "id" BIGSERIAL NOT NULL PRIMARY KEY,
+    "submission_id" BIGINT REFERENCES submissions(id) UNIQUE NOT NULL,
+    "git_source_id" BIGINT REFERENCES submission_source_git(id) NOT NULL,
+
+    "user" TEXT NOT NULL,
+	"commit" TEXT NOT NULL
+);
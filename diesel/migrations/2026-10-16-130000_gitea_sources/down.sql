@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS "submission_info_gitea";
+DROP TABLE IF EXISTS "submission_source_gitea";
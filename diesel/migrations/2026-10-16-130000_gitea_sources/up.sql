@@ -0,0 +1,24 @@
+-- Your SQL goes here
+
+-- Gitea (or Forgejo) submission source
+CREATE TABLE "submission_source_gitea" (
+    "id" BIGSERIAL NOT NULL PRIMARY KEY,
+
+    "domain" TEXT NOT NULL, -- domain, e.g. gitea.foo.bar.com
+	"owner" TEXT NOT NULL,  -- the user or organization owning the repository
+	"repo" TEXT NOT NULL,   -- repository name, excluding the owner
+
+	"ssh_url" TEXT NOT NULL, -- URL used to clone repo over SSH
+
+	UNIQUE ("domain", "owner", "repo")
+);
+
+-- Additional information about a specific submission from a Gitea source
+CREATE TABLE "submission_info_gitea" (
+    "id" BIGSERIAL NOT NULL PRIMARY KEY,
+    "submission_id" BIGINT REFERENCES submissions(id) UNIQUE NOT NULL,
+    "gitea_source_id" BIGINT REFERENCES submission_source_gitea(id) NOT NULL,
+
+    "user" TEXT NOT NULL,
+	"commit" TEXT NOT NULL
+);
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE "submissions" DROP COLUMN IF EXISTS "priority";
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+-- Queued submissions with a higher priority are graded first
+ALTER TABLE "submissions" ADD COLUMN "priority" INTEGER NOT NULL DEFAULT 0;
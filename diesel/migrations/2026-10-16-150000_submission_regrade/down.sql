@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE "submissions" DROP COLUMN IF EXISTS "silent";
+ALTER TABLE "submissions" DROP COLUMN IF EXISTS "regrade_of";
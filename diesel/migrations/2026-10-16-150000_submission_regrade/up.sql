@@ -0,0 +1,6 @@
+-- Your SQL goes here
+
+-- Re-grades refer to the original submission that they re-grade. Silent
+-- submissions do not post any comments or statuses on the commit.
+ALTER TABLE "submissions" ADD COLUMN "regrade_of" BIGINT NULL REFERENCES "submissions"("id");
+ALTER TABLE "submissions" ADD COLUMN "silent" BOOLEAN NOT NULL DEFAULT false;
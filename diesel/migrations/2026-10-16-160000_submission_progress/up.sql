@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+-- Progress of a submission while it is being graded
+ALTER TABLE "submissions" ADD COLUMN "exec_progress" JSON NULL;
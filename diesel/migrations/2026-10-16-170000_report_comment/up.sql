@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+-- Reference to the commit comment that is edited with the results of a
+-- submission as its tags finish, for incremental reporting
+ALTER TABLE "submissions" ADD COLUMN "report_comment" TEXT NULL;
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+
+-- Grades that have been posted to Canvas, such that only changed grades are
+-- posted on the next synchronization
+CREATE TABLE "canvas_grades" (
+    "id" BIGSERIAL NOT NULL PRIMARY KEY,
+
+    "canvas_user" TEXT NOT NULL,
+    "assignment_id" BIGINT NOT NULL,
+    "grade" TEXT NOT NULL,
+    "date_synced" TIMESTAMP NOT NULL,
+
+    UNIQUE ("canvas_user", "assignment_id")
+);
@@ -0,0 +1,8 @@
+#!/bin/sh
+# Accepts any greeting of a capitalized name on standard output.
+# Usage: check-greeting.sh STDOUT_FILE
+if grep -qxE 'Hello, [A-Z][a-z]+!' "$1"; then
+    exit 0
+fi
+echo "Expected a greeting such as \"Hello, World!\" on standard output."
+exit 1
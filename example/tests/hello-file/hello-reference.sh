@@ -0,0 +1,3 @@
+#!/bin/sh
+# Reference solution for the "hello-file" executable.
+echo "Hello, World!"
@@ -29,7 +29,8 @@ PAYLOAD=$(cat <<EOF
   },
   "head_commit": {
     "id": "$COMMIT_HASH",
-    "message": "$MESSAGE"
+    "message": "$MESSAGE",
+    "url": "https://$DOMAIN/$ORG/$REPO/commit/$COMMIT_HASH"
   }
 }
 EOF
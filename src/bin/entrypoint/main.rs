@@ -13,7 +13,7 @@ use id2202_autograder::{
     db::conn::DatabaseConnection,
     error::Error,
     podman,
-    utils::systemtime_to_utc_string,
+    utils::{parse_utc_datetime, path_absolute_parent, systemtime_to_utc_string},
 };
 
 #[derive(Parser, Debug)]
@@ -61,6 +61,102 @@ enum Commands {
         #[arg(long = "ps", default_value_t = false)]
         list_containers: bool,
     },
+    GradeLocal {
+        /// Grading tag to run (can be specified multiple times)
+        #[arg(short, long = "tag", required = true)]
+        tags: Vec<String>,
+
+        /// Directory containing the solution to grade
+        #[arg(long)]
+        source: String,
+
+        /// Runner ID to use for the container and network names. Defaults to
+        /// an ID that is not used by any of the started runners.
+        #[arg(long)]
+        runner_id: Option<i32>,
+
+        /// Print the report as JSON instead of markdown
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    Regrade {
+        /// Re-grade only this grading tag (can be specified multiple times).
+        /// All grading tags of the selected submissions are re-graded if
+        /// omitted.
+        #[arg(short, long = "tag")]
+        tags: Vec<String>,
+
+        /// Only re-grade submissions made at or after this date and time
+        /// (RFC 3339 or YYYY-mm-dd in UTC)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only re-grade submissions made before this date and time
+        /// (RFC 3339 or YYYY-mm-dd in UTC)
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Only re-grade submissions from repositories of this owner, i.e.
+        /// GitHub organization, GitLab namespace, or Gitea owner (can be
+        /// specified multiple times)
+        #[arg(long = "owner")]
+        owners: Vec<String>,
+
+        /// Only re-grade submissions with this status code, e.g. 403 for
+        /// failed test cases (can be specified multiple times)
+        #[arg(long = "status")]
+        statuses: Vec<i32>,
+
+        /// Priority of the re-grades. Defaults to submission.priority.regrade
+        #[arg(long)]
+        priority: Option<i32>,
+
+        /// Do not post any comments or statuses on the re-graded commits
+        #[arg(long, default_value_t = false)]
+        silent: bool,
+
+        /// Only list the submissions that would be re-graded
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+    ExportGradebook {
+        /// Include this grading tag (can be specified multiple times). All
+        /// graded tags are included if omitted.
+        #[arg(short, long = "tag")]
+        tags: Vec<String>,
+
+        /// Only include repositories of this owner, i.e. GitHub organization,
+        /// GitLab namespace, or Gitea owner (can be specified multiple times)
+        #[arg(long = "owner")]
+        owners: Vec<String>,
+
+        /// Only count passing submissions made before the deadline of the tag
+        #[arg(long, default_value_t = false)]
+        honor_deadlines: bool,
+
+        /// Export as JSON instead of CSV
+        #[arg(long, default_value_t = false)]
+        json: bool,
+
+        /// File to write the gradebook to. Written to stdout if omitted.
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    CanvasSync {
+        /// Only list the grades that would be posted to Canvas
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+    Similarity {
+        /// Check this grading tag (can be specified multiple times). All tags
+        /// in the test configuration are checked if omitted.
+        #[arg(short, long = "tag")]
+        tags: Vec<String>,
+
+        /// Print the report as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
     TestSyscommand {
         /// Test the cat command with the specific stdin
         #[arg(long = "stdin")]
@@ -91,6 +187,33 @@ fn main() -> Result<(), Error> {
             list_networks,
             list_containers,
         } => test_podman(s, list_images, list_networks, list_containers),
+        Commands::GradeLocal {
+            tags,
+            source,
+            runner_id,
+            json,
+        } => grade_local(s, tags, source, runner_id, json),
+        Commands::Regrade {
+            tags,
+            since,
+            until,
+            owners,
+            statuses,
+            priority,
+            silent,
+            dry_run,
+        } => regrade(
+            s, tags, since, until, owners, statuses, priority, silent, dry_run,
+        ),
+        Commands::ExportGradebook {
+            tags,
+            owners,
+            honor_deadlines,
+            json,
+            output,
+        } => export_gradebook(s, tags, owners, honor_deadlines, json, output),
+        Commands::CanvasSync { dry_run } => canvas_sync(s, dry_run),
+        Commands::Similarity { tags, json } => similarity(s, tags, json),
         Commands::TestSyscommand {
             example_stdin,
             std_lines,
@@ -328,7 +451,7 @@ fn check_database(
         }
     }
     if let Some(runner_id) = assign_runner {
-        match dbconn.try_assign_submission(runner_id) {
+        match dbconn.try_assign_submission(runner_id, s.submission.priority.aging_minutes) {
             Ok(Some(sub)) => {
                 println!("Assigned submission: {sub:#?}");
             }
@@ -345,6 +468,115 @@ fn check_database(
     Ok(())
 }
 
+/// Exports the earliest passing submission of each tag for each repository,
+/// as CSV or JSON.
+fn export_gradebook(
+    s: Settings,
+    tags: Vec<String>,
+    owners: Vec<String>,
+    honor_deadlines: bool,
+    json: bool,
+    output: Option<String>,
+) -> Result<(), Error> {
+    use id2202_autograder::gradebook::{Gradebook, GradebookOptions};
+
+    let mut dbconn = DatabaseConnection::connect(&s)?;
+    let records = dbconn.select_gradebook_records(&owners, &[])?;
+    let gradebook = Gradebook::compile(
+        &records,
+        &GradebookOptions {
+            tags: tags
+                .iter()
+                .map(|t| t.trim_start_matches(['#', '%']).to_string())
+                .collect(),
+            honor_deadlines: honor_deadlines,
+        },
+    );
+    log::info!(
+        "Compiled gradebook of {} repositories from {} submissions",
+        gradebook.repositories.len(),
+        records.len()
+    );
+
+    let mut out = String::new();
+    if json {
+        out = serde_json::to_string_pretty(&gradebook)?;
+        out.push('\n');
+    } else {
+        gradebook
+            .write_csv(&mut out)
+            .map_err(|e| Error::auto_msg("could not write gradebook as CSV", e))?;
+    }
+
+    match output {
+        Some(path) => std::fs::write(path, out)?,
+        None => print!("{out}"),
+    }
+    Ok(())
+}
+
+/// Synchronizes the grades to Canvas once, posting the grades that have
+/// changed since the last synchronization.
+fn canvas_sync(s: Settings, dry_run: bool) -> Result<(), Error> {
+    use id2202_autograder::canvas::{self, HttpCanvasClient};
+
+    let client = HttpCanvasClient::new(&s.canvas)?;
+    let mut dbconn = DatabaseConnection::connect(&s)?;
+    let summary = canvas::sync(&s, &mut dbconn, &client, dry_run)?;
+    println!(
+        "{} grades {}, {} failed, {} unchanged",
+        summary.posted,
+        if dry_run { "to post" } else { "posted" },
+        summary.failed,
+        summary.unchanged
+    );
+    Ok(())
+}
+
+/// Checks the similarity of the graded snapshots, and stores the report such
+/// that it is shown on the similarity page of the server.
+fn similarity(s: Settings, tags: Vec<String>, json: bool) -> Result<(), Error> {
+    use id2202_autograder::similarity::check_similarity;
+
+    let tags: Vec<String> = tags
+        .iter()
+        .map(|t| t.trim_start_matches(['#', '%']).to_string())
+        .collect();
+    let report = check_similarity(&s, &tags)?;
+
+    let report_json = serde_json::to_string_pretty(&report)?;
+    let report_dir = path_absolute_parent(&s.similarity.report_file)?;
+    std::fs::create_dir_all(report_dir)?;
+    std::fs::write(&s.similarity.report_file, &report_json)?;
+    log::info!("Stored similarity report at {}", s.similarity.report_file);
+
+    if json {
+        println!("{report_json}");
+        return Ok(());
+    }
+    for tag in report.tags.iter() {
+        println!(
+            "{} ({}, {} repositories):",
+            tag.tag_name, tag.srcdir, tag.repositories
+        );
+        if tag.pairs.is_empty() {
+            println!("  no similar pairs");
+        }
+        for pair in tag.pairs.iter() {
+            println!(
+                "  {:5.1}% {} ({:.1}%) <-> {} ({:.1}%), {} shared fingerprints",
+                100.0 * pair.similarity(),
+                pair.repo_a,
+                100.0 * pair.similarity_a,
+                pair.repo_b,
+                100.0 * pair.similarity_b,
+                pair.shared
+            );
+        }
+    }
+    Ok(())
+}
+
 /// Test the notification on a specific file
 fn test_podman(
     _s: Settings,
@@ -379,6 +611,136 @@ fn test_podman(
     Ok(())
 }
 
+/// Grades a solution in a local directory, without involving the database.
+/// This builds and runs the tests in podman the same way as a runner would,
+/// and prints the resulting report to standard output.
+fn grade_local(
+    s: Settings,
+    tags: Vec<String>,
+    source: String,
+    runner_id: Option<i32>,
+    json: bool,
+) -> Result<(), Error> {
+    use id2202_autograder::subrunner::SubmissionRunnerHandle;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    let runner_id = match runner_id {
+        Some(id) => id,
+        None => i32::try_from(s.runner.n_runners)
+            .map_err(|_| Error::runtime("n_runners does not fit a runner ID"))?,
+    };
+
+    if !std::fs::exists(&source)? {
+        return Error::err_runtime(format!("source directory \"{source}\" does not exist"));
+    }
+
+    log::debug!("Checking that the podman image exists");
+    if !podman::images()?.contains(&s.runner.podman_image) {
+        log::info!("Pulling the runner image {}", &s.runner.podman_image);
+        podman::pull(&s.runner.podman_image)?;
+    }
+    let expected_net = format!("{}{}", s.runner.podman_network_prefix, runner_id);
+    if !podman::networks()?.contains(&expected_net) {
+        log::info!("Creating podman network {expected_net}");
+        podman::create_network(&expected_net)?;
+    }
+
+    // Interrupt grading between test cases instead of leaving the container
+    // behind if we receive a signal.
+    let interrupted = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(SIGINT, Arc::clone(&interrupted))?;
+    signal_hook::flag::register(SIGTERM, Arc::clone(&interrupted))?;
+
+    let grading_tags = tags
+        .iter()
+        .map(|t| t.trim_start_matches(['#', '%']))
+        .collect::<Vec<&str>>()
+        .join(";");
+
+    log::info!("Grading \"{source}\" locally with tags {grading_tags}");
+    let report = match SubmissionRunnerHandle::new_local(&s, &source, &grading_tags, runner_id) {
+        Ok(mut handle) => {
+            while !handle.is_finished() {
+                if interrupted.load(Ordering::Relaxed) {
+                    log::warn!("Grading interrupted");
+                    handle.set_as_erroneous();
+                    break;
+                }
+                if let Err(e) = handle.run_next() {
+                    log::error!("Error when grading: {e}");
+                    handle.set_as_erroneous();
+                    break;
+                }
+            }
+            let report = handle.compile_report();
+            log::info!("Grading finished with status: {}", handle.get_status_code());
+            report
+        }
+        Err(report) => report,
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{}", report.formatter_markdown(&s.reporting));
+    }
+
+    Ok(())
+}
+
+/// Registers re-grades of the finished submissions that match the filter,
+/// for the same commits, and notifies the runners.
+fn regrade(
+    s: Settings,
+    tags: Vec<String>,
+    since: Option<String>,
+    until: Option<String>,
+    owners: Vec<String>,
+    statuses: Vec<i32>,
+    priority: Option<i32>,
+    silent: bool,
+    dry_run: bool,
+) -> Result<(), Error> {
+    use id2202_autograder::db::conn::RegradeFilter;
+
+    let filter = RegradeFilter::new(
+        &tags,
+        since.as_deref().map(parse_utc_datetime).transpose()?,
+        until.as_deref().map(parse_utc_datetime).transpose()?,
+        owners,
+        &statuses,
+    )?;
+
+    let mut dbconn = DatabaseConnection::connect(&s)?;
+    let selected = dbconn.select_regrade_submissions(&filter)?;
+    log::info!("Selected {} submissions to re-grade", selected.len());
+
+    let priority = priority.unwrap_or(s.submission.priority.regrade);
+    for (info, grading_tags) in selected.iter() {
+        let (url, commit) = info.ssh_url_and_commit();
+        let original_id = info.get_submission().id;
+        if dry_run {
+            println!("Submission {original_id} ({url} {commit}): {grading_tags}");
+        } else {
+            let regrade_id = dbconn.register_regrade(info, grading_tags, priority, silent)?;
+            println!(
+                "Submission {original_id} ({url} {commit}): {grading_tags} re-graded as submission {regrade_id}"
+            );
+        }
+    }
+
+    if !dry_run && !selected.is_empty() {
+        dbconn.notify("submission").unwrap_or_else(|e| {
+            log::warn!("Could not notify the runners about the re-grades: {e}")
+        });
+    }
+
+    Ok(())
+}
+
 /// Test the notification on a specific file
 fn test_syscommand(
     _s: Settings,
@@ -6,29 +6,25 @@ use signal_hook::{
 use std::sync::mpsc;
 use std::{
     io::Write,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime},
 };
 
 use id2202_autograder::{
-    config::Settings,
+    config::{settings::IncrementalReporting, Settings},
     db::{
         conn::DatabaseConnection,
         models::{Submission, SubmissionInfo, SubmissionStatusCode},
-        notify::listen as db_listen,
+        notify::listen_any as db_listen_any,
     },
     error::Error,
     reporting::{Report, ReportMessage, ReportWrapper},
+    subrunner::{progress::SubmissionProgress, tag_runner::TagRunner, SubmissionRunnerHandle},
     utils::{
         create_dir_if_not_exists, path_absolute_join, path_absolute_parent, syscommand_timeout,
         systemtime_to_fsfriendly_utc_string, SyscommandSettings,
     },
 };
 
-mod subrunner;
-use subrunner::SubmissionRunnerHandle;
-
-use crate::subrunner::tag_runner::TagRunner;
-
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
@@ -42,6 +38,7 @@ struct Args {
 }
 
 const MSG_NOTIFY: &'static str = "notify";
+const MSG_CANCEL: &'static str = "cancel";
 const MSG_SIGNAL: &'static str = "signal";
 
 fn main() -> Result<(), Error> {
@@ -140,17 +137,22 @@ fn main() -> Result<(), Error> {
         log::debug!("Listener thread spawned");
         let mut watching = true;
         while watching {
-            match db_listen(&notify_settings, "submission") {
-                Ok(true) => {
+            match db_listen_any(&notify_settings, &["submission", "cancel"]) {
+                Ok(Some(ch)) => {
                     // Received new event
-                    msg_send.send(MSG_NOTIFY).unwrap_or_else({
+                    let msg = if ch == "cancel" {
+                        MSG_CANCEL
+                    } else {
+                        MSG_NOTIFY
+                    };
+                    msg_send.send(msg).unwrap_or_else({
                         |e| {
                             log::error!("Could not send notification message: {e:#}");
                             watching = false;
                         }
                     });
                 }
-                Ok(false) => {} // timed out
+                Ok(None) => {} // timed out
                 Err(e) => {
                     log::error!("Received error while listening on new submissions: {e:#}");
                     watching = false;
@@ -180,6 +182,9 @@ fn main() -> Result<(), Error> {
     // (Use .take() to set this to None)
     let mut active_sub: Option<SubmissionRunnerHandle> = None;
 
+    // The last progress of the active job that was published to the database
+    let mut last_progress: Option<SubmissionProgress> = None;
+
     // Some important notes on this "main loop":
     //
     // It is possible that the subrunner may throw an error, and that must be
@@ -197,6 +202,7 @@ fn main() -> Result<(), Error> {
             // check in this branch since we do not want it to block if we
             // are running a job.
 
+            let finished_tags = run_handle.finished_tags();
             if let Err(e) = run_handle.run_next() {
                 // Runtime error when running submission.
                 // This is not the same as when failing a test case or when
@@ -205,16 +211,100 @@ fn main() -> Result<(), Error> {
                 run_handle.set_as_erroneous();
             }
 
+            let tag_finished =
+                !run_handle.is_finished() && run_handle.finished_tags() > finished_tags;
+            let progress = run_handle.progress();
+            let progress_changed = last_progress.as_ref() != Some(&progress);
+
+            // Cancellations arrive on the `cancel` channel below. As a
+            // fallback for a missed notification, the status is checked when
+            // a tag has finished, before its results are reported. Errors
+            // here are not fatal, as the job can still be graded without
+            // reporting on its progress.
+            if tag_finished || progress_changed {
+                match DatabaseConnection::connect(&settings) {
+                    Ok(mut conn) => {
+                        if tag_finished
+                            && conn
+                                .is_cancelled(run_handle.submission_id)
+                                .unwrap_or_else(|e| {
+                                    log::warn!(
+                                        "Could not check whether submission {} was cancelled: {}",
+                                        run_handle.submission_id,
+                                        e
+                                    );
+                                    false
+                                })
+                        {
+                            log::info!(
+                                "Submission {} was cancelled, stopping the running job.",
+                                run_handle.submission_id
+                            );
+                            run_handle.cleanup();
+                            active_sub.take();
+                            continue;
+                        }
+
+                        // Report the tags that finished in this step right
+                        // away. The last tag is reported together with the
+                        // final report below.
+                        if tag_finished {
+                            report_finished_tag(&settings, &mut conn, run_handle, finished_tags)
+                                .unwrap_or_else(|e| {
+                                    log::warn!(
+                                        "Could not report partial results of submission {}: {}",
+                                        run_handle.submission_id,
+                                        e
+                                    )
+                                });
+                        }
+
+                        // Publish the progress for the live view of the
+                        // submission, but only when a step has changed it.
+                        if progress_changed {
+                            conn.set_exec_progress(run_handle.submission_id, &progress)
+                                .unwrap_or_else(|e| {
+                                    log::warn!(
+                                        "Could not publish progress of submission {}: {}",
+                                        run_handle.submission_id,
+                                        e
+                                    )
+                                });
+                        }
+                    }
+                    Err(e) => log::warn!(
+                        "Could not report on the progress of submission {}: {}",
+                        run_handle.submission_id,
+                        e
+                    ),
+                }
+                last_progress = Some(progress);
+            }
+
             if run_handle.is_finished() {
                 let mut conn = DatabaseConnection::connect(&settings)?;
 
+                // The submission may have been cancelled after the last
+                // notification was handled, then there is nothing to report.
+                if conn.is_cancelled(run_handle.submission_id)? {
+                    log::info!(
+                        "Submission {} was cancelled, discarding the results.",
+                        run_handle.submission_id
+                    );
+                    run_handle.cleanup();
+                    active_sub.take();
+                    continue;
+                }
+
                 let subinfo = conn.get_submission_info(run_handle.submission_id)?;
+                let submitted = original_date_submitted(&mut conn, subinfo.get_submission())?;
 
                 // 1. First record the graded files and tag
                 //    results on the shadow repository.
                 let (report, status) = match record_to_shadow(
                     &settings,
                     &subinfo,
+                    submitted,
                     &run_handle.workspace,
                     &run_handle.source_dir,
                     &run_handle.get_tag_runners(),
@@ -268,6 +358,18 @@ fn main() -> Result<(), Error> {
                         active = false;
                     } else if cause == MSG_NOTIFY {
                         log::debug!("Received notification, but ignoring this since we are already running a job.");
+                    } else if cause == MSG_CANCEL {
+                        if let Some(run_handle) = active_sub.as_mut() {
+                            let mut conn = DatabaseConnection::connect(&settings)?;
+                            if conn.is_cancelled(run_handle.submission_id)? {
+                                log::info!(
+                                    "Submission {} was cancelled, stopping the running job.",
+                                    run_handle.submission_id
+                                );
+                                run_handle.cleanup();
+                                active_sub.take();
+                            }
+                        }
                     } else {
                         log::error!("Received invalid notification cause \"{cause}\". Cancelling current job");
                         if let Some(run_handle) = active_sub.as_mut() {
@@ -294,14 +396,23 @@ fn main() -> Result<(), Error> {
 
             let mut conn = DatabaseConnection::connect(&settings)?;
 
-            match conn.try_assign_submission(args.runner_id)? {
+            match conn
+                .try_assign_submission(args.runner_id, settings.submission.priority.aging_minutes)?
+            {
                 Some(sub) => {
                     log::info!("Assigned submission: {:#?}", sub);
 
                     let subinfo = conn.get_submission_info(sub.id)?;
-
-                    match SubmissionRunnerHandle::new(&settings, &subinfo, args.runner_id) {
+                    let submitted = original_date_submitted(&mut conn, &sub)?;
+
+                    match SubmissionRunnerHandle::new(
+                        &settings,
+                        &subinfo,
+                        submitted,
+                        args.runner_id,
+                    ) {
                         Ok(handle) => {
+                            last_progress = None;
                             active_sub = Some(handle);
                             conn.report_and_status(
                                     &settings,
@@ -357,6 +468,8 @@ fn main() -> Result<(), Error> {
                         active = false;
                     } else if cause == MSG_NOTIFY {
                         log::debug!("Received notification, TODO: check the database for new jobs");
+                    } else if cause == MSG_CANCEL {
+                        log::debug!("Received cancellation, but no job is running.");
                     } else {
                         log::error!("Received invalid notification cause \"{cause}\"");
                         active = false;
@@ -393,11 +506,51 @@ fn main() -> Result<(), Error> {
     Ok(())
 }
 
+/// Reports the results of the tag with index `tag_index`, which has just
+/// finished grading, as configured by `reporting.incremental`.
+fn report_finished_tag(
+    settings: &Settings,
+    conn: &mut DatabaseConnection,
+    run_handle: &SubmissionRunnerHandle,
+    tag_index: usize,
+) -> Result<(), Error> {
+    let report = match settings.reporting.incremental {
+        IncrementalReporting::Off => return Ok(()),
+        IncrementalReporting::EditComment => run_handle.partial_report(),
+        IncrementalReporting::PerTag => match run_handle.tag_report(tag_index) {
+            Some(report) => report,
+            None => return Ok(()),
+        },
+    };
+
+    let subinfo = conn.get_submission_info(run_handle.submission_id)?;
+    conn.report_partial(settings, &subinfo, &report)
+}
+
+/// Returns the time at which the graded commit was submitted. This is the time
+/// of the original submission for a re-grade.
+fn original_date_submitted(
+    conn: &mut DatabaseConnection,
+    sub: &Submission,
+) -> Result<SystemTime, Error> {
+    match sub.regrade_of {
+        Some(original_id) => conn
+            .get_submission(original_id)
+            .map(|original| original.date_submitted),
+        None => Ok(sub.date_submitted),
+    }
+}
+
 /// Commits the repository files in `repo_dir` and creates new ones from the
 /// `files` list to the shadow repository for this submission.
+///
+/// The results are stored in a directory named after `submitted`. The results
+/// of a re-grade are stored in a subdirectory next to the original results,
+/// and do not update the snapshot of the solution.
 fn record_to_shadow(
     settings: &Settings,
     subinfo: &SubmissionInfo,
+    submitted: SystemTime,
     workspace_dir: &str,
     source_dir: &str,
     tag_runners: &[TagRunner],
@@ -428,6 +581,27 @@ fn record_to_shadow(
                 gl_src.domain, gl_src.namespace, gl_src.repo
             ),
         )?,
+        SubmissionInfo::Git {
+            sub: _,
+            src: _,
+            git_src,
+            git_info: _,
+        } => path_absolute_join(
+            &settings.runner.shadow_dir,
+            format!("git/{}.git", git_src.repo_path()),
+        )?,
+        SubmissionInfo::Gitea {
+            sub: _,
+            src: _,
+            gt_src,
+            gt_info: _,
+        } => path_absolute_join(
+            &settings.runner.shadow_dir,
+            format!(
+                "gitea/{}/{}/{}.git",
+                gt_src.domain, gt_src.owner, gt_src.repo
+            ),
+        )?,
     };
     if !std::fs::exists(&shadow_repo)? {
         log::info!("The shadow repository does not exist. Creating new shadow repository at path {shadow_repo}");
@@ -450,11 +624,15 @@ fn record_to_shadow(
     // Set up necessary paths
     let shadow_dir = path_absolute_join(workspace_dir, "shadow")?;
 
-    let date_dir = path_absolute_join(
+    let sub = subinfo.get_submission();
+    let mut date_dir = path_absolute_join(
         &shadow_dir,
-        systemtime_to_fsfriendly_utc_string(&subinfo.get_submission().date_submitted)
+        systemtime_to_fsfriendly_utc_string(&submitted)
             .ok_or_else(|| Error::convert("could not create date for date dir"))?,
     )?;
+    if sub.regrade_of.is_some() {
+        date_dir = path_absolute_join(&date_dir, format!("regrade-{}", sub.id))?;
+    }
     let snapshot_dir = path_absolute_join(&shadow_dir, "snapshot")?;
 
     log::debug!("Cloning shadow directory {shadow_repo} to {shadow_dir}");
@@ -503,7 +681,7 @@ fn record_to_shadow(
 
     // 3. Add the new files and push
 
-    std::fs::create_dir(&date_dir)?;
+    std::fs::create_dir_all(&date_dir)?;
 
     for tr in tag_runners {
         // Create the report
@@ -515,8 +693,9 @@ fn record_to_shadow(
         // Create the snapshot for this solution only if it attempted to
         // actually build the project. If this is not the case, then there is
         // something wrong with the tag source directory and these files may
-        // contain bad files that should not be stored.
-        if tr.attempted_build() {
+        // contain bad files that should not be stored. A re-grade is of an
+        // older commit, so it must not replace the latest snapshot.
+        if tr.attempted_build() && sub.regrade_of.is_none() {
             let graded_src_dir = path_absolute_join(&source_dir, &tr.build_conf.srcdir)?;
             let target_src_dir = path_absolute_join(&snapshot_dir, &tr.build_conf.srcdir)?;
             let target_src_parent = path_absolute_parent(&target_src_dir)?;
@@ -547,7 +726,13 @@ fn record_to_shadow(
     )
     .inspect_err(|e| log::error!("Could not add files to shadow repo {shadow_repo}: {e}"))?;
 
-    let commit_msg = format!("Results for submission {}", subinfo.get_submission().id);
+    let commit_msg = match sub.regrade_of {
+        Some(original_id) => format!(
+            "Results for submission {} (re-grade of submission {})",
+            sub.id, original_id
+        ),
+        None => format!("Results for submission {}", sub.id),
+    };
     syscommand_timeout(
         &[
             "git",
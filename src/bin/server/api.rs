@@ -6,8 +6,14 @@ use actix_web::{
 use id2202_autograder::config::Settings;
 
 mod common;
+mod gradebook;
+mod history;
+pub mod progress;
+mod regrade;
 mod response;
 mod submission;
+mod submit_git;
+mod submit_gitea;
 mod submit_github;
 mod submit_gitlab;
 
@@ -27,11 +33,46 @@ pub fn config(cfg: &mut ServiceConfig, _settings: &Settings, prefix: &str) {
         web::post().to(submit_gitlab::gitlab_submit_webhook),
     );
 
+    cfg.route(
+        &format!("{prefix}/submit/gitea"),
+        web::post().to(submit_gitea::gitea_submission),
+    );
+
+    cfg.route(
+        &format!("{prefix}/submit/git"),
+        web::post().to(submit_git::git_submission),
+    );
+
+    cfg.route(
+        &format!("{prefix}/regrade"),
+        web::post().to(regrade::regrade_submissions),
+    );
+
+    cfg.route(
+        &format!("{prefix}/submissions"),
+        web::get().to(history::get_submission_history),
+    );
+
+    cfg.route(
+        &format!("{prefix}/gradebook"),
+        web::get().to(gradebook::get_gradebook),
+    );
+
     cfg.route(
         &format!("{prefix}/submission/{}", "{id}"),
         web::get().to(submission::get_submission),
     );
 
+    cfg.route(
+        &format!("{prefix}/submission/{}/cancel", "{id}"),
+        web::post().to(submission::cancel_submission),
+    );
+
+    cfg.route(
+        &format!("{prefix}/submission/{}/events", "{id}"),
+        web::get().to(submission::submission_events),
+    );
+
     // JSON schemas for responses
     cfg.route(
         &format!("{prefix}/schema/error"),
@@ -47,6 +88,26 @@ pub fn config(cfg: &mut ServiceConfig, _settings: &Settings, prefix: &str) {
         &format!("{prefix}/schema/submission"),
         web::get().to(response::schema_callback!(response::SubmissionResponse)),
     );
+
+    cfg.route(
+        &format!("{prefix}/schema/regrade"),
+        web::get().to(response::schema_callback!(response::RegradeResponse)),
+    );
+
+    cfg.route(
+        &format!("{prefix}/schema/progress"),
+        web::get().to(response::schema_callback!(response::ProgressEvent)),
+    );
+
+    cfg.route(
+        &format!("{prefix}/schema/history"),
+        web::get().to(response::schema_callback!(response::HistoryResponse)),
+    );
+
+    cfg.route(
+        &format!("{prefix}/schema/gradebook"),
+        web::get().to(response::schema_callback!(response::GradebookResponse)),
+    );
 }
 
 /// "404: Not found" response for API requests.
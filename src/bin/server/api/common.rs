@@ -1,9 +1,10 @@
-use std::{collections::BTreeSet, fmt::Display};
+use std::{collections::BTreeSet, fmt::Display, time::SystemTime};
 
 use id2202_autograder::{
     config::tag_match,
     config::Settings,
     reporting::{Report, ReportMessage, ReportWrapper},
+    utils::systemtime_to_utc_string,
 };
 use itertools::Itertools;
 
@@ -60,6 +61,77 @@ pub fn extract_grading_tags<'a>(
     }
 }
 
+/// Splits a comma separated list, ignoring empty entries.
+pub fn split_list(list: &Option<String>) -> Vec<String> {
+    list.as_deref()
+        .unwrap_or("")
+        .split(",")
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Returns the priority of a submission from the repository `repo` owned by
+/// `owner`.
+pub fn submission_priority(settings: &Settings, owner: &str, repo: &str) -> i32 {
+    let priority = &settings.submission.priority;
+    if priority.staff_owners.iter().any(|o| o == owner)
+        || priority
+            .staff_repos
+            .iter()
+            .any(|r| *r == format!("{owner}/{repo}"))
+    {
+        priority.staff
+    } else {
+        0
+    }
+}
+
+/// Creates the report to send back when a submission of `grading_tags` is
+/// rejected by a rate limit, which lifts at `next_allowed`.
+pub fn rate_limit_report(grading_tags: &[&str], next_allowed: &SystemTime) -> Report {
+    Report::Wrapper(ReportWrapper {
+        title: Some("Submission Limit Reached".to_string()),
+        reports: vec![Report::Message(ReportMessage {
+            msg: format!(
+                "Too many submissions have been made from this repository recently, so the submission of {} will not be graded. The next submission can be made at {}.",
+                grading_tags.iter().format_with(", ", |t, f| f(&format_args!("`{t}`"))),
+                systemtime_to_utc_string(next_allowed).unwrap_or("a later time".to_string()),
+            ),
+        })],
+    })
+}
+
+/// Creates the commit comment for a queued submission with ID
+/// `superseded_id` that was superseded by the submission with ID `newer_id`
+/// of the commit `newer_commit`, which is shown at `newer_commit_url`.
+///
+/// The newer submission is only linked if `server.public_url` is set, as the
+/// server cannot tell which address it is reachable at otherwise.
+pub fn superseded_message(
+    settings: &Settings,
+    superseded_id: i64,
+    newer_id: i64,
+    grading_tags: &[&str],
+    newer_commit: &str,
+    newer_commit_url: &str,
+) -> String {
+    let newer_submission = match &settings.server.public_url {
+        Some(public_url) => format!(
+            "[Submission ID: {newer_id}]({}/submission/{newer_id})",
+            public_url.trim_end_matches('/')
+        ),
+        None => format!("Submission ID: {newer_id}"),
+    };
+    format!(
+        "**[Submission ID: {} | {}]**\n\n{}",
+        superseded_id,
+        grading_tags.iter().format_with(", ", |t, f| f(&format_args!("`{t}`"))),
+        format_args!("This submission was superseded by the newer push [{newer_commit}]({newer_commit_url}) ({newer_submission}) before it was graded, so only the newer push will be graded."),
+    )
+}
+
 pub enum RejectionReason<'a> {
     InvalidGroup { group: &'a str },
     InvalidRepoPrefix { repo: &'a str },
@@ -148,3 +220,17 @@ pub fn validate_repo_prefix_suffix<'a>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asserting::prelude::*;
+
+    #[test]
+    fn test_split_list() {
+        assert_that!(split_list(&None)).is_equal_to(Vec::<String>::new());
+        assert_that!(split_list(&Some("".to_string()))).is_equal_to(Vec::<String>::new());
+        assert_that!(split_list(&Some("hello, hello-all,,".to_string())))
+            .is_equal_to(vec!["hello".to_string(), "hello-all".to_string()]);
+    }
+}
@@ -0,0 +1,111 @@
+use actix_web::{
+    web::{self},
+    HttpMessage, HttpRequest, HttpResponse,
+};
+use serde::Deserialize;
+
+use id2202_autograder::{
+    config::Settings,
+    db::conn::DatabaseConnection,
+    gradebook::{Gradebook, GradebookOptions},
+};
+
+use crate::{
+    api::{
+        common::split_list,
+        response::{ErrorResponse, GradebookResponse},
+    },
+    auth::AuthorizationInfo,
+};
+
+/// Query parameters of the gradebook export. The lists are comma separated.
+#[derive(Debug, Deserialize)]
+pub struct GradebookQuery {
+    /// Grading tags to include, with or without the leading `#` or `%`
+    /// symbol. All graded tags are included if omitted.
+    #[serde(default)]
+    tags: Option<String>,
+
+    /// Only include repositories of these GitHub organizations, GitLab
+    /// namespaces, or Gitea owners
+    #[serde(default)]
+    owners: Option<String>,
+
+    /// Only count passing submissions made before the deadline of the tag
+    #[serde(default)]
+    honor_deadlines: bool,
+
+    /// Either `json` (default) or `csv`
+    #[serde(default)]
+    format: Option<String>,
+}
+
+/// Exporting the earliest passing submission of each tag for each repository
+///
+/// Required headers:
+///  - Authorization
+pub async fn get_gradebook(
+    data: web::Data<Settings>,
+    req: HttpRequest,
+    query: web::Query<GradebookQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let auth_info = req
+        .extensions()
+        .get::<AuthorizationInfo>()
+        .ok_or_else(|| ErrorResponse::unauthorized(&req, "missing Authorization header"))?
+        .clone();
+    if !auth_info.api_auth_ok {
+        // API authentication failed
+        return Err(ErrorResponse::unauthorized(&req, "API authentication failed").into());
+    }
+
+    // Request is Authorized
+    let query = query.into_inner();
+    let csv = match query.format.as_deref() {
+        None | Some("json") => false,
+        Some("csv") => true,
+        Some(format) => {
+            return Err(
+                ErrorResponse::bad_request(&req, &format!("unknown format {format}")).into(),
+            )
+        }
+    };
+
+    let options = GradebookOptions {
+        tags: split_list(&query.tags)
+            .iter()
+            .map(|t| t.trim_start_matches(['#', '%']).to_string())
+            .collect(),
+        honor_deadlines: query.honor_deadlines,
+    };
+    let owners = split_list(&query.owners);
+
+    // Compiling the gradebook goes through every finished submission
+    let gradebook = web::block(move || {
+        let mut conn = DatabaseConnection::connect(data.get_ref())?;
+        let records = conn.select_gradebook_records(&owners, &[])?;
+        Ok::<_, id2202_autograder::error::Error>(Gradebook::compile(&records, &options))
+    })
+    .await
+    .map_err(|e| {
+        log::error!("Could not compile gradebook: {e}");
+        ErrorResponse::internal_server_error(&req)
+    })?
+    .map_err(|e| {
+        log::error!("Could not compile gradebook: {e}");
+        ErrorResponse::internal_server_error(&req)
+    })?;
+
+    if csv {
+        let mut body = String::new();
+        gradebook.write_csv(&mut body).map_err(|e| {
+            log::error!("Could not write gradebook as CSV: {e}");
+            ErrorResponse::internal_server_error(&req)
+        })?;
+        Ok(HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .body(body))
+    } else {
+        Ok(GradebookResponse::new(&req, gradebook).to_http())
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+
+use actix_web::{
+    web::{self},
+    HttpMessage, HttpRequest, Responder,
+};
+use serde::Deserialize;
+
+use id2202_autograder::{
+    config::Settings,
+    db::conn::{DatabaseConnection, HistoryFilter},
+    reporting::Report,
+    utils::parse_utc_datetime,
+};
+
+use crate::{
+    api::{
+        common::split_list,
+        response::{ErrorResponse, HistoryEntry, HistoryResponse},
+    },
+    auth::AuthorizationInfo,
+};
+
+/// Number of submissions on a page, unless specified otherwise.
+const DEFAULT_PER_PAGE: i64 = 50;
+
+/// Largest number of submissions that can be requested on a page.
+const MAX_PER_PAGE: i64 = 500;
+
+/// Query parameters of the submission history. The lists are comma separated,
+/// and omitted parameters do not restrict the selection.
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    /// Names of the submitted repositories
+    #[serde(default)]
+    repos: Option<String>,
+
+    /// GitHub organizations, GitLab namespaces, or Gitea owners
+    #[serde(default)]
+    owners: Option<String>,
+
+    /// Grading tags, with or without the leading `#` or `%` symbol
+    #[serde(default)]
+    tags: Option<String>,
+
+    /// Status codes of the submissions
+    #[serde(default)]
+    statuses: Option<String>,
+
+    /// Only include submissions made at or after this date and time
+    /// (RFC 3339 or YYYY-mm-dd in UTC)
+    #[serde(default)]
+    since: Option<String>,
+
+    /// Only include submissions made before this date and time
+    /// (RFC 3339 or YYYY-mm-dd in UTC)
+    #[serde(default)]
+    until: Option<String>,
+
+    /// Page number, starting from 1
+    #[serde(default)]
+    page: Option<i64>,
+
+    #[serde(default)]
+    per_page: Option<i64>,
+
+    /// Include the full report of each submission
+    #[serde(default)]
+    reports: bool,
+}
+
+/// Fetching a page of the submission history from the database
+///
+/// Required headers:
+///  - Authorization
+pub async fn get_submission_history(
+    data: web::Data<Settings>,
+    req: HttpRequest,
+    query: web::Query<HistoryQuery>,
+) -> Result<impl Responder, actix_web::Error> {
+    let settings = data.get_ref();
+
+    let auth_info = req
+        .extensions()
+        .get::<AuthorizationInfo>()
+        .ok_or_else(|| ErrorResponse::unauthorized(&req, "missing Authorization header"))?
+        .clone();
+    if !auth_info.api_auth_ok {
+        // API authentication failed
+        return Err(ErrorResponse::unauthorized(&req, "API authentication failed").into());
+    }
+
+    // Request is Authorized
+    let query = query.into_inner();
+
+    let parse_date = |date: &Option<String>| {
+        date.as_deref()
+            .map(parse_utc_datetime)
+            .transpose()
+            .map_err(|_| ErrorResponse::bad_request(&req, "invalid date and time"))
+    };
+
+    let mut statuses = Vec::new();
+    for code in split_list(&query.statuses) {
+        match code.parse() {
+            Ok(code) => statuses.push(code),
+            Err(_) => {
+                return Err(ErrorResponse::bad_request(
+                    &req,
+                    &format!("invalid status code {code}"),
+                )
+                .into())
+            }
+        }
+    }
+
+    let page = query.page.unwrap_or(1);
+    let per_page = query.per_page.unwrap_or(DEFAULT_PER_PAGE);
+    if page < 1 {
+        return Err(ErrorResponse::bad_request(&req, "page must be at least 1").into());
+    }
+    if !(1..=MAX_PER_PAGE).contains(&per_page) {
+        return Err(ErrorResponse::bad_request(
+            &req,
+            &format!("per_page must be between 1 and {MAX_PER_PAGE}"),
+        )
+        .into());
+    }
+
+    let offset = (page - 1)
+        .checked_mul(per_page)
+        .ok_or_else(|| ErrorResponse::bad_request(&req, "page is too large"))?;
+
+    let filter = HistoryFilter::new(
+        split_list(&query.repos),
+        split_list(&query.owners),
+        &split_list(&query.tags),
+        parse_date(&query.since)?,
+        parse_date(&query.until)?,
+        &statuses,
+    )
+    .map_err(|e| ErrorResponse::bad_request(&req, &format!("invalid filter: {e}")))?;
+
+    let mut conn = match DatabaseConnection::connect(settings) {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::error!("Could not open database connection: {e}");
+            return Err(ErrorResponse::internal_server_error(&req).into());
+        }
+    };
+
+    let (total, infos) = conn
+        .select_submission_history(&filter, offset, per_page)
+        .map_err(|e| {
+            log::error!("Could not select submission history: {e}");
+            ErrorResponse::internal_server_error(&req)
+        })?;
+
+    let mut reports: BTreeMap<i64, Report> = if query.reports {
+        let ids: Vec<i64> = infos.iter().map(|info| info.get_submission().id).collect();
+        conn.select_reports(&ids).map_err(|e| {
+            log::error!("Could not get reports of submission history: {e}");
+            ErrorResponse::internal_server_error(&req)
+        })?
+    } else {
+        BTreeMap::new()
+    };
+
+    let entries = infos
+        .iter()
+        .map(|info| HistoryEntry::new(info, reports.remove(&info.get_submission().id)))
+        .collect();
+    Ok(HistoryResponse::new(&req, total, page, per_page, entries).to_http())
+}
@@ -0,0 +1,127 @@
+//! Polling the progress of the submissions that are streamed to clients.
+//!
+//! A single background thread polls the progress of every watched submission
+//! with one query and one database connection, such that the number of
+//! connections does not grow with the number of open event streams.
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use id2202_autograder::{config::Settings, db::conn::DatabaseConnection};
+
+use crate::api::response::ProgressEvent;
+
+#[derive(Debug, Default)]
+struct Watched {
+    /// Number of streams that watch the submission
+    streams: usize,
+
+    /// The latest progress of the submission, once it has been polled
+    latest: Option<ProgressEvent>,
+}
+
+/// The progress of the watched submissions, shared between the poller thread
+/// and the event streams.
+#[derive(Debug, Default)]
+pub struct ProgressPoller {
+    watched: Mutex<BTreeMap<i64, Watched>>,
+}
+
+impl ProgressPoller {
+    /// Creates the poller and spawns the thread that polls the database every
+    /// `server.events_poll_interval_millisec`.
+    pub fn spawn(settings: Settings) -> Arc<ProgressPoller> {
+        let poller = Arc::new(ProgressPoller::default());
+        let thread_poller = poller.clone();
+        std::thread::spawn(move || thread_poller.poll_loop(settings));
+        poller
+    }
+
+    /// Starts watching the progress of the submission with ID
+    /// `submission_id`. The submission is watched until the returned handle
+    /// is dropped.
+    pub fn watch(self: Arc<Self>, submission_id: i64) -> ProgressWatch {
+        self.lock().entry(submission_id).or_default().streams += 1;
+        ProgressWatch {
+            poller: self,
+            submission_id: submission_id,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<i64, Watched>> {
+        // The map stays consistent even if a holder of the lock panicked
+        self.watched.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn poll_loop(&self, settings: Settings) {
+        let poll_interval = Duration::from_millis(settings.server.events_poll_interval_millisec);
+        let mut conn: Option<DatabaseConnection> = None;
+        loop {
+            std::thread::sleep(poll_interval);
+
+            let ids: Vec<i64> = self.lock().keys().copied().collect();
+            if ids.is_empty() {
+                // No connection is held while nothing is watched
+                conn = None;
+                continue;
+            }
+
+            if conn.is_none() {
+                conn = DatabaseConnection::connect(&settings)
+                    .inspect_err(|e| log::error!("Could not open database connection: {e}"))
+                    .ok();
+            }
+            let Some(c) = conn.as_mut() else {
+                continue;
+            };
+
+            match c.get_exec_progress_many(&ids) {
+                Ok(rows) => {
+                    let mut watched = self.lock();
+                    for (sub, progress) in rows {
+                        if let Some(w) = watched.get_mut(&sub.id) {
+                            w.latest = Some(ProgressEvent::new(&sub, progress));
+                        }
+                    }
+                }
+                Err(e) => {
+                    log::error!("Could not get progress of submissions: {e}");
+                    // Reconnecting on the next poll, in case the connection
+                    // was lost
+                    conn = None;
+                }
+            }
+        }
+    }
+}
+
+/// Handle of a stream that watches the progress of a submission.
+pub struct ProgressWatch {
+    poller: Arc<ProgressPoller>,
+    submission_id: i64,
+}
+
+impl ProgressWatch {
+    /// The latest polled progress of the submission, if it has been polled
+    /// yet.
+    pub fn latest(&self) -> Option<ProgressEvent> {
+        self.poller
+            .lock()
+            .get(&self.submission_id)
+            .and_then(|w| w.latest.clone())
+    }
+}
+
+impl Drop for ProgressWatch {
+    fn drop(&mut self) {
+        let mut watched = self.poller.lock();
+        if let Some(w) = watched.get_mut(&self.submission_id) {
+            w.streams -= 1;
+            if w.streams == 0 {
+                watched.remove(&self.submission_id);
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use actix_web::{
+    web::{self},
+    HttpMessage, HttpRequest, Responder,
+};
+use serde::{Deserialize, Serialize};
+
+use id2202_autograder::{
+    config::Settings,
+    db::conn::{DatabaseConnection, RegradeFilter},
+    utils::parse_utc_datetime,
+};
+
+use crate::{
+    api::response::{ErrorResponse, RegradeEntry, RegradeResponse},
+    auth::AuthorizationInfo,
+};
+
+/// A request to re-grade finished submissions, e.g. after the test suite of a
+/// tag has changed. Omitted fields do not restrict the selection.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegradeRequest {
+    /// Grading tags to re-grade, with or without the leading `#` or `%`
+    /// symbol. All grading tags of the selected submissions are re-graded if
+    /// empty.
+    #[serde(default)]
+    tags: Vec<String>,
+
+    /// Only re-grade submissions made at or after this date and time
+    /// (RFC 3339 or YYYY-mm-dd in UTC)
+    #[serde(default)]
+    since: Option<String>,
+
+    /// Only re-grade submissions made before this date and time
+    /// (RFC 3339 or YYYY-mm-dd in UTC)
+    #[serde(default)]
+    until: Option<String>,
+
+    /// Owners of the repositories to re-grade, i.e. GitHub organizations,
+    /// GitLab namespaces, or Gitea owners
+    #[serde(default)]
+    owners: Vec<String>,
+
+    /// Status codes of the submissions to re-grade
+    #[serde(default)]
+    statuses: Vec<i32>,
+
+    /// Priority of the re-grades. Defaults to the configured re-grade priority.
+    #[serde(default)]
+    priority: Option<i32>,
+
+    /// Do not post any comments or statuses on the re-graded commits
+    #[serde(default)]
+    silent: bool,
+
+    /// Only list the submissions that would be re-graded
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Re-grades finished submissions for the same commits.
+///
+/// Required headers:
+///  - Authorization
+pub async fn regrade_submissions(
+    data: web::Data<Settings>,
+    req: HttpRequest,
+    body: web::Json<RegradeRequest>,
+) -> Result<impl Responder, actix_web::Error> {
+    let settings = data.get_ref();
+
+    log::info!(
+        "Re-grade request from {}",
+        req.peer_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or("unknown".to_string()),
+    );
+
+    let auth_info = req
+        .extensions()
+        .get::<AuthorizationInfo>()
+        .ok_or_else(|| ErrorResponse::unauthorized(&req, "missing Authorization header"))?
+        .clone();
+    if !auth_info.api_auth_ok {
+        // API authentication failed
+        return Err(ErrorResponse::unauthorized(&req, "API authentication failed").into());
+    }
+
+    let request = body.into_inner();
+    log::debug!("Received re-grade request: {:?}", request);
+
+    let parse_date = |date: &Option<String>| {
+        date.as_deref()
+            .map(parse_utc_datetime)
+            .transpose()
+            .map_err(|_| ErrorResponse::bad_request(&req, "invalid date and time"))
+    };
+
+    let filter = RegradeFilter::new(
+        &request.tags,
+        parse_date(&request.since)?,
+        parse_date(&request.until)?,
+        request.owners,
+        &request.statuses,
+    )
+    .map_err(|e| ErrorResponse::bad_request(&req, &format!("invalid filter: {e}")))?;
+
+    let mut dbconn = DatabaseConnection::connect(settings).map_err(|err| {
+        log::error!("Could not connect to database: {err}");
+        ErrorResponse::internal_server_error(&req)
+    })?;
+
+    let selected = dbconn.select_regrade_submissions(&filter).map_err(|e| {
+        log::error!("Could not select submissions to re-grade: {e}");
+        ErrorResponse::internal_server_error(&req)
+    })?;
+
+    let priority = request
+        .priority
+        .unwrap_or(settings.submission.priority.regrade);
+    let mut regrades = Vec::new();
+    for (info, grading_tags) in selected.iter() {
+        let regrade_id = if request.dry_run {
+            None
+        } else {
+            let id = dbconn
+                .register_regrade(info, grading_tags, priority, request.silent)
+                .map_err(|e| {
+                    log::error!("Could not register re-grade with database: {e}");
+                    ErrorResponse::internal_server_error(&req)
+                })?;
+            Some(id)
+        };
+        regrades.push(RegradeEntry {
+            submission_id: info.get_submission().id,
+            grading_tags: grading_tags.split(";").map(|t| t.to_string()).collect(),
+            regrade_id: regrade_id,
+        });
+    }
+
+    if !request.dry_run && !regrades.is_empty() {
+        dbconn.notify("submission").unwrap_or_else(|e| {
+            log::warn!("Could not notify the runners about the re-grades: {e}")
+        });
+    }
+
+    log::info!(
+        "Re-grade request selected {} submissions (dry run: {})",
+        regrades.len(),
+        request.dry_run
+    );
+    Ok(RegradeResponse::new(&req, request.dry_run, regrades).to_http())
+}
@@ -7,7 +7,12 @@ use serde::{Deserialize, Serialize};
 use derive_more::derive::{Display, Error};
 use num_traits::FromPrimitive;
 
-use id2202_autograder::{db::models::SubmissionWithReport, reporting::Report};
+use id2202_autograder::{
+    db::models::{Submission, SubmissionInfo, SubmissionSourceKind, SubmissionWithReport},
+    gradebook::Gradebook,
+    reporting::Report,
+    subrunner::progress::SubmissionProgress,
+};
 
 macro_rules! schema_callback {
     ($struct_ident:path) => {
@@ -67,6 +72,24 @@ impl ErrorResponse {
             method: req.method().to_string(),
         }
     }
+    pub fn conflict(req: &HttpRequest, msg: &str) -> ErrorResponse {
+        ErrorResponse {
+            status: StatusCode::CONFLICT.as_u16(),
+            error: "conflict".to_string(),
+            message: msg.to_string(),
+            path: req.path().to_string(),
+            method: req.method().to_string(),
+        }
+    }
+    pub fn too_many_requests(req: &HttpRequest, msg: &str) -> ErrorResponse {
+        ErrorResponse {
+            status: StatusCode::TOO_MANY_REQUESTS.as_u16(),
+            error: "too many requests".to_string(),
+            message: msg.to_string(),
+            path: req.path().to_string(),
+            method: req.method().to_string(),
+        }
+    }
     pub fn internal_server_error(req: &HttpRequest) -> ErrorResponse {
         ErrorResponse {
             status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
@@ -109,12 +132,49 @@ impl SubmitResponse {
     }
 }
 
+/// A submission that is selected for a re-grade.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RegradeEntry {
+    /// ID of the original submission
+    pub submission_id: i64,
+
+    /// Grading tags that are re-graded
+    pub grading_tags: Vec<String>,
+
+    /// ID of the re-grade submission, or `None` on a dry run
+    pub regrade_id: Option<i64>,
+}
+
+/// Response to send back upon a re-grade request.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RegradeResponse {
+    code: u16,
+    path: String,
+    dry_run: bool,
+    regrades: Vec<RegradeEntry>,
+}
+
+impl RegradeResponse {
+    pub fn new(req: &HttpRequest, dry_run: bool, regrades: Vec<RegradeEntry>) -> RegradeResponse {
+        RegradeResponse {
+            code: StatusCode::OK.as_u16(),
+            path: req.path().to_string(),
+            dry_run: dry_run,
+            regrades: regrades,
+        }
+    }
+    pub fn to_http(&self) -> HttpResponse {
+        HttpResponse::Ok().json(self)
+    }
+}
+
 /// Information about a submission, to be sent back upon successful request.
 #[derive(Debug, Serialize, JsonSchema)]
 pub struct SubmissionResponse<'a> {
     code: u16,
     path: String,
     submission_id: i64,
+    regrade_of: Option<i64>,
     grading_tags: Vec<&'a str>,
     finished: bool,
     successful: Option<bool>,
@@ -132,6 +192,7 @@ impl<'a> SubmissionResponse<'a> {
             code: StatusCode::OK.as_u16(),
             path: req.path().to_string(),
             submission_id: sub.id,
+            regrade_of: sub.regrade_of,
             grading_tags: sub.grading_tags.split(";").collect(),
             finished: sub.exec_finished,
             successful: if sub.exec_finished {
@@ -152,3 +213,178 @@ impl<'a> SubmissionResponse<'a> {
         HttpResponse::Ok().json(self)
     }
 }
+
+/// A submission in the submission history.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct HistoryEntry {
+    pub submission_id: i64,
+    pub regrade_of: Option<i64>,
+
+    /// Kind of the submission source, e.g. GitHub or GitLab
+    pub source: String,
+
+    /// Domain of the instance hosting the repository, if any
+    pub domain: Option<String>,
+
+    /// Owner of the repository, i.e. the GitHub organization, GitLab
+    /// namespace, or Gitea owner
+    pub owner: Option<String>,
+
+    /// Name of the repository, or the URL for plain git sources
+    pub repo: String,
+
+    pub user: String,
+    pub commit: String,
+    pub grading_tags: Vec<String>,
+    pub finished: bool,
+    pub status_code: i32,
+    pub status: String,
+    pub successful: Option<bool>,
+    pub date_submitted: SystemTime,
+    pub date_exec_started: Option<SystemTime>,
+    pub date_exec_finished: Option<SystemTime>,
+
+    /// The full report, only included if requested
+    pub report: Option<Report>,
+}
+
+impl HistoryEntry {
+    pub fn new(info: &SubmissionInfo, report: Option<Report>) -> HistoryEntry {
+        use id2202_autograder::db::models::SubmissionStatusCode as SSC;
+
+        let sub = info.get_submission();
+        let (url, commit) = info.ssh_url_and_commit();
+        let status = SSC::from_i32(sub.exec_status_code);
+        HistoryEntry {
+            submission_id: sub.id,
+            regrade_of: sub.regrade_of,
+            source: SubmissionSourceKind::from_i32(info.get_source().kind)
+                .map_or("Unknown".to_string(), |k| k.to_string()),
+            domain: info.domain().map(|d| d.to_string()),
+            owner: info.owner().map(|o| o.to_string()),
+            repo: info.repo().unwrap_or(url).to_string(),
+            user: info.user().to_string(),
+            commit: commit.to_string(),
+            grading_tags: sub.grading_tags.split(";").map(|t| t.to_string()).collect(),
+            finished: sub.exec_finished,
+            status_code: sub.exec_status_code,
+            status: status.map_or("Unknown".to_string(), |c| c.to_string()),
+            successful: if sub.exec_finished {
+                status.map(|c| c == SSC::Success)
+            } else {
+                None
+            },
+            date_submitted: sub.date_submitted,
+            date_exec_started: sub.exec_date_started,
+            date_exec_finished: sub.exec_date_finished,
+            report: report,
+        }
+    }
+}
+
+/// A page of the submission history, with the most recent submissions first.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct HistoryResponse {
+    code: u16,
+    path: String,
+    /// Total number of submissions matching the filters
+    total: i64,
+    /// Page number, starting from 1
+    page: i64,
+    per_page: i64,
+    submissions: Vec<HistoryEntry>,
+}
+
+impl HistoryResponse {
+    pub fn new(
+        req: &HttpRequest,
+        total: i64,
+        page: i64,
+        per_page: i64,
+        submissions: Vec<HistoryEntry>,
+    ) -> HistoryResponse {
+        HistoryResponse {
+            code: StatusCode::OK.as_u16(),
+            path: req.path().to_string(),
+            total: total,
+            page: page,
+            per_page: per_page,
+            submissions: submissions,
+        }
+    }
+    pub fn to_http(&self) -> HttpResponse {
+        HttpResponse::Ok().json(self)
+    }
+}
+
+/// The earliest passing submission of each tag for each repository.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct GradebookResponse {
+    code: u16,
+    path: String,
+    #[serde(flatten)]
+    gradebook: Gradebook,
+}
+
+impl GradebookResponse {
+    pub fn new(req: &HttpRequest, gradebook: Gradebook) -> GradebookResponse {
+        GradebookResponse {
+            code: StatusCode::OK.as_u16(),
+            path: req.path().to_string(),
+            gradebook: gradebook,
+        }
+    }
+    pub fn to_http(&self) -> HttpResponse {
+        HttpResponse::Ok().json(self)
+    }
+}
+
+/// Progress of a submission, sent as the data of each server-sent event on
+/// the submission's event stream.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ProgressEvent {
+    submission_id: i64,
+    finished: bool,
+    status_code: i32,
+    status: String,
+    /// Percentage of the test cases that have been run
+    percent: u32,
+    tests_failed: usize,
+    progress: Option<SubmissionProgress>,
+}
+
+impl ProgressEvent {
+    pub fn new(sub: &Submission, progress: Option<SubmissionProgress>) -> ProgressEvent {
+        use id2202_autograder::db::models::SubmissionStatusCode as SSC;
+
+        ProgressEvent {
+            submission_id: sub.id,
+            finished: sub.exec_finished,
+            status_code: sub.exec_status_code,
+            status: SSC::from_i32(sub.exec_status_code)
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| "Unknown".to_string()),
+            percent: progress.as_ref().map(|p| p.percent()).unwrap_or(0),
+            tests_failed: progress.as_ref().map(|p| p.tests_failed()).unwrap_or(0),
+            progress: progress,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Formats this as a server-sent event, with `progress` or `finished` as
+    /// the event name.
+    pub fn to_sse(&self) -> String {
+        format!(
+            "event: {}\ndata: {}\n\n",
+            if self.finished {
+                "finished"
+            } else {
+                "progress"
+            },
+            serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string()),
+        )
+    }
+}
@@ -1,6 +1,10 @@
+use std::time::{Duration, Instant};
+
 use actix_web::{
+    http::header,
+    rt::time::sleep,
     web::{self},
-    HttpMessage, HttpRequest, Responder,
+    HttpMessage, HttpRequest, HttpResponse, Responder,
 };
 
 use id2202_autograder::{
@@ -9,7 +13,10 @@ use id2202_autograder::{
 };
 
 use crate::{
-    api::response::{ErrorResponse, SubmissionResponse},
+    api::{
+        progress::ProgressPoller,
+        response::{ErrorResponse, ProgressEvent, SubmissionResponse, SubmitResponse},
+    },
     auth::AuthorizationInfo,
 };
 
@@ -68,3 +75,171 @@ pub async fn get_submission(
 
     Ok(SubmissionResponse::new(&req, &swr).to_http())
 }
+
+/// Cancelling a submission that has not yet finished
+///
+/// Required headers:
+///  - Authorization
+pub async fn cancel_submission(
+    data: web::Data<Settings>,
+    req: HttpRequest,
+    submission_id: web::Path<String>,
+) -> Result<impl Responder, actix_web::Error> {
+    let auth_info = req
+        .extensions()
+        .get::<AuthorizationInfo>()
+        .ok_or_else(|| ErrorResponse::unauthorized(&req, "missing Authorization header"))?
+        .clone();
+    if !auth_info.api_auth_ok {
+        // API authentication failed
+        return Err(ErrorResponse::unauthorized(&req, "API authentication failed").into());
+    }
+
+    // Request is Authorized
+    let parsed_id: i64 = match submission_id.parse() {
+        Ok(v) => v,
+        Err(_) => {
+            log::error!("Bad submission id: {submission_id}");
+            return Err(ErrorResponse::bad_request(&req, "bad submission id format").into());
+        }
+    };
+
+    let mut conn = match DatabaseConnection::connect(data.get_ref()) {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::error!("Could not open database connection: {e}");
+            return Err(ErrorResponse::internal_server_error(&req).into());
+        }
+    };
+
+    if let Err(e) = conn.get_submission(parsed_id) {
+        log::error!("Could not get submission {parsed_id}: {e}");
+        return Err(ErrorResponse::not_found(&req, "submission not found").into());
+    }
+
+    // Posting the cancelled status to the source blocks
+    let cancelled = web::block(move || conn.cancel_submission(data.get_ref(), parsed_id))
+        .await
+        .map_err(|e| {
+            log::error!("Could not cancel submission {parsed_id}: {e}");
+            ErrorResponse::internal_server_error(&req)
+        })?
+        .map_err(|e| {
+            log::error!("Could not cancel submission {parsed_id}: {e}");
+            ErrorResponse::internal_server_error(&req)
+        })?;
+
+    if !cancelled {
+        return Err(ErrorResponse::conflict(&req, "submission has already finished").into());
+    }
+
+    Ok(SubmitResponse::new(&req, "submission cancelled", parsed_id).to_http())
+}
+
+/// Interval at which a comment is sent on an otherwise idle event stream, so
+/// that the connection is not closed by proxies and so that disconnected
+/// clients are noticed.
+const EVENTS_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Streaming the progress of a submission as server-sent events
+///
+/// A `progress` event is sent whenever the progress of the submission changes.
+/// Once the submission has finished, a `finished` event is sent and the stream
+/// ends. The data of each event is a `ProgressEvent` in JSON.
+///
+/// Required headers:
+///  - Authorization (or the auth_key query parameter of the submission)
+pub async fn submission_events(
+    data: web::Data<Settings>,
+    poller: web::Data<ProgressPoller>,
+    req: HttpRequest,
+    submission_id: web::Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let settings = data.get_ref();
+
+    let auth_info = req
+        .extensions()
+        .get::<AuthorizationInfo>()
+        .ok_or_else(|| ErrorResponse::unauthorized(&req, "missing Authorization header"))?
+        .clone();
+    if !auth_info.any_provided() {
+        return Err(ErrorResponse::unauthorized(&req, "missing authorization").into());
+    }
+
+    let parsed_id: i64 = match submission_id.parse() {
+        Ok(v) => v,
+        Err(_) => {
+            log::error!("Bad submission id: {submission_id}");
+            return Err(ErrorResponse::bad_request(&req, "bad submission id format").into());
+        }
+    };
+
+    let mut conn = match DatabaseConnection::connect(settings) {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::error!("Could not open database connection: {e}");
+            return Err(ErrorResponse::internal_server_error(&req).into());
+        }
+    };
+
+    let subinfo = match conn.get_submission_info(parsed_id) {
+        Ok(subinfo) => subinfo,
+        Err(e) => {
+            log::error!("Could not get submission {parsed_id}: {e}");
+            return Err(ErrorResponse::not_found(&req, "submission not found").into());
+        }
+    };
+
+    // The auth_key of the submission is sufficient for following its progress
+    if !auth_info.api_auth_ok && auth_info.auth_key.as_ref() != Some(&subinfo.get_source().auth_key)
+    {
+        return Err(ErrorResponse::unauthorized(&req, "authentication failed").into());
+    }
+
+    let poll_interval = Duration::from_millis(settings.server.events_poll_interval_millisec);
+    let max_duration = Duration::from_secs(settings.server.events_max_duration_seconds);
+    let started = Instant::now();
+
+    // The progress is polled from the database by the shared poller, such
+    // that open streams do not use up the connections to the database. The
+    // stream ends once the finished event has been sent, or after the
+    // maximum duration, after which the client is expected to reconnect.
+    let watch = poller.into_inner().watch(parsed_id);
+    let stream = futures_util::stream::unfold(
+        (watch, false, None::<ProgressEvent>),
+        move |(watch, finished, last)| async move {
+            if finished {
+                return None;
+            }
+            let idle_since = Instant::now();
+            loop {
+                if started.elapsed() >= max_duration {
+                    return None;
+                }
+
+                if let Some(event) = watch.latest() {
+                    if last.as_ref() != Some(&event) {
+                        let bytes = web::Bytes::from(event.to_sse());
+                        let finished = event.is_finished();
+                        return Some((
+                            Ok::<_, actix_web::Error>(bytes),
+                            (watch, finished, Some(event)),
+                        ));
+                    }
+                }
+
+                if idle_since.elapsed() >= EVENTS_KEEP_ALIVE {
+                    let bytes = web::Bytes::from_static(b": keep-alive\n\n");
+                    return Some((Ok(bytes), (watch, false, last)));
+                }
+
+                sleep(poll_interval).await;
+            }
+        },
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(stream))
+}
@@ -0,0 +1,272 @@
+use std::collections::BTreeSet;
+
+use actix_web::{
+    web::{self},
+    HttpMessage, HttpRequest, Responder,
+};
+use serde::{Deserialize, Serialize};
+
+use id2202_autograder::{
+    config::{tag_is_valid, Settings},
+    db::conn::{DatabaseConnection, NewSubmissionRequest},
+    error::ErrorKind,
+    utils::systemtime_to_utc_string,
+};
+
+use crate::{
+    api::response::{ErrorResponse, SubmitResponse},
+    auth::AuthorizationInfo,
+};
+
+/// A submission from a plain git repository, not tied to any specific git
+/// forge. Results are only made available through the database, since there
+/// is no forge to post commit comments to.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitSubmission {
+    /// URL used to clone the repository, e.g. `git@example.com:repo.git`
+    url: String,
+
+    /// Full hash of the commit to be graded
+    commit: String,
+
+    /// Grading tags, with or without the leading `#` or `%` symbol
+    tags: Vec<String>,
+
+    /// Optional name of the user that submitted this commit
+    #[serde(default)]
+    user: Option<String>,
+
+    /// Priority of this submission in the queue, 0 by default. Submissions
+    /// with a higher priority are graded first.
+    #[serde(default)]
+    priority: i32,
+}
+
+/// Returns `true` if the URL is an SSH or HTTPS URL that can safely be passed
+/// on to git. Local paths and other transports, such as `file://` and `ext::`,
+/// are rejected, as they would let the runner clone repositories from its own
+/// file system or run arbitrary commands.
+fn url_is_valid(url: &str) -> bool {
+    // Do not allow for URLs that git could interpret as an option
+    if url.is_empty()
+        || url.starts_with('-')
+        || url.contains(char::is_whitespace)
+        || url.contains("::")
+    {
+        return false;
+    }
+
+    let host = if let Some(rest) = url
+        .strip_prefix("ssh://")
+        .or_else(|| url.strip_prefix("https://"))
+    {
+        match rest.split_once('/') {
+            Some((authority, path)) if !path.is_empty() => authority
+                .rsplit_once('@')
+                .map_or(authority, |(_, host)| host),
+            _ => return false,
+        }
+    } else {
+        // The scp-like syntax `user@host:path`, which git only uses if there
+        // is no slash before the colon
+        match url.split_once(':') {
+            Some((user_host, path)) if !user_host.contains('/') && !path.is_empty() => {
+                match user_host.split_once('@') {
+                    Some((user, host)) if !user.is_empty() => host,
+                    _ => return false,
+                }
+            }
+            _ => return false,
+        }
+    };
+    !host.is_empty() && !host.starts_with('-')
+}
+
+/// Returns `true` if the commit is a full SHA-1 or SHA-256 commit hash.
+fn commit_is_valid(commit: &str) -> bool {
+    (commit.len() == 40 || commit.len() == 64) && commit.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Submission from a plain git repository.
+///
+/// Required headers:
+///  - Authorization
+pub async fn git_submission(
+    data: web::Data<Settings>,
+    req: HttpRequest,
+    body: web::Json<GitSubmission>,
+) -> Result<impl Responder, actix_web::Error> {
+    let settings = data.get_ref();
+
+    log::info!(
+        "Git submission request from {}",
+        req.peer_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or("unknown".to_string()),
+    );
+
+    let auth_info = req
+        .extensions()
+        .get::<AuthorizationInfo>()
+        .ok_or_else(|| ErrorResponse::unauthorized(&req, "missing Authorization header"))?
+        .clone();
+    if !auth_info.api_auth_ok {
+        // API authentication failed
+        return Err(ErrorResponse::unauthorized(&req, "API authentication failed").into());
+    }
+
+    let sub = body.into_inner();
+    log::debug!("Received git submission: {:?}", sub);
+
+    if !url_is_valid(&sub.url) {
+        log::warn!("Received invalid repository URL: {}", sub.url);
+        return Err(ErrorResponse::bad_request(&req, "invalid repository URL").into());
+    }
+
+    if !commit_is_valid(&sub.commit) {
+        log::warn!("Received invalid commit: {}", sub.commit);
+        return Err(ErrorResponse::bad_request(&req, "invalid commit hash").into());
+    }
+
+    // Same as for the webhooks, remove any duplicate tags via a BTreeSet.
+    let mut grading_tag_set: BTreeSet<&str> = BTreeSet::new();
+    for tag in sub.tags.iter() {
+        let tag = tag
+            .strip_prefix(|c: char| c == '#' || c == '%')
+            .unwrap_or(tag);
+        if !tag_is_valid(tag) {
+            log::warn!("Received invalid grading tag: {tag}");
+            return Err(ErrorResponse::bad_request(
+                &req,
+                &format!("invalid grading tag \"{tag}\""),
+            )
+            .into());
+        }
+        grading_tag_set.insert(tag);
+    }
+    let grading_tags: Vec<&str> = grading_tag_set.into_iter().collect();
+
+    if grading_tags.is_empty() {
+        return Err(ErrorResponse::bad_request(&req, "no grading tags provided").into());
+    }
+
+    let tag_length: usize = grading_tags.iter().map(|s| s.len()).sum();
+    if tag_length >= settings.submission.max_tag_length {
+        return Err(ErrorResponse::bad_request(
+            &req,
+            &format!(
+                "grading tags exceed the limit of {} characters",
+                settings.submission.max_tag_length
+            ),
+        )
+        .into());
+    }
+
+    // Connect to database and insert the submission request
+    let mut dbconn = DatabaseConnection::connect(settings).map_err(|err| {
+        log::error!("Could not connect to database: {err}");
+        ErrorResponse::internal_server_error(&req)
+    })?;
+
+    let submission_id = dbconn
+        .register_git_submission(
+            &NewSubmissionRequest {
+                grading_tags: &grading_tags,
+                rate_limits: &settings.submission.rate_limits,
+                priority: sub.priority,
+                user: sub.user.as_deref().unwrap_or(""),
+                commit: &sub.commit.to_lowercase(),
+            },
+            &sub.url,
+        )
+        .map_err(|e| match e.kind {
+            ErrorKind::RateLimited { next_allowed } => ErrorResponse::too_many_requests(
+                &req,
+                &format!(
+                    "submission limit reached, next submission allowed at {}",
+                    systemtime_to_utc_string(&next_allowed).unwrap_or("unknown".to_string())
+                ),
+            ),
+            _ => {
+                log::error!("Could not register submission with database: {e}");
+                ErrorResponse::internal_server_error(&req)
+            }
+        })?;
+
+    // There is no forge to notify about superseded submissions, they can only
+    // be seen through the database.
+    if settings.submission.coalesce_superseded {
+        dbconn
+            .supersede_queued_submissions(submission_id)
+            .unwrap_or_else(|e| {
+                log::warn!("Could not supersede queued submissions: {e}");
+                vec![]
+            });
+    }
+
+    // Notifying the other runners (TODO: make this name configurable)
+    dbconn.notify("submission").unwrap_or_else(|e| {
+        log::warn!(
+            "Could not notify the runners about the new submission: {}",
+            e
+        )
+    });
+
+    log::info!("Submission {sub:?} successfully inserted with id {submission_id}");
+    Ok(SubmitResponse::new(&req, "submission received", submission_id).to_http())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asserting::prelude::*;
+
+    #[test]
+    fn test_url_is_valid() {
+        for url in [
+            "git@git.example.com:group/repo.git",
+            "ssh://git@git.example.com/group/repo.git",
+            "ssh://git.example.com:2222/group/repo.git",
+            "https://git.example.com/group/repo.git",
+            "https://user@git.example.com/repo",
+        ] {
+            assert_that!(url_is_valid(url)).named(url).is_true();
+        }
+
+        for url in [
+            "",
+            "--upload-pack=touch /tmp/x",
+            "git@git.example.com:group/repo name.git",
+            "file:///srv/shadow/repo.git",
+            "file://localhost/srv/shadow/repo.git",
+            "ext::sh -c touch% /tmp/x",
+            "ext::ssh git@git.example.com",
+            "/srv/shadow/repo.git",
+            "./repo.git",
+            "../shadow/repo.git",
+            "repo.git",
+            "http://git.example.com/group/repo.git",
+            "git://git.example.com/group/repo.git",
+            "git.example.com:group/repo.git",
+            "@git.example.com:repo.git",
+            "git@:repo.git",
+            "git@git.example.com:",
+            "git@-oProxyCommand=x:repo.git",
+            "ssh://-oProxyCommand=x/repo.git",
+            "ssh://git.example.com",
+            "https://git.example.com/",
+            "https:///repo.git",
+            "./a@b:c",
+        ] {
+            assert_that!(url_is_valid(url)).named(url).is_false();
+        }
+    }
+
+    #[test]
+    fn test_commit_is_valid() {
+        assert_that!(commit_is_valid(&"a".repeat(40))).is_true();
+        assert_that!(commit_is_valid(&"0123456789abcdef".repeat(4))).is_true();
+        assert_that!(commit_is_valid(&"a".repeat(39))).is_false();
+        assert_that!(commit_is_valid(&"g".repeat(40))).is_false();
+    }
+}
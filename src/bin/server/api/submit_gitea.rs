@@ -0,0 +1,416 @@
+use std::fmt::Display;
+
+use actix_web::{
+    web::{self, Buf},
+    HttpRequest, Responder,
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use id2202_autograder::{
+    config::{settings::GiteaServerSettings, Settings},
+    db::conn::{DatabaseConnection, NewSubmissionRequest},
+    error::{Error, ErrorKind},
+    gitea,
+};
+
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+use crate::api::{
+    common::{
+        extract_grading_tags, rate_limit_report, submission_priority, superseded_message,
+        validate_repo_prefix_suffix,
+    },
+    response::{ErrorResponse, SubmitResponse},
+};
+
+/// A serializable Gitea submission, based on the JSON blob that is provided
+/// by the server. Forgejo sends the same payload.
+///
+/// https://docs.gitea.com/usage/webhooks#event-information
+#[derive(Debug, Serialize, Deserialize)]
+struct GiteaSubmission {
+    after: String,
+    commits: Vec<GtsCommit>,
+    repository: GtsRepository,
+    pusher: GtsUser,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GtsRepository {
+    /// Full repository name (format: `{OWNER}/{REPO}`)
+    full_name: String,
+
+    /// Repository name within the owner
+    name: String,
+
+    /// The user or organization that owns the repository
+    owner: GtsUser,
+
+    /// The URL to access the website
+    html_url: String,
+
+    /// URL for cloning the repository over SSH
+    ssh_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GtsUser {
+    login: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GtsCommit {
+    id: String,
+    message: String,
+
+    /// The URL to view the commit on the website
+    url: String,
+}
+
+/// Convenient struct with the information necessary to create a commit message
+/// and a commit status.
+#[derive(Debug)]
+struct CommitMessageInfo<'a> {
+    settings: &'a Settings,
+    instance: &'a GiteaServerSettings,
+    sub: &'a GiteaSubmission,
+}
+
+impl<'a> CommitMessageInfo<'a> {
+    async fn post_msg_status(
+        &self,
+        msg: &impl Display,
+        status: gitea::CommitState,
+        status_msg: Option<&str>,
+    ) -> Result<(), id2202_autograder::error::Error> {
+        self.post_msg_status_on(&self.sub.after, msg, status, status_msg)
+            .await
+    }
+
+    /// Same as `post_msg_status`, but for another commit in the same
+    /// repository.
+    async fn post_msg_status_on(
+        &self,
+        commit: &str,
+        msg: &impl Display,
+        status: gitea::CommitState,
+        status_msg: Option<&str>,
+    ) -> Result<(), id2202_autograder::error::Error> {
+        // The status is set first, as the message is only posted if the
+        // commit is the head of an open pull request.
+        gitea::create_commit_status(
+            self.settings,
+            self.instance,
+            &self.sub.repository.owner.login,
+            &self.sub.repository.name,
+            commit,
+            status,
+            status_msg,
+        )
+        .await
+        .inspect_err(|e| log::error!("Error creating commit status: {e}"))?;
+
+        gitea::create_commit_message(
+            self.settings,
+            self.instance,
+            &self.sub.repository.owner.login,
+            &self.sub.repository.name,
+            commit,
+            msg,
+        )
+        .await
+        .inspect_err(|e| log::error!("Error creating commit message: {e}"))?;
+
+        Ok(())
+    }
+}
+
+/// Returns the value of a Gitea webhook header. Forgejo sends its own
+/// `X-Forgejo-*` headers, but also the `X-Gitea-*` headers for compatibility,
+/// so the Forgejo variant is checked first.
+fn gitea_header<'a>(req: &'a HttpRequest, name: &str) -> Option<&'a str> {
+    req.headers()
+        .get(format!("X-Forgejo-{name}"))
+        .or_else(|| req.headers().get(format!("X-Gitea-{name}")))
+        .and_then(|hv| hv.to_str().ok())
+}
+
+/// Submission from Gitea or Forgejo. Received a webhook
+///
+/// See documentation over at docs.gitea.com/usage/webhooks
+///
+/// We expect the following for headers:
+///   X-Gitea-Delivery:  <uuid>
+///   X-Gitea-Event:     push
+///   X-Gitea-Signature: <lower case hex>
+pub async fn gitea_submission(
+    data: web::Data<Settings>,
+    req: HttpRequest,
+    payload: web::Payload,
+) -> Result<impl Responder, actix_web::Error> {
+    let settings = data.get_ref();
+
+    log::info!(
+        "Gitea submission request from {} (Delivery: {})",
+        req.peer_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or("unknown".to_string()),
+        gitea_header(&req, "Delivery").unwrap_or("unknown"),
+    );
+
+    // Disregard it request immediately if it is not a Gitea event
+    let gt_event = gitea_header(&req, "Event")
+        .ok_or(ErrorResponse::bad_request(&req, "missing event type"))?
+        .to_string();
+
+    // Validating the payload with HMAC. Unlike GitHub, Gitea does not prefix
+    // the signature with the hash algorithm.
+    let hmac256_received = gitea_header(&req, "Signature")
+        .ok_or(ErrorResponse::unauthorized(
+            &req,
+            "missing secret signature",
+        ))?
+        .to_string();
+
+    let payload_bytes = payload
+        .to_bytes_limited(settings.submission.max_payload)
+        .await
+        .map_err(|e| {
+            log::warn!("Error reading payload: {e}");
+            ErrorResponse::bad_request(&req, "bad payload")
+        })?
+        .map_err(|e| {
+            log::warn!("Error reading payload: {e}");
+            ErrorResponse::bad_request(&req, "bad payload")
+        })?;
+
+    let mut mac = HmacSha256::new_from_slice(settings.submission.gitea.webhook_secret.as_bytes())
+        .map_err(|hmac_err| {
+        log::error!("Could not create HMAC: {hmac_err:?}");
+        ErrorResponse::internal_server_error(&req)
+    })?;
+    mac.update(payload_bytes.chunk());
+
+    let mac_output_vec = mac.finalize().into_bytes();
+    let hmac256_computed = hex::encode(mac_output_vec);
+
+    log::debug!("Computed hash: \"{}\"", hmac256_computed);
+    log::debug!("Received hash: \"{}\"", hmac256_received);
+
+    if hmac256_received.to_lowercase() != hmac256_computed {
+        log::warn!("Unauthorized submission request.");
+        return Err(ErrorResponse::unauthorized(&req, "invalid secret signature").into());
+    }
+
+    log::debug!("Submission request authorized.");
+
+    // We only care about push events after this point
+    if gt_event != "push" {
+        log::warn!("Received invalid event type {}", gt_event);
+        return Err(ErrorResponse::bad_request(
+            &req,
+            &format!("invalid event type \"{gt_event}\""),
+        )
+        .into());
+    }
+
+    // Decode the payload as JSON
+    let sub: GiteaSubmission = serde_json::from_slice(payload_bytes.chunk()).map_err(|err| {
+        log::warn!("Received invalid JSON payload: {err:?}");
+        ErrorResponse::bad_request(&req, "invalid JSON format")
+    })?;
+
+    log::debug!("Received push event: {:?}", sub);
+
+    // Fetch the domain of the submission, verify that we have it configured as
+    // a source
+    let parsed_url = url::Url::parse(&sub.repository.html_url).map_err(|err| {
+        log::warn!("Received invalid repository URL: {err}");
+        ErrorResponse::bad_request(&req, "Invalid repository URL")
+    })?;
+
+    let domain = parsed_url
+        .domain()
+        .map(|dom| match parsed_url.port() {
+            Some(p) => format!("{dom}:{p}"),
+            None => dom.to_string(),
+        })
+        .ok_or_else(|| {
+            log::warn!("Received submission without domain in the repository URL");
+            ErrorResponse::bad_request(&req, "Invalid repository URL")
+        })?;
+
+    let owner = sub.repository.owner.login.as_str();
+    let repo_name = sub.repository.name.as_str();
+    if sub.repository.full_name != format!("{owner}/{repo_name}") {
+        return Err(
+            ErrorResponse::bad_request(&req, "inconsistent repository name in submission").into(),
+        );
+    }
+
+    let instance_settings = settings
+        .submission
+        .gitea
+        .known_instances
+        .iter()
+        .find(|gt| gt.domain == domain)
+        .ok_or_else(|| {
+            log::warn!("Received request from unknown Gitea instance {domain}");
+            ErrorResponse::unauthorized(&req, "Unknown Gitea instance")
+        })?;
+
+    // Extract the commit information pointing to the head of the repository
+    let commit_to_grade = match sub.commits.iter().find(|c| c.id == sub.after) {
+        Some(c) => c,
+        None => {
+            return Ok(SubmitResponse::without_id(
+                &req,
+                "pushed commits do not point to the head of the repository",
+            )
+            .to_http());
+        }
+    };
+
+    let commitinfo = CommitMessageInfo {
+        settings: &settings,
+        instance: &instance_settings,
+        sub: &sub,
+    };
+
+    if let Err(rejection) = validate_repo_prefix_suffix(
+        owner,
+        repo_name,
+        &instance_settings.allowed_owners,
+        &instance_settings.allowed_repo_prefixes,
+        &instance_settings.allowed_repo_suffixes,
+        &instance_settings.prohibited_repo_prefixes,
+        &instance_settings.prohibited_repo_suffixes,
+    ) {
+        log::info!(
+            "Push from {} will not be considered for grading: {}",
+            sub.repository.full_name,
+            rejection,
+        );
+        return Ok(SubmitResponse::without_id(&req, "not a repository to be graded").to_http());
+    }
+
+    let grading_tags: Vec<&str> = match extract_grading_tags(&settings, &commit_to_grade.message) {
+        Ok(tags) => tags,
+        Err(rep) => {
+            commitinfo
+                .post_msg_status(
+                    &rep.formatter_markdown(&settings.reporting),
+                    gitea::CommitState::Failure,
+                    Some("Invalid Grading Tags"),
+                )
+                .await
+                .unwrap_or_else(|e| log::warn!("Could not submit commit info: {e}."));
+
+            return Ok(SubmitResponse::without_id(&req, "bad grading tags").to_http());
+        }
+    };
+
+    if grading_tags.is_empty() {
+        log::info!(
+            "Push from {} will not be considered for grading, no grading tags provided",
+            sub.repository.full_name
+        );
+        return Ok(SubmitResponse::without_id(&req, "no grading tags provided").to_http());
+    }
+
+    // Connect to database and insert the submission request
+    let mut dbconn = DatabaseConnection::connect(&settings).map_err(|err| {
+        log::error!("Could not connect to database: {err}");
+        ErrorResponse::internal_server_error(&req)
+    })?;
+
+    let submission_id = match dbconn.register_gitea_submission(
+        &NewSubmissionRequest {
+            grading_tags: &grading_tags,
+            rate_limits: &settings.submission.rate_limits,
+            priority: submission_priority(settings, owner, repo_name),
+            user: &sub.pusher.login,
+            commit: &sub.after,
+        },
+        &domain,
+        owner,
+        repo_name,
+        &sub.repository.ssh_url,
+    ) {
+        Ok(submission_id) => submission_id,
+        Err(Error {
+            kind: ErrorKind::RateLimited { next_allowed },
+            ..
+        }) => {
+            commitinfo
+                .post_msg_status(
+                    &rate_limit_report(&grading_tags, &next_allowed)
+                        .formatter_markdown(&settings.reporting),
+                    gitea::CommitState::Failure,
+                    Some("Submission Limit Reached"),
+                )
+                .await
+                .unwrap_or_else(|e| log::warn!("Could not submit commit info: {e}."));
+
+            return Ok(SubmitResponse::without_id(&req, "submission limit reached").to_http());
+        }
+        Err(e) => {
+            log::error!("Could not register submission with database: {e}");
+            return Err(ErrorResponse::internal_server_error(&req).into());
+        }
+    };
+
+    // Respond to the commit message and set the commit status
+    commitinfo.post_msg_status(&format!(
+        "**[Submission ID: {} | {}]**\n\n{} {}",
+        submission_id,
+        grading_tags.iter().format_with(", ", |t, f| f(&format_args!("`{t}`"))),
+        "The autograder has successfully received your submission and will start grading as soon as a runner is available.",
+        "Additional information and results of your submission will be provided as comments here."
+    ), gitea::CommitState::Pending, Some("Waiting In Queue"))
+    .await
+    .unwrap_or_else(|e| log::warn!("Could not submit commit info: {e}. Will not reject this submission since it is already created."));
+
+    if settings.submission.coalesce_superseded {
+        let superseded = dbconn
+            .supersede_queued_submissions(submission_id)
+            .unwrap_or_else(|e| {
+                log::warn!("Could not supersede queued submissions: {e}");
+                vec![]
+            });
+        for info in superseded {
+            let (_, superseded_commit) = info.ssh_url_and_commit();
+            commitinfo
+                .post_msg_status_on(
+                    superseded_commit,
+                    &superseded_message(
+                        settings,
+                        info.get_submission().id,
+                        submission_id,
+                        &grading_tags,
+                        &commit_to_grade.id,
+                        &commit_to_grade.url,
+                    ),
+                    gitea::CommitState::Warning,
+                    Some("Superseded"),
+                )
+                .await
+                .unwrap_or_else(|e| log::warn!("Could not submit commit info: {e}."));
+        }
+    }
+
+    // Notifying the other runners (TODO: make this name configurable)
+    dbconn.notify("submission").unwrap_or_else(|e| {
+        log::warn!(
+            "Could not notify the runners about the new submission: {}",
+            e
+        )
+    });
+
+    log::info!("Submission {sub:?} successfully inserted with id {submission_id}");
+    Ok(SubmitResponse::new(&req, "submission received", submission_id).to_http())
+}
@@ -9,7 +9,8 @@ use serde::{Deserialize, Serialize};
 
 use id2202_autograder::{
     config::{settings::GitHubServerSettings, Settings},
-    db::conn::DatabaseConnection,
+    db::conn::{DatabaseConnection, NewSubmissionRequest},
+    error::{Error, ErrorKind},
     github,
 };
 
@@ -19,7 +20,10 @@ use sha2::Sha256;
 type HmacSha256 = Hmac<Sha256>;
 
 use crate::api::{
-    common::{extract_grading_tags, validate_repo_prefix_suffix},
+    common::{
+        extract_grading_tags, rate_limit_report, submission_priority, superseded_message,
+        validate_repo_prefix_suffix,
+    },
     response::{ErrorResponse, SubmitResponse},
 };
 
@@ -57,6 +61,9 @@ struct GhsRepository {
 struct GhsHeadCommit {
     id: String,
     message: String,
+
+    /// The URL to view the commit on the website
+    url: String,
 }
 #[derive(Debug, Serialize, Deserialize)]
 struct GhsPusher {
@@ -79,13 +86,26 @@ impl<'a> CommitMessageInfo<'a> {
         msg: &impl Display,
         status: github::CommitState,
         status_msg: Option<&str>,
+    ) -> Result<(), id2202_autograder::error::Error> {
+        self.post_msg_status_on(&self.sub.head_commit.id, msg, status, status_msg)
+            .await
+    }
+
+    /// Same as `post_msg_status`, but for another commit in the same
+    /// repository.
+    async fn post_msg_status_on(
+        &self,
+        commit: &str,
+        msg: &impl Display,
+        status: github::CommitState,
+        status_msg: Option<&str>,
     ) -> Result<(), id2202_autograder::error::Error> {
         github::create_commit_message(
             self.settings,
             self.instance,
             &self.sub.repository.organization,
             &self.sub.repository.name,
-            &self.sub.head_commit.id,
+            commit,
             msg,
         )
         .await
@@ -96,7 +116,7 @@ impl<'a> CommitMessageInfo<'a> {
             self.instance,
             &self.sub.repository.organization,
             &self.sub.repository.name,
-            &self.sub.head_commit.id,
+            commit,
             status,
             status_msg,
         )
@@ -286,20 +306,45 @@ pub async fn github_submission(
         ErrorResponse::internal_server_error(&req)
     })?;
 
-    let submission_id = dbconn
-        .register_github_submission(
-            &grading_tags,
-            &domain,
-            &sub.pusher.name,
-            &sub.repository.organization,
-            &sub.repository.name,
-            &sub.repository.ssh_url,
-            &sub.head_commit.id,
-        )
-        .map_err(|e| {
+    let submission_id = match dbconn.register_github_submission(
+        &NewSubmissionRequest {
+            grading_tags: &grading_tags,
+            rate_limits: &settings.submission.rate_limits,
+            priority: submission_priority(
+                settings,
+                &sub.repository.organization,
+                &sub.repository.name,
+            ),
+            user: &sub.pusher.name,
+            commit: &sub.head_commit.id,
+        },
+        &domain,
+        &sub.repository.organization,
+        &sub.repository.name,
+        &sub.repository.ssh_url,
+    ) {
+        Ok(submission_id) => submission_id,
+        Err(Error {
+            kind: ErrorKind::RateLimited { next_allowed },
+            ..
+        }) => {
+            commitinfo
+                .post_msg_status(
+                    &rate_limit_report(&grading_tags, &next_allowed)
+                        .formatter_markdown(&settings.reporting),
+                    github::CommitState::Failure,
+                    Some("Submission Limit Reached"),
+                )
+                .await
+                .unwrap_or_else(|e| log::warn!("Could not submit commit info: {e}."));
+
+            return Ok(SubmitResponse::without_id(&req, "submission limit reached").to_http());
+        }
+        Err(e) => {
             log::error!("Could not register submission with database: {e}");
-            ErrorResponse::internal_server_error(&req)
-        })?;
+            return Err(ErrorResponse::internal_server_error(&req).into());
+        }
+    };
 
     // Respond to the commit message and set the commit status
     commitinfo.post_msg_status(&format!(
@@ -312,6 +357,34 @@ pub async fn github_submission(
     .await
     .unwrap_or_else(|e| log::warn!("Could not submit commit info: {e}. Will not reject this submission since it is already created."));
 
+    if settings.submission.coalesce_superseded {
+        let superseded = dbconn
+            .supersede_queued_submissions(submission_id)
+            .unwrap_or_else(|e| {
+                log::warn!("Could not supersede queued submissions: {e}");
+                vec![]
+            });
+        for info in superseded {
+            let (_, superseded_commit) = info.ssh_url_and_commit();
+            commitinfo
+                .post_msg_status_on(
+                    superseded_commit,
+                    &superseded_message(
+                        settings,
+                        info.get_submission().id,
+                        submission_id,
+                        &grading_tags,
+                        &sub.head_commit.id,
+                        &sub.head_commit.url,
+                    ),
+                    github::CommitState::Error,
+                    Some("Superseded"),
+                )
+                .await
+                .unwrap_or_else(|e| log::warn!("Could not submit commit info: {e}."));
+        }
+    }
+
     // Notifying the other runners (TODO: make this name configurable)
     dbconn.notify("submission").unwrap_or_else(|e| {
         log::warn!(
@@ -9,12 +9,16 @@ use serde::{Deserialize, Serialize};
 
 use id2202_autograder::{
     config::{settings::GitLabServerSettings, Settings},
-    db::conn::DatabaseConnection,
+    db::conn::{DatabaseConnection, NewSubmissionRequest},
+    error::{Error, ErrorKind},
     gitlab,
 };
 
 use crate::api::{
-    common::{extract_grading_tags, validate_repo_prefix_suffix},
+    common::{
+        extract_grading_tags, rate_limit_report, submission_priority, superseded_message,
+        validate_repo_prefix_suffix,
+    },
     response::{ErrorResponse, SubmitResponse},
 };
 
@@ -49,6 +53,9 @@ struct GlsCommit {
     id: String,
     message: String,
     timestamp: String,
+
+    /// The URL to view the commit on the website
+    url: String,
 }
 
 /// Convenient struct with the information necessary to create a commit message
@@ -67,13 +74,26 @@ impl<'a> CommitMessageInfo<'a> {
         msg: &impl Display,
         status: gitlab::CommitState,
         status_msg: Option<&str>,
+    ) -> Result<(), id2202_autograder::error::Error> {
+        self.post_msg_status_on(&self.sub.after, msg, status, status_msg)
+            .await
+    }
+
+    /// Same as `post_msg_status`, but for another commit in the same
+    /// repository.
+    async fn post_msg_status_on(
+        &self,
+        commit: &str,
+        msg: &impl Display,
+        status: gitlab::CommitState,
+        status_msg: Option<&str>,
     ) -> Result<(), id2202_autograder::error::Error> {
         gitlab::create_commit_message(
             self.settings,
             self.instance,
             self.namespace,
             &self.sub.project.name,
-            &self.sub.after,
+            commit,
             msg,
         )
         .await
@@ -84,7 +104,7 @@ impl<'a> CommitMessageInfo<'a> {
             self.instance,
             self.namespace,
             &self.sub.project.name,
-            &self.sub.after,
+            commit,
             status,
             status_msg,
         )
@@ -280,20 +300,41 @@ pub async fn gitlab_submit_webhook(
         ErrorResponse::internal_server_error(&req)
     })?;
 
-    let submission_id = dbconn
-        .register_gitlab_submission(
-            &grading_tags,
-            &domain,
-            &sub.user_username,
-            &namespace,
-            &sub.project.name,
-            &sub.project.ssh_url,
-            &sub.after,
-        )
-        .map_err(|e| {
+    let submission_id = match dbconn.register_gitlab_submission(
+        &NewSubmissionRequest {
+            grading_tags: &grading_tags,
+            rate_limits: &settings.submission.rate_limits,
+            priority: submission_priority(settings, namespace, &sub.project.name),
+            user: &sub.user_username,
+            commit: &sub.after,
+        },
+        &domain,
+        &namespace,
+        &sub.project.name,
+        &sub.project.ssh_url,
+    ) {
+        Ok(submission_id) => submission_id,
+        Err(Error {
+            kind: ErrorKind::RateLimited { next_allowed },
+            ..
+        }) => {
+            commitinfo
+                .post_msg_status(
+                    &rate_limit_report(&grading_tags, &next_allowed)
+                        .formatter_markdown(&settings.reporting),
+                    gitlab::CommitState::Canceled,
+                    Some("Submission Limit Reached"),
+                )
+                .await
+                .unwrap_or_else(|e| log::warn!("Could not submit commit info: {e}."));
+
+            return Ok(SubmitResponse::without_id(&req, "submission limit reached").to_http());
+        }
+        Err(e) => {
             log::error!("Could not register submission with database: {e}");
-            ErrorResponse::internal_server_error(&req)
-        })?;
+            return Err(ErrorResponse::internal_server_error(&req).into());
+        }
+    };
 
     // Respond to the commit message and set the commit status
     commitinfo.post_msg_status(&format!(
@@ -306,6 +347,34 @@ pub async fn gitlab_submit_webhook(
         .await
         .unwrap_or_else(|e| log::warn!("Could not submit commit info: {e}. Will not reject this submission since it is already created."));
 
+    if settings.submission.coalesce_superseded {
+        let superseded = dbconn
+            .supersede_queued_submissions(submission_id)
+            .unwrap_or_else(|e| {
+                log::warn!("Could not supersede queued submissions: {e}");
+                vec![]
+            });
+        for info in superseded {
+            let (_, superseded_commit) = info.ssh_url_and_commit();
+            commitinfo
+                .post_msg_status_on(
+                    superseded_commit,
+                    &superseded_message(
+                        settings,
+                        info.get_submission().id,
+                        submission_id,
+                        &grading_tags,
+                        &commit_to_grade.id,
+                        &commit_to_grade.url,
+                    ),
+                    gitlab::CommitState::Skipped,
+                    Some("Superseded"),
+                )
+                .await
+                .unwrap_or_else(|e| log::warn!("Could not submit commit info: {e}."));
+        }
+    }
+
     // Notifying the other runners (TODO: make this name configurable)
     dbconn.notify("submission").unwrap_or_else(|e| {
         log::warn!(
@@ -35,6 +35,16 @@ async fn main() -> Result<(), Error> {
     let args: Args = Args::parse();
     let s = Settings::load(&args.settings)?;
     s.setup_logging("server")?;
+
+    if s.canvas.enabled {
+        let s_canvas = s.clone();
+        std::thread::spawn(move || id2202_autograder::canvas::sync_loop(s_canvas));
+    }
+
+    // Shared by all workers, so that every event stream is served by the
+    // same database connection
+    let progress_poller = api::progress::ProgressPoller::spawn(s.clone());
+
     let s_clone1 = s.clone();
     HttpServer::new(move || {
         let s = s_clone1.clone();
@@ -42,6 +52,7 @@ async fn main() -> Result<(), Error> {
             .wrap(Logger::default())
             .wrap(actix_web::middleware::from_fn(auth::authenticate))
             .app_data(web::Data::new(s.clone()))
+            .app_data(web::Data::from(progress_poller.clone()))
             .configure(|cfg| route::config(cfg, &s))
             .configure(|cfg| api::config(cfg, &s, "/api"))
             .default_service(web::to(not_found))
@@ -10,6 +10,7 @@ mod common;
 mod error_msg;
 mod index;
 mod job_info;
+mod similarity;
 mod submission;
 
 // Used for generated static routes below
@@ -26,10 +27,19 @@ pub fn config(cfg: &mut ServiceConfig, _settings: &Settings) {
         web::get().to(|data, req| job_info::get_job_info("/job_info", data, req)),
     );
 
+    cfg.route(
+        "/similarity",
+        web::get().to(|data, req| similarity::get_similarity("/similarity", data, req)),
+    );
+
     cfg.service(web::resource("/submission/{id}").route(web::get().to(submission::get_submission)));
     cfg.service(
         web::resource("/submission/{id}/").route(web::get().to(submission::get_submission)),
     );
+    cfg.service(
+        web::resource("/submission/{id}/cancel")
+            .route(web::post().to(submission::post_cancel_submission)),
+    );
     cfg.service(
         web::resource("/submission/{id}/markdown")
             .route(web::get().to(submission::get_submission_markdown)),
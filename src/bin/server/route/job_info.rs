@@ -80,7 +80,7 @@ pub async fn get_job_info(
                     .map_or("Unknown".to_string(), |c| format!("{c}")),
                 status_symbol_and_class: SSC::from_i32(sub.exec_status_code).and_then(
                     |c| match c {
-                        SSC::NotStarted | SSC::Running => None,
+                        SSC::NotStarted | SSC::Running | SSC::Superseded | SSC::Cancelled => None,
                         SSC::Success => Some((
                             settings.reporting.markdown.symbol_ok.to_owned(),
                             "text-success-emphasis".to_string(),
@@ -0,0 +1,67 @@
+use actix_web::{
+    error::InternalError,
+    http::StatusCode,
+    web::{self},
+    HttpMessage, HttpRequest, HttpResponse,
+};
+use sailfish::TemplateSimple;
+
+use id2202_autograder::{config::Settings, similarity::SimilarityReport};
+
+use crate::{
+    auth::AuthorizationInfo,
+    route::{common::CommonInformation, error_msg},
+};
+
+/// Template for showing the latest similarity report
+#[derive(TemplateSimple)]
+#[template(path = "route/similarity.stpl")]
+struct SimilarityTemplate {
+    common: CommonInformation,
+    // For this template only
+    report: Option<SimilarityReport>,
+}
+
+/// Page that shows the latest similarity report. Only accessible with an API
+/// token, as the report names the repositories of the students.
+pub async fn get_similarity(
+    current_route: &str,
+    data: web::Data<Settings>,
+    req: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let settings = data.get_ref();
+
+    let api_auth_ok = req
+        .extensions()
+        .get::<AuthorizationInfo>()
+        .is_some_and(|auth_info| auth_info.api_auth_ok);
+    if !api_auth_ok {
+        return error_msg::unauthorized(settings);
+    }
+
+    let report: Option<SimilarityReport> =
+        match std::fs::read_to_string(&settings.similarity.report_file) {
+            Ok(content) => match serde_json::from_str(&content) {
+                Ok(report) => Some(report),
+                Err(e) => {
+                    log::error!("Could not parse similarity report: {e}");
+                    return error_msg::internal_server_error(settings);
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                log::error!("Could not read similarity report: {e}");
+                return error_msg::internal_server_error(settings);
+            }
+        };
+
+    let tpl = SimilarityTemplate {
+        common: CommonInformation::from_title_route(settings, "Similarity", current_route),
+        report: report,
+    };
+    let body: String = tpl
+        .render_once()
+        .map_err(|e| InternalError::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    Ok(HttpResponse::Ok().body(body))
+}
@@ -9,7 +9,9 @@ use num_traits::FromPrimitive;
 use sailfish::TemplateSimple;
 
 use id2202_autograder::{
-    config::Settings, db::models::SubmissionInfo, reporting::Report,
+    config::Settings,
+    db::{conn::DatabaseConnection, models::SubmissionInfo},
+    reporting::Report,
     utils::systemtime_to_utc_string,
 };
 
@@ -28,6 +30,8 @@ struct SubmissionTemplate<'a> {
     common: CommonInformation,
     // For this template only
     submission_id: i64,
+    cancel_action: Option<String>,
+    events_url: Option<String>,
     status_lists: Vec<SubmissionStatusList<'a>>,
     report: RenderReport<'a>,
 }
@@ -47,17 +51,15 @@ struct SubmissionStatusListItem<'a> {
     svg_icon: Option<&'a str>,
 }
 
-/// Helper function for authenticating and fetching the submission and the
-/// report.
+/// Helper function for authenticating and fetching the submission. Returns
+/// the database connection that was used, such that it can be reused.
 ///
 /// This can be authenticated using the auth_key parameter
-fn fetch_submission_and_report(
+fn fetch_submission(
     settings: &Settings,
     req: &HttpRequest,
     submission_id_string: &str,
-) -> Result<(SubmissionInfo, Option<Report>), Result<HttpResponse, actix_web::Error>> {
-    use id2202_autograder::db::conn::DatabaseConnection;
-
+) -> Result<(DatabaseConnection, SubmissionInfo), Result<HttpResponse, actix_web::Error>> {
     let auth_info = req
         .extensions()
         .get::<AuthorizationInfo>()
@@ -92,7 +94,6 @@ fn fetch_submission_and_report(
         }
     };
 
-    let sub = subinfo.get_submission();
     let src = subinfo.get_source();
     if auth_info.api_auth_ok {
         // OK, this counts as a valid authentication for all submissions
@@ -105,7 +106,21 @@ fn fetch_submission_and_report(
         return Err(error_msg::unauthorized(settings));
     }
 
-    let report = match conn.get_submission_report(sub.id) {
+    Ok((conn, subinfo))
+}
+
+/// Helper function for authenticating and fetching the submission and the
+/// report.
+///
+/// This can be authenticated using the auth_key parameter
+fn fetch_submission_and_report(
+    settings: &Settings,
+    req: &HttpRequest,
+    submission_id_string: &str,
+) -> Result<(SubmissionInfo, Option<Report>), Result<HttpResponse, actix_web::Error>> {
+    let (mut conn, subinfo) = fetch_submission(settings, req, submission_id_string)?;
+
+    let report = match conn.get_submission_report(subinfo.get_submission().id) {
         Ok(maybe_r) => maybe_r,
         Err(e) => {
             log::warn!("Could not fetch report: {:?}", e);
@@ -147,6 +162,10 @@ pub async fn get_submission(
     if let Some(ssc) = SSC::from_i32(sub.exec_status_code) {
         let (li_class, rhs_symbol) = match ssc {
             SSC::NotStarted | SSC::Running => (None, "".to_string()),
+            SSC::Superseded | SSC::Cancelled => (
+                Some("list-group-item-secondary".to_string()),
+                "".to_string(),
+            ),
             SSC::Success => (
                 Some("list-group-item-success".to_string()),
                 settings.reporting.markdown.symbol_ok.clone(),
@@ -291,12 +310,107 @@ pub async fn get_submission(
                 ..Default::default()
             });
         }
+        SubmissionInfo::Git {
+            sub: _,
+            src: _,
+            git_src,
+            git_info,
+        } => {
+            statlist_source.items.push(SubmissionStatusListItem {
+                label: "Origin",
+                value: "Git".to_string(),
+                svg_icon: Some("source-git"),
+                ..Default::default()
+            });
+            statlist_source.items.push(SubmissionStatusListItem {
+                label: "URL",
+                value: git_src.url.clone(),
+                ..Default::default()
+            });
+            statlist_source.items.push(SubmissionStatusListItem {
+                label: "User",
+                value: git_info.user.clone(),
+                ..Default::default()
+            });
+            statlist_source.items.push(SubmissionStatusListItem {
+                label: "Commit",
+                value: git_info.commit.clone(),
+                ..Default::default()
+            });
+        }
+        SubmissionInfo::Gitea {
+            sub: _,
+            src: _,
+            gt_src,
+            gt_info,
+        } => {
+            let protocol = if settings
+                .submission
+                .gitea
+                .known_instances
+                .iter()
+                .find(|ki| ki.domain == gt_src.domain)
+                .map(|ki| ki.use_https)
+                .unwrap_or(true)
+            {
+                "https"
+            } else {
+                "http"
+            };
+            statlist_source.title_href = Some(format!(
+                "{}://{}/{}/{}/commit/{}",
+                protocol, gt_src.domain, gt_src.owner, gt_src.repo, gt_info.commit
+            ));
+
+            statlist_source.items.push(SubmissionStatusListItem {
+                label: "Origin",
+                value: "Gitea".to_string(),
+                svg_icon: Some("source-git"),
+                ..Default::default()
+            });
+            statlist_source.items.push(SubmissionStatusListItem {
+                label: "Domain",
+                value: gt_src.domain.clone(),
+                ..Default::default()
+            });
+            statlist_source.items.push(SubmissionStatusListItem {
+                label: "Owner",
+                value: gt_src.owner.clone(),
+                ..Default::default()
+            });
+            statlist_source.items.push(SubmissionStatusListItem {
+                label: "Repository",
+                value: gt_src.repo.clone(),
+                ..Default::default()
+            });
+            statlist_source.items.push(SubmissionStatusListItem {
+                label: "Commit",
+                value: gt_info.commit.clone(),
+                ..Default::default()
+            });
+        }
     }
     status_lists.push(statlist_source);
 
     let mut tpl = SubmissionTemplate {
         common: CommonInformation::from_title(settings, &format!("Submission {}", sub.id)),
         submission_id: sub.id,
+        cancel_action: if sub.exec_finished {
+            None
+        } else {
+            Some(with_query(
+                &format!("/submission/{}/cancel", sub.id),
+                req.query_string(),
+            ))
+        },
+        events_url: if sub.exec_finished {
+            None
+        } else {
+            Some(with_query(
+                &format!("/api/submission/{}/events", sub.id),
+                req.query_string(),
+            ))
+        },
         status_lists: status_lists,
         report: RenderReport {
             v: opt_report,
@@ -334,3 +448,54 @@ pub async fn get_submission_markdown(
         .insert_header(header::ContentType::plaintext())
         .body(BoxBody::new(md_text)))
 }
+
+/// Cancel a submission that has not yet finished, and redirect back to the
+/// submission page.
+pub async fn post_cancel_submission(
+    data: web::Data<Settings>,
+    req: HttpRequest,
+    submission_id: web::Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    use actix_web::http::header;
+
+    let settings = data.get_ref();
+
+    let (mut conn, subinfo) = match fetch_submission(settings, &req, submission_id.as_str()) {
+        Ok(tup) => tup,
+        Err(e) => {
+            return e;
+        }
+    };
+    let sub_id = subinfo.get_submission().id;
+
+    // Posting the cancelled status to the source blocks
+    let block_data = data.clone();
+    match web::block(move || conn.cancel_submission(block_data.get_ref(), sub_id)).await {
+        Ok(Ok(true)) => log::info!("Submission {sub_id} cancelled from the web page"),
+        Ok(Ok(false)) => log::info!("Submission {sub_id} already finished, not cancelling"),
+        Ok(Err(e)) => {
+            log::error!("Could not cancel submission {sub_id}: {e}");
+            return error_msg::internal_server_error(settings);
+        }
+        Err(e) => {
+            log::error!("Could not cancel submission {sub_id}: {e}");
+            return error_msg::internal_server_error(settings);
+        }
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((
+            header::LOCATION,
+            with_query(&format!("/submission/{sub_id}"), req.query_string()),
+        ))
+        .finish())
+}
+
+/// Appends the query string, such as the auth_key, to the path if non-empty.
+fn with_query(path: &str, query_string: &str) -> String {
+    if query_string.is_empty() {
+        path.to_string()
+    } else {
+        format!("{path}?{query_string}")
+    }
+}
@@ -0,0 +1,519 @@
+/// Synchronization of the results of each tag to assignments in Canvas LMS.
+/// Grades are only posted when they differ from the grade last posted to
+/// Canvas, and grades that could not be posted are retried on the next
+/// synchronization.
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+use reqwest::Client as ReqwestClient;
+
+use crate::{
+    config::{
+        settings::{CanvasGrading, CanvasSettings},
+        Settings,
+    },
+    db::conn::DatabaseConnection,
+    error::{Error, ErrorKind},
+    gradebook::GradebookRecord,
+};
+
+/// Posts grades to Canvas. Implemented by `HttpCanvasClient` for the Canvas
+/// REST API, and by stand-ins when testing.
+pub trait CanvasClient {
+    /// Sets the grade of `user` on the assignment, where `user` is either a
+    /// Canvas user ID or e.g. "sis_user_id:u1abcdef".
+    fn set_grade(&self, assignment_id: u64, user: &str, grade: &str) -> Result<(), Error>;
+}
+
+/// Client for the Canvas REST API.
+pub struct HttpCanvasClient {
+    url: String,
+    auth_token: String,
+    course_id: u64,
+    client: ReqwestClient,
+    rt: tokio::runtime::Runtime,
+}
+
+impl HttpCanvasClient {
+    pub fn new(canvas: &CanvasSettings) -> Result<Self, Error> {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| Error::auto_msg("could not unwrap tokio runtime", e))?;
+        Ok(HttpCanvasClient {
+            url: canvas.url.trim_end_matches('/').to_string(),
+            auth_token: canvas.auth_token.clone(),
+            course_id: canvas.course_id,
+            client: ReqwestClient::new(),
+            rt: rt,
+        })
+    }
+}
+
+impl CanvasClient for HttpCanvasClient {
+    fn set_grade(&self, assignment_id: u64, user: &str, grade: &str) -> Result<(), Error> {
+        self.rt.block_on(async {
+            let response = self
+                .client
+                .put(format!(
+                    "{}/api/v1/courses/{}/assignments/{}/submissions/{}",
+                    self.url, self.course_id, assignment_id, user
+                ))
+                .bearer_auth(&self.auth_token)
+                .form(&[("submission[posted_grade]", grade)])
+                .send()
+                .await
+                .map_err(|e| Error::auto_msg("error with Canvas grade request", e))?;
+
+            if response.status().is_success() {
+                log::debug!("Set Canvas grade of {user} on assignment {assignment_id} to {grade}");
+                Ok(())
+            } else {
+                Error::err_http_response(
+                    "when setting Canvas grade".to_string(),
+                    response.status().as_u16(),
+                    response
+                        .text()
+                        .await
+                        .unwrap_or("no text received".to_string()),
+                )
+            }
+        })
+    }
+}
+
+/// A grade of a Canvas user on an assignment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanvasGrade {
+    pub user: String,
+    pub assignment_id: u64,
+    pub grade: String,
+}
+
+/// Summary of a synchronization.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncSummary {
+    /// Number of grades that were already up to date in Canvas
+    pub unchanged: usize,
+    pub posted: usize,
+    pub failed: usize,
+}
+
+/// Returns the Canvas user of a repository, from the first matching entry in
+/// the settings. Entries without a repository only match with `any_repo`.
+pub fn canvas_user<'a>(
+    canvas: &'a CanvasSettings,
+    owner: Option<&str>,
+    repo: &str,
+) -> Option<&'a str> {
+    canvas
+        .users
+        .iter()
+        .find(|u| {
+            u.owner.as_ref().is_none_or(|o| Some(o.as_str()) == owner)
+                && u.repo.as_ref().map_or(u.any_repo, |r| r == repo)
+        })
+        .map(|u| u.user.as_str())
+}
+
+/// Compiles the grade of each mapped user on each assignment from the
+/// finished submissions in `records`. Grades are only compiled for the
+/// assignments whose tag the user has submitted, and users get a failing
+/// grade on those that they have not passed.
+pub fn desired_grades(canvas: &CanvasSettings, records: &[GradebookRecord]) -> Vec<CanvasGrade> {
+    // Best percentage of each user on each assignment, where passing without
+    // a score counts as 100% and failing without a score as 0%. Late results
+    // are not counted with `honor_deadlines`, but they still give the user a
+    // grade on the assignment.
+    let mut best: BTreeMap<(String, u64), Option<f64>> = BTreeMap::new();
+    for record in records {
+        let Some(user) = canvas_user(canvas, record.owner.as_deref(), &record.repo) else {
+            continue;
+        };
+        for assignment in canvas.assignments.iter() {
+            for graded in record.tags.iter().filter(|t| t.tag_name == assignment.tag) {
+                let entry = best
+                    .entry((user.to_string(), assignment.assignment_id))
+                    .or_insert(None);
+                if canvas.honor_deadlines && graded.late {
+                    continue;
+                }
+                let percentage = match canvas.grading {
+                    CanvasGrading::PassFail if graded.ok => 100.0,
+                    CanvasGrading::PassFail => 0.0,
+                    CanvasGrading::Score => {
+                        graded
+                            .percentage
+                            .unwrap_or(if graded.ok { 100.0 } else { 0.0 })
+                    }
+                };
+                if entry.is_none_or(|p| percentage > p) {
+                    *entry = Some(percentage);
+                }
+            }
+        }
+    }
+
+    best.into_iter()
+        .map(|((user, assignment_id), percentage)| {
+            let percentage = percentage.unwrap_or(0.0);
+            let grade = match canvas.grading {
+                CanvasGrading::PassFail if percentage > 0.0 => "complete".to_string(),
+                CanvasGrading::PassFail => "incomplete".to_string(),
+                CanvasGrading::Score => format!("{percentage:.1}%"),
+            };
+            CanvasGrade {
+                user: user,
+                assignment_id: assignment_id,
+                grade: grade,
+            }
+        })
+        .collect()
+}
+
+/// Returns the grades in `desired` that differ from the grades that have
+/// already been posted to Canvas.
+pub fn grades_to_post(
+    desired: Vec<CanvasGrade>,
+    synced: &HashMap<(String, u64), String>,
+) -> Vec<CanvasGrade> {
+    desired
+        .into_iter()
+        .filter(|g| synced.get(&(g.user.clone(), g.assignment_id)) != Some(&g.grade))
+        .collect()
+}
+
+/// Whether posting a grade may succeed if retried, i.e. the request failed to
+/// be sent, was rate limited, or failed on the server.
+fn is_retryable(e: &Error) -> bool {
+    match &e.kind {
+        ErrorKind::HttpResponse { code, .. } => *code == 429 || *code >= 500,
+        _ => true,
+    }
+}
+
+/// Posts a grade, retrying up to `max_retries` times with exponential
+/// backoff starting at `backoff`.
+pub fn post_with_retry(
+    client: &impl CanvasClient,
+    grade: &CanvasGrade,
+    max_retries: u32,
+    backoff: Duration,
+) -> Result<(), Error> {
+    let mut attempt = 0;
+    loop {
+        match client.set_grade(grade.assignment_id, &grade.user, &grade.grade) {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < max_retries && is_retryable(&e) => {
+                let delay = backoff * 2u32.saturating_pow(attempt);
+                log::warn!(
+                    "Could not set Canvas grade of {} on assignment {}, retrying in {:?}: {e}",
+                    grade.user,
+                    grade.assignment_id,
+                    delay
+                );
+                std::thread::sleep(delay);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Posts the grades that have changed since the last synchronization. With
+/// `dry_run`, the grades are only logged.
+pub fn sync(
+    s: &Settings,
+    dbconn: &mut DatabaseConnection,
+    client: &impl CanvasClient,
+    dry_run: bool,
+) -> Result<SyncSummary, Error> {
+    // Only the tags of the assignments are needed, and no tags means all tags
+    let tags: Vec<String> = s.canvas.assignments.iter().map(|a| a.tag.clone()).collect();
+    let records = if tags.is_empty() {
+        Vec::new()
+    } else {
+        dbconn.select_gradebook_records(&[], &tags)?
+    };
+    let desired = desired_grades(&s.canvas, &records);
+    let synced: HashMap<(String, u64), String> = dbconn
+        .get_canvas_grades()?
+        .into_iter()
+        .map(|g| ((g.canvas_user, g.assignment_id as u64), g.grade))
+        .collect();
+
+    let mut summary = SyncSummary {
+        unchanged: desired.len(),
+        ..Default::default()
+    };
+    for grade in grades_to_post(desired, &synced) {
+        summary.unchanged -= 1;
+        if dry_run {
+            log::info!(
+                "Would set Canvas grade of {} on assignment {} to {}",
+                grade.user,
+                grade.assignment_id,
+                grade.grade
+            );
+            summary.posted += 1;
+            continue;
+        }
+
+        match post_with_retry(client, &grade, s.canvas.max_retries, Duration::from_secs(1)) {
+            Ok(()) => {
+                dbconn.set_canvas_grade(&grade.user, grade.assignment_id as i64, &grade.grade)?;
+                summary.posted += 1;
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to set Canvas grade of {} on assignment {}: {e}",
+                    grade.user,
+                    grade.assignment_id
+                );
+                summary.failed += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Synchronizes the grades to Canvas every `canvas.sync_interval_seconds`.
+/// Never returns, and is meant to be run in a background thread.
+pub fn sync_loop(s: Settings) {
+    let interval = Duration::from_secs(s.canvas.sync_interval_seconds);
+    loop {
+        let result = HttpCanvasClient::new(&s.canvas).and_then(|client| {
+            let mut dbconn = DatabaseConnection::connect(&s)?;
+            sync(&s, &mut dbconn, &client, false)
+        });
+        match result {
+            Ok(summary) => log::info!(
+                "Canvas sync: {} posted, {} failed, {} unchanged",
+                summary.posted,
+                summary.failed,
+                summary.unchanged
+            ),
+            Err(e) => log::error!("Canvas sync failed: {e}"),
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asserting::prelude::*;
+    use std::{
+        cell::RefCell,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use crate::{
+        config::settings::{CanvasAssignmentSettings, CanvasUserSettings},
+        gradebook::GradedTag,
+        utils::local_http,
+    };
+
+    fn canvas_settings(grading: CanvasGrading) -> CanvasSettings {
+        CanvasSettings {
+            enabled: true,
+            url: "http://127.0.0.1".to_string(),
+            auth_token: "secret".to_string(),
+            course_id: 42,
+            grading: grading,
+            honor_deadlines: false,
+            sync_interval_seconds: 300,
+            max_retries: 2,
+            assignments: vec![CanvasAssignmentSettings {
+                tag: "hello".to_string(),
+                assignment_id: 7,
+            }],
+            users: vec![
+                CanvasUserSettings {
+                    owner: Some("id2202".to_string()),
+                    repo: Some("student-1".to_string()),
+                    any_repo: false,
+                    user: "sis_user_id:u1".to_string(),
+                },
+                CanvasUserSettings {
+                    owner: Some("id2202".to_string()),
+                    repo: None,
+                    any_repo: true,
+                    user: "sis_user_id:u2".to_string(),
+                },
+            ],
+        }
+    }
+
+    fn record(repo: &str, ok: bool, late: bool, percentage: Option<f64>) -> GradebookRecord {
+        GradebookRecord {
+            source_id: 1,
+            source: "GitHub".to_string(),
+            domain: None,
+            owner: Some("id2202".to_string()),
+            repo: repo.to_string(),
+            user: "user".to_string(),
+            submission_id: 1,
+            submitted: UNIX_EPOCH + Duration::from_secs(100),
+            tags: vec![GradedTag {
+                tag_name: "hello".to_string(),
+                ok: ok,
+                late: late,
+                percentage: percentage,
+            }],
+        }
+    }
+
+    fn grade(user: &str, grade: &str) -> CanvasGrade {
+        CanvasGrade {
+            user: user.to_string(),
+            assignment_id: 7,
+            grade: grade.to_string(),
+        }
+    }
+
+    /// Stand-in client that fails with the given HTTP codes before succeeding.
+    struct MockClient {
+        failures: RefCell<Vec<u16>>,
+        calls: RefCell<usize>,
+    }
+
+    impl CanvasClient for MockClient {
+        fn set_grade(&self, _assignment_id: u64, _user: &str, _grade: &str) -> Result<(), Error> {
+            *self.calls.borrow_mut() += 1;
+            match self.failures.borrow_mut().pop() {
+                Some(code) => Error::err_http_response("mock", code, "mock failure"),
+                None => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_canvas_user() {
+        let canvas = canvas_settings(CanvasGrading::PassFail);
+        assert_that!(canvas_user(&canvas, Some("id2202"), "student-1"))
+            .is_equal_to(Some("sis_user_id:u1"));
+        assert_that!(canvas_user(&canvas, Some("id2202"), "student-2"))
+            .is_equal_to(Some("sis_user_id:u2"));
+        assert_that!(canvas_user(&canvas, Some("other"), "student-1")).is_equal_to(None);
+        assert_that!(canvas.validate()).is_ok();
+
+        // An entry without a repository has to match any repository explicitly
+        let mut canvas = canvas;
+        canvas.users[1].any_repo = false;
+        assert_that!(canvas_user(&canvas, Some("id2202"), "student-2")).is_equal_to(None);
+        assert_that!(canvas.validate()).is_err();
+    }
+
+    #[test]
+    fn test_desired_grades() {
+        let mut records = vec![
+            record("student-1", false, false, Some(40.0)),
+            record("student-1", true, true, Some(90.0)),
+            record("student-2", false, false, None),
+            record("unmapped", true, false, None),
+        ];
+        records[3].owner = Some("other".to_string());
+
+        // No grades are posted for assignments that have not been submitted
+        let mut canvas = canvas_settings(CanvasGrading::PassFail);
+        canvas.assignments.push(CanvasAssignmentSettings {
+            tag: "world".to_string(),
+            assignment_id: 8,
+        });
+        assert_that!(desired_grades(&canvas, &records)).is_equal_to(vec![
+            grade("sis_user_id:u1", "complete"),
+            grade("sis_user_id:u2", "incomplete"),
+        ]);
+
+        canvas.grading = CanvasGrading::Score;
+        assert_that!(desired_grades(&canvas, &records)).is_equal_to(vec![
+            grade("sis_user_id:u1", "90.0%"),
+            grade("sis_user_id:u2", "0.0%"),
+        ]);
+
+        canvas.honor_deadlines = true;
+        assert_that!(desired_grades(&canvas, &records)).is_equal_to(vec![
+            grade("sis_user_id:u1", "40.0%"),
+            grade("sis_user_id:u2", "0.0%"),
+        ]);
+    }
+
+    #[test]
+    fn test_grades_to_post() {
+        let synced = HashMap::from([
+            (("sis_user_id:u1".to_string(), 7), "complete".to_string()),
+            (("sis_user_id:u2".to_string(), 7), "incomplete".to_string()),
+        ]);
+        let desired = vec![
+            grade("sis_user_id:u1", "complete"),
+            grade("sis_user_id:u2", "complete"),
+            grade("sis_user_id:u3", "incomplete"),
+        ];
+        assert_that!(grades_to_post(desired, &synced)).is_equal_to(vec![
+            grade("sis_user_id:u2", "complete"),
+            grade("sis_user_id:u3", "incomplete"),
+        ]);
+    }
+
+    #[test]
+    fn test_post_with_retry() {
+        let g = grade("sis_user_id:u1", "complete");
+
+        let client = MockClient {
+            failures: RefCell::new(vec![500, 429]),
+            calls: RefCell::new(0),
+        };
+        assert_that!(post_with_retry(&client, &g, 2, Duration::ZERO)).is_ok();
+        assert_that!(*client.calls.borrow()).is_equal_to(3);
+
+        let client = MockClient {
+            failures: RefCell::new(vec![503, 503, 503]),
+            calls: RefCell::new(0),
+        };
+        assert_that!(post_with_retry(&client, &g, 2, Duration::ZERO)).is_err();
+        assert_that!(*client.calls.borrow()).is_equal_to(3);
+
+        // Client errors are not retried
+        let client = MockClient {
+            failures: RefCell::new(vec![404]),
+            calls: RefCell::new(0),
+        };
+        assert_that!(post_with_retry(&client, &g, 2, Duration::ZERO)).is_err();
+        assert_that!(*client.calls.borrow()).is_equal_to(1);
+    }
+
+    #[test]
+    fn test_http_canvas_client() {
+        let (address, server) = local_http::serve(vec![("200 OK", "{}".to_string())]);
+        let mut canvas = canvas_settings(CanvasGrading::PassFail);
+        canvas.url = format!("http://{address}/");
+        let client = HttpCanvasClient::new(&canvas).unwrap();
+        assert_that!(client.set_grade(7, "sis_user_id:u1", "complete")).is_ok();
+
+        let request = server.join().unwrap().remove(0);
+        assert_that!(request.as_str())
+            .starts_with("PUT /api/v1/courses/42/assignments/7/submissions/sis_user_id:u1 ");
+        assert_that!(request
+            .to_lowercase()
+            .contains("authorization: bearer secret"))
+        .is_true();
+        assert_that!(request.as_str()).ends_with("submission%5Bposted_grade%5D=complete");
+
+        let (address, server) =
+            local_http::serve(vec![("503 Service Unavailable", "{}".to_string())]);
+        canvas.url = format!("http://{address}");
+        let client = HttpCanvasClient::new(&canvas).unwrap();
+        let code = match client.set_grade(7, "sis_user_id:u1", "complete") {
+            Err(Error {
+                kind: ErrorKind::HttpResponse { code, .. },
+                ..
+            }) => Some(code),
+            _ => None,
+        };
+        server.join().unwrap();
+        assert_that!(code).is_equal_to(Some(503));
+    }
+}
@@ -46,6 +46,12 @@ pub struct Settings {
     #[config(nested)]
     pub reporting: ReportingSettings,
 
+    #[config(nested)]
+    pub canvas: CanvasSettings,
+
+    #[config(nested)]
+    pub similarity: SimilaritySettings,
+
     /// Relative dir to the configuration file
     #[config(default = "")]
     pub reldir: String,
@@ -102,6 +108,72 @@ pub struct SubmissionSettings {
     /// Settings for submissions coming from a GitLab instance
     #[config(nested)]
     pub gitlab: GitLabSettings,
+
+    /// Settings for submissions coming from a Gitea or Forgejo instance
+    #[config(nested)]
+    pub gitea: GiteaSettings,
+
+    /// Limits on how often the same repository can be submitted for grading.
+    /// A submission is rejected if it would exceed any of these limits.
+    #[config(default = [])]
+    pub rate_limits: Vec<RateLimitSettings>,
+
+    /// Settings for the order in which queued submissions are graded
+    #[config(nested)]
+    pub priority: PrioritySettings,
+
+    /// Whether a queued submission is superseded by a newer submission from
+    /// the same repository with the same grading tags, such that only the
+    /// newer one is graded.
+    #[config(env = "AUTOGRADER_SUBMISSION_COALESCE_SUPERSEDED", parse_env = parse_env_bool, default = false)]
+    pub coalesce_superseded: bool,
+}
+
+/// Settings for prioritizing queued submissions. Submissions with a higher
+/// priority are graded first, but every queued submission gains priority as it
+/// waits, such that no submission is starved.
+#[derive(Config, Deserialize, Debug, Clone)]
+pub struct PrioritySettings {
+    /// Priority of submissions from staff repositories. Other submissions
+    /// have priority 0.
+    #[config(default = 10)]
+    pub staff: i32,
+
+    /// Owners of staff repositories, i.e. GitHub organizations, GitLab
+    /// namespaces, or Gitea owners.
+    #[config(default = [])]
+    pub staff_owners: Vec<String>,
+
+    /// Full names of staff repositories, e.g. `"my-org/reference-solution"`
+    #[config(default = [])]
+    pub staff_repos: Vec<String>,
+
+    /// Number of minutes that a submission has to wait in the queue to gain
+    /// one level of priority. 0 disables aging.
+    #[config(default = 10)]
+    pub aging_minutes: u32,
+
+    /// Priority of re-grades. This is lower than the priority of student
+    /// submissions by default, such that a bulk re-grade does not delay them.
+    #[config(default = -10)]
+    pub regrade: i32,
+}
+
+/// A limit on the number of submissions from the same repository within a
+/// sliding period of time.
+#[derive(Deserialize, Debug, Clone)]
+pub struct RateLimitSettings {
+    /// Maximum number of submissions within the period. 0 disables the limit.
+    pub max_submissions: usize,
+
+    /// Length of the period in minutes, e.g. 60 for a limit per hour. 0
+    /// disables the limit.
+    pub period_minutes: u64,
+
+    /// Whether the submissions are counted separately for each grading tag,
+    /// rather than for the repository as a whole.
+    #[serde(default)]
+    pub per_tag: bool,
 }
 
 /// Settings specific to incoming GitHub requests. See `ServerSettings` for
@@ -196,6 +268,57 @@ pub struct GitLabServerSettings {
     pub use_https: bool,
 }
 
+/// Settings specific to incoming Gitea requests. Forgejo instances are
+/// configured here as well. See `ServerSettings` for generic HTTP settings that
+/// applies to all incoming requests.
+#[derive(Config, Deserialize, Debug, Clone)]
+pub struct GiteaSettings {
+    /// Webhook secret used to validate incoming requests
+    #[config(env = "AUTOGRADER_SUBMISSION_GITEA_WEBHOOK_SECRET")]
+    pub webhook_secret: String,
+
+    /// Information for specific instances.
+    pub known_instances: Vec<GiteaServerSettings>,
+}
+
+/// Settings for a single Gitea server. See `GiteaSettings` for settings that
+/// apply to all Gitea servers.
+#[derive(Config, Deserialize, Debug, Clone)]
+pub struct GiteaServerSettings {
+    /// The domain address at which the Gitea instance is hosted at
+    pub domain: String,
+
+    /// Gitea authorization token for using the API
+    pub auth_token: String,
+
+    /// Gitea users or organizations to accept grading requests from. If not
+    /// empty, the repository must be owned by one of these.
+    pub allowed_owners: Vec<String>,
+
+    /// Allowed repository prefixes: If not empty, a repository must start with
+    /// one of these prefix strings to be graded.
+    pub allowed_repo_prefixes: Vec<String>,
+
+    /// Allowed repository suffixes: If not empty, a repository must end with
+    /// one of these suffix strings to be graded.
+    pub allowed_repo_suffixes: Vec<String>,
+
+    /// A repository is not allowed to start with one of these strings to be
+    /// graded.
+    pub prohibited_repo_prefixes: Vec<String>,
+
+    /// A repository is not allowed to end with one of these strings to be
+    /// graded.
+    pub prohibited_repo_suffixes: Vec<String>,
+
+    /// Whether or not HTTPS should be used when invoking the API
+    ///
+    /// ### Warning
+    /// This should only ever be used when testing against a local Gitea
+    /// instance. Use with caution.
+    pub use_https: bool,
+}
+
 #[derive(Config, Deserialize, Debug, Clone)]
 pub struct PostgresSettings {
     /// Postgres username
@@ -225,6 +348,23 @@ pub struct ServerSettings {
     #[config(env = "AUTOGRADER_SERVER_PORT")]
     pub port: u16,
 
+    /// The URL that the server is publicly reachable at, e.g.
+    /// `https://autograder.example.com`. This is used to link to submissions
+    /// in the comments posted on commits.
+    #[config(env = "AUTOGRADER_SERVER_PUBLIC_URL")]
+    pub public_url: Option<String>,
+
+    /// How often the progress of the submissions that are streamed to clients
+    /// as server-sent events is polled from the database.
+    #[config(default = 1000)]
+    pub events_poll_interval_millisec: u64,
+
+    /// For how long a server-sent event stream is kept open before it is
+    /// closed by the server. Clients reconnect on their own, so this only
+    /// limits how long a stream to an abandoned page can stay open.
+    #[config(default = 600)]
+    pub events_max_duration_seconds: u64,
+
     /// Secrets used for client authentication
     #[config(nested)]
     pub secrets: ServerSecretsSettings,
@@ -283,11 +423,169 @@ pub struct RunnerSettings {
 
 #[derive(Config, Deserialize, Debug, Clone)]
 pub struct ReportingSettings {
+    /// How the results are reported on the commit while the submission is
+    /// being graded. This is only implemented for GitHub and GitLab.
+    #[config(default = "off")]
+    pub incremental: IncrementalReporting,
+
     /// Markdown output settings
     #[config(nested)]
     pub markdown: ReportingMarkdownSettings,
 }
 
+/// How the results of a submission are reported while it is being graded.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IncrementalReporting {
+    /// The results are only reported once every tag has been graded.
+    Off,
+
+    /// A comment is posted when the first tag has been graded, and is then
+    /// edited as each following tag finishes. The final report is written to
+    /// the same comment.
+    EditComment,
+
+    /// A separate comment is posted with the results of each tag as it
+    /// finishes, followed by the final report.
+    PerTag,
+}
+
+/// Settings for synchronizing the results of each tag to assignments in
+/// Canvas LMS.
+#[derive(Config, Deserialize, Debug, Clone)]
+pub struct CanvasSettings {
+    /// Whether the grades are synchronized to Canvas in the background.
+    #[config(env = "AUTOGRADER_CANVAS_ENABLED", parse_env = parse_env_bool, default = false)]
+    pub enabled: bool,
+
+    /// Base URL of the Canvas instance, e.g. "https://canvas.kth.se"
+    #[config(env = "AUTOGRADER_CANVAS_URL", default = "")]
+    pub url: String,
+
+    /// Access token used for the Canvas REST API.
+    #[config(env = "AUTOGRADER_CANVAS_AUTH_TOKEN", default = "")]
+    pub auth_token: String,
+
+    /// ID of the course that the assignments belong to.
+    #[config(default = 0)]
+    pub course_id: u64,
+
+    /// Whether to post pass/fail grades or scores.
+    #[config(default = "pass_fail")]
+    pub grading: CanvasGrading,
+
+    /// Only count submissions made before the deadline of the tag.
+    #[config(default = false)]
+    pub honor_deadlines: bool,
+
+    /// How often the grades are synchronized.
+    #[config(default = 300)]
+    pub sync_interval_seconds: u64,
+
+    /// Number of times that posting a grade is retried before it is left for
+    /// the next synchronization.
+    #[config(default = 3)]
+    pub max_retries: u32,
+
+    /// The Canvas assignment of each synchronized tag.
+    #[config(default = [])]
+    pub assignments: Vec<CanvasAssignmentSettings>,
+
+    /// The Canvas user of each repository. The first matching entry is used,
+    /// and repositories without a matching entry are not synchronized.
+    #[config(default = [])]
+    pub users: Vec<CanvasUserSettings>,
+}
+
+impl CanvasSettings {
+    /// Checks that every user entry names a repository, unless it explicitly
+    /// matches any repository.
+    pub fn validate(&self) -> Result<(), Error> {
+        match self.users.iter().find(|u| u.repo.is_none() && !u.any_repo) {
+            Some(u) => Error::err_format(
+                "canvas.users entry without repo, set repo or any_repo = true",
+                &u.user,
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+/// How the results of a tag are graded in Canvas.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CanvasGrading {
+    /// "complete" if the tag has been passed, otherwise "incomplete"
+    PassFail,
+
+    /// The best score of the tag as a percentage. Tags without points score
+    /// 100% when passed.
+    Score,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CanvasAssignmentSettings {
+    /// Grading tag, without the leading `#` or `%` symbol
+    pub tag: String,
+
+    pub assignment_id: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CanvasUserSettings {
+    /// Owner of the repository, i.e. the GitHub organization, GitLab
+    /// namespace, or Gitea owner. Matches any owner if omitted.
+    #[serde(default)]
+    pub owner: Option<String>,
+
+    /// Name of the repository. An entry without a repository only matches
+    /// if `any_repo` is set.
+    #[serde(default)]
+    pub repo: Option<String>,
+
+    /// Whether an entry without `repo` matches every repository of the
+    /// owner. This has to be set explicitly, as such an entry would send the
+    /// results of e.g. every student repository in a course organization to
+    /// the same Canvas user.
+    #[serde(default)]
+    pub any_repo: bool,
+
+    /// The Canvas user, either as a Canvas user ID or as e.g.
+    /// "sis_user_id:u1abcdef".
+    pub user: String,
+}
+
+/// Settings for the similarity check across the graded snapshots.
+#[derive(Config, Deserialize, Debug, Clone)]
+pub struct SimilaritySettings {
+    /// Directories with template/skeleton code handed out to the students.
+    /// Code that also occurs in these directories does not count towards the
+    /// similarity.
+    #[config(default = [])]
+    pub template_dirs: Vec<String>,
+
+    /// Number of consecutive tokens that make up a fingerprinted k-gram.
+    /// Matches shorter than this are not detected.
+    #[config(default = 12)]
+    pub kgram_length: usize,
+
+    /// Number of consecutive k-grams that a fingerprint is selected from.
+    /// Every match of at least `kgram_length + window_size - 1` tokens is
+    /// guaranteed to be detected.
+    #[config(default = 8)]
+    pub window_size: usize,
+
+    /// Pairs of repositories below this similarity (0.0 - 1.0) are left out
+    /// of the report.
+    #[config(default = 0.3)]
+    pub min_similarity: f64,
+
+    /// File that the latest similarity report is stored in, and which is
+    /// shown on the similarity page of the server.
+    #[config(default = "./data/similarity.json")]
+    pub report_file: String,
+}
+
 /// Settings for markdown output on reports
 #[derive(Config, Deserialize, Debug, Clone)]
 pub struct ReportingMarkdownSettings {
@@ -347,6 +645,12 @@ impl Settings {
         s.runner.workspace_dir = path_absolute_join(&s.reldir, &s.runner.workspace_dir)?;
         s.runner.shadow_dir = path_absolute_join(&s.reldir, &s.runner.shadow_dir)?;
         s.runner.test_config = path_absolute_join(&s.reldir, &s.runner.test_config)?;
+        s.similarity.report_file = path_absolute_join(&s.reldir, &s.similarity.report_file)?;
+        for dir in s.similarity.template_dirs.iter_mut() {
+            *dir = path_absolute_join(&s.reldir, &dir)?;
+        }
+
+        s.canvas.validate()?;
 
         // Additional environment variables not captured by confique
         if let Ok(values) = std::env::var("AUTOGRADER_GITHUB_AUTH_TOKENS") {
@@ -410,6 +714,36 @@ impl Settings {
             }
         }
 
+        if let Ok(values) = std::env::var("AUTOGRADER_GITEA_AUTH_TOKENS") {
+            for v in values.split(";") {
+                match v.split("=").collect::<Vec<_>>().as_slice() {
+                    &[domain, token] => {
+                        match s
+                            .submission
+                            .gitea
+                            .known_instances
+                            .iter_mut()
+                            .find(|gt| gt.domain == domain.trim())
+                        {
+                            Some(gt_instance) => {
+                                gt_instance.auth_token = token.to_string();
+                            }
+                            None => {
+                                log::warn!(
+                                    "Unrecognized domain in environment variable AUTOGRADER_GITEA_AUTH_TOKENS"
+                                );
+                            }
+                        }
+                    }
+                    _ => {
+                        log::warn!(
+                            "Invalid format for environment variable AUTOGRADER_GITEA_AUTH_TOKENS"
+                        );
+                    }
+                }
+            }
+        }
+
         Ok(s)
     }
 
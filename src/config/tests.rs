@@ -1,5 +1,6 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, path::Path};
 
 use crate::{
     error::Error,
@@ -16,17 +17,261 @@ pub struct TestkindRun {
     pub stdout: Vec<String>,
     pub stdout_trim: bool,
     pub stdout_strip_whitespace: bool,
+    #[serde(default)]
+    pub stdout_match: OutputMatch,
     pub stderr: Vec<String>,
     pub stderr_trim: bool,
     pub stderr_strip_whitespace: bool,
+    #[serde(default)]
+    pub stderr_match: OutputMatch,
+    /// Absolute and relative tolerance of numbers in outputs that are
+    /// matched with `OutputMatch::Numeric`
+    #[serde(default)]
+    pub abs_tolerance: f64,
+    #[serde(default)]
+    pub rel_tolerance: f64,
     pub input_files: Vec<String>,
 
     /// Suffixes for automatically discovering input files, e.g. ["*.cpp"]
     pub auto_input_files: Vec<String>,
 }
 
+/// How the output of a program is compared against the expected values. The
+/// output is trimmed or has its whitespace removed before it is compared,
+/// while the expected values are only treated this way for `Exact` and
+/// `Contains`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMatch {
+    /// The output is equal to an expected value
+    #[default]
+    Exact,
+    /// The output contains an expected value
+    Contains,
+    /// The whole output matches an expected regular expression
+    Regex,
+    /// The whole output matches an expected glob pattern, where `*` matches
+    /// any sequence of characters and `?` matches any single character. These
+    /// are matched literally when escaped with `\`.
+    Glob,
+    /// The output has the same tokens as an expected value, where numbers
+    /// are equal within a tolerance and other tokens are equal exactly
+    Numeric,
+}
+
+impl OutputMatch {
+    /// Checks that `pattern` is valid for this matching mode. Returns a
+    /// description of the problem if it is not.
+    pub fn validate_pattern(&self, pattern: &str) -> Result<(), String> {
+        match self {
+            OutputMatch::Exact | OutputMatch::Contains | OutputMatch::Numeric => Ok(()),
+            OutputMatch::Regex => Self::full_regex(pattern)
+                .map(|_| ())
+                .map_err(|e| format!("invalid regex \"{pattern}\": {e}")),
+            OutputMatch::Glob => {
+                let trailing_escapes = pattern.chars().rev().take_while(|c| *c == '\\').count();
+                if trailing_escapes % 2 == 1 {
+                    Err(format!("invalid glob \"{pattern}\": trailing escape"))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if `output` matches the expected `pattern`. Invalid
+    /// patterns never match, but these are rejected when loading the tests.
+    /// Numbers are compared exactly, see `is_match_with_tolerance`.
+    pub fn is_match(&self, output: &str, pattern: &str) -> bool {
+        self.is_match_with_tolerance(output, pattern, &NumericTolerance::default())
+    }
+
+    /// Same as `is_match`, but compares numbers with `tolerance` when
+    /// matching with `Numeric`.
+    pub fn is_match_with_tolerance(
+        &self,
+        output: &str,
+        pattern: &str,
+        tolerance: &NumericTolerance,
+    ) -> bool {
+        match self {
+            OutputMatch::Exact => output == pattern,
+            OutputMatch::Contains => output.contains(pattern),
+            OutputMatch::Regex => Self::full_regex(pattern).is_ok_and(|re| re.is_match(output)),
+            OutputMatch::Glob => {
+                let pattern: Vec<char> = pattern.chars().collect();
+                let output: Vec<char> = output.chars().collect();
+                glob_match(&pattern, &output)
+            }
+            OutputMatch::Numeric => tolerance.is_match(output, pattern),
+        }
+    }
+
+    /// A regex that only matches if `pattern` matches the whole string.
+    fn full_regex(pattern: &str) -> Result<regex::Regex, regex::Error> {
+        regex::Regex::new(&format!("\\A(?:{pattern})\\z"))
+    }
+
+    /// Description of how the output is compared, shown when it does not
+    /// match. `None` for exact matching, which needs no explanation.
+    pub fn description(&self) -> Option<&'static str> {
+        match self {
+            OutputMatch::Exact => None,
+            OutputMatch::Contains => Some("The output has to contain the expected value."),
+            OutputMatch::Regex => {
+                Some("The output has to match the expected regular expression in full.")
+            }
+            OutputMatch::Glob => Some(
+                "The output has to match the expected glob pattern in full, where `*` matches any sequence of characters and `?` matches any single character.",
+            ),
+            OutputMatch::Numeric => Some(
+                "Numbers in the output are compared with a tolerance, while any other text has to be equal to the expected value.",
+            ),
+        }
+    }
+}
+
+/// Tolerance when comparing numbers in outputs. Two numbers are equal if
+/// they differ by at most `absolute`, or by at most `relative` times the
+/// larger of their magnitudes.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NumericTolerance {
+    pub absolute: f64,
+    pub relative: f64,
+}
+
+impl NumericTolerance {
+    /// Checks that the tolerances are non-negative numbers.
+    fn validate(&self) -> Result<(), String> {
+        if self.absolute >= 0.0 && self.relative >= 0.0 {
+            Ok(())
+        } else {
+            Err("abs_tolerance and rel_tolerance must be non-negative numbers".to_string())
+        }
+    }
+
+    /// Whether two numbers are equal within the tolerance. Infinities are
+    /// only equal to themselves, and NaN is never equal.
+    pub fn numbers_equal(&self, a: f64, b: f64) -> bool {
+        if a == b {
+            return true;
+        }
+        if !a.is_finite() || !b.is_finite() {
+            return false;
+        }
+        let diff = (a - b).abs();
+        diff <= self.absolute || diff <= self.relative * a.abs().max(b.abs())
+    }
+
+    /// Returns `true` if `output` has the same tokens as `expected`, with
+    /// numeric tokens equal within the tolerance. Tokens are separated by
+    /// whitespace, and numbers are split from any text that they are
+    /// attached to, e.g. "x=1.50," becomes "x=", "1.50", and ",".
+    pub fn is_match(&self, output: &str, expected: &str) -> bool {
+        let output = numeric_tokens(output);
+        let expected = numeric_tokens(expected);
+        output.len() == expected.len()
+            && output.iter().zip(expected.iter()).all(|pair| match pair {
+                (NumericToken::Number(a), NumericToken::Number(b)) => self.numbers_equal(*a, *b),
+                (NumericToken::Text(a), NumericToken::Text(b)) => a == b,
+                _ => false,
+            })
+    }
+
+    /// Description of the tolerance, shown when the output does not match.
+    pub fn description(&self) -> String {
+        format!(
+            "Numbers may differ by an absolute tolerance of {} or a relative tolerance of {}.",
+            self.absolute, self.relative
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum NumericToken<'a> {
+    Number(f64),
+    Text(&'a str),
+}
+
+/// Splits `s` into the tokens compared by `NumericTolerance::is_match`.
+fn numeric_tokens(s: &str) -> Vec<NumericToken<'_>> {
+    static NUMBER: std::sync::LazyLock<regex::Regex> = std::sync::LazyLock::new(|| {
+        regex::Regex::new(r"[-+]?(?:[0-9]+(?:\.[0-9]*)?|\.[0-9]+)(?:[eE][-+]?[0-9]+)?")
+            .expect("valid number regex")
+    });
+    let mut tokens = Vec::new();
+    for word in s.split_whitespace() {
+        let mut pos = 0;
+        for m in NUMBER.find_iter(word) {
+            if m.start() > pos {
+                tokens.push(NumericToken::Text(&word[pos..m.start()]));
+            }
+            match m.as_str().parse::<f64>() {
+                Ok(n) => tokens.push(NumericToken::Number(n)),
+                Err(_) => tokens.push(NumericToken::Text(m.as_str())),
+            }
+            pos = m.end();
+        }
+        if pos < word.len() {
+            tokens.push(NumericToken::Text(&word[pos..]));
+        }
+    }
+    tokens
+}
+
+/// Matches `output` against a glob `pattern` with `*`, `?`, and `\`
+/// escapes. Backtracks to the most recent `*` on a mismatch, which runs in
+/// `O(pattern * output)` time.
+fn glob_match(pattern: &[char], output: &[char]) -> bool {
+    let (mut p, mut o) = (0, 0);
+    // Position after the latest `*` in the pattern, and the position in the
+    // output that it currently matches up to.
+    let mut star: Option<(usize, usize)> = None;
+    while o < output.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                star = Some((p, o));
+                continue;
+            }
+            Some('?') => {
+                p += 1;
+                o += 1;
+                continue;
+            }
+            Some('\\') if pattern.get(p + 1) == Some(&output[o]) => {
+                p += 2;
+                o += 1;
+                continue;
+            }
+            Some(c) if *c != '\\' && *c == output[o] => {
+                p += 1;
+                o += 1;
+                continue;
+            }
+            _ => {}
+        }
+        match star {
+            Some((star_p, star_o)) => {
+                p = star_p;
+                o = star_o + 1;
+                star = Some((star_p, star_o + 1));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
 impl TestkindRun {
     const IDENT: &'static str = "run";
+
+    pub fn tolerance(&self) -> NumericTolerance {
+        NumericTolerance {
+            absolute: self.abs_tolerance,
+            relative: self.rel_tolerance,
+        }
+    }
 }
 
 /// Configuration for running a built binary to generate an assembly file,
@@ -43,6 +288,8 @@ pub struct TestkindGenASMAndRun {
     pub stderr: Vec<String>,
     pub stderr_trim: bool,
     pub stderr_strip_whitespace: bool,
+    #[serde(default)]
+    pub stderr_match: OutputMatch,
     pub input_files: Vec<String>,
     /// Suffixes for automatically discovering input files, e.g. ["*.cpp"]
     pub auto_input_files: Vec<String>,
@@ -63,13 +310,241 @@ pub struct TestkindGenASMAndRun {
     pub run_stdout: Vec<String>,
     pub run_stdout_trim: bool,
     pub run_stdout_strip_whitespace: bool,
+    #[serde(default)]
+    pub run_stdout_match: OutputMatch,
     pub run_stderr: Vec<String>,
     pub run_stderr_trim: bool,
     pub run_stderr_strip_whitespace: bool,
+    #[serde(default)]
+    pub run_stderr_match: OutputMatch,
+    /// Absolute and relative tolerance of numbers in outputs that are
+    /// matched with `OutputMatch::Numeric`
+    #[serde(default)]
+    pub abs_tolerance: f64,
+    #[serde(default)]
+    pub rel_tolerance: f64,
 }
 
 impl TestkindGenASMAndRun {
     const IDENT: &'static str = "gen_asm_and_run";
+
+    pub fn tolerance(&self) -> NumericTolerance {
+        NumericTolerance {
+            absolute: self.abs_tolerance,
+            relative: self.rel_tolerance,
+        }
+    }
+}
+
+/// Configuration for the same stages as `TestkindGenASMAndRun`, where the
+/// compiled binary is also run several times after its output has been
+/// checked, to compare its performance against thresholds.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TestkindGenASMAndBenchmark {
+    #[serde(flatten)]
+    pub gen_asm_and_run: TestkindGenASMAndRun,
+
+    /// Number of times to run the compiled binary when benchmarking. The
+    /// median of these runs is compared against the thresholds.
+    pub benchmark_runs: u32,
+    /// Maximum median runtime in milliseconds. 0 means that the runtime is
+    /// not checked.
+    pub benchmark_max_time_ms: u64,
+    /// Maximum median number of executed instructions. 0 means that the
+    /// instruction count is not checked.
+    pub benchmark_max_instructions: u64,
+    /// Command prefix for counting the instructions executed by the compiled
+    /// binary. <COUNT_FILE> is a template which will be replaced with the
+    /// file that the count is written to, in the CSV format of `perf stat`.
+    pub benchmark_instructions_cmd: Vec<String>,
+}
+
+impl TestkindGenASMAndBenchmark {
+    const IDENT: &'static str = "gen_asm_and_benchmark";
+
+    /// Defaults of "gen_asm_and_benchmark", which extend the defaults of
+    /// "gen_asm_and_run" with the benchmark options of
+    /// `[default.kind.gen_asm_and_benchmark]`.
+    fn from_gen_asm_and_run(
+        gen_asm_and_run: &TestkindGenASMAndRun,
+        benchmark: &TestkindBenchmarkDefault,
+    ) -> Self {
+        TestkindGenASMAndBenchmark {
+            gen_asm_and_run: gen_asm_and_run.to_owned(),
+            benchmark_runs: benchmark.benchmark_runs,
+            benchmark_max_time_ms: benchmark.benchmark_max_time_ms,
+            benchmark_max_instructions: benchmark.benchmark_max_instructions,
+            benchmark_instructions_cmd: benchmark.benchmark_instructions_cmd.clone(),
+        }
+    }
+}
+
+/// The benchmark options of `[default.kind.gen_asm_and_benchmark]`. Only
+/// these can be set there, the other defaults are those of
+/// `[default.kind.gen_asm_and_run]`. See `TestkindGenASMAndBenchmark`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TestkindBenchmarkDefault {
+    pub benchmark_runs: u32,
+    pub benchmark_max_time_ms: u64,
+    pub benchmark_max_instructions: u64,
+    pub benchmark_instructions_cmd: Vec<String>,
+}
+
+impl Default for TestkindBenchmarkDefault {
+    fn default() -> Self {
+        TestkindBenchmarkDefault {
+            benchmark_runs: 5,
+            benchmark_max_time_ms: 0,
+            benchmark_max_instructions: 0,
+            benchmark_instructions_cmd: [
+                "perf",
+                "stat",
+                "-x,",
+                "-e",
+                "instructions:u",
+                "-o",
+                "<COUNT_FILE>",
+                "--",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
+
+/// Configuration for running a built binary like `TestkindRun`, where the
+/// result is decided by a checker program provided by the course staff rather
+/// than by comparing against expected outputs. This allows for tests where
+/// many outputs are correct, e.g. any valid topological order.
+///
+/// The checker is run outside of the container once the binary has finished,
+/// with the templates below replaced in `checker_args`:
+///
+/// - `<STDIN_FILE>`: file with the text passed to standard input
+/// - `<STDOUT_FILE>`, `<STDERR_FILE>`: files with the output of the binary
+/// - `<EXPECTED_FILE>`: file with the `expected` data
+/// - `<CODE>`: the exit code of the binary
+/// - `<INPUT_FILES>`: the paths to the input files, as separate arguments
+///
+/// The output is accepted if the checker exits with code 0 and rejected if it
+/// exits with code 1, in which case its standard output is shown as the
+/// reason. Any other exit code means that the checker itself failed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TestkindChecker {
+    pub bin: String,
+    pub args: Vec<String>,
+    pub stdin: String,
+    pub stdin_ignore: bool,
+    pub input_files: Vec<String>,
+    /// Suffixes for automatically discovering input files, e.g. ["*.cpp"]
+    pub auto_input_files: Vec<String>,
+
+    /// Path to the checker executable, relative to the directory of the test
+    pub checker: String,
+    pub checker_args: Vec<String>,
+    /// Expected data that is passed to the checker, e.g. a reference answer
+    pub expected: String,
+}
+
+impl TestkindChecker {
+    const IDENT: &'static str = "checker";
+
+    /// Defaults used when `[default.kind.checker]` is absent, which extends
+    /// the defaults of "run".
+    fn from_run(run: &TestkindRun) -> Self {
+        TestkindChecker {
+            bin: run.bin.clone(),
+            args: run.args.clone(),
+            stdin: run.stdin.clone(),
+            stdin_ignore: run.stdin_ignore,
+            input_files: run.input_files.clone(),
+            auto_input_files: run.auto_input_files.clone(),
+            checker: "".to_string(),
+            checker_args: ["<STDIN_FILE>", "<STDOUT_FILE>", "<EXPECTED_FILE>"]
+                .map(String::from)
+                .to_vec(),
+            expected: "".to_string(),
+        }
+    }
+}
+
+/// Configuration for running a built binary like `TestkindRun`, where the
+/// expected exit code, standard output, and standard error are those of a
+/// reference solution provided by the course staff, run on the same
+/// arguments, standard input, and input files. This avoids writing down the
+/// expected output of every test case by hand.
+///
+/// The reference is run in a fresh container of the grading image, which is
+/// never shared with the graded solutions. Its outputs are cached by a hash
+/// of the reference executable and the inputs, such that it is only run once
+/// for each test case.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TestkindReference {
+    pub bin: String,
+    pub args: Vec<String>,
+    pub stdin: String,
+    pub stdin_ignore: bool,
+    pub input_files: Vec<String>,
+    /// Suffixes for automatically discovering input files, e.g. ["*.cpp"]
+    pub auto_input_files: Vec<String>,
+
+    /// Path to the reference executable, relative to the directory of the
+    /// test
+    pub reference: String,
+    pub code_ignore: bool,
+    pub stdout_ignore: bool,
+    pub stdout_trim: bool,
+    pub stdout_strip_whitespace: bool,
+    #[serde(default)]
+    pub stdout_match: OutputMatch,
+    pub stderr_ignore: bool,
+    pub stderr_trim: bool,
+    pub stderr_strip_whitespace: bool,
+    #[serde(default)]
+    pub stderr_match: OutputMatch,
+    /// Absolute and relative tolerance of numbers in outputs that are
+    /// matched with `OutputMatch::Numeric`
+    #[serde(default)]
+    pub abs_tolerance: f64,
+    #[serde(default)]
+    pub rel_tolerance: f64,
+}
+
+impl TestkindReference {
+    const IDENT: &'static str = "reference";
+
+    /// Defaults used when `[default.kind.reference]` is absent, which extends
+    /// the defaults of "run".
+    fn from_run(run: &TestkindRun) -> Self {
+        TestkindReference {
+            bin: run.bin.clone(),
+            args: run.args.clone(),
+            stdin: run.stdin.clone(),
+            stdin_ignore: run.stdin_ignore,
+            input_files: run.input_files.clone(),
+            auto_input_files: run.auto_input_files.clone(),
+            reference: "".to_string(),
+            code_ignore: false,
+            stdout_ignore: false,
+            stdout_trim: run.stdout_trim,
+            stdout_strip_whitespace: run.stdout_strip_whitespace,
+            stdout_match: OutputMatch::Exact,
+            stderr_ignore: true,
+            stderr_trim: run.stderr_trim,
+            stderr_strip_whitespace: run.stderr_strip_whitespace,
+            stderr_match: OutputMatch::Exact,
+            abs_tolerance: run.abs_tolerance,
+            rel_tolerance: run.rel_tolerance,
+        }
+    }
+
+    pub fn tolerance(&self) -> NumericTolerance {
+        NumericTolerance {
+            absolute: self.abs_tolerance,
+            relative: self.rel_tolerance,
+        }
+    }
 }
 
 /// Configuration for checking if a specific file exists, and that it is of the
@@ -89,7 +564,13 @@ impl TestkindCheckFileExists {
 pub struct TestkindDefault {
     pub run: TestkindRun,
     pub gen_asm_and_run: TestkindGenASMAndRun,
+    #[serde(default)]
+    pub gen_asm_and_benchmark: TestkindBenchmarkDefault,
     pub check_file_exists: TestkindCheckFileExists,
+    #[serde(default)]
+    pub checker: Option<TestkindChecker>,
+    #[serde(default)]
+    pub reference: Option<TestkindReference>,
 }
 
 impl TestkindDefault {
@@ -101,15 +582,36 @@ impl TestkindDefault {
             TestkindGenASMAndRun::IDENT => {
                 toml::Table::try_from(&self.gen_asm_and_run).map_err(Error::from)
             }
+            TestkindGenASMAndBenchmark::IDENT => {
+                toml::Table::try_from(TestkindGenASMAndBenchmark::from_gen_asm_and_run(
+                    &self.gen_asm_and_run,
+                    &self.gen_asm_and_benchmark,
+                ))
+                .map_err(Error::from)
+            }
             TestkindCheckFileExists::IDENT => {
                 toml::Table::try_from(&self.check_file_exists).map_err(Error::from)
             }
+            TestkindChecker::IDENT => match &self.checker {
+                Some(t) => toml::Table::try_from(t).map_err(Error::from),
+                None => {
+                    toml::Table::try_from(TestkindChecker::from_run(&self.run)).map_err(Error::from)
+                }
+            },
+            TestkindReference::IDENT => match &self.reference {
+                Some(t) => toml::Table::try_from(t).map_err(Error::from),
+                None => toml::Table::try_from(TestkindReference::from_run(&self.run))
+                    .map_err(Error::from),
+            },
             _ => Error::err_identifier(
                 ident,
                 vec![
                     TestkindRun::IDENT.to_string(),
                     TestkindGenASMAndRun::IDENT.to_string(),
+                    TestkindGenASMAndBenchmark::IDENT.to_string(),
                     TestkindCheckFileExists::IDENT.to_string(),
+                    TestkindChecker::IDENT.to_string(),
+                    TestkindReference::IDENT.to_string(),
                 ],
             ),
         }
@@ -121,7 +623,10 @@ impl TestkindDefault {
 pub enum Testkind {
     Run(TestkindRun),
     GenASMAndRun(TestkindGenASMAndRun),
+    GenASMAndBenchmark(TestkindGenASMAndBenchmark),
     CheckFileExists(TestkindCheckFileExists),
+    Checker(TestkindChecker),
+    Reference(TestkindReference),
 }
 
 impl Testkind {
@@ -163,7 +668,60 @@ impl Testkind {
             Self::GenASMAndRun(t) => {
                 find_input_files(&mut t.input_files, &t.auto_input_files, dir, prefix)
             }
+            Self::GenASMAndBenchmark(t) => find_input_files(
+                &mut t.gen_asm_and_run.input_files,
+                &t.gen_asm_and_run.auto_input_files,
+                dir,
+                prefix,
+            ),
             Self::CheckFileExists(_) => Ok(()),
+            Self::Checker(t) => {
+                find_input_files(&mut t.input_files, &t.auto_input_files, dir, prefix)
+            }
+            Self::Reference(t) => {
+                find_input_files(&mut t.input_files, &t.auto_input_files, dir, prefix)
+            }
+        }
+    }
+
+    /// Checks that the expected outputs are valid patterns for their matching
+    /// modes, and that the numeric tolerances are valid. Returns a
+    /// description of the first problem.
+    fn validate(&self) -> Result<(), String> {
+        fn check(key: &str, mode: OutputMatch, patterns: &[String]) -> Result<(), String> {
+            patterns
+                .iter()
+                .try_for_each(|p| mode.validate_pattern(p))
+                .map_err(|msg| format!("{key}: {msg}"))
+        }
+        fn check_reference(key: &str, mode: OutputMatch) -> Result<(), String> {
+            match mode {
+                OutputMatch::Regex | OutputMatch::Glob => Err(format!(
+                    "{key}: the output of the reference cannot be used as a pattern"
+                )),
+                OutputMatch::Exact | OutputMatch::Contains | OutputMatch::Numeric => Ok(()),
+            }
+        }
+        fn check_gen_asm_and_run(t: &TestkindGenASMAndRun) -> Result<(), String> {
+            t.tolerance().validate()?;
+            check("stderr", t.stderr_match, &t.stderr)?;
+            check("run_stdout", t.run_stdout_match, &t.run_stdout)?;
+            check("run_stderr", t.run_stderr_match, &t.run_stderr)
+        }
+        match self {
+            Self::Run(t) => {
+                t.tolerance().validate()?;
+                check("stdout", t.stdout_match, &t.stdout)?;
+                check("stderr", t.stderr_match, &t.stderr)
+            }
+            Self::GenASMAndRun(t) => check_gen_asm_and_run(t),
+            Self::GenASMAndBenchmark(t) => check_gen_asm_and_run(&t.gen_asm_and_run),
+            Self::CheckFileExists(_) | Self::Checker(_) => Ok(()),
+            Self::Reference(t) => {
+                t.tolerance().validate()?;
+                check_reference("stdout_match", t.stdout_match)?;
+                check_reference("stderr_match", t.stderr_match)
+            }
         }
     }
 }
@@ -204,9 +762,179 @@ pub struct TestDefault {
     /// allowed regardless.
     pub build_allowed_binary_mimetypes: Vec<String>,
 
+    /// Default number of test cases that may be graded concurrently inside the
+    /// container of a tag. Only "run", "gen_asm_and_run", "checker", and
+    /// "reference" test cases are graded concurrently.
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+
+    /// Default memory limit of the grading container, e.g. `"512m"`. Passed
+    /// to podman as `--memory`. No limit is applied if absent.
+    #[serde(default)]
+    pub memory_limit: Option<String>,
+
+    /// Default number of CPUs available to the grading container, e.g. `2.0`.
+    /// Passed to podman as `--cpus`. No limit is applied if absent.
+    #[serde(default)]
+    pub cpu_limit: Option<f64>,
+
+    /// Default maximum number of processes inside the grading container.
+    /// Passed to podman as `--pids-limit`. No limit is applied if absent.
+    #[serde(default)]
+    pub pids_limit: Option<u32>,
+
+    /// Default size of the writable space inside the grading container, e.g.
+    /// `"256m"`. If set, the build directory and `/tmp` are each mounted as a
+    /// tmpfs of this size. Note that files on a tmpfs also count towards the
+    /// memory limit.
+    #[serde(default)]
+    pub disk_limit: Option<String>,
+
     pub kind: TestkindDefault,
 }
 
+fn default_concurrency() -> usize {
+    1
+}
+
+impl TestDefault {
+    /// The default resource limits for grading containers.
+    pub fn limits(&self) -> ResourceLimits {
+        ResourceLimits {
+            memory: self.memory_limit.clone(),
+            cpus: self.cpu_limit,
+            pids: self.pids_limit,
+            disk: self.disk_limit.clone(),
+        }
+    }
+}
+
+/// Resource limits applied to the container that a tag is graded in. A value
+/// of `None` means that the resource is not limited.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResourceLimits {
+    /// Memory limit, e.g. `"512m"`
+    pub memory: Option<String>,
+
+    /// Number of CPUs
+    pub cpus: Option<f64>,
+
+    /// Maximum number of processes
+    pub pids: Option<u32>,
+
+    /// Size of the writable tmpfs mounts, e.g. `"256m"`
+    pub disk: Option<String>,
+}
+
+impl ResourceLimits {
+    /// Returns these limits with any limit specified in `other` taking
+    /// precedence.
+    fn overridden_by(&self, other: &ResourceLimits) -> ResourceLimits {
+        ResourceLimits {
+            memory: other.memory.clone().or_else(|| self.memory.clone()),
+            cpus: other.cpus.or(self.cpus),
+            pids: other.pids.or(self.pids),
+            disk: other.disk.clone().or_else(|| self.disk.clone()),
+        }
+    }
+
+    /// Checks that the limits can be passed on to podman. Returns a
+    /// description of the first invalid limit that is found.
+    fn validate(&self) -> Result<(), String> {
+        /// Sizes are a number followed by an optional unit (b, k, m, or g).
+        fn size_is_valid(size: &str) -> bool {
+            let digits = size.trim_end_matches(|c: char| "bkmgBKMG".contains(c));
+            size.len() - digits.len() <= 1
+                && !digits.is_empty()
+                && digits.chars().all(|c| c.is_ascii_digit())
+        }
+
+        if let Some(memory) = &self.memory {
+            if !size_is_valid(memory) {
+                return Err(format!("invalid memory_limit \"{memory}\""));
+            }
+        }
+        if let Some(cpus) = self.cpus {
+            if cpus.is_nan() || cpus <= 0.0 {
+                return Err(format!("cpu_limit must be positive, got {cpus}"));
+            }
+        }
+        if self.pids == Some(0) {
+            return Err("pids_limit must be at least 1".to_string());
+        }
+        if let Some(disk) = &self.disk {
+            if !size_is_valid(disk) {
+                return Err(format!("invalid disk_limit \"{disk}\""));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Deadline and late-submission policy of a tag. Without a deadline, every
+/// submission is on time.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatePolicy {
+    /// Submissions made after this time are late.
+    pub deadline: Option<DateTime<Utc>>,
+
+    /// Late submissions made after this time are not accepted. If `None`,
+    /// late submissions are always accepted.
+    pub late_until: Option<DateTime<Utc>>,
+
+    /// Fraction of the score that should be deducted for a late submission.
+    /// This is only recorded in the results, it is up to the course staff to
+    /// apply it.
+    pub late_penalty: Option<f64>,
+}
+
+impl LatePolicy {
+    /// Returns this policy with any option specified in `other` taking
+    /// precedence.
+    fn overridden_by(&self, other: &LatePolicy) -> LatePolicy {
+        LatePolicy {
+            deadline: other.deadline.or(self.deadline),
+            late_until: other.late_until.or(self.late_until),
+            late_penalty: other.late_penalty.or(self.late_penalty),
+        }
+    }
+
+    /// Checks that the policy is consistent. Returns a description of the
+    /// first problem that is found.
+    fn validate(&self) -> Result<(), String> {
+        match (self.deadline, self.late_until) {
+            (None, Some(_)) => return Err("late_until requires a deadline".to_string()),
+            (Some(deadline), Some(late_until)) if late_until < deadline => {
+                return Err("late_until must not be before the deadline".to_string());
+            }
+            _ => {}
+        }
+        if let Some(penalty) = self.late_penalty {
+            if self.deadline.is_none() {
+                return Err("late_penalty requires a deadline".to_string());
+            }
+            if !(0.0..=1.0).contains(&penalty) {
+                return Err(format!(
+                    "late_penalty must be between 0 and 1, got {penalty}"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if a submission made at `submitted` is late.
+    pub fn is_late(&self, submitted: &DateTime<Utc>) -> bool {
+        self.deadline.is_some_and(|deadline| *submitted > deadline)
+    }
+
+    /// Returns `true` if a submission made at `submitted` is accepted, i.e.
+    /// that it is not made after `late_until`.
+    pub fn accepts(&self, submitted: &DateTime<Utc>) -> bool {
+        self.late_until
+            .is_none_or(|late_until| *submitted <= late_until)
+    }
+}
+
 /// Configuration related to building the project for a test tag.
 #[derive(Debug, Clone)]
 pub struct TagBuildConfig {
@@ -237,6 +965,10 @@ pub struct TagConfig {
     pub name: String,
     pub dirs: Vec<String>,
     pub build: TagBuildConfig,
+    pub concurrency: usize,
+    pub limits: ResourceLimits,
+    pub pass_threshold: Option<f64>,
+    pub late_policy: LatePolicy,
 }
 
 /// A test case to run.
@@ -246,6 +978,10 @@ pub struct Test {
     pub description: Option<String>,
     pub timeout: u32,
     pub kind: Testkind,
+
+    /// Points awarded for passing this test case. `None` means that the test
+    /// case is worth 1 point.
+    pub points: Option<f64>,
 }
 
 /// A group of test cases to run. Can also involve several subtests.
@@ -255,6 +991,20 @@ pub struct TestGroup {
     pub description: Option<String>,
     pub tests: Vec<Test>,
     pub subgroups: Vec<TestGroup>,
+
+    /// Total points of this group. If set, the points of the test cases and
+    /// subgroups within are scaled such that they sum up to the weight.
+    pub weight: Option<f64>,
+}
+
+impl TestGroup {
+    /// Returns `true` if points or weights are specified anywhere within this
+    /// group.
+    fn has_points(&self) -> bool {
+        self.weight.is_some()
+            || self.tests.iter().any(|t| t.points.is_some())
+            || self.subgroups.iter().any(|sg| sg.has_points())
+    }
 }
 
 /// A test tag that can be invoked and graded.
@@ -263,6 +1013,28 @@ pub struct Tag {
     pub name: String,
     pub test_groups: Vec<TestGroup>,
     pub build: TagBuildConfig,
+
+    /// Maximum number of test cases to grade concurrently for this tag.
+    pub concurrency: usize,
+
+    /// Resource limits of the container that this tag is graded in.
+    pub limits: ResourceLimits,
+
+    /// Fraction of the maximum score that is needed for this tag to pass. If
+    /// `None`, every test case has to pass.
+    pub pass_threshold: Option<f64>,
+
+    /// Deadline and late-submission policy of this tag.
+    pub late_policy: LatePolicy,
+}
+
+impl Tag {
+    /// Returns `true` if a score should be computed when grading this tag,
+    /// which is the case if it has a pass threshold or if points or weights
+    /// are specified for any of its tests.
+    pub fn is_scored(&self) -> bool {
+        self.pass_threshold.is_some() || self.test_groups.iter().any(|tg| tg.has_points())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -286,6 +1058,64 @@ impl Tests {
         struct _UntreatedExtensibleTag {
             extends: String,
             dirs: Vec<String>,
+            concurrency: Option<usize>,
+            pass_threshold: Option<f64>,
+            #[serde(flatten)]
+            limits: _UntreatedLimits,
+            #[serde(flatten)]
+            late_policy: _UntreatedLatePolicy,
+        }
+
+        #[derive(Deserialize, Debug, Clone)]
+        struct _UntreatedLimits {
+            memory_limit: Option<String>,
+            cpu_limit: Option<f64>,
+            pids_limit: Option<u32>,
+            disk_limit: Option<String>,
+        }
+
+        impl _UntreatedLimits {
+            fn into_limits(self) -> ResourceLimits {
+                ResourceLimits {
+                    memory: self.memory_limit,
+                    cpus: self.cpu_limit,
+                    pids: self.pids_limit,
+                    disk: self.disk_limit,
+                }
+            }
+        }
+
+        #[derive(Deserialize, Debug, Clone)]
+        struct _UntreatedLatePolicy {
+            // Datetimes are passed on as strings when flattened
+            deadline: Option<String>,
+            late_until: Option<String>,
+            late_penalty: Option<f64>,
+        }
+
+        impl _UntreatedLatePolicy {
+            fn into_late_policy(self) -> Result<LatePolicy, String> {
+                /// Dates must include an offset, such that the deadline does
+                /// not depend on the time zone of the runner.
+                fn to_utc(key: &str, dt: String) -> Result<DateTime<Utc>, String> {
+                    DateTime::parse_from_rfc3339(&dt)
+                        .map(|d| d.with_timezone(&Utc))
+                        .map_err(|_| {
+                            format!(
+                                "{key} must be a date and time with an offset, e.g. 2025-01-31T23:59:00+01:00, got {dt}"
+                            )
+                        })
+                }
+
+                Ok(LatePolicy {
+                    deadline: self.deadline.map(|dt| to_utc("deadline", dt)).transpose()?,
+                    late_until: self
+                        .late_until
+                        .map(|dt| to_utc("late_until", dt))
+                        .transpose()?,
+                    late_penalty: self.late_penalty,
+                })
+            }
         }
 
         #[derive(Deserialize, Debug, Clone)]
@@ -302,6 +1132,12 @@ impl Tests {
         struct _UntreatedTag {
             dirs: Vec<String>,
             build: _UntreatedTagBuild,
+            concurrency: Option<usize>,
+            pass_threshold: Option<f64>,
+            #[serde(flatten)]
+            limits: _UntreatedLimits,
+            #[serde(flatten)]
+            late_policy: _UntreatedLatePolicy,
         }
 
         log::debug!("Loading root test configuration from {path}");
@@ -331,6 +1167,16 @@ impl Tests {
                                     name: name.to_owned(),
                                     dirs: [t_found.dirs.to_owned(), uetg.dirs].concat(),
                                     build: t_found.build.to_owned(),
+                                    concurrency: uetg.concurrency.unwrap_or(t_found.concurrency),
+                                    limits: t_found
+                                        .limits
+                                        .overridden_by(&uetg.limits.into_limits()),
+                                    pass_threshold: uetg.pass_threshold.or(t_found.pass_threshold),
+                                    late_policy: t_found.late_policy.overridden_by(
+                                        &uetg.late_policy.into_late_policy().map_err(|msg| {
+                                            Error::test_config_msg(msg).tag(name).path(path)
+                                        })?,
+                                    ),
                                 };
                                 log::debug!("Found tag {t:?}");
                                 found.push(name.to_string());
@@ -362,6 +1208,15 @@ impl Tests {
                                             ut.default.build_allowed_binary_mimetypes.clone(),
                                         ),
                                 },
+                                concurrency: utg.concurrency.unwrap_or(ut.default.concurrency),
+                                limits: ut
+                                    .default
+                                    .limits()
+                                    .overridden_by(&utg.limits.into_limits()),
+                                pass_threshold: utg.pass_threshold,
+                                late_policy: utg.late_policy.into_late_policy().map_err(|msg| {
+                                    Error::test_config_msg(msg).tag(name).path(path)
+                                })?,
                             };
                             log::debug!("Found tag {t:?}");
                             found.push(name.to_string());
@@ -443,6 +1298,7 @@ impl Tests {
 struct _UntreatedTest {
     pub kind: Option<String>,
     pub timeout: Option<u32>,
+    pub points: Option<f64>,
     pub options: Option<toml::Table>,
 }
 
@@ -454,6 +1310,7 @@ impl _UntreatedTest {
             let mut new_ut = self.clone();
             new_ut.kind = ut.kind.clone().or_else(|| self.kind.clone());
             new_ut.timeout = ut.timeout.or(self.timeout);
+            new_ut.points = ut.points.or(self.points);
             if let Some(opts) = &ut.options {
                 if let Some(ut_opts) = &self.options {
                     // Override options from ut_opts
@@ -495,10 +1352,38 @@ impl TagConfig {
                 .into());
         }
 
+        if self.concurrency == 0 {
+            return Err(Error::test_config_msg("concurrency must be at least 1")
+                .tag(&self.name)
+                .into());
+        }
+
+        if let Err(msg) = self.limits.validate() {
+            return Err(Error::test_config_msg(msg).tag(&self.name).into());
+        }
+
+        if let Err(msg) = self.late_policy.validate() {
+            return Err(Error::test_config_msg(msg).tag(&self.name).into());
+        }
+
+        if let Some(threshold) = self.pass_threshold {
+            if !(0.0..=1.0).contains(&threshold) {
+                return Err(Error::test_config_msg(format!(
+                    "pass_threshold must be between 0 and 1, got {threshold}"
+                ))
+                .tag(&self.name)
+                .into());
+            }
+        }
+
         let mut t = Tag {
             name: self.name.to_owned(),
             test_groups: vec![],
             build: self.build.to_owned(),
+            concurrency: self.concurrency,
+            limits: self.limits.to_owned(),
+            pass_threshold: self.pass_threshold,
+            late_policy: self.late_policy.to_owned(),
         };
 
         log::debug!("Converting each directory to a test group");
@@ -511,6 +1396,7 @@ impl TagConfig {
                 &_UntreatedTest {
                     kind: None,
                     timeout: None,
+                    points: None,
                     options: None,
                 },
                 vec![],
@@ -555,9 +1441,15 @@ impl TestGroup {
             pub title: Option<String>,
             pub description: Option<String>,
             pub include: Option<Vec<String>>,
+            pub weight: Option<f64>,
             pub test: Option<_UntreatedTest>,
         }
 
+        /// Points and weights must be non-negative numbers.
+        fn points_are_valid(points: Option<f64>) -> bool {
+            points.is_none_or(|p| p.is_finite() && p >= 0.0)
+        }
+
         let config_path = path_join(dir, "config.toml")?;
 
         let mut tc_err = Error::test_config().path(&config_path);
@@ -577,6 +1469,10 @@ impl TestGroup {
                 .with_cause(Box::new(e))
         })?;
 
+        if !points_are_valid(utg.weight) {
+            return Err(tc_err.msg("weight must be a non-negative number").into());
+        }
+
         // Setting up the defaults for this test group
         let testgroup_defaults = test_defaults.merge(&utg.test);
 
@@ -599,6 +1495,7 @@ impl TestGroup {
             description: utg.description.map(single_linefeed_to_space),
             tests: vec![],
             subgroups: vec![],
+            weight: utg.weight,
         };
 
         // Find all the test cases in the same directory
@@ -666,7 +1563,16 @@ impl TestGroup {
                             .with_cause(Box::new(e))
                     })?;
 
+                if test_contents.weight.is_some() {
+                    return Err(tc_err
+                        .msg("weight can only be set for test groups, use test.points instead")
+                        .into());
+                }
+
                 let testkind_opts = testgroup_defaults.clone().merge(&test_contents.test);
+                if !points_are_valid(testkind_opts.points) {
+                    return Err(tc_err.msg("points must be a non-negative number").into());
+                }
 
                 let kind_ident = testkind_opts
                     .kind
@@ -693,13 +1599,49 @@ impl TestGroup {
                 let mut tk = match kind_ident.as_str() {
                     TestkindRun::IDENT => Testkind::Run(run_opts.try_into()?),
                     TestkindGenASMAndRun::IDENT => Testkind::GenASMAndRun(run_opts.try_into()?),
+                    TestkindGenASMAndBenchmark::IDENT => {
+                        let t: TestkindGenASMAndBenchmark = run_opts.try_into()?;
+                        if t.benchmark_runs == 0 {
+                            return Err(tc_err.msg("benchmark_runs must be at least 1").into());
+                        }
+                        Testkind::GenASMAndBenchmark(t)
+                    }
                     TestkindCheckFileExists::IDENT => {
                         Testkind::CheckFileExists(run_opts.try_into()?)
                     }
+                    TestkindChecker::IDENT => {
+                        let mut t: TestkindChecker = run_opts.try_into()?;
+                        if t.checker.is_empty() {
+                            return Err(tc_err.msg("no checker provided").into());
+                        }
+                        t.checker = path_absolute_join(dir, &t.checker)?;
+                        if !Path::new(&t.checker).is_file() {
+                            return Err(tc_err
+                                .msg(format!("checker \"{}\" does not exist", t.checker))
+                                .into());
+                        }
+                        Testkind::Checker(t)
+                    }
+                    TestkindReference::IDENT => {
+                        let mut t: TestkindReference = run_opts.try_into()?;
+                        if t.reference.is_empty() {
+                            return Err(tc_err.msg("no reference provided").into());
+                        }
+                        t.reference = path_absolute_join(dir, &t.reference)?;
+                        if !Path::new(&t.reference).is_file() {
+                            return Err(tc_err
+                                .msg(format!("reference \"{}\" does not exist", t.reference))
+                                .into());
+                        }
+                        Testkind::Reference(t)
+                    }
                     _ => return Err(tc_err.msg("invalid test kind").into()),
                 };
 
                 tk.auto_discover_input_files(dir, prefix)?;
+                if let Err(msg) = tk.validate() {
+                    return Err(tc_err.msg(msg).into());
+                }
 
                 tg.tests.push(Test {
                     name: prefix.to_string(),
@@ -709,6 +1651,7 @@ impl TestGroup {
                         .or(tg.description.clone()),
                     timeout: testkind_opts.timeout.unwrap_or(defaults.timeout_test),
                     kind: tk,
+                    points: testkind_opts.points,
                 });
             }
         }
@@ -768,9 +1711,259 @@ mod tests {
         assert_that!(tests.default.max_output).is_equal_to(4194304);
         assert_that!(tests.default.truncate_len).is_equal_to(2000);
         assert_that!(tests.default.shown_failures).is_equal_to(3);
+        assert_that!(tests.default.concurrency).is_equal_to(1);
         assert_that!(tests.default.build_prohibit_binary_files).is_true();
     }
 
+    #[test]
+    fn test_example_tag_concurrency() {
+        let tests = Tests::load(EXAMPLE_TESTS_TOML).expect("Failed to load example tests.toml");
+
+        // Tags without a concurrency level fall back to the default
+        let hello_tag = &tests.tag_groups.get("hello").expect("hello tag not found")[0];
+        assert_that!(hello_tag.concurrency).is_equal_to(1);
+
+        let hello_extra = &tests
+            .tag_groups
+            .get("hello-extra")
+            .expect("hello-extra tag not found")[0];
+        assert_that!(hello_extra.concurrency).is_equal_to(4);
+    }
+
+    #[test]
+    fn test_example_tag_limits() {
+        let tests = Tests::load(EXAMPLE_TESTS_TOML).expect("Failed to load example tests.toml");
+
+        // Tags without their own limits use the defaults
+        let hello_tag = &tests.tag_groups.get("hello").expect("hello tag not found")[0];
+        assert_that!(hello_tag.limits.clone()).is_equal_to(tests.default.limits());
+        assert_that!(hello_tag.limits.memory.clone()).is_equal_to(Some("1g".to_string()));
+        assert_that!(hello_tag.limits.pids).is_equal_to(Some(256));
+
+        // Overrides only replace the limits that are specified
+        let hello_extra = &tests
+            .tag_groups
+            .get("hello-extra")
+            .expect("hello-extra tag not found")[0];
+        assert_that!(hello_extra.limits.memory.clone()).is_equal_to(Some("2g".to_string()));
+        assert_that!(hello_extra.limits.pids).is_equal_to(Some(256));
+    }
+
+    #[test]
+    fn test_example_tag_scoring() {
+        let tests = Tests::load(EXAMPLE_TESTS_TOML).expect("Failed to load example tests.toml");
+
+        // Tags without points, weights, or a pass threshold are not scored
+        let hello_tag = &tests.tag_groups.get("hello").expect("hello tag not found")[0];
+        assert_that!(hello_tag.pass_threshold).is_equal_to(None);
+        assert_that!(hello_tag.is_scored()).is_false();
+
+        let hello_extra = &tests
+            .tag_groups
+            .get("hello-extra")
+            .expect("hello-extra tag not found")[0];
+        assert_that!(hello_extra.pass_threshold).is_equal_to(Some(0.9));
+        assert_that!(hello_extra.is_scored()).is_true();
+
+        let he_3code = &hello_extra.test_groups[0].subgroups[2];
+        assert_eq!(he_3code.title, "3. Code Only");
+        assert_that!(he_3code.weight).is_equal_to(Some(3.0));
+        let points: Vec<(&str, Option<f64>)> = he_3code
+            .tests
+            .iter()
+            .map(|t| (t.name.as_str(), t.points))
+            .collect();
+        assert_eq!(
+            points,
+            vec![("code0", None), ("code10", None), ("code15", Some(2.0))]
+        );
+    }
+
+    #[test]
+    fn test_resource_limits_validate() {
+        let ok = ResourceLimits {
+            memory: Some("512m".to_string()),
+            cpus: Some(1.5),
+            pids: Some(64),
+            disk: Some("1024".to_string()),
+        };
+        assert_that!(ok.validate()).is_ok();
+
+        for bad in [
+            ResourceLimits {
+                memory: Some("512mb".to_string()),
+                ..ok.clone()
+            },
+            ResourceLimits {
+                memory: Some("m".to_string()),
+                ..ok.clone()
+            },
+            ResourceLimits {
+                cpus: Some(0.0),
+                ..ok.clone()
+            },
+            ResourceLimits {
+                pids: Some(0),
+                ..ok.clone()
+            },
+            ResourceLimits {
+                disk: Some("1g,exec".to_string()),
+                ..ok.clone()
+            },
+        ] {
+            assert_that!(bad.validate()).is_err();
+        }
+    }
+
+    #[test]
+    fn test_output_match() {
+        assert_that!(OutputMatch::Exact.is_match("abc", "abc")).is_true();
+        assert_that!(OutputMatch::Exact.is_match("abcd", "abc")).is_false();
+        assert_that!(OutputMatch::Contains.is_match("xabcx", "abc")).is_true();
+        assert_that!(OutputMatch::Contains.is_match("xabx", "abc")).is_false();
+
+        // Regexes have to match the whole output
+        assert_that!(OutputMatch::Regex.is_match("0x7ffd", "0x[0-9a-f]+")).is_true();
+        assert_that!(OutputMatch::Regex.is_match("ptr 0x7ffd", "0x[0-9a-f]+")).is_false();
+        assert_that!(OutputMatch::Regex.is_match("a\nb", "a|a\nb")).is_true();
+
+        for (output, pattern, expected) in [
+            ("", "", true),
+            ("", "*", true),
+            ("abc", "a*", true),
+            ("abc", "*c", true),
+            ("abc", "a?c", true),
+            ("a\nc", "a?c", true),
+            ("abc", "a*b*c", true),
+            ("abbbc", "a*bc", true),
+            ("abc", "ab", false),
+            ("abc", "a?", false),
+            ("a*c", "a\\*c", true),
+            ("abc", "a\\*c", false),
+            ("a?", "a\\?", true),
+            ("a\\", "a\\\\", true),
+        ] {
+            assert_that!(OutputMatch::Glob.is_match(output, pattern))
+                .named(format!("glob {pattern:?} on {output:?}"))
+                .is_equal_to(expected);
+        }
+
+        assert_that!(OutputMatch::Regex.validate_pattern("(a|b")).is_err();
+        assert_that!(OutputMatch::Glob.validate_pattern("a\\")).is_err();
+        assert_that!(OutputMatch::Glob.validate_pattern("a\\\\")).is_ok();
+        assert_that!(OutputMatch::Exact.validate_pattern("(a|b")).is_ok();
+    }
+
+    #[test]
+    fn test_numeric_tolerance() {
+        let exact = NumericTolerance::default();
+        assert_that!(exact.is_match("x = 1.50,  y = 2", "x = 1.5, y = 2.0")).is_true();
+        assert_that!(exact.is_match("x = 1.5", "x = 1.50001")).is_false();
+        assert_that!(exact.is_match("x: 1.5", "x = 1.5")).is_false();
+        assert_that!(exact.is_match("1.5 2", "1.5")).is_false();
+        assert_that!(exact.is_match("inf nan", "inf nan")).is_true();
+
+        let absolute = NumericTolerance {
+            absolute: 0.01,
+            relative: 0.0,
+        };
+        assert_that!(absolute.is_match("sum=0.333", "sum=0.33")).is_true();
+        assert_that!(absolute.is_match("sum=0.35", "sum=0.33")).is_false();
+        assert_that!(absolute.is_match("-1e-3", "0.005")).is_true();
+        assert_that!(absolute.numbers_equal(f64::NAN, f64::NAN)).is_false();
+        assert_that!(absolute.numbers_equal(f64::INFINITY, f64::INFINITY)).is_true();
+
+        let relative = NumericTolerance {
+            absolute: 0.0,
+            relative: 1e-6,
+        };
+        assert_that!(relative.is_match("1000000.9", "1000000")).is_true();
+        assert_that!(relative.is_match("1.9", "1")).is_false();
+        assert_that!(relative.numbers_equal(f64::INFINITY, 1.0)).is_false();
+        assert_that!(relative.is_match("1e999", "1")).is_false();
+
+        assert_that!(OutputMatch::Numeric.is_match_with_tolerance("(0.1)", "(0.11)", &absolute))
+            .is_true();
+        assert_that!(OutputMatch::Numeric.is_match("(0.1)", "(0.11)")).is_false();
+    }
+
+    #[test]
+    fn test_testkind_validate() {
+        let tests = Tests::load(EXAMPLE_TESTS_TOML).expect("Failed to load example tests.toml");
+
+        let mut run = tests.default.kind.run.clone();
+        run.stdout = vec!["(unclosed".to_string()];
+        assert_that!(Testkind::Run(run.clone()).validate()).is_ok();
+        run.stdout_match = OutputMatch::Regex;
+        assert_that!(Testkind::Run(run).validate()).is_err();
+
+        let mut gen_asm_and_run = tests.default.kind.gen_asm_and_run.clone();
+        gen_asm_and_run.run_stderr = vec!["[".to_string()];
+        gen_asm_and_run.run_stderr_match = OutputMatch::Regex;
+        assert_that!(Testkind::GenASMAndRun(gen_asm_and_run).validate()).is_err();
+
+        let mut run = tests.default.kind.run.clone();
+        run.rel_tolerance = -1.0;
+        assert_that!(Testkind::Run(run).validate()).is_err();
+    }
+
+    #[test]
+    fn test_example_tag_late_policy() {
+        let tests = Tests::load(EXAMPLE_TESTS_TOML).expect("Failed to load example tests.toml");
+
+        // Tags without a deadline accept every submission on time
+        let hello_tag = &tests.tag_groups.get("hello").expect("hello tag not found")[0];
+        assert_that!(hello_tag.late_policy.clone()).is_equal_to(LatePolicy::default());
+
+        let hello_asm = &tests
+            .tag_groups
+            .get("hello-asm")
+            .expect("hello-asm tag not found")[0];
+        let policy = &hello_asm.late_policy;
+        let utc = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        assert_that!(policy.deadline).is_equal_to(Some(utc("2025-01-31T22:59:00Z")));
+        assert_that!(policy.late_until).is_equal_to(Some(utc("2025-02-07T22:59:00Z")));
+        assert_that!(policy.late_penalty).is_equal_to(Some(0.1));
+
+        let on_time = utc("2025-01-31T22:59:00Z");
+        assert_that!(policy.is_late(&on_time)).is_false();
+        assert_that!(policy.accepts(&on_time)).is_true();
+        let late = utc("2025-02-01T12:00:00Z");
+        assert_that!(policy.is_late(&late)).is_true();
+        assert_that!(policy.accepts(&late)).is_true();
+        let too_late = utc("2025-02-07T23:00:00Z");
+        assert_that!(policy.is_late(&too_late)).is_true();
+        assert_that!(policy.accepts(&too_late)).is_false();
+    }
+
+    #[test]
+    fn test_late_policy_validate() {
+        let utc = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        let ok = LatePolicy {
+            deadline: Some(utc("2025-01-31T23:59:00Z")),
+            late_until: Some(utc("2025-02-07T23:59:00Z")),
+            late_penalty: Some(0.25),
+        };
+        assert_that!(ok.validate()).is_ok();
+
+        for bad in [
+            LatePolicy {
+                deadline: None,
+                ..ok.clone()
+            },
+            LatePolicy {
+                late_until: Some(utc("2025-01-30T23:59:00Z")),
+                ..ok.clone()
+            },
+            LatePolicy {
+                late_penalty: Some(1.5),
+                ..ok.clone()
+            },
+        ] {
+            assert_that!(bad.validate()).is_err();
+        }
+    }
+
     #[test]
     fn test_example_tags_exist() {
         let tests = Tests::load(EXAMPLE_TESTS_TOML).expect("Failed to load example tests.toml");
@@ -903,6 +2096,123 @@ mod tests {
         assert_that!(&asm_config.run_code).contains_exactly(&[0i32]);
     }
 
+    #[test]
+    fn test_example_kind_gen_asm_and_benchmark() {
+        let tests = Tests::load(EXAMPLE_TESTS_TOML).expect("Failed to load example tests.toml");
+
+        let hello_asm = tests
+            .tag_groups
+            .get("hello-asm")
+            .expect("hello-asm tag not found");
+        let bench_test = hello_asm[0].test_groups[0]
+            .tests
+            .iter()
+            .find(|t| t.name == "genasm-bench")
+            .expect("genasm-bench test not found");
+        let Testkind::GenASMAndBenchmark(bench) = &bench_test.kind else {
+            panic!("Expected Testkind::GenASMAndBenchmark");
+        };
+        // Options from config.toml and the defaults are inherited
+        assert_that!(bench.gen_asm_and_run.bin.as_str()).is_equal_to("hello-asm");
+        assert_eq!(bench.gen_asm_and_run.run_stdout, vec!["Hello, World!"]);
+        assert_eq!(bench.gen_asm_and_run.input_files.len(), 1);
+        assert_that!(bench.benchmark_runs).is_equal_to(3);
+        assert_that!(bench.benchmark_max_time_ms).is_equal_to(100);
+        assert_that!(bench.benchmark_max_instructions).is_equal_to(0);
+
+        // The defaults of gen_asm_and_run are extended with the benchmark
+        // options, which have defaults of their own
+        assert_that!(tests.default.kind.gen_asm_and_benchmark.benchmark_runs).is_equal_to(5);
+        let mut kind_default = tests.default.kind.clone();
+        kind_default.gen_asm_and_benchmark = TestkindBenchmarkDefault::default();
+        assert_that!(toml::from_str::<TestkindBenchmarkDefault>(
+            "bin = \"cigrid\""
+        ))
+        .is_err();
+        let fallback: TestkindGenASMAndBenchmark = kind_default
+            .toml_from_ident(TestkindGenASMAndBenchmark::IDENT)
+            .expect("Failed to get gen_asm_and_benchmark defaults")
+            .try_into()
+            .expect("Failed to parse gen_asm_and_benchmark defaults");
+        assert_that!(fallback.gen_asm_and_run.bin.as_str()).is_equal_to("cigrid");
+        assert_that!(fallback.benchmark_runs).is_equal_to(5);
+        assert_that!(fallback.benchmark_instructions_cmd.is_empty()).is_false();
+    }
+
+    #[test]
+    fn test_example_kind_checker() {
+        let tests = Tests::load(EXAMPLE_TESTS_TOML).expect("Failed to load example tests.toml");
+
+        let hello_file = tests
+            .tag_groups
+            .get("hello-file")
+            .expect("hello-file tag not found");
+        let checker_test = hello_file[0].test_groups[0]
+            .tests
+            .iter()
+            .find(|t| t.name == "4-hello-file-checker")
+            .expect("4-hello-file-checker test not found");
+        let Testkind::Checker(checker) = &checker_test.kind else {
+            panic!("Expected Testkind::Checker");
+        };
+        assert_that!(checker.bin.as_str()).is_equal_to("hello-file");
+        assert_eq!(checker.checker_args, vec!["<STDOUT_FILE>"]);
+        // The checker is relative to the directory of the test
+        assert_that!(Path::new(&checker.checker).is_absolute()).is_true();
+        assert_that!(checker.checker.as_str()).ends_with("/hello-file/check-greeting.sh");
+
+        // Without explicit defaults, those of run are used
+        let mut kind_default = tests.default.kind.clone();
+        kind_default.checker = None;
+        let fallback: TestkindChecker = kind_default
+            .toml_from_ident(TestkindChecker::IDENT)
+            .expect("Failed to get checker defaults")
+            .try_into()
+            .expect("Failed to parse checker defaults");
+        assert_that!(fallback.bin.as_str()).is_equal_to("cigrid");
+        assert_that!(fallback.checker.as_str()).is_equal_to("");
+    }
+
+    #[test]
+    fn test_example_kind_reference() {
+        let tests = Tests::load(EXAMPLE_TESTS_TOML).expect("Failed to load example tests.toml");
+
+        let hello_file = tests
+            .tag_groups
+            .get("hello-file")
+            .expect("hello-file tag not found");
+        let reference_test = hello_file[0].test_groups[0]
+            .tests
+            .iter()
+            .find(|t| t.name == "5-hello-file-reference")
+            .expect("5-hello-file-reference test not found");
+        let Testkind::Reference(reference) = &reference_test.kind else {
+            panic!("Expected Testkind::Reference");
+        };
+        assert_that!(reference.bin.as_str()).is_equal_to("hello-file");
+        assert_that!(reference.stdout_ignore).is_false();
+        assert_that!(reference.stderr_ignore).is_true();
+        // The reference is relative to the directory of the test
+        assert_that!(Path::new(&reference.reference).is_absolute()).is_true();
+        assert_that!(reference.reference.as_str()).ends_with("/hello-file/hello-reference.sh");
+
+        // The output of the reference can not be used as a pattern
+        let mut regex = reference.clone();
+        regex.stdout_match = OutputMatch::Regex;
+        assert_that!(Testkind::Reference(regex).validate()).is_err();
+
+        // Without explicit defaults, those of run are used
+        let mut kind_default = tests.default.kind.clone();
+        kind_default.reference = None;
+        let fallback: TestkindReference = kind_default
+            .toml_from_ident(TestkindReference::IDENT)
+            .expect("Failed to get reference defaults")
+            .try_into()
+            .expect("Failed to parse reference defaults");
+        assert_that!(fallback.bin.as_str()).is_equal_to("cigrid");
+        assert_that!(fallback.reference.as_str()).is_equal_to("");
+    }
+
     #[test]
     fn test_example_allowed_binary_files() {
         let tests = Tests::load(EXAMPLE_TESTS_TOML).expect("Failed to load example tests.toml");
@@ -7,24 +7,179 @@ use diesel::{
 use num_traits::FromPrimitive;
 use rand::Rng;
 use serde::Deserialize;
-use std::time::SystemTime;
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    time::{Duration, SystemTime},
+};
 
 use crate::{
-    config::Settings,
+    config::{
+        settings::{IncrementalReporting, RateLimitSettings},
+        Settings,
+    },
     db::models::{
-        NewSubmission, Submission, SubmissionInfo, SubmissionInfoGitHub, SubmissionInfoGitLab,
-        SubmissionSource, SubmissionSourceGitHub, SubmissionSourceGitLab, SubmissionSourceKind,
-        SubmissionStatusCode,
+        CanvasSyncedGrade, NewSubmission, Submission, SubmissionInfo, SubmissionInfoGit,
+        SubmissionInfoGitHub, SubmissionInfoGitLab, SubmissionInfoGitea, SubmissionSource,
+        SubmissionSourceGit, SubmissionSourceGitHub, SubmissionSourceGitLab, SubmissionSourceGitea,
+        SubmissionSourceKind, SubmissionStatusCode,
     },
     error::Error,
-    github, gitlab,
-    reporting::Report,
+    gitea, github, gitlab,
+    gradebook::{GradebookRecord, GradedTag},
+    reporting::{DetailsTagScore, Report, ReportMessage, ReportWrapper},
+    subrunner::progress::SubmissionProgress,
 };
 
 pub struct DatabaseConnection {
     pub conn: PgConnection,
 }
 
+/// Selection of submissions to re-grade. Empty lists and `None` values do not
+/// restrict the selection.
+#[derive(Debug, Clone, Default)]
+pub struct RegradeFilter {
+    /// Grading tags to re-grade. Submissions are selected if they graded any
+    /// of these tags, and only these tags are re-graded.
+    pub tags: Vec<String>,
+
+    /// Only select submissions submitted at or after this time
+    pub since: Option<SystemTime>,
+
+    /// Only select submissions submitted before this time
+    pub until: Option<SystemTime>,
+
+    /// Owners of the submitted repositories, i.e. GitHub organizations, GitLab
+    /// namespaces, or Gitea owners.
+    pub owners: Vec<String>,
+
+    /// Statuses of the submissions to select
+    pub statuses: Vec<SubmissionStatusCode>,
+}
+
+/// Selection of submissions in the submission history. Empty lists and `None`
+/// values do not restrict the selection.
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    /// Names of the submitted repositories. Plain git sources have no name,
+    /// and are never selected when this is set.
+    pub repos: Vec<String>,
+
+    /// Owners of the submitted repositories, i.e. GitHub organizations,
+    /// GitLab namespaces, or Gitea owners
+    pub owners: Vec<String>,
+
+    /// Submissions are selected if they graded any of these tags
+    pub tags: Vec<String>,
+
+    /// Only select submissions submitted at or after this time
+    pub since: Option<SystemTime>,
+
+    /// Only select submissions submitted before this time
+    pub until: Option<SystemTime>,
+
+    pub statuses: Vec<SubmissionStatusCode>,
+}
+
+/// The parts of a new submission that are common to all submission sources.
+#[derive(Debug, Clone)]
+pub struct NewSubmissionRequest<'a> {
+    /// Grading tags to grade
+    pub grading_tags: &'a [&'a str],
+
+    /// Rate limits that the submission may not exceed
+    pub rate_limits: &'a [RateLimitSettings],
+
+    /// Priority of the submission in the queue
+    pub priority: i32,
+
+    /// User that pushed the commit
+    pub user: &'a str,
+
+    /// Commit to grade
+    pub commit: &'a str,
+}
+
+impl RegradeFilter {
+    /// Creates a filter from grading tags as they are written in commit
+    /// messages, i.e. with or without the `#` or `%` prefix, and raw status
+    /// codes. Fails if any status code is invalid.
+    pub fn new(
+        tags: &[String],
+        since: Option<SystemTime>,
+        until: Option<SystemTime>,
+        owners: Vec<String>,
+        statuses: &[i32],
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            tags: strip_tag_prefixes(tags),
+            since: since,
+            until: until,
+            owners: owners,
+            statuses: parse_status_codes(statuses)?,
+        })
+    }
+}
+
+impl HistoryFilter {
+    /// Creates a filter from grading tags as they are written in commit
+    /// messages, i.e. with or without the `#` or `%` prefix, and raw status
+    /// codes. Fails if any status code is invalid.
+    pub fn new(
+        repos: Vec<String>,
+        owners: Vec<String>,
+        tags: &[String],
+        since: Option<SystemTime>,
+        until: Option<SystemTime>,
+        statuses: &[i32],
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            repos: repos,
+            owners: owners,
+            tags: strip_tag_prefixes(tags),
+            since: since,
+            until: until,
+            statuses: parse_status_codes(statuses)?,
+        })
+    }
+}
+
+/// Deserializes the `exec_progress` of the submission with ID
+/// `submission_id`.
+fn deserialize_progress(
+    submission_id: i64,
+    progress: Option<serde_json::Value>,
+) -> Result<Option<SubmissionProgress>, Error> {
+    progress
+        .map(|v| {
+            SubmissionProgress::deserialize(v).map_err(|e| {
+                Error::auto_msg(
+                    format!("could not deserialize progress of submission {submission_id}"),
+                    e,
+                )
+            })
+        })
+        .transpose()
+}
+
+/// Removes the `#` or `%` prefix from grading tags.
+fn strip_tag_prefixes(tags: &[String]) -> Vec<String> {
+    tags.iter()
+        .map(|t| t.trim_start_matches(['#', '%']).to_string())
+        .collect()
+}
+
+/// Converts raw status codes to their `SubmissionStatusCode`.
+fn parse_status_codes(codes: &[i32]) -> Result<Vec<SubmissionStatusCode>, Error> {
+    codes
+        .iter()
+        .map(|code| {
+            SubmissionStatusCode::from_i32(*code)
+                .ok_or_else(|| Error::parse_type("status code".to_string(), code.to_string()))
+        })
+        .collect()
+}
+
 impl DatabaseConnection {
     /// Connects to the database using the postgres settings
     pub fn connect(s: &Settings) -> Result<Self, Error> {
@@ -65,7 +220,9 @@ impl DatabaseConnection {
         Ok(())
     }
 
-    /// Registers an incoming GitHub submission in the database.
+    /// Registers an incoming GitHub submission in the database. Returns an
+    /// error of kind `RateLimited` if the submission would exceed any of the
+    /// rate limits of the `request`.
     ///
     /// ## Warning about Race Conditions
     /// This may return an error if two threads attempt to register a
@@ -73,13 +230,11 @@ impl DatabaseConnection {
     /// time.
     pub fn register_github_submission(
         &mut self,
-        grading_tags: &Vec<&str>,
+        request: &NewSubmissionRequest,
         domain: &str,
-        user: &str,
         org: &str,
         repo: &str,
         ssh_url: &str,
-        commit: &str,
     ) -> Result<i64, Error> {
         use crate::db::{
             models::{NewSubmissionInfoGitHub, SubmissionSourceGitHub},
@@ -169,13 +324,19 @@ impl DatabaseConnection {
         // Atomically execute the insert into the database, ensuring that we
         // roll back both the submission and source info on failure.
         self.conn.transaction(|conn| {
+            let now = SystemTime::now();
+            check_rate_limits(conn, src.id, request.grading_tags, request.rate_limits, now)?;
+
             let sub: Submission = diesel::insert_into(submissions::table)
                 .values(NewSubmission {
-                    date_submitted: std::time::SystemTime::now(),
-                    grading_tags: grading_tags.join(";"),
+                    date_submitted: now,
+                    grading_tags: request.grading_tags.join(";"),
                     exec_finished: false,
                     exec_status_code: SubmissionStatusCode::NotStarted as i32,
                     source_id: src.id,
+                    priority: request.priority,
+                    regrade_of: None,
+                    silent: false,
                 })
                 .returning(Submission::as_returning())
                 .get_result(conn)
@@ -188,8 +349,8 @@ impl DatabaseConnection {
                 .values(NewSubmissionInfoGitHub {
                     submission_id: sub.id,
                     github_source_id: gh_src.id,
-                    commit: commit.to_string(),
-                    user: user.to_string(),
+                    commit: request.commit.to_string(),
+                    user: request.user.to_string(),
                 })
                 .execute(conn)
                 .map_err(|e: diesel::result::Error| {
@@ -206,13 +367,11 @@ impl DatabaseConnection {
     /// See `register_github_submission` for more details.
     pub fn register_gitlab_submission(
         &mut self,
-        grading_tags: &Vec<&str>,
+        request: &NewSubmissionRequest,
         domain: &str,
-        user: &str,
         namespace: &str,
         repo: &str,
         ssh_url: &str,
-        commit: &str,
     ) -> Result<i64, Error> {
         use crate::db::{
             models::{NewSubmissionInfoGitLab, SubmissionSourceGitLab},
@@ -287,13 +446,19 @@ impl DatabaseConnection {
             })?;
 
         self.conn.transaction(|conn| {
+            let now = SystemTime::now();
+            check_rate_limits(conn, src.id, request.grading_tags, request.rate_limits, now)?;
+
             let sub: Submission = diesel::insert_into(submissions::table)
                 .values(NewSubmission {
-                    date_submitted: std::time::SystemTime::now(),
-                    grading_tags: grading_tags.join(";"),
+                    date_submitted: now,
+                    grading_tags: request.grading_tags.join(";"),
                     exec_finished: false,
                     exec_status_code: SubmissionStatusCode::NotStarted as i32,
                     source_id: src.id,
+                    priority: request.priority,
+                    regrade_of: None,
+                    silent: false,
                 })
                 .returning(Submission::as_returning())
                 .get_result(conn)
@@ -306,8 +471,8 @@ impl DatabaseConnection {
                 .values(NewSubmissionInfoGitLab {
                     submission_id: sub.id,
                     gitlab_source_id: gh_src.id,
-                    commit: commit.to_string(),
-                    user: user.to_string(),
+                    commit: request.commit.to_string(),
+                    user: request.user.to_string(),
                 })
                 .execute(conn)
                 .map_err(|e: diesel::result::Error| {
@@ -319,11 +484,264 @@ impl DatabaseConnection {
         })
     }
 
+    /// Registers a submission from a plain git source in the database. These
+    /// are identified by the URL used to clone the repository.
+    ///
+    /// See `register_github_submission` for more details.
+    pub fn register_git_submission(
+        &mut self,
+        request: &NewSubmissionRequest,
+        url: &str,
+    ) -> Result<i64, Error> {
+        use crate::db::{
+            models::NewSubmissionInfoGit,
+            schema::{submission_info_git, submissions},
+        };
+
+        let (src, git_src) = self.conn.transaction(|conn| {
+            use crate::db::{
+                models::{NewSubmissionSource, NewSubmissionSourceGit},
+                schema::{
+                    submission_source_git::{self, columns as gitsrc_col},
+                    submission_sources::{self, columns as src_col},
+                },
+            };
+            let gitsrc_insert_check = diesel::insert_into(submission_source_git::table)
+                .values(NewSubmissionSourceGit {
+                    url: url.to_string(),
+                })
+                .on_conflict_do_nothing()
+                .returning(SubmissionSourceGit::as_returning())
+                .get_result(conn)
+                .optional()?;
+
+            if let Some(new_git_src) = gitsrc_insert_check {
+                // Inserted a new row into the git sources, so need to insert a
+                // row into the submission_source table too. Also generate a
+                // random auth_key for this source.
+                let mut key: Vec<u8> = vec![0u8; 32];
+                rand::rng().fill_bytes(key.as_mut_slice());
+
+                let src = diesel::insert_into(submission_sources::table)
+                    .values(NewSubmissionSource {
+                        kind: SubmissionSourceKind::Git as i32,
+                        kind_id: new_git_src.id,
+                        auth_key: bs58::encode(key).into_string(),
+                    })
+                    .returning(SubmissionSource::as_returning())
+                    .get_result(conn)
+                    .inspect_err(|e: &diesel::result::Error| {
+                        log::error!(
+                            "Could not insert a submission source for git source id {}: {}",
+                            new_git_src.id,
+                            e,
+                        )
+                    })?;
+
+                Ok::<_, diesel::result::Error>((src, new_git_src))
+            } else {
+                let git_src = submission_source_git::table
+                    .select(SubmissionSourceGit::as_select())
+                    .filter(gitsrc_col::url.eq(url))
+                    .first(conn)
+                    .inspect_err(|e: &diesel::result::Error| {
+                        log::error!(
+                            "Expected to find an existing git source in the database with {}: {}",
+                            url,
+                            e
+                        )
+                    })?;
+
+                let src = submission_sources::table
+                    .select(SubmissionSource::as_select())
+                    .filter(src_col::kind.eq(SubmissionSourceKind::Git as i32))
+                    .filter(src_col::kind_id.eq(git_src.id))
+                    .first(conn)
+                    .inspect_err(|e: &diesel::result::Error| {
+                        log::error!("Expected to find a submission source referencing git source with id {}: {}", git_src.id, e)
+                    })?;
+
+                Ok((src, git_src))
+            }
+        })?;
+
+        self.conn.transaction(|conn| {
+            let now = SystemTime::now();
+            check_rate_limits(conn, src.id, request.grading_tags, request.rate_limits, now)?;
+
+            let sub: Submission = diesel::insert_into(submissions::table)
+                .values(NewSubmission {
+                    date_submitted: now,
+                    grading_tags: request.grading_tags.join(";"),
+                    exec_finished: false,
+                    exec_status_code: SubmissionStatusCode::NotStarted as i32,
+                    source_id: src.id,
+                    priority: request.priority,
+                    regrade_of: None,
+                    silent: false,
+                })
+                .returning(Submission::as_returning())
+                .get_result(conn)
+                .map_err(|e: diesel::result::Error| {
+                    log::error!("Could not insert new submission into database: {e}");
+                    Error::auto_msg("could not insert new submission into database", e)
+                })?;
+
+            diesel::insert_into(submission_info_git::table)
+                .values(NewSubmissionInfoGit {
+                    submission_id: sub.id,
+                    git_source_id: git_src.id,
+                    commit: request.commit.to_string(),
+                    user: request.user.to_string(),
+                })
+                .execute(conn)
+                .map_err(|e: diesel::result::Error| {
+                    log::error!("Could not insert git info into database: {e}");
+                    Error::auto_msg("could not insert new submission into database", e)
+                })?;
+
+            Ok(sub.id)
+        })
+    }
+
+    /// Registers an incoming Gitea submission in the database.
+    ///
+    /// See `register_github_submission` for more details.
+    pub fn register_gitea_submission(
+        &mut self,
+        request: &NewSubmissionRequest,
+        domain: &str,
+        owner: &str,
+        repo: &str,
+        ssh_url: &str,
+    ) -> Result<i64, Error> {
+        use crate::db::{
+            models::{NewSubmissionInfoGitea, SubmissionSourceGitea},
+            schema::{submission_info_gitea, submissions},
+        };
+
+        let (src, gt_src) = self.conn.transaction(|conn| {
+            use crate::db::{
+                models::{NewSubmissionSource, NewSubmissionSourceGitea},
+                schema::{
+                    submission_source_gitea::{self, columns as gtsrc_col},
+                    submission_sources::{self, columns as src_col},
+                },
+            };
+            let gtsrc_insert_check = diesel::insert_into(submission_source_gitea::table)
+                .values(NewSubmissionSourceGitea {
+                    domain: domain.to_string(),
+                    owner: owner.to_string(),
+                    repo: repo.to_string(),
+                    ssh_url: ssh_url.to_string(),
+                })
+                .on_conflict_do_nothing()
+                .returning(SubmissionSourceGitea::as_returning())
+                .get_result(conn)
+                .optional()?;
+
+            if let Some(new_gt_src) = gtsrc_insert_check {
+                // Inserted a new row into the Gitea sources, so need to insert
+                // a row into the submission_source table too. Also generate a
+                // random auth_key for this source.
+                let mut key: Vec<u8> = vec![0u8; 32];
+                rand::rng().fill_bytes(key.as_mut_slice());
+
+                let src = diesel::insert_into(submission_sources::table)
+                    .values(NewSubmissionSource {
+                        kind: SubmissionSourceKind::Gitea as i32,
+                        kind_id: new_gt_src.id,
+                        auth_key: bs58::encode(key).into_string(),
+                    })
+                    .returning(SubmissionSource::as_returning())
+                    .get_result(conn)
+                    .inspect_err(|e: &diesel::result::Error| {
+                        log::error!(
+                            "Could not insert a submission source for Gitea source id {}: {}",
+                            new_gt_src.id,
+                            e,
+                        )
+                    })?;
+
+                Ok::<_, diesel::result::Error>((src, new_gt_src))
+            } else {
+                let gt_src = submission_source_gitea::table
+                    .select(SubmissionSourceGitea::as_select())
+                    .filter(gtsrc_col::domain.eq(domain))
+                    .filter(gtsrc_col::owner.eq(owner))
+                    .filter(gtsrc_col::repo.eq(repo))
+                    .first(conn)
+                    .inspect_err(|e: &diesel::result::Error| {
+                        log::error!(
+                            "Expected to find an existing Gitea source in the database with {} {} {}: {}",
+                            domain,
+                            owner,
+                            repo,
+                            e
+                        )
+                    })?;
+
+                let src = submission_sources::table
+                    .select(SubmissionSource::as_select())
+                    .filter(src_col::kind.eq(SubmissionSourceKind::Gitea as i32))
+                    .filter(src_col::kind_id.eq(gt_src.id))
+                    .first(conn)
+                    .inspect_err(|e: &diesel::result::Error| {
+                        log::error!("Expected to find a submission source referencing Gitea source with id {}: {}", gt_src.id, e)
+                    })?;
+
+                Ok((src, gt_src))
+            }
+        })?;
+
+        self.conn.transaction(|conn| {
+            let now = SystemTime::now();
+            check_rate_limits(conn, src.id, request.grading_tags, request.rate_limits, now)?;
+
+            let sub: Submission = diesel::insert_into(submissions::table)
+                .values(NewSubmission {
+                    date_submitted: now,
+                    grading_tags: request.grading_tags.join(";"),
+                    exec_finished: false,
+                    exec_status_code: SubmissionStatusCode::NotStarted as i32,
+                    source_id: src.id,
+                    priority: request.priority,
+                    regrade_of: None,
+                    silent: false,
+                })
+                .returning(Submission::as_returning())
+                .get_result(conn)
+                .map_err(|e: diesel::result::Error| {
+                    log::error!("Could not insert new submission into database: {e}");
+                    Error::auto_msg("could not insert new submission into database", e)
+                })?;
+
+            diesel::insert_into(submission_info_gitea::table)
+                .values(NewSubmissionInfoGitea {
+                    submission_id: sub.id,
+                    gitea_source_id: gt_src.id,
+                    commit: request.commit.to_string(),
+                    user: request.user.to_string(),
+                })
+                .execute(conn)
+                .map_err(|e: diesel::result::Error| {
+                    log::error!("Could not insert Gitea info into database: {e}");
+                    Error::auto_msg("could not insert new submission into database", e)
+                })?;
+
+            Ok(sub.id)
+        })
+    }
+
     /// Returns all information submission with the specified submission id.
     pub fn get_submission_info(&mut self, sub_id: i64) -> Result<SubmissionInfo, Error> {
         use crate::db::schema::{
+            submission_info_git::{self, columns as gitinfo_col},
+            submission_info_gitea::{self, columns as gtinfo_col},
             submission_info_github::{self, columns as ghinfo_col},
             submission_info_gitlab::{self, columns as glinfo_col},
+            submission_source_git::{self, columns as gitsrc_col},
+            submission_source_gitea::{self, columns as gtsrc_col},
             submission_source_github::{self, columns as ghsrc_col},
             submission_source_gitlab::{self, columns as glsrc_col},
             submission_sources::{self, columns as subsrc_col},
@@ -387,6 +805,44 @@ impl DatabaseConnection {
                     gl_info: gl_info,
                 })
             }
+            Some(SubmissionSourceKind::Git) => {
+                let git_src = submission_source_git::table
+                    .select(SubmissionSourceGit::as_select())
+                    .filter(gitsrc_col::id.eq(src.kind_id))
+                    .first(&mut self.conn)?;
+
+                let git_info = submission_info_git::table
+                    .select(SubmissionInfoGit::as_select())
+                    .filter(gitinfo_col::submission_id.eq(sub.id))
+                    .filter(gitinfo_col::git_source_id.eq(git_src.id))
+                    .first(&mut self.conn)?;
+
+                Ok(SubmissionInfo::Git {
+                    sub: sub,
+                    src: src,
+                    git_src: git_src,
+                    git_info: git_info,
+                })
+            }
+            Some(SubmissionSourceKind::Gitea) => {
+                let gt_src = submission_source_gitea::table
+                    .select(SubmissionSourceGitea::as_select())
+                    .filter(gtsrc_col::id.eq(src.kind_id))
+                    .first(&mut self.conn)?;
+
+                let gt_info = submission_info_gitea::table
+                    .select(SubmissionInfoGitea::as_select())
+                    .filter(gtinfo_col::submission_id.eq(sub.id))
+                    .filter(gtinfo_col::gitea_source_id.eq(gt_src.id))
+                    .first(&mut self.conn)?;
+
+                Ok(SubmissionInfo::Gitea {
+                    sub: sub,
+                    src: src,
+                    gt_src: gt_src,
+                    gt_info: gt_info,
+                })
+            }
             None => Error::err_runtime(format!(
                 "Invalid source kind {} for submission source with id {}",
                 src.kind, src.id
@@ -424,40 +880,724 @@ impl DatabaseConnection {
         }
     }
 
-    /// Return all the queued submissions in the database, oldest first
-    pub fn queued_submissions(&mut self) -> Result<Vec<Submission>, Error> {
-        use crate::db::schema::submissions::{
-            self, assigned_runner_id, date_submitted, exec_finished,
-        };
+    /// Returns the reports of the submissions with the IDs in `sub_ids`.
+    /// Submissions without a report, or with a report that could not be
+    /// deserialized, are left out.
+    pub fn select_reports(&mut self, sub_ids: &[i64]) -> Result<BTreeMap<i64, Report>, Error> {
+        use crate::db::schema::submissions::{self, columns as sub_col};
+
+        let reports = submissions::table
+            .select((sub_col::id, sub_col::exec_report))
+            .filter(sub_col::id.eq_any(sub_ids))
+            .load::<(i64, Option<serde_json::Value>)>(&mut self.conn)
+            .map_err(|e| Error::auto_msg("could not select reports of submissions", e))?;
+
+        Ok(reports
+            .into_iter()
+            .filter_map(|(id, v)| match Report::deserialize(v?) {
+                Ok(report) => Some((id, report)),
+                Err(e) => {
+                    log::warn!("Could not deserialize report for submission {id}: {e}");
+                    None
+                }
+            })
+            .collect())
+    }
 
-        let ret: Vec<Submission> = submissions::table
+    /// Marks the queued submissions that are superseded by the submission
+    /// with ID `submission_id`, i.e. the older submissions from the same
+    /// source with the same grading tags that have not yet been assigned to a
+    /// runner. Returns the information about each superseded submission, such
+    /// that their submitters can be notified.
+    pub fn supersede_queued_submissions(
+        &mut self,
+        submission_id: i64,
+    ) -> Result<Vec<SubmissionInfo>, Error> {
+        use crate::db::schema::submissions::{self, columns as sub_col};
+
+        let newer: Submission = submissions::table
+            .find(submission_id)
             .select(Submission::as_select())
-            .filter(exec_finished.eq(false))
-            .filter(assigned_runner_id.is_null())
-            .order(date_submitted.asc())
-            .load(&mut self.conn)
-            .map_err(|e: diesel::result::Error| {
-                Error::auto_msg("could not get queued submissions from database", e)
+            .first(&mut self.conn)
+            .map_err(|e| {
+                Error::auto_msg(format!("could not find submission {submission_id}"), e)
             })?;
 
-        Ok(ret)
+        let report = Report::Message(ReportMessage {
+            msg: format!(
+                "This submission was superseded by submission {submission_id} before it was graded."
+            ),
+        });
+
+        // The conditions on the runner are checked again by postgres after
+        // locking each row, so a submission that was assigned in the meantime
+        // is never superseded.
+        let superseded_ids: Vec<i64> = diesel::update(submissions::table)
+            .filter(sub_col::source_id.eq(newer.source_id))
+            .filter(sub_col::grading_tags.eq(&newer.grading_tags))
+            .filter(sub_col::id.lt(submission_id))
+            .filter(sub_col::regrade_of.is_null())
+            .filter(sub_col::exec_finished.eq(false))
+            .filter(sub_col::assigned_runner_id.is_null())
+            .set((
+                sub_col::exec_status_code.eq(SubmissionStatusCode::Superseded as i32),
+                sub_col::exec_finished.eq(true),
+                sub_col::exec_report.eq(serde_json::to_value(&report)?),
+            ))
+            .returning(sub_col::id)
+            .get_results(&mut self.conn)
+            .map_err(|e| {
+                Error::auto_msg(
+                    format!("could not supersede submissions older than {submission_id}"),
+                    e,
+                )
+            })?;
+
+        if !superseded_ids.is_empty() {
+            log::info!("Submission {submission_id} superseded submissions {superseded_ids:?}");
+        }
+
+        superseded_ids
+            .into_iter()
+            .map(|id| self.get_submission_info(id))
+            .collect()
     }
 
-    /// Tries to assign a submission to the runner with the specified ID, if
-    /// there are any queued submissions.
-    ///
-    /// Returns None if we could not assign a new submission to this runner.
-    /// If there was a queued submission that was assigned to this specific
-    /// runner ID, then the database is atomically updated and the ID assigned
-    /// to this runner.
-    pub fn try_assign_submission(&mut self, runner_id: i32) -> Result<Option<Submission>, Error> {
-        // Using custom SQL here, don't know how to do this in Diesel directly...
-        // The .bind function for safe queries does not appear to work either...
-        // (Formatting a single int argument should be safe though.)
-        // https://www.postgresql.org/docs/17/sql-update.html
-        // https://www.postgresql.org/docs/17/sql-select.html
-        // https://www.postgresql.org/docs/17/functions-conditional.html
-        let assigned_subs: Vec<Submission> = diesel::sql_query(format!(
+    /// Returns the finished submissions that match the `filter`, together
+    /// with the `;` separated grading tags that should be re-graded for each
+    /// of them. Only original submissions are selected, never re-grades.
+    /// Superseded submissions are only selected if `filter.statuses`
+    /// explicitly includes them.
+    pub fn select_regrade_submissions(
+        &mut self,
+        filter: &RegradeFilter,
+    ) -> Result<Vec<(SubmissionInfo, String)>, Error> {
+        use crate::db::schema::submissions::{self, columns as sub_col};
+
+        let mut query = submissions::table
+            .select(Submission::as_select())
+            .filter(sub_col::exec_finished.eq(true))
+            .filter(sub_col::regrade_of.is_null())
+            .into_boxed();
+        if let Some(since) = filter.since {
+            query = query.filter(sub_col::date_submitted.ge(since));
+        }
+        if let Some(until) = filter.until {
+            query = query.filter(sub_col::date_submitted.lt(until));
+        }
+        if filter.statuses.is_empty() {
+            query =
+                query.filter(sub_col::exec_status_code.ne(SubmissionStatusCode::Superseded as i32));
+        } else {
+            query = query.filter(
+                sub_col::exec_status_code.eq_any(
+                    filter
+                        .statuses
+                        .iter()
+                        .map(|s| *s as i32)
+                        .collect::<Vec<i32>>(),
+                ),
+            );
+        }
+
+        let candidates: Vec<Submission> = query
+            .order(sub_col::id.asc())
+            .load(&mut self.conn)
+            .map_err(|e| Error::auto_msg("could not select submissions to re-grade", e))?;
+
+        let mut selected = Vec::new();
+        for sub in candidates {
+            let Some(tags) = regrade_tags(&sub.grading_tags, &filter.tags) else {
+                continue;
+            };
+            let info = self.get_submission_info(sub.id)?;
+            let owner_matches = filter.owners.is_empty()
+                || info
+                    .owner()
+                    .is_some_and(|owner| filter.owners.iter().any(|o| o == owner));
+            if owner_matches {
+                selected.push((info, tags));
+            }
+        }
+        Ok(selected)
+    }
+
+    /// Selects a page of the submission history, with the most recent
+    /// submissions first. Returns the total number of submissions in the
+    /// selection together with the submissions on the page.
+    pub fn select_submission_history(
+        &mut self,
+        filter: &HistoryFilter,
+        offset: i64,
+        limit: i64,
+    ) -> Result<(i64, Vec<SubmissionInfo>), Error> {
+        use crate::db::schema::submissions::{self, columns as sub_col};
+        use diesel::{
+            pg::Pg, sql_types::Bool, BoolExpressionMethods, BoxableExpression,
+            TextExpressionMethods,
+        };
+
+        let source_ids = if filter.repos.is_empty() && filter.owners.is_empty() {
+            None
+        } else {
+            Some(self.select_source_ids(&filter.repos, &filter.owners)?)
+        };
+
+        // The query is built twice, once for counting and once for the page
+        let build_query = || {
+            let mut query = submissions::table.into_boxed();
+            if let Some(ids) = &source_ids {
+                query = query.filter(sub_col::source_id.eq_any(ids.clone()));
+            }
+            if let Some(since) = filter.since {
+                query = query.filter(sub_col::date_submitted.ge(since));
+            }
+            if let Some(until) = filter.until {
+                query = query.filter(sub_col::date_submitted.lt(until));
+            }
+            if !filter.statuses.is_empty() {
+                query = query.filter(
+                    sub_col::exec_status_code.eq_any(
+                        filter
+                            .statuses
+                            .iter()
+                            .map(|s| *s as i32)
+                            .collect::<Vec<i32>>(),
+                    ),
+                );
+            }
+
+            // The grading tags are stored as a `;` separated list
+            let mut tag_cond: Option<
+                Box<dyn BoxableExpression<submissions::table, Pg, SqlType = Bool>>,
+            > = None;
+            for tag in filter.tags.iter() {
+                let escaped = escape_like(tag);
+                let cond = sub_col::grading_tags
+                    .eq(tag.clone())
+                    .or(sub_col::grading_tags.like(format!("{escaped};%")))
+                    .or(sub_col::grading_tags.like(format!("%;{escaped}")))
+                    .or(sub_col::grading_tags.like(format!("%;{escaped};%")));
+                tag_cond = Some(match tag_cond {
+                    Some(prev) => Box::new(prev.or(cond)),
+                    None => Box::new(cond),
+                });
+            }
+            if let Some(cond) = tag_cond {
+                query = query.filter(cond);
+            }
+            query
+        };
+
+        let total: i64 = build_query()
+            .count()
+            .get_result(&mut self.conn)
+            .map_err(|e| Error::auto_msg("could not count submissions in history", e))?;
+
+        let subs: Vec<Submission> = build_query()
+            .select(Submission::as_select())
+            .order(sub_col::id.desc())
+            .offset(offset)
+            .limit(limit)
+            .load(&mut self.conn)
+            .map_err(|e| Error::auto_msg("could not select submissions from history", e))?;
+
+        let infos = subs
+            .iter()
+            .map(|sub| self.get_submission_info(sub.id))
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok((total, infos))
+    }
+
+    /// Returns the finished submissions from repositories of the owners in
+    /// `owners`, or from all repositories if empty, for compiling the
+    /// gradebook. Only the gradings of the tags in `tags` are included, or of
+    /// all tags if empty.
+    pub fn select_gradebook_records(
+        &mut self,
+        owners: &[String],
+        tags: &[String],
+    ) -> Result<Vec<GradebookRecord>, Error> {
+        use diesel::sql_types::{Array, BigInt, Bool, Double, Int4, Nullable, Text, Timestamp};
+
+        #[derive(diesel::QueryableByName)]
+        struct GradingRow {
+            #[diesel(sql_type = BigInt)]
+            submission_id: i64,
+            #[diesel(sql_type = BigInt)]
+            source_id: i64,
+            #[diesel(sql_type = Int4)]
+            kind: i32,
+            #[diesel(sql_type = Nullable<Text>)]
+            domain: Option<String>,
+            #[diesel(sql_type = Nullable<Text>)]
+            owner: Option<String>,
+            #[diesel(sql_type = Text)]
+            repo: String,
+            #[diesel(sql_type = Text)]
+            user: String,
+            #[diesel(sql_type = Timestamp)]
+            submitted: SystemTime,
+            #[diesel(sql_type = Text)]
+            tag_name: String,
+            #[diesel(sql_type = Bool)]
+            ok: bool,
+            #[diesel(sql_type = Bool)]
+            late: bool,
+            #[diesel(sql_type = Nullable<Double>)]
+            points: Option<f64>,
+            #[diesel(sql_type = Nullable<Double>)]
+            max_points: Option<f64>,
+        }
+
+        // Using custom SQL here, such that only the tag gradings are extracted
+        // from the reports, which can be very large, and such that the source
+        // information is joined in rather than fetched for every submission.
+        // The tag gradings are the objects with a `tag_name` and `ok` field,
+        // which are found either directly under `tag_grading` or in the
+        // `tag_reports` of a submission report, in the order of the report.
+        // Re-grades count as submitted at the time of the original submission.
+        // https://www.postgresql.org/docs/17/functions-json.html#FUNCTIONS-SQLJSON-PATH
+        let rows: Vec<GradingRow> = diesel::sql_query(format!(
+            "
+            SELECT
+                s.id AS submission_id,
+                s.source_id,
+                src.kind,
+                COALESCE(gh_src.domain, gl_src.domain, gt_src.domain) AS domain,
+                COALESCE(gh_src.org, gl_src.namespace, gt_src.owner) AS owner,
+                COALESCE(gh_src.repo, gl_src.repo, gt_src.repo, git_src.url) AS repo,
+                COALESCE(gh_info.user, gl_info.user, gt_info.user, git_info.user) AS user,
+                COALESCE(orig.date_submitted, s.date_submitted) AS submitted,
+                tg.grading->>'tag_name' AS tag_name,
+                (tg.grading->>'ok')::boolean AS ok,
+                COALESCE((tg.grading->>'late')::boolean, false) AS late,
+                (tg.grading->'score'->>'points')::double precision AS points,
+                (tg.grading->'score'->>'max_points')::double precision AS max_points
+            FROM submissions AS s
+            JOIN submission_sources AS src ON src.id = s.source_id
+            LEFT JOIN submission_source_github AS gh_src
+                ON src.kind = {github} AND gh_src.id = src.kind_id
+            LEFT JOIN submission_source_gitlab AS gl_src
+                ON src.kind = {gitlab} AND gl_src.id = src.kind_id
+            LEFT JOIN submission_source_git AS git_src
+                ON src.kind = {git} AND git_src.id = src.kind_id
+            LEFT JOIN submission_source_gitea AS gt_src
+                ON src.kind = {gitea} AND gt_src.id = src.kind_id
+            LEFT JOIN submission_info_github AS gh_info
+                ON gh_info.submission_id = s.id AND gh_info.github_source_id = gh_src.id
+            LEFT JOIN submission_info_gitlab AS gl_info
+                ON gl_info.submission_id = s.id AND gl_info.gitlab_source_id = gl_src.id
+            LEFT JOIN submission_info_git AS git_info
+                ON git_info.submission_id = s.id AND git_info.git_source_id = git_src.id
+            LEFT JOIN submission_info_gitea AS gt_info
+                ON gt_info.submission_id = s.id AND gt_info.gitea_source_id = gt_src.id
+            LEFT JOIN submissions AS orig ON orig.id = s.regrade_of
+            CROSS JOIN LATERAL jsonb_path_query(
+                s.exec_report::jsonb,
+                'strict $.** ? (exists (@.tag_name) && exists (@.ok))'
+            ) WITH ORDINALITY AS tg(grading, ordinal)
+            WHERE
+                s.exec_finished = true
+            AND
+                ($1 IS NULL OR COALESCE(gh_src.org, gl_src.namespace, gt_src.owner) = ANY($1))
+            AND
+                ($2 IS NULL OR tg.grading->>'tag_name' = ANY($2))
+            ORDER BY s.id ASC, tg.ordinal ASC;
+            ",
+            github = SubmissionSourceKind::GitHub as i32,
+            gitlab = SubmissionSourceKind::GitLab as i32,
+            git = SubmissionSourceKind::Git as i32,
+            gitea = SubmissionSourceKind::Gitea as i32,
+        ))
+        .bind::<Nullable<Array<Text>>, _>((!owners.is_empty()).then_some(owners))
+        .bind::<Nullable<Array<Text>>, _>((!tags.is_empty()).then_some(tags))
+        .load(&mut self.conn)
+        .map_err(|e| Error::auto_msg("could not select tag gradings for the gradebook", e))?;
+
+        let mut records: Vec<GradebookRecord> = Vec::new();
+        for row in rows {
+            let graded = GradedTag {
+                tag_name: row.tag_name,
+                ok: row.ok,
+                late: row.late,
+                percentage: row.points.zip(row.max_points).map(|(points, max_points)| {
+                    DetailsTagScore {
+                        points: points,
+                        max_points: max_points,
+                        pass_threshold: None,
+                    }
+                    .percentage()
+                }),
+            };
+            match records.last_mut() {
+                Some(record) if record.submission_id == row.submission_id => {
+                    record.tags.push(graded)
+                }
+                _ => records.push(GradebookRecord {
+                    source_id: row.source_id,
+                    source: SubmissionSourceKind::from_i32(row.kind)
+                        .map_or("Unknown".to_string(), |k| k.to_string()),
+                    domain: row.domain,
+                    owner: row.owner,
+                    repo: row.repo,
+                    user: row.user,
+                    submission_id: row.submission_id,
+                    submitted: row.submitted,
+                    tags: vec![graded],
+                }),
+            }
+        }
+        Ok(records)
+    }
+
+    /// Returns the grades that have been posted to Canvas.
+    pub fn get_canvas_grades(&mut self) -> Result<Vec<CanvasSyncedGrade>, Error> {
+        use crate::db::schema::canvas_grades;
+
+        canvas_grades::table
+            .select(CanvasSyncedGrade::as_select())
+            .load(&mut self.conn)
+            .map_err(|e| Error::auto_msg("could not get synced Canvas grades", e))
+    }
+
+    /// Records that `grade` has been posted to Canvas for the user on the
+    /// assignment, replacing any previous grade.
+    pub fn set_canvas_grade(
+        &mut self,
+        canvas_user: &str,
+        assignment_id: i64,
+        grade: &str,
+    ) -> Result<(), Error> {
+        use crate::db::{models::NewCanvasSyncedGrade, schema::canvas_grades};
+
+        let now = SystemTime::now();
+        diesel::insert_into(canvas_grades::table)
+            .values(NewCanvasSyncedGrade {
+                canvas_user: canvas_user.to_string(),
+                assignment_id: assignment_id,
+                grade: grade.to_string(),
+                date_synced: now,
+            })
+            .on_conflict((canvas_grades::canvas_user, canvas_grades::assignment_id))
+            .do_update()
+            .set((
+                canvas_grades::grade.eq(grade),
+                canvas_grades::date_synced.eq(now),
+            ))
+            .execute(&mut self.conn)
+            .map(|_| ())
+            .map_err(|e| {
+                Error::auto_msg(
+                    format!("could not record Canvas grade of {canvas_user} on assignment {assignment_id}"),
+                    e,
+                )
+            })
+    }
+
+    /// Returns the IDs of the submission sources whose repository has one of
+    /// the names in `repos` and one of the owners in `owners`, where an empty
+    /// list matches any repository.
+    fn select_source_ids(
+        &mut self,
+        repos: &[String],
+        owners: &[String],
+    ) -> Result<Vec<i64>, Error> {
+        use crate::db::schema::{
+            submission_source_gitea::{self, columns as gtsrc_col},
+            submission_source_github::{self, columns as ghsrc_col},
+            submission_source_gitlab::{self, columns as glsrc_col},
+            submission_sources::{self, columns as subsrc_col},
+        };
+
+        let mut ids = Vec::new();
+
+        let mut gh_query = submission_source_github::table
+            .select(ghsrc_col::id)
+            .into_boxed();
+        if !repos.is_empty() {
+            gh_query = gh_query.filter(ghsrc_col::repo.eq_any(repos));
+        }
+        if !owners.is_empty() {
+            gh_query = gh_query.filter(ghsrc_col::org.eq_any(owners));
+        }
+        let gh_ids: Vec<i64> = gh_query.load(&mut self.conn)?;
+
+        let mut gl_query = submission_source_gitlab::table
+            .select(glsrc_col::id)
+            .into_boxed();
+        if !repos.is_empty() {
+            gl_query = gl_query.filter(glsrc_col::repo.eq_any(repos));
+        }
+        if !owners.is_empty() {
+            gl_query = gl_query.filter(glsrc_col::namespace.eq_any(owners));
+        }
+        let gl_ids: Vec<i64> = gl_query.load(&mut self.conn)?;
+
+        let mut gt_query = submission_source_gitea::table
+            .select(gtsrc_col::id)
+            .into_boxed();
+        if !repos.is_empty() {
+            gt_query = gt_query.filter(gtsrc_col::repo.eq_any(repos));
+        }
+        if !owners.is_empty() {
+            gt_query = gt_query.filter(gtsrc_col::owner.eq_any(owners));
+        }
+        let gt_ids: Vec<i64> = gt_query.load(&mut self.conn)?;
+
+        for (kind, kind_ids) in [
+            (SubmissionSourceKind::GitHub, gh_ids),
+            (SubmissionSourceKind::GitLab, gl_ids),
+            (SubmissionSourceKind::Gitea, gt_ids),
+        ] {
+            if kind_ids.is_empty() {
+                continue;
+            }
+            let kind_source_ids: Vec<i64> = submission_sources::table
+                .select(subsrc_col::id)
+                .filter(subsrc_col::kind.eq(kind as i32))
+                .filter(subsrc_col::kind_id.eq_any(kind_ids))
+                .load(&mut self.conn)?;
+            ids.extend(kind_source_ids);
+        }
+        Ok(ids)
+    }
+
+    /// Registers a re-grade of the submission in `info`, for the same commit
+    /// and source, but only for the `;` separated `grading_tags`. If `silent`
+    /// is set, then no comments or statuses are posted on the commit for the
+    /// re-grade. Returns the ID of the new submission.
+    ///
+    /// Re-grades are not subject to rate limits, and are never superseded.
+    pub fn register_regrade(
+        &mut self,
+        info: &SubmissionInfo,
+        grading_tags: &str,
+        priority: i32,
+        silent: bool,
+    ) -> Result<i64, Error> {
+        use crate::db::{
+            models::{
+                NewSubmissionInfoGit, NewSubmissionInfoGitHub, NewSubmissionInfoGitLab,
+                NewSubmissionInfoGitea,
+            },
+            schema::{
+                submission_info_git, submission_info_gitea, submission_info_github,
+                submission_info_gitlab, submissions,
+            },
+        };
+
+        let original = info.get_submission();
+
+        self.conn.transaction(|conn| {
+            let sub: Submission = diesel::insert_into(submissions::table)
+                .values(NewSubmission {
+                    date_submitted: SystemTime::now(),
+                    grading_tags: grading_tags.to_string(),
+                    exec_finished: false,
+                    exec_status_code: SubmissionStatusCode::NotStarted as i32,
+                    source_id: original.source_id,
+                    priority: priority,
+                    regrade_of: Some(original.id),
+                    silent: silent,
+                })
+                .returning(Submission::as_returning())
+                .get_result(conn)
+                .map_err(|e: diesel::result::Error| {
+                    log::error!("Could not insert re-grade submission into database: {e}");
+                    Error::auto_msg("could not insert re-grade submission into database", e)
+                })?;
+
+            match info {
+                SubmissionInfo::GitHub { gh_info, .. } => {
+                    diesel::insert_into(submission_info_github::table)
+                        .values(NewSubmissionInfoGitHub {
+                            submission_id: sub.id,
+                            github_source_id: gh_info.github_source_id,
+                            user: gh_info.user.clone(),
+                            commit: gh_info.commit.clone(),
+                        })
+                        .execute(conn)
+                }
+                SubmissionInfo::GitLab { gl_info, .. } => {
+                    diesel::insert_into(submission_info_gitlab::table)
+                        .values(NewSubmissionInfoGitLab {
+                            submission_id: sub.id,
+                            gitlab_source_id: gl_info.gitlab_source_id,
+                            user: gl_info.user.clone(),
+                            commit: gl_info.commit.clone(),
+                        })
+                        .execute(conn)
+                }
+                SubmissionInfo::Git { git_info, .. } => {
+                    diesel::insert_into(submission_info_git::table)
+                        .values(NewSubmissionInfoGit {
+                            submission_id: sub.id,
+                            git_source_id: git_info.git_source_id,
+                            user: git_info.user.clone(),
+                            commit: git_info.commit.clone(),
+                        })
+                        .execute(conn)
+                }
+                SubmissionInfo::Gitea { gt_info, .. } => {
+                    diesel::insert_into(submission_info_gitea::table)
+                        .values(NewSubmissionInfoGitea {
+                            submission_id: sub.id,
+                            gitea_source_id: gt_info.gitea_source_id,
+                            user: gt_info.user.clone(),
+                            commit: gt_info.commit.clone(),
+                        })
+                        .execute(conn)
+                }
+            }
+            .map_err(|e: diesel::result::Error| {
+                log::error!("Could not insert re-grade info into database: {e}");
+                Error::auto_msg("could not insert re-grade submission into database", e)
+            })?;
+
+            log::info!(
+                "Registered submission {} as a re-grade of submission {}",
+                sub.id,
+                original.id
+            );
+            Ok(sub.id)
+        })
+    }
+
+    /// Returns the submission with the specified ID, without its report.
+    pub fn get_submission(&mut self, sub_id: i64) -> Result<Submission, Error> {
+        use crate::db::schema::submissions;
+
+        submissions::table
+            .find(sub_id)
+            .select(Submission::as_select())
+            .first(&mut self.conn)
+            .map_err(|e| {
+                Error::auto_msg(
+                    format!("could not get submission {sub_id} from database"),
+                    e,
+                )
+            })
+    }
+
+    /// Returns whether the submission with the specified ID has been
+    /// cancelled.
+    pub fn is_cancelled(&mut self, sub_id: i64) -> Result<bool, Error> {
+        self.get_submission(sub_id)
+            .map(|sub| sub.exec_status_code == SubmissionStatusCode::Cancelled as i32)
+    }
+
+    /// Cancels the submission with ID `submission_id` if it has not yet
+    /// finished, and posts a cancelled status to its source. If the
+    /// submission is being graded, the runners are notified on the `cancel`
+    /// channel such that the assigned runner stops grading it. Returns
+    /// `Ok(false)` if the submission had already finished.
+    ///
+    /// This blocks on the requests to the submission source, see
+    /// `report_and_status`.
+    pub fn cancel_submission(
+        &mut self,
+        settings: &Settings,
+        submission_id: i64,
+    ) -> Result<bool, Error> {
+        use crate::db::schema::submissions::{self, columns as sub_col};
+
+        let report = Report::Message(ReportMessage {
+            msg: "This submission was cancelled before it finished grading.".to_string(),
+        });
+
+        // Marking it as finished first, such that a runner cannot be assigned
+        // the submission or report any other results for it.
+        let cancelled: Option<Option<i32>> = diesel::update(submissions::table)
+            .filter(sub_col::id.eq(submission_id))
+            .filter(sub_col::exec_finished.eq(false))
+            .set((
+                sub_col::exec_status_code.eq(SubmissionStatusCode::Cancelled as i32),
+                sub_col::exec_finished.eq(true),
+                sub_col::exec_date_finished.eq(SystemTime::now()),
+                sub_col::exec_report.eq(serde_json::to_value(&report)?),
+            ))
+            .returning(sub_col::assigned_runner_id)
+            .get_result(&mut self.conn)
+            .optional()
+            .map_err(|e| {
+                Error::auto_msg(format!("could not cancel submission {submission_id}"), e)
+            })?;
+
+        let Some(assigned_runner_id) = cancelled else {
+            return Ok(false);
+        };
+        log::info!("Cancelled submission {submission_id}");
+
+        let info = self.get_submission_info(submission_id)?;
+        self.report_and_status(
+            settings,
+            &info,
+            &report,
+            SubmissionStatusCode::Cancelled,
+            true,
+        )?;
+
+        if let Some(runner_id) = assigned_runner_id {
+            log::debug!("Notifying runner {runner_id} about cancelled submission {submission_id}");
+            self.notify("cancel")?;
+        }
+
+        Ok(true)
+    }
+
+    /// Return all the queued submissions in the database, oldest first
+    pub fn queued_submissions(&mut self) -> Result<Vec<Submission>, Error> {
+        use crate::db::schema::submissions::{
+            self, assigned_runner_id, date_submitted, exec_finished,
+        };
+
+        let ret: Vec<Submission> = submissions::table
+            .select(Submission::as_select())
+            .filter(exec_finished.eq(false))
+            .filter(assigned_runner_id.is_null())
+            .order(date_submitted.asc())
+            .load(&mut self.conn)
+            .map_err(|e: diesel::result::Error| {
+                Error::auto_msg("could not get queued submissions from database", e)
+            })?;
+
+        Ok(ret)
+    }
+
+    /// Tries to assign a submission to the runner with the specified ID, if
+    /// there are any queued submissions.
+    ///
+    /// The submission with the highest priority is assigned, where a
+    /// submission gains one level of priority for every `aging_minutes` that
+    /// it has been queued. If `aging_minutes` is 0, the priority does not
+    /// change while queued. Ties are broken by submission date.
+    ///
+    /// Returns None if we could not assign a new submission to this runner.
+    /// If there was a queued submission that was assigned to this specific
+    /// runner ID, then the database is atomically updated and the ID assigned
+    /// to this runner.
+    pub fn try_assign_submission(
+        &mut self,
+        runner_id: i32,
+        aging_minutes: u32,
+    ) -> Result<Option<Submission>, Error> {
+        let effective_priority = if aging_minutes > 0 {
+            format!(
+                "s2.priority + EXTRACT(EPOCH FROM ((now() AT TIME ZONE 'UTC') - s2.date_submitted)) / {}",
+                u64::from(aging_minutes) * 60
+            )
+        } else {
+            "s2.priority".to_string()
+        };
+
+        // Using custom SQL here, don't know how to do this in Diesel directly...
+        // The .bind function for safe queries does not appear to work either...
+        // (Formatting integer arguments should be safe though.)
+        // https://www.postgresql.org/docs/17/sql-update.html
+        // https://www.postgresql.org/docs/17/sql-select.html
+        // https://www.postgresql.org/docs/17/functions-conditional.html
+        let assigned_subs: Vec<Submission> = diesel::sql_query(format!(
             "
             WITH queued_entries AS (
                 SELECT s2.id FROM submissions AS s2
@@ -475,7 +1615,7 @@ impl DatabaseConnection {
                           s3.assigned_runner_id IS NOT NULL
                       FOR UPDATE
                   )
-                ORDER BY date_submitted ASC
+                ORDER BY {effective_priority} DESC, s2.date_submitted ASC
                 LIMIT 1
                 -- This below is important to ensure that is gets executed
                 -- atomically.
@@ -541,6 +1681,50 @@ impl DatabaseConnection {
             })
     }
 
+    /// Sets the progress of a submission that is being graded.
+    pub fn set_exec_progress(
+        &mut self,
+        submission_id: i64,
+        progress: &SubmissionProgress,
+    ) -> Result<(), Error> {
+        use crate::db::schema::submissions;
+
+        diesel::update(submissions::table)
+            .filter(submissions::id.eq(submission_id))
+            .set(submissions::exec_progress.eq(serde_json::to_value(progress)?))
+            .execute(&mut self.conn)
+            .map(|_| ())
+            .map_err(|e| {
+                Error::auto_msg(
+                    format!("Could not set exec_progress for submission {submission_id}"),
+                    e,
+                )
+            })
+    }
+
+    /// Returns the submissions with the specified IDs together with their
+    /// latest progress, if any has been published. Submissions that do not
+    /// exist are left out.
+    pub fn get_exec_progress_many(
+        &mut self,
+        submission_ids: &[i64],
+    ) -> Result<Vec<(Submission, Option<SubmissionProgress>)>, Error> {
+        use crate::db::schema::submissions;
+
+        let rows: Vec<(Submission, Option<serde_json::Value>)> = submissions::table
+            .filter(submissions::id.eq_any(submission_ids))
+            .select((Submission::as_select(), submissions::exec_progress))
+            .load(&mut self.conn)
+            .map_err(|e| Error::auto_msg("could not get progress of submissions", e))?;
+
+        rows.into_iter()
+            .map(|(sub, progress)| {
+                let progress = deserialize_progress(sub.id, progress)?;
+                Ok((sub, progress))
+            })
+            .collect()
+    }
+
     /// Sets the exec_date_finished to the current time and date
     pub fn set_exec_date_finished(&mut self, submission_id: i64) -> Result<(), Error> {
         use crate::db::schema::submissions;
@@ -561,143 +1745,465 @@ impl DatabaseConnection {
             })
     }
 
-    /// Updates the entry in the database, and also sends a report back to the
-    /// submission source. The format of the sent report depends on the kind of
-    /// source.
-    pub fn report_and_status(
+    /// Reports the results of a submission that is still being graded, as
+    /// configured by `reporting.incremental`. The commit status is left as is.
+    ///
+    /// In the `edit_comment` mode, the first call posts a comment that is
+    /// edited by the following calls and by the final `report_and_status`.
+    ///
+    /// Nothing is posted for a submission that has been cancelled.
+    pub fn report_partial(
         &mut self,
         settings: &Settings,
         info: &SubmissionInfo,
         report: &Report,
-        status: SubmissionStatusCode,
-        exec_finished: bool,
     ) -> Result<(), Error> {
-        use crate::db::{models::SubmissionStatusCode as SSC, schema::submissions};
+        let sub = info.get_submission();
+        let edit = match settings.reporting.incremental {
+            IncrementalReporting::Off => return Ok(()),
+            IncrementalReporting::EditComment => true,
+            IncrementalReporting::PerTag => false,
+        };
+        if sub.silent {
+            log::debug!(
+                "Not posting partial results for silent submission {}",
+                sub.id
+            );
+            return Ok(());
+        }
+        if self.is_cancelled(sub.id)? {
+            log::debug!(
+                "Not posting partial results for cancelled submission {}",
+                sub.id
+            );
+            return Ok(());
+        }
 
-        // async is annoying when you don't need it...
         let rt = tokio::runtime::Builder::new_current_thread()
             .enable_all()
             .build()
             .map_err(|e| Error::auto_msg("could not unwrap tokio runtime", e))?;
 
-        match info {
+        let report_comment = if edit {
+            self.get_report_comment(sub.id)?
+        } else {
+            None
+        };
+
+        let mut md_output = String::new();
+        regrade_wrapped(info, report).render_markdown(&settings.reporting, &mut md_output)?;
+
+        // The reference to a newly created comment, if any
+        let created_comment: Option<String> = match info {
             SubmissionInfo::GitHub {
-                sub,
-                src: _,
-                gh_src,
-                gh_info,
+                gh_src, gh_info, ..
             } => {
-                use crate::github::CommitState as GHCS;
-
-                log::debug!("Setting commit information for submission {}", sub.id);
                 let (domain, org, repo, commit) =
                     (&gh_src.domain, &gh_src.org, &gh_src.repo, &gh_info.commit);
-
-                if let Some(instance) = settings
+                let instance = settings
                     .submission
                     .github
                     .known_instances
                     .iter()
                     .find(|ki| ki.domain == *domain)
-                {
-                    let mut md_output = String::new();
-                    report.render_markdown(&settings.reporting, &mut md_output)?;
-                    rt.block_on(async {
-                        github::create_commit_message(
-                            settings, instance, org, repo, commit, &md_output,
+                    .ok_or_else(|| {
+                        Error::runtime(format!("no GitHub instance configured for domain {domain}"))
+                    })?;
+                rt.block_on(async {
+                    match report_comment.as_deref().and_then(|c| c.parse().ok()) {
+                        Some(comment_id) => github::edit_commit_comment(
+                            settings, instance, org, repo, comment_id, &md_output,
                         )
                         .await
-                    })
-                    .unwrap_or_else(|e| {
-                        log::warn!("Could not create message for commit {commit} on {repo}: {e}");
-                    });
-
-                    let gh_state: github::CommitState = match status {
-                        SSC::NotStarted | SSC::Running => GHCS::Pending,
-                        SSC::Success => GHCS::Success,
-                        SSC::SubmissionError
-                        | SSC::BuildError
-                        | SSC::BuildTimedOut
-                        | SSC::TestCasesFailed
-                        | SSC::TestCasesTimedOut
-                        | SSC::OutputLimitExceeded
-                        | SSC::SubmissionTimedOut => GHCS::Failure,
-                        SSC::AutograderFailure => GHCS::Failure,
-                    };
-
-                    rt.block_on(async {
-                        github::create_commit_status(
-                            settings, instance, org, repo, commit, gh_state, None,
+                        .map(|_| None),
+                        None => github::create_commit_comment(
+                            settings, instance, org, repo, commit, &md_output,
                         )
                         .await
-                    })
-                    .unwrap_or_else(|e| {
-                        log::warn!("Could not set status for commit {commit} on {repo}: {e}");
-                    });
-                } else {
-                    log::warn!("Could not set statis for commit {commit}: No GitHub instance configured for domain {domain}.");
-                }
+                        .map(|comment_id| Some(comment_id.to_string())),
+                    }
+                })?
             }
             SubmissionInfo::GitLab {
-                sub,
-                src: _,
-                gl_src,
-                gl_info,
+                gl_src, gl_info, ..
             } => {
-                use crate::gitlab::CommitState as GLCS;
-
-                log::debug!("Setting commit information for submission {}", sub.id);
                 let (domain, namespace, repo, commit) = (
                     &gl_src.domain,
                     &gl_src.namespace,
                     &gl_src.repo,
                     &gl_info.commit,
                 );
-
-                if let Some(instance) = settings
+                let instance = settings
                     .submission
                     .gitlab
                     .known_instances
                     .iter()
                     .find(|ki| ki.domain == *domain)
-                {
-                    let mut md_output = String::new();
-                    report.render_markdown(&settings.reporting, &mut md_output)?;
-                    rt.block_on(async {
-                        gitlab::create_commit_message(
-                            settings, instance, namespace, repo, commit, &md_output,
+                    .ok_or_else(|| {
+                        Error::runtime(format!("no GitLab instance configured for domain {domain}"))
+                    })?;
+                rt.block_on(async {
+                    match report_comment.as_deref().and_then(|c| c.parse().ok()) {
+                        Some(note) => gitlab::edit_commit_note(
+                            settings, instance, namespace, repo, commit, &note, &md_output,
                         )
                         .await
-                    })
-                    .unwrap_or_else(|e| {
-                        log::warn!("Could not create message for commit {commit} on {repo}: {e}");
-                    });
-
-                    let gl_state: gitlab::CommitState = match status {
-                        SSC::NotStarted => GLCS::Pending,
-                        SSC::Running => GLCS::Running,
-                        SSC::Success => GLCS::Success,
-                        SSC::SubmissionError
-                        | SSC::BuildError
-                        | SSC::BuildTimedOut
-                        | SSC::TestCasesFailed
-                        | SSC::TestCasesTimedOut
-                        | SSC::OutputLimitExceeded
-                        | SSC::SubmissionTimedOut => GLCS::Failed,
-                        SSC::AutograderFailure => GLCS::Canceled,
-                    };
-
-                    rt.block_on(async {
-                        gitlab::set_commit_status(
-                            settings, instance, namespace, repo, commit, gl_state, None,
+                        .map(|_| None),
+                        None => gitlab::create_commit_discussion(
+                            settings, instance, namespace, repo, commit, &md_output,
                         )
                         .await
-                    })
-                    .unwrap_or_else(|e| {
-                        log::warn!("Could not set status for commit {commit} on {repo}: {e}");
-                    });
-                } else {
-                    log::warn!("Could not set statis for commit {commit}: No GitLab instance configured for domain {domain}.");
+                        .map(|note| Some(note.to_string())),
+                    }
+                })?
+            }
+            SubmissionInfo::Git { .. } | SubmissionInfo::Gitea { .. } => {
+                // Incremental reporting is only implemented for GitHub and
+                // GitLab, these sources get the final report only.
+                None
+            }
+        };
+
+        if let (true, Some(comment)) = (edit, created_comment) {
+            self.set_report_comment(sub.id, &comment)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the reference to the comment that is edited with the results
+    /// of the submission, if one has been posted.
+    fn get_report_comment(&mut self, submission_id: i64) -> Result<Option<String>, Error> {
+        use crate::db::schema::submissions;
+
+        submissions::table
+            .find(submission_id)
+            .select(submissions::report_comment)
+            .first(&mut self.conn)
+            .map_err(|e| {
+                Error::auto_msg(
+                    format!("could not get report comment of submission {submission_id}"),
+                    e,
+                )
+            })
+    }
+
+    fn set_report_comment(&mut self, submission_id: i64, comment: &str) -> Result<(), Error> {
+        use crate::db::schema::submissions;
+
+        diesel::update(submissions::table)
+            .filter(submissions::id.eq(submission_id))
+            .set(submissions::report_comment.eq(comment))
+            .execute(&mut self.conn)
+            .map(|_| ())
+            .map_err(|e| {
+                Error::auto_msg(
+                    format!("could not set report comment of submission {submission_id}"),
+                    e,
+                )
+            })
+    }
+
+    /// Updates the entry in the database, and also sends a report back to the
+    /// submission source. The format of the sent report depends on the kind of
+    /// source.
+    ///
+    /// Submissions that have already finished are not updated in the
+    /// database, such that a cancelled submission is never reverted by the
+    /// runner that was grading it. For the same reason, nothing but the
+    /// cancellation itself is reported for a cancelled submission.
+    pub fn report_and_status(
+        &mut self,
+        settings: &Settings,
+        info: &SubmissionInfo,
+        report: &Report,
+        status: SubmissionStatusCode,
+        exec_finished: bool,
+    ) -> Result<(), Error> {
+        use crate::db::{models::SubmissionStatusCode as SSC, schema::submissions};
+
+        if status != SSC::Cancelled && self.is_cancelled(info.get_submission().id)? {
+            log::info!(
+                "Not reporting status {:?} for cancelled submission {}",
+                status,
+                info.get_submission().id
+            );
+            return Ok(());
+        }
+
+        // async is annoying when you don't need it...
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| Error::auto_msg("could not unwrap tokio runtime", e))?;
+
+        // Re-grades are marked as such, both on the commit and in the stored
+        // report.
+        let report = &regrade_wrapped(info, report);
+
+        // With incremental reporting, the final report replaces the results
+        // that have been posted so far.
+        let report_comment = if exec_finished
+            && settings.reporting.incremental == IncrementalReporting::EditComment
+        {
+            self.get_report_comment(info.get_submission().id)?
+        } else {
+            None
+        };
+
+        // The commit status is only given a description where the state alone
+        // does not say what happened.
+        let description = match status {
+            SSC::Cancelled => Some("Cancelled"),
+            _ => None,
+        };
+
+        if info.get_submission().silent {
+            log::debug!(
+                "Not posting commit information for silent submission {}",
+                info.get_submission().id
+            );
+        } else {
+            match info {
+                SubmissionInfo::GitHub {
+                    sub,
+                    src: _,
+                    gh_src,
+                    gh_info,
+                } => {
+                    use crate::github::CommitState as GHCS;
+
+                    log::debug!("Setting commit information for submission {}", sub.id);
+                    let (domain, org, repo, commit) =
+                        (&gh_src.domain, &gh_src.org, &gh_src.repo, &gh_info.commit);
+
+                    if let Some(instance) = settings
+                        .submission
+                        .github
+                        .known_instances
+                        .iter()
+                        .find(|ki| ki.domain == *domain)
+                    {
+                        let mut md_output = String::new();
+                        report.render_markdown(&settings.reporting, &mut md_output)?;
+                        rt.block_on(async {
+                            match report_comment.as_deref().and_then(|c| c.parse().ok()) {
+                                Some(comment_id) => {
+                                    github::edit_commit_comment(
+                                        settings, instance, org, repo, comment_id, &md_output,
+                                    )
+                                    .await
+                                }
+                                None => {
+                                    github::create_commit_message(
+                                        settings, instance, org, repo, commit, &md_output,
+                                    )
+                                    .await
+                                }
+                            }
+                        })
+                        .unwrap_or_else(|e| {
+                            log::warn!(
+                                "Could not create message for commit {commit} on {repo}: {e}"
+                            );
+                        });
+
+                        let gh_state: github::CommitState = match status {
+                            SSC::NotStarted | SSC::Running => GHCS::Pending,
+                            SSC::Success => GHCS::Success,
+                            SSC::Superseded | SSC::Cancelled => GHCS::Error,
+                            SSC::SubmissionError
+                            | SSC::BuildError
+                            | SSC::BuildTimedOut
+                            | SSC::TestCasesFailed
+                            | SSC::TestCasesTimedOut
+                            | SSC::OutputLimitExceeded
+                            | SSC::DeadlinePassed
+                            | SSC::SubmissionTimedOut => GHCS::Failure,
+                            SSC::AutograderFailure => GHCS::Failure,
+                        };
+
+                        rt.block_on(async {
+                            github::create_commit_status(
+                                settings,
+                                instance,
+                                org,
+                                repo,
+                                commit,
+                                gh_state,
+                                description,
+                            )
+                            .await
+                        })
+                        .unwrap_or_else(|e| {
+                            log::warn!("Could not set status for commit {commit} on {repo}: {e}");
+                        });
+                    } else {
+                        log::warn!("Could not set statis for commit {commit}: No GitHub instance configured for domain {domain}.");
+                    }
+                }
+                SubmissionInfo::GitLab {
+                    sub,
+                    src: _,
+                    gl_src,
+                    gl_info,
+                } => {
+                    use crate::gitlab::CommitState as GLCS;
+
+                    log::debug!("Setting commit information for submission {}", sub.id);
+                    let (domain, namespace, repo, commit) = (
+                        &gl_src.domain,
+                        &gl_src.namespace,
+                        &gl_src.repo,
+                        &gl_info.commit,
+                    );
+
+                    if let Some(instance) = settings
+                        .submission
+                        .gitlab
+                        .known_instances
+                        .iter()
+                        .find(|ki| ki.domain == *domain)
+                    {
+                        let mut md_output = String::new();
+                        report.render_markdown(&settings.reporting, &mut md_output)?;
+                        rt.block_on(async {
+                            match report_comment.as_deref().and_then(|c| c.parse().ok()) {
+                                Some(note) => {
+                                    gitlab::edit_commit_note(
+                                        settings, instance, namespace, repo, commit, &note,
+                                        &md_output,
+                                    )
+                                    .await
+                                }
+                                None => {
+                                    gitlab::create_commit_message(
+                                        settings, instance, namespace, repo, commit, &md_output,
+                                    )
+                                    .await
+                                }
+                            }
+                        })
+                        .unwrap_or_else(|e| {
+                            log::warn!(
+                                "Could not create message for commit {commit} on {repo}: {e}"
+                            );
+                        });
+
+                        let gl_state: gitlab::CommitState = match status {
+                            SSC::NotStarted => GLCS::Pending,
+                            SSC::Running => GLCS::Running,
+                            SSC::Success => GLCS::Success,
+                            SSC::Superseded => GLCS::Skipped,
+                            SSC::Cancelled => GLCS::Canceled,
+                            SSC::SubmissionError
+                            | SSC::BuildError
+                            | SSC::BuildTimedOut
+                            | SSC::TestCasesFailed
+                            | SSC::TestCasesTimedOut
+                            | SSC::OutputLimitExceeded
+                            | SSC::DeadlinePassed
+                            | SSC::SubmissionTimedOut => GLCS::Failed,
+                            SSC::AutograderFailure => GLCS::Canceled,
+                        };
+
+                        rt.block_on(async {
+                            gitlab::set_commit_status(
+                                settings,
+                                instance,
+                                namespace,
+                                repo,
+                                commit,
+                                gl_state,
+                                description,
+                            )
+                            .await
+                        })
+                        .unwrap_or_else(|e| {
+                            log::warn!("Could not set status for commit {commit} on {repo}: {e}");
+                        });
+                    } else {
+                        log::warn!("Could not set statis for commit {commit}: No GitLab instance configured for domain {domain}.");
+                    }
+                }
+                SubmissionInfo::Git { sub, .. } => {
+                    // Plain git sources have nowhere to post comments to. The
+                    // results are only available through the database.
+                    log::debug!(
+                        "Not posting commit information for submission {} from a plain git source",
+                        sub.id
+                    );
+                }
+                SubmissionInfo::Gitea {
+                    sub,
+                    src: _,
+                    gt_src,
+                    gt_info,
+                } => {
+                    use crate::gitea::CommitState as GTCS;
+
+                    log::debug!("Setting commit information for submission {}", sub.id);
+                    let (domain, owner, repo, commit) =
+                        (&gt_src.domain, &gt_src.owner, &gt_src.repo, &gt_info.commit);
+
+                    if let Some(instance) = settings
+                        .submission
+                        .gitea
+                        .known_instances
+                        .iter()
+                        .find(|ki| ki.domain == *domain)
+                    {
+                        let mut md_output = String::new();
+                        report.render_markdown(&settings.reporting, &mut md_output)?;
+                        rt.block_on(async {
+                            gitea::create_commit_message(
+                                settings, instance, owner, repo, commit, &md_output,
+                            )
+                            .await
+                        })
+                        .unwrap_or_else(|e| {
+                            log::warn!(
+                                "Could not create message for commit {commit} on {repo}: {e}"
+                            );
+                        });
+
+                        let gt_state: gitea::CommitState = match status {
+                            SSC::NotStarted | SSC::Running => GTCS::Pending,
+                            SSC::Success => GTCS::Success,
+                            SSC::Superseded | SSC::Cancelled => GTCS::Warning,
+                            SSC::SubmissionError
+                            | SSC::BuildError
+                            | SSC::BuildTimedOut
+                            | SSC::TestCasesFailed
+                            | SSC::TestCasesTimedOut
+                            | SSC::OutputLimitExceeded
+                            | SSC::DeadlinePassed
+                            | SSC::SubmissionTimedOut => GTCS::Failure,
+                            SSC::AutograderFailure => GTCS::Error,
+                        };
+
+                        // The report is only posted if the commit heads an
+                        // open pull request, so the status always says what
+                        // the result was and which submission has the report.
+                        let gt_description = format!("Submission {}: {}", sub.id, status);
+
+                        rt.block_on(async {
+                            gitea::create_commit_status(
+                                settings,
+                                instance,
+                                owner,
+                                repo,
+                                commit,
+                                gt_state,
+                                Some(&gt_description),
+                            )
+                            .await
+                        })
+                        .unwrap_or_else(|e| {
+                            log::warn!("Could not set status for commit {commit} on {repo}: {e}");
+                        });
+                    } else {
+                        log::warn!("Could not set statis for commit {commit}: No Gitea instance configured for domain {domain}.");
+                    }
                 }
             }
         }
@@ -712,6 +2218,7 @@ impl DatabaseConnection {
 
         diesel::update(submissions::table)
             .filter(submissions::id.eq(sub.id))
+            .filter(submissions::exec_finished.eq(false))
             .set((
                 submissions::exec_status_code.eq(status as i32),
                 submissions::exec_finished.eq(exec_finished),
@@ -759,3 +2266,287 @@ impl DatabaseConnection {
             })
     }
 }
+
+/// Checks that registering a submission of `grading_tags` from the source
+/// `source_id` at `now` does not exceed any of the `rate_limits`. The source
+/// is locked until the end of the transaction, such that concurrent
+/// submissions from the same source cannot both slip through.
+fn check_rate_limits(
+    conn: &mut PgConnection,
+    source_id: i64,
+    grading_tags: &[&str],
+    rate_limits: &[RateLimitSettings],
+    now: SystemTime,
+) -> Result<(), Error> {
+    use crate::db::schema::{
+        submission_sources,
+        submissions::{self, columns as sub_col},
+    };
+
+    let Some(longest_period) = rate_limits.iter().map(|rl| rl.period_minutes).max() else {
+        return Ok(());
+    };
+
+    submission_sources::table
+        .find(source_id)
+        .select(submission_sources::id)
+        .for_update()
+        .first::<i64>(conn)
+        .map_err(|e| Error::auto_msg("could not lock submission source", e))?;
+
+    let since = now
+        .checked_sub(Duration::from_secs(60 * longest_period))
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let recent: Vec<(SystemTime, String)> = submissions::table
+        .select((sub_col::date_submitted, sub_col::grading_tags))
+        .filter(sub_col::source_id.eq(source_id))
+        .filter(sub_col::regrade_of.is_null())
+        .filter(sub_col::date_submitted.ge(since))
+        .order(sub_col::date_submitted.asc())
+        .load(conn)
+        .map_err(|e| Error::auto_msg("could not count recent submissions", e))?;
+
+    match rate_limit_next_allowed(&recent, grading_tags, rate_limits, now) {
+        Some(next_allowed) => {
+            log::info!(
+                "Submission from source {source_id} rejected by rate limit until {:?}",
+                next_allowed
+            );
+            Error::err_rate_limited(next_allowed)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Returns the earliest time at which a submission of `grading_tags` is
+/// allowed, or `None` if it is allowed at `now`. The `recent` submissions from
+/// the same source are pairs of submission time and `;` separated grading
+/// tags, in ascending order of time.
+fn rate_limit_next_allowed(
+    recent: &[(SystemTime, String)],
+    grading_tags: &[&str],
+    rate_limits: &[RateLimitSettings],
+    now: SystemTime,
+) -> Option<SystemTime> {
+    let mut next_allowed: Option<SystemTime> = None;
+    for rl in rate_limits {
+        if rl.max_submissions == 0 || rl.period_minutes == 0 {
+            // The limit is disabled
+            continue;
+        }
+        let period = Duration::from_secs(60 * rl.period_minutes);
+        let in_period = |t: &SystemTime| now.duration_since(*t).map_or(true, |d| d < period);
+
+        // Times of the submissions that count towards this limit, once for
+        // every submitted tag if the limit is per tag.
+        let counted: Vec<Vec<SystemTime>> = if rl.per_tag {
+            grading_tags
+                .iter()
+                .map(|tag| {
+                    recent
+                        .iter()
+                        .filter(|(t, tags)| in_period(t) && tags.split(";").any(|rt| rt == *tag))
+                        .map(|(t, _)| *t)
+                        .collect()
+                })
+                .collect()
+        } else {
+            vec![recent
+                .iter()
+                .filter(|(t, _)| in_period(t))
+                .map(|(t, _)| *t)
+                .collect()]
+        };
+
+        for times in counted {
+            if times.len() < rl.max_submissions {
+                continue;
+            }
+            // Allowed once enough of the counted submissions have left the
+            // period, such that fewer than max_submissions remain.
+            let allowed = times
+                .get(times.len() - rl.max_submissions)
+                .map_or(now, |t| *t)
+                + period;
+            next_allowed = Some(next_allowed.map_or(allowed, |na| na.max(allowed)));
+        }
+    }
+    next_allowed
+}
+
+/// Returns the `;` separated tags of `grading_tags` to re-grade when only
+/// `tags` are requested, or `None` if none of them were graded. All tags are
+/// re-graded if `tags` is empty.
+fn regrade_tags(grading_tags: &str, tags: &[String]) -> Option<String> {
+    let selected: Vec<&str> = grading_tags
+        .split(";")
+        .filter(|gt| !gt.is_empty() && (tags.is_empty() || tags.iter().any(|t| t == gt)))
+        .collect();
+    if selected.is_empty() {
+        None
+    } else {
+        Some(selected.join(";"))
+    }
+}
+
+/// Escapes the wildcards of a `LIKE` pattern, using `\` which is the default
+/// escape character in postgres.
+fn escape_like(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Marks the report of a re-grade as such, as it is posted on the same commit
+/// as the results of the original submission.
+fn regrade_wrapped<'a>(info: &SubmissionInfo, report: &'a Report) -> Cow<'a, Report> {
+    match info.get_submission().regrade_of {
+        Some(original_id) => Cow::Owned(Report::Wrapper(ReportWrapper {
+            title: Some(format!("Re-grade of submission {original_id}")),
+            reports: vec![report.clone()],
+        })),
+        None => Cow::Borrowed(report),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asserting::prelude::*;
+
+    #[test]
+    fn test_rate_limit_next_allowed() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(100_000);
+        let mins_ago = |m: u64| now - Duration::from_secs(60 * m);
+        let recent: Vec<(SystemTime, String)> = vec![
+            (mins_ago(90), "hello".to_string()),
+            (mins_ago(50), "hello;hello-asm".to_string()),
+            (mins_ago(20), "hello-asm".to_string()),
+        ];
+        let per_hour = |max_submissions: usize, per_tag: bool| RateLimitSettings {
+            max_submissions: max_submissions,
+            period_minutes: 60,
+            per_tag: per_tag,
+        };
+
+        // Only the last two submissions are within the last hour
+        assert_that!(rate_limit_next_allowed(
+            &recent,
+            &["hello"],
+            &[per_hour(3, false)],
+            now
+        ))
+        .is_equal_to(None);
+        assert_that!(rate_limit_next_allowed(
+            &recent,
+            &["hello"],
+            &[per_hour(2, false)],
+            now
+        ))
+        .is_equal_to(Some(mins_ago(50) + Duration::from_secs(3600)));
+        assert_that!(rate_limit_next_allowed(
+            &recent,
+            &["hello"],
+            &[per_hour(1, false)],
+            now
+        ))
+        .is_equal_to(Some(mins_ago(20) + Duration::from_secs(3600)));
+
+        // Per tag, only one of the submissions within the last hour is for hello
+        assert_that!(rate_limit_next_allowed(
+            &recent,
+            &["hello"],
+            &[per_hour(2, true)],
+            now
+        ))
+        .is_equal_to(None);
+        assert_that!(rate_limit_next_allowed(
+            &recent,
+            &["hello", "hello-asm"],
+            &[per_hour(2, true)],
+            now
+        ))
+        .is_equal_to(Some(mins_ago(50) + Duration::from_secs(3600)));
+
+        // The latest time is used when several limits are exceeded
+        assert_that!(rate_limit_next_allowed(
+            &recent,
+            &["hello"],
+            &[per_hour(1, false), per_hour(2, false)],
+            now
+        ))
+        .is_equal_to(Some(mins_ago(20) + Duration::from_secs(3600)));
+
+        // Limits with a maximum or period of 0 are disabled
+        let no_period = RateLimitSettings {
+            period_minutes: 0,
+            ..per_hour(1, false)
+        };
+        assert_that!(rate_limit_next_allowed(
+            &recent,
+            &["hello"],
+            &[per_hour(0, false), per_hour(0, true), no_period],
+            now
+        ))
+        .is_equal_to(None);
+    }
+
+    #[test]
+    fn test_filter_new() {
+        let tags = vec![
+            "#hello".to_string(),
+            "%hello-asm".to_string(),
+            "x".to_string(),
+        ];
+        let filter = RegradeFilter::new(&tags, None, None, vec![], &[200, 403]).unwrap();
+        assert_that!(filter.tags).is_equal_to(vec![
+            "hello".to_string(),
+            "hello-asm".to_string(),
+            "x".to_string(),
+        ]);
+        assert_that!(filter.statuses).is_equal_to(vec![
+            SubmissionStatusCode::Success,
+            SubmissionStatusCode::TestCasesFailed,
+        ]);
+
+        assert_that!(RegradeFilter::new(&tags, None, None, vec![], &[201])).is_err();
+        assert_that!(HistoryFilter::new(
+            vec![],
+            vec![],
+            &tags,
+            None,
+            None,
+            &[999]
+        ))
+        .is_err();
+    }
+
+    #[test]
+    fn test_regrade_tags() {
+        let tags = |ts: &[&str]| ts.iter().map(|t| t.to_string()).collect::<Vec<String>>();
+
+        assert_that!(regrade_tags("hello;hello-asm", &[]))
+            .is_equal_to(Some("hello;hello-asm".to_string()));
+        assert_that!(regrade_tags("hello;hello-asm", &tags(&["hello-asm"])))
+            .is_equal_to(Some("hello-asm".to_string()));
+        assert_that!(regrade_tags(
+            "hello;hello-asm",
+            &tags(&["hello-asm", "hello", "other"])
+        ))
+        .is_equal_to(Some("hello;hello-asm".to_string()));
+        assert_that!(regrade_tags("hello", &tags(&["hello-asm"]))).is_equal_to(None);
+        assert_that!(regrade_tags("", &[])).is_equal_to(None);
+    }
+
+    #[test]
+    fn test_escape_like() {
+        assert_that!(escape_like("hello-all")).is_equal_to("hello-all".to_string());
+        assert_that!(escape_like("hello_asm")).is_equal_to("hello\\_asm".to_string());
+        assert_that!(escape_like("100%\\")).is_equal_to("100\\%\\\\".to_string());
+    }
+}
@@ -14,6 +14,10 @@ pub enum SubmissionStatusCode {
     NotStarted = 0,
     Running = 100,
     Success = 200,
+    // Not graded since a newer submission replaced it in the queue
+    Superseded = 300,
+    // Cancelled by course staff or the submitter before it finished grading
+    Cancelled = 301,
     SubmissionError = 400,
     BuildError = 401,
     BuildTimedOut = 402,
@@ -21,6 +25,7 @@ pub enum SubmissionStatusCode {
     TestCasesTimedOut = 404,
     SubmissionTimedOut = 405,
     OutputLimitExceeded = 406,
+    DeadlinePassed = 408,
     // Internal failure by the autograder
     AutograderFailure = 500,
 }
@@ -44,6 +49,8 @@ impl std::fmt::Display for SubmissionStatusCode {
             Self::NotStarted => write!(f, "Not Started"),
             Self::Running => write!(f, "Running"),
             Self::Success => write!(f, "Success"),
+            Self::Superseded => write!(f, "Superseded"),
+            Self::Cancelled => write!(f, "Cancelled"),
             Self::SubmissionError => write!(f, "Submission Error"),
             Self::BuildError => write!(f, "Build Error"),
             Self::BuildTimedOut => write!(f, "Build Timed Out"),
@@ -51,6 +58,7 @@ impl std::fmt::Display for SubmissionStatusCode {
             Self::TestCasesTimedOut => write!(f, "Test Cases Timed Out"),
             Self::SubmissionTimedOut => write!(f, "Submission Timed Out"),
             Self::OutputLimitExceeded => write!(f, "Output Limit Exceeded"),
+            Self::DeadlinePassed => write!(f, "Deadline Passed"),
             Self::AutograderFailure => write!(f, "Autograder Failure"),
         }
     }
@@ -61,6 +69,8 @@ impl std::fmt::Display for SubmissionStatusCode {
 pub enum SubmissionSourceKind {
     GitHub = 0,
     GitLab = 1,
+    Git = 2,
+    Gitea = 3,
 }
 
 impl std::fmt::Display for SubmissionSourceKind {
@@ -68,6 +78,8 @@ impl std::fmt::Display for SubmissionSourceKind {
         match self {
             Self::GitHub => write!(f, "GitHub"),
             Self::GitLab => write!(f, "GitLab"),
+            Self::Git => write!(f, "Git"),
+            Self::Gitea => write!(f, "Gitea"),
         }
     }
 }
@@ -92,6 +104,9 @@ pub struct Submission {
     pub exec_date_started: Option<SystemTime>,
     pub exec_date_finished: Option<SystemTime>,
     pub source_id: i64,
+    pub priority: i32,
+    pub regrade_of: Option<i64>,
+    pub silent: bool,
 }
 
 /// Same as `Submission`, but this also includes the `exec_report`.
@@ -113,6 +128,9 @@ pub struct SubmissionWithReport {
     pub exec_date_finished: Option<SystemTime>,
     pub exec_report: Option<serde_json::Value>,
     pub source_id: i64,
+    pub priority: i32,
+    pub regrade_of: Option<i64>,
+    pub silent: bool,
 }
 
 #[derive(Debug, Clone, Insertable)]
@@ -124,6 +142,9 @@ pub struct NewSubmission {
     pub exec_finished: bool,
     pub exec_status_code: i32,
     pub source_id: i64,
+    pub priority: i32,
+    pub regrade_of: Option<i64>,
+    pub silent: bool,
 }
 
 #[derive(Debug, Clone, Queryable, Identifiable, QueryableByName, Selectable)]
@@ -233,6 +254,135 @@ pub struct NewSubmissionInfoGitLab {
     pub commit: String,
 }
 
+#[derive(Debug, Clone, Queryable, Identifiable, QueryableByName, Selectable)]
+#[diesel(table_name = crate::db::schema::submission_source_git)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SubmissionSourceGit {
+    pub id: i64,
+    pub url: String,
+}
+
+impl SubmissionSourceGit {
+    /// Returns a relative path identifying the repository, derived from its
+    /// URL. E.g. both `git@git.foo.com:bar/baz.git` and
+    /// `https://git.foo.com/bar/baz.git` become `git.foo.com/bar/baz`.
+    ///
+    /// Empty components, as well as `.` and `..`, are removed from the path.
+    pub fn repo_path(&self) -> String {
+        let without_scheme = match self.url.split_once("://") {
+            Some((_, rest)) => rest,
+            None => self.url.as_str(),
+        };
+        let without_user = match without_scheme.split_once('@') {
+            Some((_, rest)) => rest,
+            None => without_scheme,
+        };
+        without_user
+            .trim_end_matches('/')
+            .trim_end_matches(".git")
+            .split(['/', ':'])
+            .filter(|c| !c.is_empty() && *c != "." && *c != "..")
+            .collect::<Vec<&str>>()
+            .join("/")
+    }
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = crate::db::schema::submission_source_git)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewSubmissionSourceGit {
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Queryable, Identifiable, QueryableByName, Selectable, Associations)]
+#[diesel(belongs_to(Submission))]
+#[diesel(belongs_to(SubmissionSourceGit, foreign_key = git_source_id))]
+#[diesel(table_name = crate::db::schema::submission_info_git)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SubmissionInfoGit {
+    pub id: i64,
+    pub submission_id: i64,
+    pub git_source_id: i64,
+    pub user: String,
+    pub commit: String,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = crate::db::schema::submission_info_git)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewSubmissionInfoGit {
+    pub submission_id: i64,
+    pub git_source_id: i64,
+    pub user: String,
+    pub commit: String,
+}
+
+#[derive(Debug, Clone, Queryable, Identifiable, QueryableByName, Selectable)]
+#[diesel(table_name = crate::db::schema::submission_source_gitea)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SubmissionSourceGitea {
+    pub id: i64,
+    pub domain: String,
+    pub owner: String,
+    pub repo: String,
+    pub ssh_url: String,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = crate::db::schema::submission_source_gitea)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewSubmissionSourceGitea {
+    pub domain: String,
+    pub owner: String,
+    pub repo: String,
+    pub ssh_url: String,
+}
+
+#[derive(Debug, Clone, Queryable, Identifiable, QueryableByName, Selectable, Associations)]
+#[diesel(belongs_to(Submission))]
+#[diesel(belongs_to(SubmissionSourceGitea, foreign_key = gitea_source_id))]
+#[diesel(table_name = crate::db::schema::submission_info_gitea)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SubmissionInfoGitea {
+    pub id: i64,
+    pub submission_id: i64,
+    pub gitea_source_id: i64,
+    pub user: String,
+    pub commit: String,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = crate::db::schema::submission_info_gitea)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewSubmissionInfoGitea {
+    pub submission_id: i64,
+    pub gitea_source_id: i64,
+    pub user: String,
+    pub commit: String,
+}
+
+/// A grade that has been posted to Canvas.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate::db::schema::canvas_grades)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct CanvasSyncedGrade {
+    pub id: i64,
+    pub canvas_user: String,
+    pub assignment_id: i64,
+    pub grade: String,
+    pub date_synced: SystemTime,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = crate::db::schema::canvas_grades)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewCanvasSyncedGrade {
+    pub canvas_user: String,
+    pub assignment_id: i64,
+    pub grade: String,
+    pub date_synced: SystemTime,
+}
+
 /// Enumerator containing information over the possible sources
 #[derive(Debug, Clone)]
 pub enum SubmissionInfo {
@@ -248,6 +398,18 @@ pub enum SubmissionInfo {
         gl_src: SubmissionSourceGitLab,
         gl_info: SubmissionInfoGitLab,
     },
+    Git {
+        sub: Submission,
+        src: SubmissionSource,
+        git_src: SubmissionSourceGit,
+        git_info: SubmissionInfoGit,
+    },
+    Gitea {
+        sub: Submission,
+        src: SubmissionSource,
+        gt_src: SubmissionSourceGitea,
+        gt_info: SubmissionInfoGitea,
+    },
 }
 
 impl SubmissionInfo {
@@ -255,12 +417,59 @@ impl SubmissionInfo {
         match self {
             Self::GitHub { sub, .. } => sub,
             Self::GitLab { sub, .. } => sub,
+            Self::Git { sub, .. } => sub,
+            Self::Gitea { sub, .. } => sub,
         }
     }
     pub fn get_source(&self) -> &SubmissionSource {
         match self {
             Self::GitHub { src, .. } => src,
             Self::GitLab { src, .. } => src,
+            Self::Git { src, .. } => src,
+            Self::Gitea { src, .. } => src,
+        }
+    }
+
+    /// Returns the owner of the submitted repository, i.e. the GitHub
+    /// organization, the GitLab namespace, or the Gitea owner. Plain git
+    /// sources have no owner.
+    pub fn owner(&self) -> Option<&str> {
+        match self {
+            Self::GitHub { gh_src, .. } => Some(&gh_src.org),
+            Self::GitLab { gl_src, .. } => Some(&gl_src.namespace),
+            Self::Git { .. } => None,
+            Self::Gitea { gt_src, .. } => Some(&gt_src.owner),
+        }
+    }
+
+    /// Returns the name of the submitted repository. Plain git sources are
+    /// only identified by their URL, and have no name.
+    pub fn repo(&self) -> Option<&str> {
+        match self {
+            Self::GitHub { gh_src, .. } => Some(&gh_src.repo),
+            Self::GitLab { gl_src, .. } => Some(&gl_src.repo),
+            Self::Git { .. } => None,
+            Self::Gitea { gt_src, .. } => Some(&gt_src.repo),
+        }
+    }
+
+    /// Returns the domain of the instance that the repository is hosted on.
+    pub fn domain(&self) -> Option<&str> {
+        match self {
+            Self::GitHub { gh_src, .. } => Some(&gh_src.domain),
+            Self::GitLab { gl_src, .. } => Some(&gl_src.domain),
+            Self::Git { .. } => None,
+            Self::Gitea { gt_src, .. } => Some(&gt_src.domain),
+        }
+    }
+
+    /// Returns the user that made the submission.
+    pub fn user(&self) -> &str {
+        match self {
+            Self::GitHub { gh_info, .. } => &gh_info.user,
+            Self::GitLab { gl_info, .. } => &gl_info.user,
+            Self::Git { git_info, .. } => &git_info.user,
+            Self::Gitea { gt_info, .. } => &gt_info.user,
         }
     }
 
@@ -273,6 +482,12 @@ impl SubmissionInfo {
             Self::GitLab {
                 gl_src, gl_info, ..
             } => (&gl_src.ssh_url, &gl_info.commit),
+            Self::Git {
+                git_src, git_info, ..
+            } => (&git_src.url, &git_info.commit),
+            Self::Gitea {
+                gt_src, gt_info, ..
+            } => (&gt_src.ssh_url, &gt_info.commit),
         }
     }
 }
@@ -11,21 +11,34 @@ use crate::config::Settings;
 use crate::error::Error;
 
 /// Listens to a notification on the channel `ch` on the postgres database
-/// configured in the settings `s`.
+/// configured in the settings `s`. Returns `Ok(false)` if no notification was
+/// received before the poll timeout.
+///
+/// See `listen_any` for more details.
+pub fn listen<S: AsRef<str>>(s: &Settings, ch: S) -> Result<bool, Error> {
+    listen_any(s, &[ch]).map(|received| received.is_some())
+}
+
+/// Listens to a notification on any of the channels `chs` on the postgres
+/// database configured in the settings `s`. Returns the channel of the
+/// received notification, or `None` if no notification was received before
+/// the poll timeout.
 ///
 /// See this link for more information:
 /// https://www.postgresql.org/docs/current/sql-listen.html
 ///
 /// The `NOTIFY` command is handled in `conn.rs`.
 ///
-/// Warning: The value for `ch` can never come from a user as that will be
+/// Warning: The values in `chs` can never come from a user as they will be
 /// hardcoded into the query.
 ///
 /// Note: This will open up a new connection to the database.
-pub fn listen<S: AsRef<str>>(s: &Settings, ch: S) -> Result<bool, Error> {
-    // Check that the channel is only ASCII alphabet chars
-    if !ch.as_ref().bytes().all(|c| c.is_ascii_alphabetic()) {
-        return Error::err_format("notify channel", ch.as_ref());
+pub fn listen_any<S: AsRef<str>>(s: &Settings, chs: &[S]) -> Result<Option<String>, Error> {
+    // Check that the channels are only ASCII alphabet chars
+    for ch in chs {
+        if !ch.as_ref().bytes().all(|c| c.is_ascii_alphabetic()) {
+            return Error::err_format("notify channel", ch.as_ref());
+        }
     }
 
     let conn_string: String = format!(
@@ -35,14 +48,16 @@ pub fn listen<S: AsRef<str>>(s: &Settings, ch: S) -> Result<bool, Error> {
     let mut client = Client::connect(&conn_string, NoTls)
         .map_err(|e| Error::auto_msg("could not connect to database for listen()", e))?;
 
-    client
-        .execute(&format!("LISTEN {};", ch.as_ref()), &[])
-        .map_err(|e| {
-            Error::auto_msg(
-                format!("could not LISTEN on channel \"{}\"", ch.as_ref()),
-                e,
-            )
-        })?;
+    for ch in chs {
+        client
+            .execute(&format!("LISTEN {};", ch.as_ref()), &[])
+            .map_err(|e| {
+                Error::auto_msg(
+                    format!("could not LISTEN on channel \"{}\"", ch.as_ref()),
+                    e,
+                )
+            })?;
+    }
 
     // https://docs.rs/postgres/0.19.12/postgres/struct.Notifications.html
     // https://docs.rs/postgres/0.19.12/postgres/notifications/struct.TimeoutIter.html
@@ -50,8 +65,5 @@ pub fn listen<S: AsRef<str>>(s: &Settings, ch: S) -> Result<bool, Error> {
     let mut to_iter =
         notifications.timeout_iter(Duration::from_millis(s.notify.poll_timeout_millisec as u64));
 
-    match to_iter.next()? {
-        Some(_) => Ok(true),
-        None => Ok(false),
-    }
+    Ok(to_iter.next()?.map(|n| n.channel().to_string()))
 }
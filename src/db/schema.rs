@@ -1,5 +1,35 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    canvas_grades (id) {
+        id -> Int8,
+        canvas_user -> Text,
+        assignment_id -> Int8,
+        grade -> Text,
+        date_synced -> Timestamp,
+    }
+}
+
+diesel::table! {
+    submission_info_git (id) {
+        id -> Int8,
+        submission_id -> Int8,
+        git_source_id -> Int8,
+        user -> Text,
+        commit -> Text,
+    }
+}
+
+diesel::table! {
+    submission_info_gitea (id) {
+        id -> Int8,
+        submission_id -> Int8,
+        gitea_source_id -> Int8,
+        user -> Text,
+        commit -> Text,
+    }
+}
+
 diesel::table! {
     submission_info_github (id) {
         id -> Int8,
@@ -20,6 +50,23 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    submission_source_git (id) {
+        id -> Int8,
+        url -> Text,
+    }
+}
+
+diesel::table! {
+    submission_source_gitea (id) {
+        id -> Int8,
+        domain -> Text,
+        owner -> Text,
+        repo -> Text,
+        ssh_url -> Text,
+    }
+}
+
 diesel::table! {
     submission_source_github (id) {
         id -> Int8,
@@ -62,9 +109,18 @@ diesel::table! {
         exec_date_finished -> Nullable<Timestamp>,
         exec_report -> Nullable<Json>,
         source_id -> Int8,
+        priority -> Int4,
+        regrade_of -> Nullable<Int8>,
+        silent -> Bool,
+        exec_progress -> Nullable<Json>,
+        report_comment -> Nullable<Text>,
     }
 }
 
+diesel::joinable!(submission_info_git -> submission_source_git (git_source_id));
+diesel::joinable!(submission_info_git -> submissions (submission_id));
+diesel::joinable!(submission_info_gitea -> submission_source_gitea (gitea_source_id));
+diesel::joinable!(submission_info_gitea -> submissions (submission_id));
 diesel::joinable!(submission_info_github -> submission_source_github (github_source_id));
 diesel::joinable!(submission_info_github -> submissions (submission_id));
 diesel::joinable!(submission_info_gitlab -> submission_source_gitlab (gitlab_source_id));
@@ -72,8 +128,13 @@ diesel::joinable!(submission_info_gitlab -> submissions (submission_id));
 diesel::joinable!(submissions -> submission_sources (source_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    canvas_grades,
+    submission_info_git,
+    submission_info_gitea,
     submission_info_github,
     submission_info_gitlab,
+    submission_source_git,
+    submission_source_gitea,
     submission_source_github,
     submission_source_gitlab,
     submission_sources,
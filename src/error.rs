@@ -200,6 +200,7 @@ define_error_kinds! {
     Format { msg: String, value: String },
     Identifier { got: String, expected: Vec<String> },
     HttpResponse { msg: String, code: u16, text: String },
+    RateLimited { next_allowed: std::time::SystemTime },
 }
 
 // Add these manually for the predefined TestConfigError and SyscommandError in the macro
@@ -271,6 +272,14 @@ impl std::fmt::Display for Error {
                     msg, code, text
                 )
             }
+            ErrorKind::RateLimited { next_allowed } => {
+                write!(
+                    f,
+                    "rate limit exceeded, next submission allowed at {}",
+                    crate::utils::systemtime_to_utc_string(next_allowed)
+                        .unwrap_or("unknown time".to_string())
+                )
+            }
             ErrorKind::Syscommand(e) => {
                 write!(f, "syscommand error on {:?}", e.cmd)?;
                 if let Some(msg) = &e.msg {
@@ -0,0 +1,345 @@
+/// Various Gitea related utilities. Forgejo is API compatible with Gitea, so
+/// these are used for Forgejo instances as well.
+use crate::{
+    config::{settings::GiteaServerSettings, Settings},
+    error::Error,
+};
+use reqwest::{
+    self,
+    header::{HeaderMap, HeaderValue},
+    Client as ReqwestClient,
+};
+use serde::{Deserialize, Serialize};
+
+/// Common HTTP headers for Gitea API calls.
+fn common_headers(
+    _settings: &Settings,
+    instance: &GiteaServerSettings,
+) -> Result<HeaderMap, Error> {
+    let mut headers = HeaderMap::new();
+    headers.insert("Accept", HeaderValue::from_static("application/json"));
+    headers.insert(
+        "Authorization",
+        format!("token {}", instance.auth_token)
+            .parse()
+            .map_err(|e| {
+                log::error!("Could not convert Gitea auth token to header value");
+                Error::parse_type(
+                    "Gitea auth token header value".to_string(),
+                    instance.auth_token.clone(),
+                )
+                .with_cause(Box::new(e))
+            })?,
+    );
+    Ok(headers)
+}
+
+/// Base URL of the API of a Gitea instance.
+fn api_url(instance: &GiteaServerSettings) -> String {
+    format!(
+        "{}://{}/api/v1",
+        if instance.use_https { "https" } else { "http" },
+        instance.domain
+    )
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GtComment {
+    body: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GtPullRequestHead {
+    sha: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GtPullRequest {
+    number: u64,
+    head: GtPullRequestHead,
+}
+
+/// Number of pull requests fetched per page when listing pull requests.
+const PULL_REQUESTS_PER_PAGE: usize = 50;
+
+/// Returns the number of the open pull request whose head is the given
+/// commit, if there is any.
+/// https://docs.gitea.com/api/1.22/#tag/repository/operation/repoListPullRequests
+async fn find_pull_request(
+    c: &ReqwestClient,
+    headers: &HeaderMap,
+    instance: &GiteaServerSettings,
+    owner: &str,
+    repo_name: &str,
+    commit_hash: &str,
+) -> Result<Option<u64>, Error> {
+    for page in 1.. {
+        let response = c
+            .get(format!(
+                "{}/repos/{}/{}/pulls?state=open&page={}&limit={}",
+                api_url(instance),
+                owner,
+                repo_name,
+                page,
+                PULL_REQUESTS_PER_PAGE
+            ))
+            .headers(headers.clone())
+            .send()
+            .await
+            .map_err(|e| {
+                log::error!("Error with Gitea pull request lookup: {e}");
+                Error::auto_msg("error with Gitea pull request lookup", e)
+            })?;
+
+        let code = response.status().as_u16();
+        if !(200..300).contains(&code) {
+            return Error::err_http_response(
+                "when listing the pull requests of a repository".to_string(),
+                code,
+                response
+                    .text()
+                    .await
+                    .unwrap_or("no text received".to_string()),
+            );
+        }
+        let pull_requests = response
+            .json::<Vec<GtPullRequest>>()
+            .await
+            .map_err(|e| Error::auto_msg("could not parse the Gitea pull requests", e))?;
+
+        if let Some(pr) = pull_requests.iter().find(|pr| pr.head.sha == commit_hash) {
+            return Ok(Some(pr.number));
+        }
+        if pull_requests.len() < PULL_REQUESTS_PER_PAGE {
+            break;
+        }
+    }
+    Ok(None)
+}
+
+/// Creates a commit message for that specific repo and commit hash.
+///
+/// Neither Gitea nor Forgejo expose commit comments over their API, so the
+/// message is posted as a comment on the open pull request whose head is the
+/// commit instead. If there is no such pull request, there is nowhere to post
+/// the message and it is skipped, leaving only the commit status.
+/// https://docs.gitea.com/api/1.22/#tag/issue/operation/issueCreateComment
+pub async fn create_commit_message(
+    settings: &Settings,
+    instance: &GiteaServerSettings,
+    owner: &str,
+    repo_name: &str,
+    commit_hash: &str,
+    message: &impl std::fmt::Display,
+) -> Result<(), Error> {
+    let c = ReqwestClient::new();
+    let headers = common_headers(settings, instance)?;
+    let Some(pull_request) =
+        find_pull_request(&c, &headers, instance, owner, repo_name, commit_hash).await?
+    else {
+        log::info!(
+            "Not posting message for commit {commit_hash} on {owner}/{repo_name}, as it is not the head of an open pull request"
+        );
+        return Ok(());
+    };
+
+    let response = c
+        .post(format!(
+            "{}/repos/{}/{}/issues/{}/comments",
+            api_url(instance),
+            owner,
+            repo_name,
+            pull_request
+        ))
+        .headers(headers)
+        .json(&GtComment {
+            body: format!(
+                "Commit {}:\n\n{}\n\n{}",
+                commit_hash, message, settings.submission.comment_signature
+            ),
+        })
+        .send()
+        .await
+        .map_err(|e| {
+            log::error!("Error with Gitea comment: {e}");
+            Error::auto_msg("error with Gitea comment request", e)
+        })?;
+
+    if 200 <= response.status().as_u16() && response.status().as_u16() < 300 {
+        log::debug!(
+            "Successfully posted comment for commit {} to pull request #{}",
+            commit_hash,
+            pull_request
+        );
+        Ok(())
+    } else {
+        Error::err_http_response(
+            "when creating pull request comment".to_string(),
+            response.status().as_u16(),
+            response
+                .text()
+                .await
+                .unwrap_or("no text received".to_string()),
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum CommitState {
+    Pending,
+    Success,
+    Error,
+    Failure,
+    Warning,
+}
+impl CommitState {
+    fn to_str(&self) -> &str {
+        match self {
+            CommitState::Pending => "pending",
+            CommitState::Success => "success",
+            CommitState::Error => "error",
+            CommitState::Failure => "failure",
+            CommitState::Warning => "warning",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GtCommitStatus {
+    state: String,
+    context: String,
+    description: Option<String>,
+}
+
+/// Creates a commit status for that specific repo and commit hash
+/// https://docs.gitea.com/api/1.22/#tag/repository/operation/repoCreateStatus
+pub async fn create_commit_status(
+    settings: &Settings,
+    instance: &GiteaServerSettings,
+    owner: &str,
+    repo_name: &str,
+    commit_hash: &str,
+    state: CommitState,
+    description: Option<&str>,
+) -> Result<(), Error> {
+    let c = ReqwestClient::new();
+    let response = c
+        .post(format!(
+            "{}/repos/{}/{}/statuses/{}",
+            api_url(instance),
+            owner,
+            repo_name,
+            commit_hash
+        ))
+        .headers(common_headers(settings, instance)?)
+        .json(&GtCommitStatus {
+            state: state.to_str().to_string(),
+            context: "autograder".to_string(),
+            description: description.map(|s| s.to_owned()),
+        })
+        .send()
+        .await
+        .map_err(|e| {
+            log::error!("Error with Gitea commit status: {e}");
+            Error::auto_msg("error with Gitea commit status request", e)
+        })?;
+
+    if 200 <= response.status().as_u16() && response.status().as_u16() < 300 {
+        log::debug!(
+            "Successfully created commit status on commit {}",
+            commit_hash
+        );
+        Ok(())
+    } else {
+        Error::err_http_response(
+            "when creating commit status".to_string(),
+            response.status().as_u16(),
+            response
+                .text()
+                .await
+                .unwrap_or("no text received".to_string()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::local_http;
+    use asserting::prelude::*;
+
+    fn instance(domain: String) -> GiteaServerSettings {
+        GiteaServerSettings {
+            domain: domain,
+            auth_token: "secret".to_string(),
+            allowed_owners: vec![],
+            allowed_repo_prefixes: vec![],
+            allowed_repo_suffixes: vec![],
+            prohibited_repo_prefixes: vec![],
+            prohibited_repo_suffixes: vec![],
+            use_https: false,
+        }
+    }
+
+    fn pull_requests(numbers: std::ops::Range<u64>, sha: impl Fn(u64) -> String) -> String {
+        let prs: Vec<String> = numbers
+            .map(|n| format!("{{\"number\": {n}, \"head\": {{\"sha\": \"{}\"}}}}", sha(n)))
+            .collect();
+        format!("[{}]", prs.join(","))
+    }
+
+    fn find(responses: Vec<(&'static str, String)>) -> (Result<Option<u64>, Error>, Vec<String>) {
+        let (domain, server) = local_http::serve(responses);
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let found = rt.block_on(find_pull_request(
+            &ReqwestClient::new(),
+            &HeaderMap::new(),
+            &instance(domain),
+            "owner",
+            "repo",
+            "abc123",
+        ));
+        let requests = server.join().unwrap();
+        let request_lines = requests
+            .iter()
+            .map(|r| r.lines().next().unwrap_or("").to_string())
+            .collect();
+        (found, request_lines)
+    }
+
+    #[test]
+    fn test_find_pull_request() {
+        // The commit is the head of an open pull request on the second page
+        let (found, requests) = find(vec![
+            ("200 OK", pull_requests(1..51, |n| format!("sha{n}"))),
+            (
+                "200 OK",
+                pull_requests(51..53, |n| {
+                    if n == 52 {
+                        "abc123".to_string()
+                    } else {
+                        format!("sha{n}")
+                    }
+                }),
+            ),
+        ]);
+        assert_that!(found.unwrap()).is_equal_to(Some(52));
+        assert_that!(requests).is_equal_to(vec![
+            "GET /api/v1/repos/owner/repo/pulls?state=open&page=1&limit=50 HTTP/1.1".to_string(),
+            "GET /api/v1/repos/owner/repo/pulls?state=open&page=2&limit=50 HTTP/1.1".to_string(),
+        ]);
+
+        // A commit without an open pull request is not an error
+        let (found, requests) = find(vec![("200 OK", pull_requests(1..3, |n| format!("sha{n}")))]);
+        assert_that!(found.unwrap()).is_equal_to(None);
+        assert_that!(requests).has_length(1);
+        let (found, _) = find(vec![("200 OK", "[]".to_string())]);
+        assert_that!(found.unwrap()).is_equal_to(None);
+
+        let (found, _) = find(vec![("500 Internal Server Error", "{}".to_string())]);
+        assert_that!(found).is_err();
+    }
+}
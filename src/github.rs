@@ -57,6 +57,33 @@ pub async fn create_commit_message(
     commit_hash: &str,
     message: &impl std::fmt::Display,
 ) -> Result<(), Error> {
+    create_commit_comment(
+        settings,
+        instance,
+        organization_name,
+        repo_name,
+        commit_hash,
+        message,
+    )
+    .await
+    .map(|_| ())
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GhCommitComment {
+    id: u64,
+}
+
+/// Same as `create_commit_message`, but returns the ID of the created comment
+/// such that it can be edited later on.
+pub async fn create_commit_comment(
+    settings: &Settings,
+    instance: &GitHubServerSettings,
+    organization_name: &str,
+    repo_name: &str,
+    commit_hash: &str,
+    message: &impl std::fmt::Display,
+) -> Result<u64, Error> {
     let c = ReqwestClient::new();
     let response = c
         .post(format!(
@@ -76,7 +103,11 @@ pub async fn create_commit_message(
 
     if 200 <= response.status().as_u16() && response.status().as_u16() < 300 {
         log::debug!("Successfully posted comment to commit {}", commit_hash);
-        Ok(())
+        response
+            .json::<GhCommitComment>()
+            .await
+            .map(|comment| comment.id)
+            .map_err(|e| Error::auto_msg("could not parse the created GitHub commit comment", e))
     } else {
         Error::err_http_response(
             "when submitting commit comment".to_string(),
@@ -89,6 +120,48 @@ pub async fn create_commit_message(
     }
 }
 
+/// Replaces the message of an existing commit comment
+/// https://docs.github.com/en/enterprise-server@3.16/rest/commits/comments?apiVersion=2022-11-28#update-a-commit-comment
+pub async fn edit_commit_comment(
+    settings: &Settings,
+    instance: &GitHubServerSettings,
+    organization_name: &str,
+    repo_name: &str,
+    comment_id: u64,
+    message: &impl std::fmt::Display,
+) -> Result<(), Error> {
+    let c = ReqwestClient::new();
+    let response = c
+        .patch(format!(
+            "https://{}/api/v3/repos/{}/{}/comments/{}",
+            instance.domain, organization_name, repo_name, comment_id
+        ))
+        .headers(common_headers(settings, instance)?)
+        .json(&GhCommitMessage {
+            body: format!("{}\n\n{}", message, settings.submission.comment_signature),
+        })
+        .send()
+        .await
+        .map_err(|e| {
+            log::error!("Error with GitHub commit comment: {e}");
+            Error::auto_msg("error with GitHub commit comment request", e)
+        })?;
+
+    if 200 <= response.status().as_u16() && response.status().as_u16() < 300 {
+        log::debug!("Successfully edited commit comment {}", comment_id);
+        Ok(())
+    } else {
+        Error::err_http_response(
+            "when editing commit comment".to_string(),
+            response.status().as_u16(),
+            response
+                .text()
+                .await
+                .unwrap_or("no text received".to_string()),
+        )
+    }
+}
+
 pub enum CommitState {
     Error,
     Failure,
@@ -6,6 +6,7 @@ use crate::{
     error::Error,
 };
 use reqwest::{self, header::HeaderMap, Client as ReqwestClient};
+use serde::Deserialize;
 
 fn common_headers(
     _settings: &Settings,
@@ -81,6 +82,167 @@ pub async fn create_commit_message(
     }
 }
 
+/// A note in a discussion on a commit, which can be edited later on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitNote {
+    pub discussion_id: String,
+    pub note_id: u64,
+}
+
+/// Formatted as `<discussion_id>/<note_id>`, such that it can be stored.
+impl std::fmt::Display for CommitNote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.discussion_id, self.note_id)
+    }
+}
+
+impl std::str::FromStr for CommitNote {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once("/") {
+            Some((discussion_id, note_id)) if !discussion_id.is_empty() => Ok(CommitNote {
+                discussion_id: discussion_id.to_string(),
+                note_id: note_id
+                    .parse()
+                    .map_err(|_| Error::parse_type("GitLab commit note", s))?,
+            }),
+            _ => Error::err_parse_type("GitLab commit note", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GlDiscussion {
+    id: String,
+    notes: Vec<GlNote>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GlNote {
+    id: u64,
+}
+
+/// Starts a discussion on a commit. Unlike commit comments, the notes of a
+/// discussion can be edited.
+/// https://docs.gitlab.com/api/discussions/#create-a-commit-thread
+pub async fn create_commit_discussion(
+    settings: &Settings,
+    instance: &GitLabServerSettings,
+    namespace: &str,
+    repo_name: &str,
+    commit_hash: &str,
+    message: &impl std::fmt::Display,
+) -> Result<CommitNote, Error> {
+    let c = ReqwestClient::new();
+
+    let mut form_params = BTreeMap::new();
+    form_params.insert(
+        "body",
+        format!("{}\n\n{}", message, settings.submission.comment_signature),
+    );
+
+    let response = c
+        .post(format!(
+            "{}://{}/api/v4/projects/{}/repository/commits/{}/discussions",
+            if instance.use_https { "https" } else { "http" },
+            &instance.domain,
+            repo_id(namespace, repo_name),
+            commit_hash
+        ))
+        .headers(common_headers(settings, instance)?)
+        .form(&form_params)
+        .send()
+        .await
+        .map_err(|e| {
+            log::error!("Error with GitLab commit discussion: {e}");
+            Error::auto_msg("error with GitLab commit discussion request", e)
+        })?;
+
+    if 200 <= response.status().as_u16() && response.status().as_u16() < 300 {
+        log::debug!("Successfully started discussion on commit {}", commit_hash);
+        let discussion = response.json::<GlDiscussion>().await.map_err(|e| {
+            Error::auto_msg("could not parse the created GitLab commit discussion", e)
+        })?;
+        let note = discussion.notes.first().ok_or_else(|| {
+            Error::runtime(format!(
+                "GitLab commit discussion {} has no notes",
+                discussion.id
+            ))
+        })?;
+        Ok(CommitNote {
+            note_id: note.id,
+            discussion_id: discussion.id,
+        })
+    } else {
+        Error::err_http_response(
+            "when starting commit discussion".to_string(),
+            response.status().as_u16(),
+            response
+                .text()
+                .await
+                .unwrap_or("no text received".to_string()),
+        )
+    }
+}
+
+/// Replaces the message of a note in a commit discussion
+/// https://docs.gitlab.com/api/discussions/#modify-an-existing-commit-thread-note
+pub async fn edit_commit_note(
+    settings: &Settings,
+    instance: &GitLabServerSettings,
+    namespace: &str,
+    repo_name: &str,
+    commit_hash: &str,
+    note: &CommitNote,
+    message: &impl std::fmt::Display,
+) -> Result<(), Error> {
+    let c = ReqwestClient::new();
+
+    let mut form_params = BTreeMap::new();
+    form_params.insert(
+        "body",
+        format!("{}\n\n{}", message, settings.submission.comment_signature),
+    );
+
+    let response = c
+        .put(format!(
+            "{}://{}/api/v4/projects/{}/repository/commits/{}/discussions/{}/notes/{}",
+            if instance.use_https { "https" } else { "http" },
+            &instance.domain,
+            repo_id(namespace, repo_name),
+            commit_hash,
+            note.discussion_id,
+            note.note_id
+        ))
+        .headers(common_headers(settings, instance)?)
+        .form(&form_params)
+        .send()
+        .await
+        .map_err(|e| {
+            log::error!("Error with GitLab commit note: {e}");
+            Error::auto_msg("error with GitLab commit note request", e)
+        })?;
+
+    if 200 <= response.status().as_u16() && response.status().as_u16() < 300 {
+        log::debug!(
+            "Successfully edited note {} on commit {}",
+            note.note_id,
+            commit_hash
+        );
+        Ok(())
+    } else {
+        Error::err_http_response(
+            "when editing commit note".to_string(),
+            response.status().as_u16(),
+            response
+                .text()
+                .await
+                .unwrap_or("no text received".to_string()),
+        )
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum CommitState {
     Pending,
@@ -153,3 +315,24 @@ pub async fn set_commit_status(
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asserting::prelude::*;
+
+    #[test]
+    fn test_commit_note_ref() {
+        let note = CommitNote {
+            discussion_id: "6a9c1750b37d513a43987b574953fceb50b03ce7".to_string(),
+            note_id: 1126,
+        };
+        assert_that!(note.to_string())
+            .is_equal_to("6a9c1750b37d513a43987b574953fceb50b03ce7/1126".to_string());
+        assert_that!(note.to_string().parse::<CommitNote>().ok()).is_equal_to(Some(note));
+
+        assert_that!("1126".parse::<CommitNote>().is_err()).is_true();
+        assert_that!("/1126".parse::<CommitNote>().is_err()).is_true();
+        assert_that!("abc/def".parse::<CommitNote>().is_err()).is_true();
+    }
+}
@@ -0,0 +1,300 @@
+/// Tag completion per repository, compiled from the results of the graded
+/// submissions for export into a learning management system.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+    time::SystemTime,
+};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::utils::systemtime_to_utc_string;
+
+/// The result of grading a single tag in a submission.
+#[derive(Debug, Clone)]
+pub struct GradedTag {
+    pub tag_name: String,
+    pub ok: bool,
+    pub late: bool,
+    /// Score as a percentage of the maximum points, if the tag is scored
+    pub percentage: Option<f64>,
+}
+
+/// A finished submission, as used for compiling the gradebook.
+#[derive(Debug, Clone)]
+pub struct GradebookRecord {
+    /// ID of the submission source, identifying the repository
+    pub source_id: i64,
+    /// Kind of the submission source, e.g. GitHub or GitLab
+    pub source: String,
+    pub domain: Option<String>,
+    pub owner: Option<String>,
+    /// Name of the repository, or the URL for plain git sources
+    pub repo: String,
+    pub user: String,
+    pub submission_id: i64,
+    /// Time of submission, which is the time of the original submission for
+    /// re-grades.
+    pub submitted: SystemTime,
+    pub tags: Vec<GradedTag>,
+}
+
+/// Options for compiling the gradebook.
+#[derive(Debug, Clone, Default)]
+pub struct GradebookOptions {
+    /// The tags to include. All graded tags are included if empty.
+    pub tags: Vec<String>,
+
+    /// Only count passing submissions that were made before the deadline of
+    /// the tag.
+    pub honor_deadlines: bool,
+}
+
+/// The earliest passing submission of a tag.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct TagCompletion {
+    pub submission_id: i64,
+    /// Time of submission, in UTC
+    pub date_submitted: String,
+    /// Whether the submission was made after the deadline of the tag
+    pub late: bool,
+    #[serde(skip)]
+    submitted: SystemTime,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct GradebookRepository {
+    pub source: String,
+    pub domain: Option<String>,
+    pub owner: Option<String>,
+    pub repo: String,
+    /// Users that have made submissions from the repository
+    pub users: Vec<String>,
+    /// The earliest passing submission of each tag, or `None` if the tag has
+    /// not been passed.
+    pub tags: BTreeMap<String, Option<TagCompletion>>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Gradebook {
+    /// The tags in the gradebook, in the order of the CSV columns
+    pub tags: Vec<String>,
+    pub repositories: Vec<GradebookRepository>,
+}
+
+impl Gradebook {
+    /// Compiles the gradebook from the finished submissions in `records`.
+    pub fn compile(records: &[GradebookRecord], options: &GradebookOptions) -> Gradebook {
+        let tags: Vec<String> = if options.tags.is_empty() {
+            records
+                .iter()
+                .flat_map(|r| r.tags.iter().map(|t| t.tag_name.clone()))
+                .collect::<BTreeSet<String>>()
+                .into_iter()
+                .collect()
+        } else {
+            options.tags.clone()
+        };
+
+        let mut repositories: BTreeMap<i64, GradebookRepository> = BTreeMap::new();
+        for record in records {
+            let repository =
+                repositories
+                    .entry(record.source_id)
+                    .or_insert_with(|| GradebookRepository {
+                        source: record.source.clone(),
+                        domain: record.domain.clone(),
+                        owner: record.owner.clone(),
+                        repo: record.repo.clone(),
+                        users: vec![],
+                        tags: tags.iter().map(|t| (t.clone(), None)).collect(),
+                    });
+            if !repository.users.contains(&record.user) {
+                repository.users.push(record.user.clone());
+            }
+
+            for graded in record.tags.iter() {
+                if !graded.ok || (options.honor_deadlines && graded.late) {
+                    continue;
+                }
+                let Some(completion) = repository.tags.get_mut(&graded.tag_name) else {
+                    // Not one of the selected tags
+                    continue;
+                };
+                let earlier = completion.as_ref().is_none_or(|c| {
+                    (record.submitted, record.submission_id) < (c.submitted, c.submission_id)
+                });
+                if earlier {
+                    *completion = Some(TagCompletion {
+                        submission_id: record.submission_id,
+                        date_submitted: systemtime_to_utc_string(&record.submitted)
+                            .unwrap_or("NO_TIME".to_string()),
+                        late: graded.late,
+                        submitted: record.submitted,
+                    });
+                }
+            }
+        }
+
+        let mut repositories: Vec<GradebookRepository> = repositories.into_values().collect();
+        repositories.sort_by(|a, b| (&a.owner, &a.repo).cmp(&(&b.owner, &b.repo)));
+        Gradebook {
+            tags: tags,
+            repositories: repositories,
+        }
+    }
+
+    /// Writes the gradebook as CSV, with one row per repository. The column of
+    /// each tag holds the time of the earliest passing submission, and is
+    /// empty if the tag has not been passed.
+    pub fn write_csv(&self, dst: &mut impl Write) -> std::fmt::Result {
+        let header = ["source", "domain", "owner", "repo", "users"]
+            .into_iter()
+            .chain(self.tags.iter().map(|t| t.as_str()));
+        write_csv_row(dst, header)?;
+
+        for repository in self.repositories.iter() {
+            let users = repository.users.join(";");
+            let completions: Vec<&str> = self
+                .tags
+                .iter()
+                .map(|t| match repository.tags.get(t) {
+                    Some(Some(c)) => c.date_submitted.as_str(),
+                    _ => "",
+                })
+                .collect();
+            let row = [
+                repository.source.as_str(),
+                repository.domain.as_deref().unwrap_or(""),
+                repository.owner.as_deref().unwrap_or(""),
+                repository.repo.as_str(),
+                users.as_str(),
+            ]
+            .into_iter()
+            .chain(completions);
+            write_csv_row(dst, row)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes a CSV row, quoting the fields that need it.
+fn write_csv_row<'a>(
+    dst: &mut impl Write,
+    fields: impl IntoIterator<Item = &'a str>,
+) -> std::fmt::Result {
+    for (i, field) in fields.into_iter().enumerate() {
+        if i > 0 {
+            dst.write_char(',')?;
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            write!(dst, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            dst.write_str(field)?;
+        }
+    }
+    dst.write_str("\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asserting::prelude::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn record(
+        source_id: i64,
+        submission_id: i64,
+        secs: u64,
+        tags: &[(&str, bool, bool)],
+    ) -> GradebookRecord {
+        GradebookRecord {
+            source_id: source_id,
+            source: "GitHub".to_string(),
+            domain: Some("gits-15.sys.kth.se".to_string()),
+            owner: Some("id2202".to_string()),
+            repo: format!("student-{source_id}"),
+            user: format!("user-{source_id}"),
+            submission_id: submission_id,
+            submitted: UNIX_EPOCH + Duration::from_secs(secs),
+            tags: tags
+                .iter()
+                .map(|(tag_name, ok, late)| GradedTag {
+                    tag_name: tag_name.to_string(),
+                    ok: *ok,
+                    late: *late,
+                    percentage: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_gradebook_compile() {
+        let records = vec![
+            record(1, 3, 300, &[("hello", true, true)]),
+            record(
+                1,
+                2,
+                200,
+                &[("hello", true, false), ("hello-all", false, false)],
+            ),
+            record(2, 1, 100, &[("hello", false, false)]),
+            // A re-grade of the first submission, at the time of the original
+            record(
+                1,
+                4,
+                100,
+                &[("hello", false, false), ("hello-all", true, false)],
+            ),
+        ];
+
+        let gradebook = Gradebook::compile(&records, &GradebookOptions::default());
+        assert_that!(gradebook.tags.clone())
+            .is_equal_to(vec!["hello".to_string(), "hello-all".to_string()]);
+        assert_that!(gradebook.repositories.len()).is_equal_to(2);
+
+        let repository = &gradebook.repositories[0];
+        assert_that!(repository.repo.clone()).is_equal_to("student-1".to_string());
+        let hello = repository.tags["hello"].as_ref().map(|c| c.submission_id);
+        assert_that!(hello).is_equal_to(Some(2));
+        let hello_all = repository.tags["hello-all"]
+            .as_ref()
+            .map(|c| c.submission_id);
+        assert_that!(hello_all).is_equal_to(Some(4));
+        assert_that!(gradebook.repositories[1].tags["hello"].clone()).is_equal_to(None);
+
+        // Late submissions are only counted if deadlines are not honored
+        let records = vec![record(1, 1, 100, &[("hello", true, true)])];
+        let gradebook = Gradebook::compile(&records, &GradebookOptions::default());
+        let late = gradebook.repositories[0].tags["hello"]
+            .as_ref()
+            .map(|c| c.late);
+        assert_that!(late).is_equal_to(Some(true));
+
+        let options = GradebookOptions {
+            tags: vec!["hello".to_string(), "hello-asm".to_string()],
+            honor_deadlines: true,
+        };
+        let gradebook = Gradebook::compile(&records, &options);
+        assert_that!(gradebook.tags.clone()).is_equal_to(options.tags.clone());
+        assert_that!(gradebook.repositories[0].tags["hello"].clone()).is_equal_to(None);
+        assert_that!(gradebook.repositories[0].tags["hello-asm"].clone()).is_equal_to(None);
+    }
+
+    #[test]
+    fn test_gradebook_csv() {
+        let mut records = vec![record(1, 1, 100, &[("hello", true, false)])];
+        records[0].repo = "student, \"one\"".to_string();
+        let gradebook = Gradebook::compile(&records, &GradebookOptions::default());
+
+        let mut dst = String::new();
+        assert_that!(gradebook.write_csv(&mut dst)).is_ok();
+        assert_that!(dst).is_equal_to(
+            "source,domain,owner,repo,users,hello\r\n\
+             GitHub,gits-15.sys.kth.se,id2202,\"student, \"\"one\"\"\",user-1,1970-01-01 00:01:40 UTC\r\n"
+                .to_string(),
+        );
+    }
+}
@@ -1,10 +1,15 @@
+pub mod canvas;
 pub mod config;
 /// This is useful for the other binaries to be able to access the common
 /// configuration items.
 pub mod db;
 pub mod error;
+pub mod gitea;
 pub mod github;
 pub mod gitlab;
+pub mod gradebook;
 pub mod podman;
 pub mod reporting;
+pub mod similarity;
+pub mod subrunner;
 pub mod utils;
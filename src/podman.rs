@@ -64,6 +64,22 @@ pub fn images() -> Result<Vec<String>, Error> {
     Ok(Vec::from_iter(imgs.into_iter()))
 }
 
+/// Returns the ID of an image, which changes whenever the image is rebuilt
+/// even if its name stays the same.
+pub fn image_id(image: &str) -> Result<String, Error> {
+    let output = syscommand_timeout(
+        &["podman", "image", "inspect", "--format", "{{.Id}}", image],
+        SyscommandSettings {
+            expected_code: Some(0),
+            max_stdout_length: Some(1024),
+            max_stderr_length: Some(128 * 1024),
+            ..Default::default()
+        },
+    )?;
+
+    Ok(output.stdout.trim().to_string())
+}
+
 /// A selection of JSON output fields when listing the networks
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct PodmanNetworkOutput {
@@ -192,6 +208,14 @@ pub struct ContainerOptions {
     pub container_name: String,
     pub network_name: String,
     pub mounts: Vec<(String, String, String)>,
+    /// tmpfs mounts as (mount path, size), e.g. `("/tmp", "256m")`
+    pub tmpfs_mounts: Vec<(String, String)>,
+    /// Memory limit, e.g. `"512m"`. Swap is disabled when this is set.
+    pub memory: Option<String>,
+    /// Number of CPUs available to the container
+    pub cpus: Option<f64>,
+    /// Maximum number of processes inside the container
+    pub pids_limit: Option<u32>,
 }
 
 /// Create a container and start it
@@ -214,6 +238,25 @@ pub fn start_container(opts: &ContainerOptions) -> Result<(), Error> {
         cmd.push("-v".to_owned());
         cmd.push(format!("{host_path}:{mount_path}:{opts}"));
     }
+    for (mount_path, size) in opts.tmpfs_mounts.iter() {
+        // Binaries are built and run from the tmpfs, so it must allow exec
+        cmd.push("--tmpfs".to_owned());
+        cmd.push(format!("{mount_path}:rw,exec,size={size}"));
+    }
+    if let Some(memory) = &opts.memory {
+        cmd.push("--memory".to_owned());
+        cmd.push(memory.to_owned());
+        cmd.push("--memory-swap".to_owned());
+        cmd.push(memory.to_owned());
+    }
+    if let Some(cpus) = opts.cpus {
+        cmd.push("--cpus".to_owned());
+        cmd.push(cpus.to_string());
+    }
+    if let Some(pids_limit) = opts.pids_limit {
+        cmd.push("--pids-limit".to_owned());
+        cmd.push(pids_limit.to_string());
+    }
     cmd.push(opts.image.to_owned());
     // Set the image to just loop indefinitely
     cmd.push("bash".to_string());
@@ -253,6 +296,54 @@ pub fn exec(container_name: &str, exec_cmd: &[&str]) -> Result<(), Error> {
     Ok(())
 }
 
+/// Counters from the cgroup of a container that indicate whether any of its
+/// resource limits have been hit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceEvents {
+    /// Number of processes killed by the OOM killer.
+    pub oom_kill: u64,
+    /// Number of times that a fork failed due to the process limit.
+    pub pids_max: u64,
+}
+
+/// Parses a flat keyed cgroup file, such as `memory.events`, and returns the
+/// value for `key`. Absent keys are treated as 0.
+fn cgroup_event_count(contents: &str, key: &str) -> Result<u64, Error> {
+    for line in contents.lines() {
+        if let Some((k, v)) = line.split_once(' ') {
+            if k == key {
+                return v.trim().parse().map_err(|e| {
+                    Error::parse_type("cgroup event count".to_string(), v.to_string())
+                        .with_cause(Box::new(e))
+                });
+            }
+        }
+    }
+    Ok(0)
+}
+
+/// Reads the resource limit counters of a running container. This requires
+/// cgroups v2, where the container sees its own cgroup under /sys/fs/cgroup.
+pub fn resource_events(container_name: &str) -> Result<ResourceEvents, Error> {
+    let read_events = |file: &str| {
+        syscommand_timeout(
+            &["podman", "exec", container_name, "cat", file],
+            SyscommandSettings {
+                expected_code: Some(0),
+                max_stdout_length: Some(128 * 1024),
+                max_stderr_length: Some(128 * 1024),
+                ..Default::default()
+            },
+        )
+        .map(|output| output.stdout)
+    };
+
+    Ok(ResourceEvents {
+        oom_kill: cgroup_event_count(&read_events("/sys/fs/cgroup/memory.events")?, "oom_kill")?,
+        pids_max: cgroup_event_count(&read_events("/sys/fs/cgroup/pids.events")?, "max")?,
+    })
+}
+
 /// Force removal of a container
 pub fn force_rm(container_name: &str) -> Result<(), Error> {
     let _output = syscommand_timeout(
@@ -266,3 +357,18 @@ pub fn force_rm(container_name: &str) -> Result<(), Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asserting::prelude::*;
+
+    #[test]
+    fn test_cgroup_event_count() {
+        let memory_events = "low 0\nhigh 0\nmax 12\noom 2\noom_kill 1\noom_group_kill 0\n";
+        assert_that!(cgroup_event_count(memory_events, "oom_kill").unwrap()).is_equal_to(1);
+        assert_that!(cgroup_event_count(memory_events, "max").unwrap()).is_equal_to(12);
+        assert_that!(cgroup_event_count(memory_events, "missing").unwrap()).is_equal_to(0);
+        assert_that!(cgroup_event_count("max bad\n", "max")).is_err();
+    }
+}
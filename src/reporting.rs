@@ -12,6 +12,10 @@ use serde::{Deserialize, Serialize};
 
 use crate::{config::ReportingSettings, error::Error};
 
+pub mod diff;
+
+use diff::OutputDiff;
+
 /// Returns a markdown preformatted block <pre> containing the provided text
 /// `s` as verbatim, making sure to escape parts that could otherwise be
 /// interpreted as HTML.
@@ -160,6 +164,16 @@ impl Report {
         }
     }
 
+    /// Returns the tag gradings contained in the report.
+    pub fn tag_gradings(&self) -> Vec<&ReportTagGrading> {
+        match self {
+            Self::Wrapper(r) => r.reports.iter().flat_map(|r| r.tag_gradings()).collect(),
+            Self::InvalidTag(_) | Self::Message(_) => vec![],
+            Self::Submission(r) => r.tag_reports.iter().collect(),
+            Self::TagGrading(r) => vec![r],
+        }
+    }
+
     pub fn formatter_markdown<'a>(
         &'a self,
         settings: &'a ReportingSettings,
@@ -536,9 +550,18 @@ impl ReportSubmission {
             };
             write!(
                 dst,
-                " <code class=\"{tag_code_class}\">{}</code></h4>",
+                " <code class=\"{tag_code_class}\">{}</code>",
                 html_formatter_str(&grading_report.tag_name, escape)
             )?;
+            if let Some(score) = &grading_report.score {
+                write!(
+                    dst,
+                    " <small class=\"text-body-secondary\">({}/{})</small>",
+                    format_points(score.points),
+                    format_points(score.max_points)
+                )?;
+            }
+            dst.write_str("</h4>")?;
 
             dst.write_str("</button>")?;
             dst.write_str("</h2>")?;
@@ -575,6 +598,99 @@ pub struct ReportTagGrading {
 
     /// Test groups
     pub groups: Vec<DetailsTagGradingGroup>,
+
+    /// Score of the tag. Only present if points, weights, or a pass threshold
+    /// are configured for the tag.
+    pub score: Option<DetailsTagScore>,
+
+    /// Whether the submission was made after the deadline of the tag.
+    #[serde(default)]
+    pub late: bool,
+
+    /// Details about the deadline, only present if the submission was late.
+    pub late_submission: Option<DetailsLateSubmission>,
+}
+
+/// Details about a submission made after the deadline of a tag.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct DetailsLateSubmission {
+    /// The deadline of the tag
+    pub deadline: String,
+    /// Last time at which late submissions are accepted, if limited
+    pub late_until: Option<String>,
+    /// When the submission was made
+    pub submitted: String,
+    /// Whether the submission was accepted, i.e. not made after `late_until`
+    pub accepted: bool,
+    /// Fraction of the score that should be deducted for being late
+    pub penalty: Option<f64>,
+}
+
+impl DetailsLateSubmission {
+    /// Short description of when the submission was made and whether it is
+    /// accepted.
+    fn summary(&self) -> String {
+        let mut summary = format!(
+            "Submitted {}, after the deadline {}.",
+            self.submitted, self.deadline
+        );
+        if !self.accepted {
+            summary.push_str(" Late submissions were only accepted until ");
+            summary.push_str(self.late_until.as_deref().unwrap_or("the deadline"));
+            summary.push_str(", so this submission is not accepted.");
+        } else if let Some(penalty) = self.penalty {
+            summary.push_str(&format!(
+                " A late penalty of {}% applies.",
+                format_points(100.0 * penalty)
+            ));
+        }
+        summary
+    }
+}
+
+/// Score of a graded tag, computed from the points of its test cases.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct DetailsTagScore {
+    /// Points awarded for the test cases that passed
+    pub points: f64,
+    /// Points awarded if every test case passes
+    pub max_points: f64,
+    /// Fraction of the maximum points that is needed to pass the tag
+    pub pass_threshold: Option<f64>,
+}
+
+impl DetailsTagScore {
+    /// Returns the score as a percentage of the maximum points.
+    pub fn percentage(&self) -> f64 {
+        if self.max_points > 0.0 {
+            100.0 * self.points / self.max_points
+        } else {
+            100.0
+        }
+    }
+
+    /// Short description of the score, e.g. "7.5/10 points (75%)".
+    fn summary(&self) -> String {
+        let mut summary = format!(
+            "{}/{} points ({}%)",
+            format_points(self.points),
+            format_points(self.max_points),
+            format_points(self.percentage())
+        );
+        if let Some(threshold) = self.pass_threshold {
+            summary.push_str(&format!(
+                ", {}% required to pass",
+                format_points(100.0 * threshold)
+            ));
+        }
+        summary
+    }
+}
+
+/// Formats points with at most two decimals, without trailing zeros.
+fn format_points(points: f64) -> String {
+    let s = format!("{points:.2}");
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
 }
 
 impl ReportTagGrading {
@@ -638,6 +754,14 @@ impl ReportTagGrading {
 
         dst.write_str("\n\n")?;
 
+        if let Some(late) = &self.late_submission {
+            write!(dst, "**Late submission:** {}\n\n", late.summary())?;
+        }
+
+        if let Some(score) = &self.score {
+            write!(dst, "**Score:** {}\n\n", score.summary())?;
+        }
+
         if let Some(bs) = &self.build_failure {
             bs.render_markdown(settings, dst)?;
         } else {
@@ -685,6 +809,22 @@ impl ReportTagGrading {
             dst.write_str(")</em></p>")?;
         }
 
+        if let Some(late) = &self.late_submission {
+            write!(
+                dst,
+                "<p><strong>Late submission:</strong> {}</p>",
+                html_formatter_str(&late.summary(), escape)
+            )?;
+        }
+
+        if let Some(score) = &self.score {
+            write!(
+                dst,
+                "<p><strong>Score:</strong> {}</p>",
+                html_formatter_str(&score.summary(), escape)
+            )?;
+        }
+
         if let Some(bs) = &self.build_failure {
             dst.write_str("<div>")?;
             bs.render_html(settings, dst, escape, header_level + 1)?;
@@ -752,6 +892,32 @@ pub struct DetailsTagGradingGroup {
     pub tests_passed: usize,
 
     pub test_details: Vec<DetailsTestFailure>,
+
+    /// Resources used by the test cases that were run in this group. Reports
+    /// created before this was recorded do not have this field.
+    #[serde(default)]
+    pub test_usage: Vec<DetailsTestUsage>,
+
+    /// Points awarded to this group, including its subgroups. Only present
+    /// if the tag is scored.
+    pub points: Option<f64>,
+    /// Points that this group is worth, including its subgroups.
+    pub max_points: Option<f64>,
+}
+
+/// Resources used while running a single test case.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct DetailsTestUsage {
+    /// Name of the test case
+    pub test_name: String,
+    /// Timeout of the test case in seconds
+    pub timeout: u32,
+    /// Wall time in milliseconds, measured from outside of the container
+    pub wall_time_ms: u64,
+    /// User and system CPU time in milliseconds, if it could be measured
+    pub cpu_time_ms: Option<u64>,
+    /// Peak resident set size in kilobytes, if it could be measured
+    pub peak_rss_kb: Option<u64>,
 }
 
 /// A copy of DetailsTagGradingGroup, but used for propagating status about the
@@ -765,6 +931,9 @@ struct AnnotatedDetailsTagGradingGroup<'a> {
     pub tests_passed: usize,
 
     pub test_details: &'a Vec<DetailsTestFailure>,
+    pub test_usage: &'a Vec<DetailsTestUsage>,
+    pub points: Option<f64>,
+    pub max_points: Option<f64>,
 
     pub all_run: bool,
     pub all_ok: bool,
@@ -793,6 +962,9 @@ impl DetailsTagGradingGroup {
             tests_run: self.tests_run,
             tests_passed: self.tests_passed,
             test_details: &self.test_details,
+            test_usage: &self.test_usage,
+            points: self.points,
+            max_points: self.max_points,
             all_run: all_run,
             all_ok: all_ok,
         }
@@ -812,6 +984,19 @@ impl<'a> AnnotatedDetailsTagGradingGroup<'a> {
             &settings.markdown.symbol_ok
         }
     }
+    /// Returns the points of this group formatted as "4.5/7 points", if the
+    /// group is scored.
+    fn points_summary(&self) -> Option<String> {
+        match (self.points, self.max_points) {
+            (Some(points), Some(max_points)) => Some(format!(
+                "{}/{} points",
+                format_points(points),
+                format_points(max_points)
+            )),
+            _ => None,
+        }
+    }
+
     /// Generates the test results within a grading tag.
     ///
     /// Note: the generated string will always terminate with a newline.
@@ -847,6 +1032,9 @@ impl<'a> AnnotatedDetailsTagGradingGroup<'a> {
                 )?;
             }
         }
+        if let Some(points) = self.points_summary() {
+            write!(dst, " [{points}]")?;
+        }
         if self.test_details.len() > 0 {
             write!(dst, "\n{:>indent$}   [", "",)?;
             for (i, d) in self.test_details.iter().enumerate() {
@@ -902,7 +1090,20 @@ impl<'a> AnnotatedDetailsTagGradingGroup<'a> {
                 )?;
             }
         }
+        if let Some(points) = self.points_summary() {
+            write!(dst, " [{}]", html_formatter_str(&points, escape))?;
+        }
         dst.write_str("</span>")?;
+        if let Some(slowest) = self.test_usage.iter().max_by_key(|u| u.wall_time_ms) {
+            dst.write_str("<span class=\"text-body-secondary small ms-2\">slowest: ")?;
+            html_write_str(dst, &slowest.test_name, escape)?;
+            write!(
+                dst,
+                " ({:.2}/{} s)</span>",
+                slowest.wall_time_ms as f64 / 1000.0,
+                slowest.timeout
+            )?;
+        }
         for detail in self.test_details {
             details.push(detail.clone());
             let target_id = format!("{accordion_prefix}_{}", details.len());
@@ -1126,6 +1327,10 @@ pub struct DetailsTestFailure {
 
     /// A list of MIME-type mismatches
     pub mimetype_mismatch_files: Vec<MIMETypeInfo>,
+
+    /// Performance measurements that exceeded their thresholds
+    #[serde(default)]
+    pub threshold_exceeded: Vec<ThresholdInfo>,
 }
 
 impl DetailsTestFailure {
@@ -1145,6 +1350,9 @@ impl DetailsTestFailure {
         if self.stderr_mismatch.is_some() {
             fail_causes.push("Standard error mismatch.");
         }
+        if !self.threshold_exceeded.is_empty() {
+            fail_causes.push("Performance threshold exceeded.");
+        }
         fail_causes
     }
 
@@ -1234,6 +1442,11 @@ impl DetailsTestFailure {
             mm.render_markdown(settings, dst, "Standard Error Mismatch", "stderr")?;
         }
 
+        for th in &self.threshold_exceeded {
+            component_spacing(dst, &mut spacing_state)?;
+            th.render_markdown(settings, dst)?;
+        }
+
         for (title, file_info) in &self.additional_files {
             component_spacing(dst, &mut spacing_state)?;
             write!(dst, "### {}\n\n", title)?;
@@ -1361,6 +1574,10 @@ impl DetailsTestFailure {
             )?;
         }
 
+        for th in &self.threshold_exceeded {
+            th.render_html(settings, dst, escape, header_level + 1)?;
+        }
+
         for (title, file_info) in &self.additional_files {
             dst.write_str("<h6>")?;
             html_write_str(dst, title, escape)?;
@@ -1381,6 +1598,101 @@ impl DetailsTestFailure {
     }
 }
 
+/// Information about a measurement that exceeded its threshold, such as the
+/// median runtime of a benchmarked program.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct ThresholdInfo {
+    /// What was measured, e.g. "runtime"
+    pub metric: String,
+    /// Unit of the measured values, e.g. "ms"
+    pub unit: String,
+    /// The median of the samples, which is compared against the threshold
+    pub received: f64,
+    /// The largest allowed value
+    pub threshold: f64,
+    /// Each of the measured values
+    pub samples: Vec<f64>,
+}
+
+impl ThresholdInfo {
+    fn title(&self) -> String {
+        let mut title = self.metric.clone();
+        if let Some(first) = title.get_mut(0..1) {
+            first.make_ascii_uppercase();
+        }
+        format!("{title} Threshold Exceeded")
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "The median {} of {} runs was {} {}, which exceeds the threshold of {} {}.",
+            self.metric,
+            self.samples.len(),
+            self.received,
+            self.unit,
+            self.threshold,
+            self.unit
+        )
+    }
+
+    fn render_markdown(
+        &self,
+        _settings: &ReportingSettings,
+        dst: &mut impl Write,
+    ) -> Result<(), Error> {
+        write!(
+            dst,
+            "### {}
+
+{}
+
+",
+            self.title(),
+            self.summary()
+        )?;
+        write!(
+            dst,
+            "**Measured ({})**: {}",
+            self.unit,
+            self.samples
+                .iter()
+                .format_with(", ", |s, f| f(&format_args!("`{s}`")))
+        )?;
+        Ok(())
+    }
+
+    fn render_html(
+        &self,
+        _settings: &ReportingSettings,
+        dst: &mut impl Write,
+        escape: bool,
+        _header_level: usize,
+    ) -> Result<(), Error> {
+        dst.write_str("<div class=\"card mt-3 border border-2 border-danger rounded\">")?;
+        write!(
+            dst,
+            "<h4 class=\"card-header border-danger bg-danger-subtle\">{}</h4>",
+            html_formatter_str(&self.title(), escape)
+        )?;
+        dst.write_str("<div class=\"card-body\">")?;
+        write!(
+            dst,
+            "<p>{}</p>",
+            html_formatter_str(&self.summary(), escape)
+        )?;
+        write!(
+            dst,
+            "<p><strong>Measured ({})</strong>: {}</p>",
+            html_formatter_str(&self.unit, escape),
+            self.samples
+                .iter()
+                .format_with(", ", |s, f| f(&format_args!("<code>{s}</code>")))
+        )?;
+        dst.write_str("</div></div>")?;
+        Ok(())
+    }
+}
+
 /// Information about a mismatch when comparing what was received to the
 /// allowed alternatives.
 #[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
@@ -1391,6 +1703,10 @@ pub struct MismatchInfo<A> {
     pub allowed_alternatives: Vec<A>,
     /// Optional additional messages
     pub msgs: Vec<String>,
+    /// Line-level diff of the received output against the closest allowed
+    /// alternative, if computed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff: Option<OutputDiff>,
 }
 
 impl<A> MismatchInfo<A> {
@@ -1447,6 +1763,12 @@ impl MismatchInfo<String> {
     ) -> Result<(), Error> {
         self.render_markdown_header(dst, title)?;
 
+        if let Some(diff) = &self.diff {
+            write!(dst, "**Difference in {}**:\n\n", output_name)?;
+            diff.render_markdown(dst, Some(settings.markdown.truncate_len))?;
+            dst.write_str("\n\n")?;
+        }
+
         write!(dst, "**Received {}**:\n\n", output_name)?;
 
         markdown_write_preformatted_with_truncation(
@@ -1490,7 +1812,13 @@ impl MismatchInfo<String> {
     ) -> Result<(), Error> {
         self.render_html_begin(dst, escape, title)?;
 
-        dst.write_str("<h6>Received</h6>")?;
+        if let Some(diff) = &self.diff {
+            dst.write_str("<h6>Difference</h6>")?;
+            diff.render_html(dst, escape)?;
+            dst.write_str("<h6 class=\"mt-2\">Received</h6>")?;
+        } else {
+            dst.write_str("<h6>Received</h6>")?;
+        }
         html_write_codeblock(dst, &self.received, escape)?;
         dst.write_str("<h6 class=\"mt-2\">Expected")?;
         if self.allowed_alternatives.len() > 1 {
@@ -1703,6 +2031,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_tag_score_summary() {
+        assert_that!(format_points(10.0)).is_equal_to("10".to_string());
+        assert_that!(format_points(7.5)).is_equal_to("7.5".to_string());
+        assert_that!(format_points(2.0 / 3.0)).is_equal_to("0.67".to_string());
+
+        let score = DetailsTagScore {
+            points: 7.5,
+            max_points: 10.0,
+            pass_threshold: Some(0.6),
+        };
+        assert_that!(score.summary())
+            .is_equal_to("7.5/10 points (75%), 60% required to pass".to_string());
+    }
+
+    #[test]
+    fn test_late_submission_summary() {
+        let late = DetailsLateSubmission {
+            deadline: "2025-01-31 22:59:00 UTC".to_string(),
+            late_until: Some("2025-02-07 22:59:00 UTC".to_string()),
+            submitted: "2025-02-01 12:00:00 UTC".to_string(),
+            accepted: true,
+            penalty: Some(0.1),
+        };
+        assert_that!(late.summary()).is_equal_to(
+            "Submitted 2025-02-01 12:00:00 UTC, after the deadline 2025-01-31 22:59:00 UTC. A late penalty of 10% applies.".to_string(),
+        );
+
+        let not_accepted = DetailsLateSubmission {
+            submitted: "2025-02-08 12:00:00 UTC".to_string(),
+            accepted: false,
+            ..late
+        };
+        assert_that!(not_accepted.summary()).is_equal_to(
+            "Submitted 2025-02-08 12:00:00 UTC, after the deadline 2025-01-31 22:59:00 UTC. Late submissions were only accepted until 2025-02-07 22:59:00 UTC, so this submission is not accepted.".to_string(),
+        );
+    }
+
     #[test]
     fn test_md_preformatted_truncated() {
         let mut dst = String::new();
@@ -0,0 +1,549 @@
+/// Line-level diffs between the expected and the received output of a test
+/// case, rendered as unified diffs in markdown and side by side in HTML.
+use std::fmt::Write;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::Error, reporting::html_write_str};
+
+/// Number of unchanged lines shown around each change.
+const CONTEXT_LINES: usize = 3;
+
+/// Maximum number of changed lines that the diff algorithm searches for.
+/// Outputs that differ more than this are shown as entirely replaced, which
+/// keeps the time and memory use bounded for large outputs.
+const MAX_EDIT_DISTANCE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffLineKind {
+    /// The line is in both the expected and the received output
+    Same,
+    /// The line is only in the expected output
+    Removed,
+    /// The line is only in the received output
+    Added,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub text: String,
+}
+
+/// A group of changes with the unchanged lines around them.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct DiffHunk {
+    /// First line of the hunk in the expected output, starting at 1
+    pub expected_start: usize,
+    pub expected_len: usize,
+    /// First line of the hunk in the received output, starting at 1
+    pub received_start: usize,
+    pub received_len: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+impl DiffHunk {
+    /// The `@@ -a,b +c,d @@` header of the hunk in a unified diff.
+    fn header(&self) -> String {
+        format!(
+            "@@ -{},{} +{},{} @@",
+            self.expected_start, self.expected_len, self.received_start, self.received_len
+        )
+    }
+}
+
+/// Diff of the received output against the closest allowed alternative.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct OutputDiff {
+    /// Index of the allowed alternative that the output is compared against
+    pub alternative: usize,
+    pub hunks: Vec<DiffHunk>,
+}
+
+impl OutputDiff {
+    /// Diffs `received` against each of the `alternatives`, returning the
+    /// diff with the fewest changed lines. `None` if there are no
+    /// alternatives or if `received` equals one of them.
+    pub fn closest(received: &str, alternatives: &[String]) -> Option<OutputDiff> {
+        alternatives
+            .iter()
+            .enumerate()
+            .map(|(i, expected)| {
+                let lines = diff_lines(expected, received);
+                let changes = lines
+                    .iter()
+                    .filter(|l| l.kind != DiffLineKind::Same)
+                    .count();
+                (changes, i, lines)
+            })
+            .min_by_key(|(changes, i, _)| (*changes, *i))
+            .filter(|(changes, _, _)| *changes > 0)
+            .map(|(_, i, lines)| OutputDiff {
+                alternative: i,
+                hunks: group_hunks(lines),
+            })
+    }
+
+    /// Writes the diff as a fenced `diff` block in markdown. If the diff is
+    /// longer than `truncate` characters, it is cut off once the limit is
+    /// reached. The lines up to the first removed line, and the first added
+    /// line that replaces it, are always kept.
+    pub fn render_markdown(
+        &self,
+        dst: &mut impl Write,
+        truncate: Option<usize>,
+    ) -> Result<(), Error> {
+        // Index of the first changed line of the first hunk, and of the
+        // first added line in the same run of changes if that line is removed
+        let first_change = self.hunks.first().map(|hunk| {
+            let first = hunk
+                .lines
+                .iter()
+                .position(|l| l.kind != DiffLineKind::Same)
+                .unwrap_or(0);
+            let replacement = hunk
+                .lines
+                .get(first)
+                .filter(|l| l.kind == DiffLineKind::Removed)
+                .and_then(|_| {
+                    hunk.lines[first..]
+                        .iter()
+                        .take_while(|l| l.kind != DiffLineKind::Same)
+                        .position(|l| l.kind == DiffLineKind::Added)
+                })
+                .map(|i| first + i);
+            (first, replacement)
+        });
+
+        let mut text = String::from("--- expected\n+++ received\n");
+        let mut truncated = false;
+        for (h, hunk) in self.hunks.iter().enumerate() {
+            // The header has no line index
+            let lines = std::iter::once((None, hunk.header())).chain(
+                hunk.lines.iter().enumerate().map(|(i, l)| {
+                    let prefix = match l.kind {
+                        DiffLineKind::Same => ' ',
+                        DiffLineKind::Removed => '-',
+                        DiffLineKind::Added => '+',
+                    };
+                    (
+                        Some(i),
+                        format!("{prefix}{}", truncate_line(&l.text, truncate)),
+                    )
+                }),
+            );
+            for (i, line) in lines {
+                let kept = h == 0
+                    && first_change.is_some_and(|(first, replacement)| {
+                        i.is_none_or(|i| i <= first || Some(i) == replacement)
+                    });
+                if !kept && (truncated || truncate.is_some_and(|t| text.len() + line.len() > t)) {
+                    truncated = true;
+                    continue;
+                }
+                text.push_str(&line);
+                text.push('\n');
+            }
+        }
+        if truncated {
+            text.push_str("...\nTRUNCATED\n");
+        }
+
+        // The fence has to be longer than any run of backticks in the diff
+        let longest_run = text
+            .split(|c| c != '`')
+            .map(|run| run.len())
+            .max()
+            .unwrap_or(0);
+        let fence = "`".repeat(longest_run.max(2) + 1);
+        write!(dst, "{fence}diff\n{text}{fence}")?;
+        Ok(())
+    }
+
+    /// Writes the diff as a table with the expected output on the left and
+    /// the received output on the right. Removed and added lines next to
+    /// each other are shown on the same row.
+    pub fn render_html(&self, dst: &mut impl Write, escape: bool) -> Result<(), Error> {
+        fn write_cell(
+            dst: &mut impl Write,
+            escape: bool,
+            line: Option<(usize, &DiffLine)>,
+        ) -> Result<(), Error> {
+            match line {
+                Some((num, l)) => {
+                    let class = match l.kind {
+                        DiffLineKind::Same => "",
+                        DiffLineKind::Removed => " bg-danger-subtle",
+                        DiffLineKind::Added => " bg-success-subtle",
+                    };
+                    write!(
+                        dst,
+                        "<td class=\"text-body-secondary text-end{class}\">{num}</td><td class=\"{class}\"><pre class=\"mb-0\">"
+                    )?;
+                    html_write_str(dst, &l.text, escape)?;
+                    dst.write_str("</pre></td>")?;
+                }
+                None => dst.write_str("<td></td><td></td>")?,
+            }
+            Ok(())
+        }
+
+        dst.write_str(
+            "<div class=\"table-responsive\"><table class=\"table table-sm font-monospace mb-0\">",
+        )?;
+        dst.write_str("<thead><tr><th colspan=\"2\">Expected</th><th colspan=\"2\">Received</th></tr></thead><tbody>")?;
+        for hunk in self.hunks.iter() {
+            write!(
+                dst,
+                "<tr><td colspan=\"4\" class=\"text-body-secondary\">{}</td></tr>",
+                hunk.header()
+            )?;
+            let (mut expected_num, mut received_num) = (hunk.expected_start, hunk.received_start);
+            let mut i = 0;
+            while i < hunk.lines.len() {
+                if hunk.lines[i].kind == DiffLineKind::Same {
+                    dst.write_str("<tr>")?;
+                    write_cell(dst, escape, Some((expected_num, &hunk.lines[i])))?;
+                    write_cell(dst, escape, Some((received_num, &hunk.lines[i])))?;
+                    dst.write_str("</tr>")?;
+                    expected_num += 1;
+                    received_num += 1;
+                    i += 1;
+                    continue;
+                }
+
+                // Pair up a run of removed lines with the added lines after it
+                let removed: Vec<&DiffLine> = hunk.lines[i..]
+                    .iter()
+                    .take_while(|l| l.kind == DiffLineKind::Removed)
+                    .collect();
+                let added: Vec<&DiffLine> = hunk.lines[i + removed.len()..]
+                    .iter()
+                    .take_while(|l| l.kind == DiffLineKind::Added)
+                    .collect();
+                for row in 0..removed.len().max(added.len()) {
+                    dst.write_str("<tr>")?;
+                    write_cell(
+                        dst,
+                        escape,
+                        removed.get(row).map(|l| (expected_num + row, *l)),
+                    )?;
+                    write_cell(
+                        dst,
+                        escape,
+                        added.get(row).map(|l| (received_num + row, *l)),
+                    )?;
+                    dst.write_str("</tr>")?;
+                }
+                expected_num += removed.len();
+                received_num += added.len();
+                i += removed.len() + added.len();
+            }
+        }
+        dst.write_str("</tbody></table></div>")?;
+        Ok(())
+    }
+}
+
+/// Cuts a line to at most `truncate` characters.
+fn truncate_line(line: &str, truncate: Option<usize>) -> String {
+    match truncate {
+        Some(t) if line.chars().count() > t => {
+            format!("{}...", line.chars().take(t).collect::<String>())
+        }
+        _ => line.to_string(),
+    }
+}
+
+/// Computes the line-level diff between `expected` and `received`, as the
+/// sequence of lines in both with each line marked as same, removed, or
+/// added.
+pub fn diff_lines(expected: &str, received: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = expected.split('\n').collect();
+    let b: Vec<&str> = received.split('\n').collect();
+    let line = |kind: DiffLineKind, text: &str| DiffLine {
+        kind: kind,
+        text: text.to_string(),
+    };
+
+    // Common prefix and suffix are found directly, such that the search is
+    // only done over the lines in between.
+    let prefix = a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut lines: Vec<DiffLine> = a[..prefix]
+        .iter()
+        .map(|l| line(DiffLineKind::Same, l))
+        .collect();
+    match myers(a_mid, b_mid, MAX_EDIT_DISTANCE) {
+        Some(edits) => {
+            let (mut x, mut y) = (0, 0);
+            for edit in edits {
+                match edit {
+                    DiffLineKind::Same => {
+                        lines.push(line(edit, a_mid[x]));
+                        x += 1;
+                        y += 1;
+                    }
+                    DiffLineKind::Removed => {
+                        lines.push(line(edit, a_mid[x]));
+                        x += 1;
+                    }
+                    DiffLineKind::Added => {
+                        lines.push(line(edit, b_mid[y]));
+                        y += 1;
+                    }
+                }
+            }
+        }
+        None => {
+            lines.extend(a_mid.iter().map(|l| line(DiffLineKind::Removed, l)));
+            lines.extend(b_mid.iter().map(|l| line(DiffLineKind::Added, l)));
+        }
+    }
+    lines.extend(
+        a[a.len() - suffix..]
+            .iter()
+            .map(|l| line(DiffLineKind::Same, l)),
+    );
+    lines
+}
+
+/// Finds the shortest edit script from `a` to `b` with Myers' algorithm.
+/// Returns `None` if more than `max_d` lines have to be removed or added.
+fn myers(a: &[&str], b: &[&str], max_d: usize) -> Option<Vec<DiffLineKind>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    // The furthest x reached on each diagonal k = x - y after d edits, stored
+    // at index k + d.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut found = false;
+    for d in 0..=(max_d as isize) {
+        let mut v = vec![0; 2 * d as usize + 1];
+        for k in (-d..=d).step_by(2) {
+            let mut x = if d == 0 {
+                0
+            } else {
+                let prev = &trace[d as usize - 1];
+                let at = |k: isize| prev[(k + d - 1) as usize];
+                if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+                    at(k + 1)
+                } else {
+                    at(k - 1) + 1
+                }
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(k + d) as usize] = x;
+            if x >= n && y >= m {
+                found = true;
+                break;
+            }
+        }
+        trace.push(v);
+        if found {
+            break;
+        }
+    }
+    if !found {
+        return None;
+    }
+
+    // Backtrack from the end through the diagonals of each step
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (1..trace.len() as isize).rev() {
+        let prev = &trace[d as usize - 1];
+        let at = |k: isize| prev[(k + d - 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            edits.push(DiffLineKind::Same);
+            x -= 1;
+            y -= 1;
+        }
+        if x == prev_x {
+            edits.push(DiffLineKind::Added);
+        } else {
+            edits.push(DiffLineKind::Removed);
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    while x > 0 && y > 0 {
+        edits.push(DiffLineKind::Same);
+        x -= 1;
+        y -= 1;
+    }
+    edits.reverse();
+    Some(edits)
+}
+
+/// Groups the lines into hunks of changes, each with up to `CONTEXT_LINES`
+/// unchanged lines around it. Changes with few unchanged lines in between
+/// share the same hunk.
+fn group_hunks(lines: Vec<DiffLine>) -> Vec<DiffHunk> {
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| l.kind != DiffLineKind::Same)
+        .map(|(i, _)| i)
+        .collect();
+
+    // Ranges of line indices covered by each hunk
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for i in changed {
+        let start = i.saturating_sub(CONTEXT_LINES);
+        let end = (i + CONTEXT_LINES + 1).min(lines.len());
+        match ranges.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    // Line numbers in the expected and received output at each index
+    let mut hunks = Vec::new();
+    let (mut expected_num, mut received_num) = (1, 1);
+    let mut pos = 0;
+    for (start, end) in ranges {
+        for l in lines[pos..start].iter() {
+            expected_num += (l.kind != DiffLineKind::Added) as usize;
+            received_num += (l.kind != DiffLineKind::Removed) as usize;
+        }
+        let hunk_lines = lines[start..end].to_vec();
+        let expected_len = hunk_lines
+            .iter()
+            .filter(|l| l.kind != DiffLineKind::Added)
+            .count();
+        let received_len = hunk_lines
+            .iter()
+            .filter(|l| l.kind != DiffLineKind::Removed)
+            .count();
+        hunks.push(DiffHunk {
+            expected_start: expected_num,
+            expected_len: expected_len,
+            received_start: received_num,
+            received_len: received_len,
+            lines: hunk_lines,
+        });
+        expected_num += expected_len;
+        received_num += received_len;
+        pos = end;
+    }
+    hunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asserting::prelude::*;
+
+    fn kinds(lines: &[DiffLine]) -> String {
+        lines
+            .iter()
+            .map(|l| match l.kind {
+                DiffLineKind::Same => ' ',
+                DiffLineKind::Removed => '-',
+                DiffLineKind::Added => '+',
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        assert_that!(kinds(&diff_lines("a\nb\nc", "a\nb\nc"))).is_equal_to("   ".to_string());
+        assert_that!(kinds(&diff_lines("a\nb\nc", "a\nx\nc"))).is_equal_to(" -+ ".to_string());
+        assert_that!(kinds(&diff_lines("a\nb\nc\nd", "b\nc\nd\ne")))
+            .is_equal_to("-   +".to_string());
+        assert_that!(kinds(&diff_lines("x\na\nb\ny", "a\nz\nb"))).is_equal_to("- + -".to_string());
+        // A trailing newline is a difference of its own
+        assert_that!(kinds(&diff_lines("a\n", "a"))).is_equal_to(" -".to_string());
+
+        // Every line of both outputs is kept, in order
+        let lines = diff_lines("x\na\nb\ny", "a\nz\nb");
+        let expected: Vec<&str> = lines
+            .iter()
+            .filter(|l| l.kind != DiffLineKind::Added)
+            .map(|l| l.text.as_str())
+            .collect();
+        let received: Vec<&str> = lines
+            .iter()
+            .filter(|l| l.kind != DiffLineKind::Removed)
+            .map(|l| l.text.as_str())
+            .collect();
+        assert_that!(expected).is_equal_to(vec!["x", "a", "b", "y"]);
+        assert_that!(received).is_equal_to(vec!["a", "z", "b"]);
+    }
+
+    #[test]
+    fn test_diff_closest_and_hunks() {
+        let expected: Vec<String> = (1..=20).map(|i| i.to_string()).collect();
+        let mut received = expected.clone();
+        received[4] = "five".to_string();
+        received[15] = "sixteen".to_string();
+        let alternatives = vec!["nothing alike".to_string(), expected.join("\n")];
+
+        let diff = OutputDiff::closest(&received.join("\n"), &alternatives).unwrap();
+        assert_that!(diff.alternative).is_equal_to(1);
+        assert_that!(diff.hunks.len()).is_equal_to(2);
+        assert_that!(diff.hunks[0].header()).is_equal_to("@@ -2,7 +2,7 @@".to_string());
+        assert_that!(diff.hunks[1].header()).is_equal_to("@@ -13,7 +13,7 @@".to_string());
+
+        assert_that!(OutputDiff::closest("same", &["same".to_string()])).is_equal_to(None);
+        assert_that!(OutputDiff::closest("same", &[])).is_equal_to(None);
+    }
+
+    #[test]
+    fn test_diff_render() {
+        let expected: Vec<String> = (1..=100).map(|i| format!("line {i}")).collect();
+        let mut received = expected.clone();
+        received[49] = "line fifty".to_string();
+        received[89] = "line ninety".to_string();
+        let diff = OutputDiff::closest(&received.join("\n"), &[expected.join("\n")]).unwrap();
+
+        let mut md = String::new();
+        diff.render_markdown(&mut md, None).unwrap();
+        assert_that!(md.as_str())
+            .starts_with("```diff\n--- expected\n+++ received\n@@ -47,7 +47,7 @@\n");
+        assert_that!(md.as_str()).contains("\n-line 50\n+line fifty\n");
+        assert_that!(md.as_str()).ends_with("\n```");
+
+        // The first change is kept even if it exceeds the truncation limit
+        let mut md = String::new();
+        diff.render_markdown(&mut md, Some(10)).unwrap();
+        assert_that!(md.as_str()).contains("\n-line 50\n+line fifty\n...\nTRUNCATED\n");
+        assert_that!(md.as_str()).does_not_contain("line ninety");
+
+        // Only the first mismatching lines are kept if every line differs
+        let original: Vec<String> = (1..=100).map(|i| format!("expected {i}")).collect();
+        let replacement: Vec<String> = (1..=100).map(|i| format!("received {i}")).collect();
+        let replaced =
+            OutputDiff::closest(&replacement.join("\n"), &[original.join("\n")]).unwrap();
+        let mut md = String::new();
+        replaced.render_markdown(&mut md, Some(10)).unwrap();
+        assert_that!(md.as_str()).is_equal_to(
+            "```diff\n--- expected\n+++ received\n@@ -1,100 +1,100 @@\n-expected 1\n+received 1\n...\nTRUNCATED\n```"
+                .to_string(),
+        );
+
+        let mut html = String::new();
+        diff.render_html(&mut html, true).unwrap();
+        assert_that!(html.as_str()).contains("<td class=\" bg-danger-subtle\"><pre class=\"mb-0\">line 50</pre></td><td class=\"text-body-secondary text-end bg-success-subtle\">50</td>");
+    }
+}
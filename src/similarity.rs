@@ -0,0 +1,506 @@
+/// Similarity check across the graded snapshots in the shadow repositories.
+///
+/// The source files of each snapshot are tokenized with identifiers and
+/// literals normalized, such that renaming variables does not hide a match.
+/// Keywords are kept, as they carry the structure of the code.
+/// The token k-grams are hashed and fingerprints are selected using
+/// winnowing (Schleimer, Wilkerson, and Aiken, 2003). Two repositories are
+/// similar if they share a large fraction of their fingerprints.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    time::SystemTime,
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{settings::SimilaritySettings, tests::Tests, Settings},
+    error::Error,
+    utils::{
+        create_dir_if_not_exists, path_absolute_join, syscommand_timeout, systemtime_to_utc_string,
+        SyscommandSettings,
+    },
+};
+
+/// Keywords of the languages that solutions are commonly written in, mainly
+/// C and C++, separated by whitespace. These are kept as tokens of their
+/// own, as they carry the structure of the code that identifiers are
+/// normalized away from.
+const KEYWORDS: &str = "auto bool break case catch char class const continue default delete do \
+    double else enum extern false float for goto if inline int long namespace new nullptr private \
+    protected public register return short signed sizeof static struct switch template this throw \
+    true try typedef union unsigned using virtual void volatile while";
+
+/// Splits source code into normalized tokens. Comments (`//` and `/* */`)
+/// and whitespace are skipped, keywords are kept, other identifiers become
+/// `id`, numbers and character literals become `num`, and string literals
+/// become `str`. Any other character is a token of its own.
+pub fn tokenize(src: &str) -> Vec<&'static str> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            match KEYWORDS.split_whitespace().find(|k| *k == word) {
+                Some(keyword) => tokens.push(keyword),
+                None => tokens.push("id"),
+            }
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push("num");
+        } else if c == '"' {
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            i += 1;
+            tokens.push("str");
+        } else if c == '\'' {
+            i += 1;
+            while i < chars.len() && chars[i] != '\'' {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            i += 1;
+            tokens.push("num");
+        } else {
+            // Punctuation and operators are kept as they are, with the token
+            // being the character itself.
+            tokens.push(punctuation_token(c));
+            i += 1;
+        }
+    }
+    tokens
+}
+
+/// Returns the token of a punctuation character. Characters outside of
+/// ASCII are all treated as the same token.
+fn punctuation_token(c: char) -> &'static str {
+    const ASCII_PUNCTUATION: &str = "!\"#$%&'()*+,-./:;<=>?@[\\]^`{|}~";
+    match ASCII_PUNCTUATION.find(c) {
+        Some(i) => &ASCII_PUNCTUATION[i..i + 1],
+        None => "?",
+    }
+}
+
+/// FNV-1a hash of a k-gram, which is stable across runs and platforms.
+fn hash_kgram(kgram: &[&str]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for token in kgram {
+        for b in token.bytes().chain(std::iter::once(0)) {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+/// Selects the fingerprints of the tokens by winnowing the hashes of their
+/// k-grams. From each window of `window_size` consecutive hashes, the
+/// minimum hash is selected, picking the rightmost one on ties.
+pub fn winnow(tokens: &[&str], kgram_length: usize, window_size: usize) -> BTreeSet<u64> {
+    let kgram_length = kgram_length.max(1);
+    let window_size = window_size.max(1);
+    let hashes: Vec<u64> = tokens.windows(kgram_length).map(hash_kgram).collect();
+
+    let mut fingerprints = BTreeSet::new();
+    if hashes.is_empty() {
+        return fingerprints;
+    }
+    if hashes.len() <= window_size {
+        fingerprints.extend(hashes.iter().min());
+        return fingerprints;
+    }
+
+    let mut last_selected: Option<usize> = None;
+    for start in 0..=(hashes.len() - window_size) {
+        let (pos, hash) = hashes[start..start + window_size]
+            .iter()
+            .enumerate()
+            .rev()
+            .min_by_key(|(_, h)| **h)
+            .map(|(i, h)| (start + i, *h))
+            .expect("window is not empty");
+        if last_selected != Some(pos) {
+            fingerprints.insert(hash);
+            last_selected = Some(pos);
+        }
+    }
+    fingerprints
+}
+
+/// Fingerprints all text files within `dir`. Files that are not valid UTF-8
+/// are skipped, as are hidden files and directories.
+pub fn fingerprint_dir(
+    dir: impl AsRef<Path>,
+    kgram_length: usize,
+    window_size: usize,
+) -> Result<BTreeSet<u64>, Error> {
+    let mut fingerprints = BTreeSet::new();
+    if !dir.as_ref().is_dir() {
+        return Ok(fingerprints);
+    }
+    for entry in std::fs::read_dir(&dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            fingerprints.append(&mut fingerprint_dir(&path, kgram_length, window_size)?);
+        } else if let Ok(src) = std::fs::read_to_string(&path) {
+            fingerprints.append(&mut winnow(&tokenize(&src), kgram_length, window_size));
+        }
+    }
+    Ok(fingerprints)
+}
+
+/// Similarity between two repositories.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct SimilarityPair {
+    pub repo_a: String,
+    pub repo_b: String,
+    /// Number of fingerprints of each repository, after excluding the
+    /// template code
+    pub fingerprints_a: usize,
+    pub fingerprints_b: usize,
+    /// Number of fingerprints that the repositories share
+    pub shared: usize,
+    /// Fraction of the fingerprints of `repo_a` that also occur in `repo_b`
+    pub similarity_a: f64,
+    /// Fraction of the fingerprints of `repo_b` that also occur in `repo_a`
+    pub similarity_b: f64,
+}
+
+impl SimilarityPair {
+    /// The larger of the two similarities.
+    pub fn similarity(&self) -> f64 {
+        self.similarity_a.max(self.similarity_b)
+    }
+}
+
+/// Pairwise similarities of the snapshots of a tag.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct TagSimilarity {
+    pub tag_name: String,
+    /// The directory within the snapshots that was compared
+    pub srcdir: String,
+    /// Number of repositories with a snapshot of the tag
+    pub repositories: usize,
+    /// Pairs at or above the minimum similarity, most similar first
+    pub pairs: Vec<SimilarityPair>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct SimilarityReport {
+    /// Time that the report was generated, in UTC
+    pub date_generated: String,
+    pub tags: Vec<TagSimilarity>,
+}
+
+/// Compares the fingerprints of every pair of repositories, after removing
+/// the fingerprints in `template`. Pairs below `min_similarity` are left
+/// out, and the rest are sorted by similarity.
+pub fn compare(
+    fingerprints: &BTreeMap<String, BTreeSet<u64>>,
+    template: &BTreeSet<u64>,
+    min_similarity: f64,
+) -> Vec<SimilarityPair> {
+    let fingerprints: Vec<(&String, BTreeSet<u64>)> = fingerprints
+        .iter()
+        .map(|(repo, fp)| (repo, fp.difference(template).copied().collect()))
+        .filter(|(_, fp): &(&String, BTreeSet<u64>)| !fp.is_empty())
+        .collect();
+
+    let mut pairs = Vec::new();
+    for (i, (repo_a, fp_a)) in fingerprints.iter().enumerate() {
+        for (repo_b, fp_b) in fingerprints[i + 1..].iter() {
+            let shared = fp_a.intersection(fp_b).count();
+            let pair = SimilarityPair {
+                repo_a: repo_a.to_string(),
+                repo_b: repo_b.to_string(),
+                fingerprints_a: fp_a.len(),
+                fingerprints_b: fp_b.len(),
+                shared: shared,
+                similarity_a: shared as f64 / fp_a.len() as f64,
+                similarity_b: shared as f64 / fp_b.len() as f64,
+            };
+            if shared > 0 && pair.similarity() >= min_similarity {
+                pairs.push(pair);
+            }
+        }
+    }
+    pairs.sort_by(|a, b| b.similarity().total_cmp(&a.similarity()));
+    pairs
+}
+
+/// Finds the bare shadow repositories within `dir`, returning their paths
+/// relative to `dir` without the `.git` suffix, e.g.
+/// "github/gits-15.sys.kth.se/id2202/student-1".
+fn find_shadow_repos(dir: &Path, prefix: &str) -> Result<Vec<(String, String)>, Error> {
+    let mut repos = Vec::new();
+    if !dir.is_dir() {
+        return Ok(repos);
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        let rel = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{prefix}/{name}")
+        };
+        match rel.strip_suffix(".git") {
+            Some(repo) => repos.push((
+                repo.to_string(),
+                path.to_str()
+                    .ok_or_else(|| Error::convert("non-UTF-8 shadow repository path"))?
+                    .to_string(),
+            )),
+            None => repos.append(&mut find_shadow_repos(&path, &rel)?),
+        }
+    }
+    repos.sort();
+    Ok(repos)
+}
+
+/// Checks the similarity of the graded snapshots in the shadow repositories
+/// for each of `tags`, or for every tag in the test configuration if empty.
+pub fn check_similarity(settings: &Settings, tags: &[String]) -> Result<SimilarityReport, Error> {
+    let sim: &SimilaritySettings = &settings.similarity;
+    let tests = Tests::load(&settings.runner.test_config)?;
+
+    // The source directory of each tag to check
+    let mut srcdirs: BTreeMap<String, String> = BTreeMap::new();
+    for tag in tests.tag_groups.values().flatten() {
+        if tags.is_empty() || tags.contains(&tag.name) {
+            srcdirs.insert(tag.name.clone(), tag.build.srcdir.clone());
+        }
+    }
+    if let Some(unknown) = tags.iter().find(|t| !srcdirs.contains_key(*t)) {
+        return Err(Error::runtime(format!("unknown tag \"{unknown}\"")));
+    }
+
+    let mut template = BTreeSet::new();
+    for dir in sim.template_dirs.iter() {
+        template.append(&mut fingerprint_dir(
+            dir,
+            sim.kgram_length,
+            sim.window_size,
+        )?);
+    }
+
+    // Check out the latest snapshot of each repository
+    create_dir_if_not_exists(&settings.runner.workspace_dir)?;
+    let checkout_dir = tempfile::Builder::new()
+        .prefix("similarity")
+        .tempdir_in(&settings.runner.workspace_dir)?;
+    let mut snapshots: BTreeMap<String, String> = BTreeMap::new();
+    let shadow_repos = find_shadow_repos(Path::new(&settings.runner.shadow_dir), "")?;
+    for (i, (repo, shadow_repo)) in shadow_repos.iter().enumerate() {
+        let clone_dir = path_absolute_join(checkout_dir.path(), i.to_string())?;
+        match syscommand_timeout(
+            [
+                "git",
+                "clone",
+                "--local",
+                "--quiet",
+                shadow_repo,
+                &clone_dir,
+            ],
+            SyscommandSettings {
+                expected_code: Some(0),
+                ..Default::default()
+            },
+        ) {
+            Ok(_) => {
+                snapshots.insert(repo.clone(), path_absolute_join(&clone_dir, "snapshot")?);
+            }
+            Err(e) => log::warn!("Could not clone shadow repository {shadow_repo}: {e}"),
+        }
+    }
+
+    // Tags that build from the same directory share the same fingerprints
+    let mut fingerprints_by_srcdir: BTreeMap<&str, BTreeMap<String, BTreeSet<u64>>> =
+        BTreeMap::new();
+    let mut tag_similarities = Vec::new();
+    for (tag_name, srcdir) in srcdirs.iter() {
+        if !fingerprints_by_srcdir.contains_key(srcdir.as_str()) {
+            let mut fingerprints = BTreeMap::new();
+            for (repo, snapshot_dir) in snapshots.iter() {
+                let dir = path_absolute_join(snapshot_dir, srcdir)?;
+                let fp = fingerprint_dir(&dir, sim.kgram_length, sim.window_size)?;
+                if !fp.is_empty() {
+                    fingerprints.insert(repo.clone(), fp);
+                }
+            }
+            fingerprints_by_srcdir.insert(srcdir, fingerprints);
+        }
+        let fingerprints = &fingerprints_by_srcdir[srcdir.as_str()];
+        log::info!(
+            "Comparing {} snapshots of tag {tag_name}",
+            fingerprints.len()
+        );
+        tag_similarities.push(TagSimilarity {
+            tag_name: tag_name.clone(),
+            srcdir: srcdir.clone(),
+            repositories: fingerprints.len(),
+            pairs: compare(fingerprints, &template, sim.min_similarity),
+        });
+    }
+
+    Ok(SimilarityReport {
+        date_generated: systemtime_to_utc_string(&SystemTime::now())
+            .unwrap_or("NO_TIME".to_string()),
+        tags: tag_similarities,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asserting::prelude::*;
+
+    const SOLUTION: &str = r#"
+        // Computes the sum of the first n numbers
+        int sum(int n) {
+            int total = 0;
+            for (int i = 1; i <= n; i++) {
+                total += i;
+            }
+            printf("%d\n", total);
+            return total;
+        }
+    "#;
+
+    const RENAMED: &str = r#"
+        int accumulate(int count) { /* renamed */
+            int acc = 0;
+            for (int k = 1; k <= count; k++) { acc += k; }
+            printf("result: %d\n", acc);
+            return acc;
+        }
+    "#;
+
+    const UNRELATED: &str = r#"
+        struct node *reverse(struct node *head) {
+            struct node *prev = NULL;
+            while (head != NULL) {
+                struct node *next = head->next;
+                head->next = prev;
+                prev = head;
+                head = next;
+            }
+            return prev;
+        }
+    "#;
+
+    #[test]
+    fn test_tokenize() {
+        assert_that!(tokenize("x1 = y_2 + 3.5; // comment"))
+            .is_equal_to(vec!["id", "=", "id", "+", "num", ";"]);
+        assert_that!(tokenize("f(\"a \\\" b\", /* c */ 'd')"))
+            .is_equal_to(vec!["id", "(", "str", ",", "num", ")"]);
+        assert_that!(tokenize("c == '\"'; x = 1;"))
+            .is_equal_to(vec!["id", "=", "=", "num", ";", "id", "=", "num", ";"]);
+        assert_that!(tokenize("'\\''")).is_equal_to(vec!["num"]);
+        assert_that!(tokenize(SOLUTION)).is_equal_to(tokenize(RENAMED));
+
+        // Keywords are not normalized like identifiers
+        assert_that!(tokenize("if (x) return y;"))
+            .is_equal_to(vec!["if", "(", "id", ")", "return", "id", ";"]);
+        assert_that!(tokenize("if (x) return y;")).is_not_equal_to(tokenize("f(x) g y;"));
+    }
+
+    #[test]
+    fn test_winnow() {
+        let tokens = tokenize(SOLUTION);
+        let fp = winnow(&tokens, 5, 4);
+        assert_that!(fp.is_empty()).is_false();
+        // At least one fingerprint per window
+        assert_that!(fp.len()).is_at_least((tokens.len() - 5 + 1) / 4);
+        assert_that!(winnow(&tokens[..3], 5, 4).is_empty()).is_true();
+        assert_that!(winnow(&tokens[..5], 5, 4).len()).is_equal_to(1);
+    }
+
+    #[test]
+    fn test_compare() {
+        let fp = |src: &str| winnow(&tokenize(src), 5, 4);
+        let fingerprints = BTreeMap::from([
+            ("a".to_string(), fp(SOLUTION)),
+            ("b".to_string(), fp(RENAMED)),
+            ("c".to_string(), fp(UNRELATED)),
+        ]);
+
+        let pairs = compare(&fingerprints, &BTreeSet::new(), 0.5);
+        assert_that!(pairs.len()).is_equal_to(1);
+        assert_that!(pairs[0].repo_a.as_str()).is_equal_to("a");
+        assert_that!(pairs[0].repo_b.as_str()).is_equal_to("b");
+        assert_that!(pairs[0].similarity()).is_equal_to(1.0);
+
+        // Shared template code does not count
+        let pairs = compare(&fingerprints, &fp(SOLUTION), 0.0);
+        assert_that!(pairs.is_empty()).is_true();
+    }
+
+    #[test]
+    fn test_find_shadow_repos() {
+        let dir = tempfile::tempdir().unwrap();
+        let shadow_dir = dir.path().to_str().unwrap();
+        for repo in ["github/gits-15.sys.kth.se/id2202/a.git", "git/b.git"] {
+            std::fs::create_dir_all(path_absolute_join(shadow_dir, repo).unwrap()).unwrap();
+        }
+        let repos: Vec<String> = find_shadow_repos(dir.path(), "")
+            .unwrap()
+            .into_iter()
+            .map(|(repo, _)| repo)
+            .collect();
+        assert_that!(repos).is_equal_to(vec![
+            "git/b".to_string(),
+            "github/gits-15.sys.kth.se/id2202/a".to_string(),
+        ]);
+
+        // Hidden and non-UTF-8 files are not fingerprinted
+        let src_dir = path_absolute_join(shadow_dir, "src").unwrap();
+        std::fs::create_dir_all(&src_dir).unwrap();
+        std::fs::write(path_absolute_join(&src_dir, ".hidden.c").unwrap(), SOLUTION).unwrap();
+        std::fs::write(
+            path_absolute_join(&src_dir, "binary").unwrap(),
+            [0xff, 0xfe],
+        )
+        .unwrap();
+        assert_that!(fingerprint_dir(&src_dir, 5, 4).unwrap().is_empty()).is_true();
+        std::fs::write(path_absolute_join(&src_dir, "main.c").unwrap(), SOLUTION).unwrap();
+        assert_that!(fingerprint_dir(&src_dir, 5, 4).unwrap()).is_equal_to(winnow(
+            &tokenize(SOLUTION),
+            5,
+            4,
+        ));
+    }
+}
@@ -1,5 +1,6 @@
 pub mod container;
 pub mod handle;
+pub mod progress;
 pub mod tag_runner;
 pub mod test_grader;
 
@@ -1,9 +1,12 @@
 /// Information about the container used for grading a solution.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ContainerInfo {
     /// The image which is used to run the container.
     pub podman_image: String,
 
+    /// The ID of the image, identifying the exact image that is run.
+    pub podman_image_id: String,
+
     /// The name of the container.
     pub podman_container_name: String,
 
@@ -24,4 +27,8 @@ pub struct ContainerInfo {
 
     /// The directory outside the container that tests are mounted from.
     pub external_tests: String,
+
+    /// The directory outside the container where the outputs of reference
+    /// solutions are cached. This is kept between graded solutions.
+    pub reference_cache_dir: String,
 }
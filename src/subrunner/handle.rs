@@ -4,15 +4,20 @@ use std::{
     time::{Duration, SystemTime},
 };
 
-use id2202_autograder::{
+use crate::{
     config::{Settings, Tests},
     db::models::{SubmissionInfo, SubmissionStatusCode},
     error::Error,
-    reporting::{Report, ReportInvalidTag, ReportMessage, ReportSubmission, ReportTagGrading},
+    podman,
+    reporting::{
+        Report, ReportInvalidTag, ReportMessage, ReportSubmission, ReportTagGrading, ReportWrapper,
+    },
     utils::{path_absolute_join, syscommand_timeout, SyscommandSettings},
 };
 
-use crate::subrunner::{container::ContainerInfo, tag_runner::TagRunner};
+use crate::subrunner::{
+    container::ContainerInfo, progress::SubmissionProgress, tag_runner::TagRunner, test_grader,
+};
 
 static ERRMSG_INTERNAL_ERROR: &str = "Internal error when starting job. Contact course staff.";
 
@@ -65,10 +70,103 @@ impl SubmissionRunnerHandle {
     /// Creates a new handle, or returns an error message to be shown to the
     /// user. Internal error messages should be presented as log messages only,
     /// using map_err or inspect_err.
+    ///
+    /// The solution is graded as if it was submitted at `submitted`, which
+    /// for a re-grade is the time of the original submission.
     pub fn new(
         settings: &Settings,
         subinfo: &SubmissionInfo,
+        submitted: SystemTime,
+        runner_id: i32,
+    ) -> Result<Self, Report> {
+        let sub = subinfo.get_submission();
+        let (ssh_url, commit) = subinfo.ssh_url_and_commit();
+
+        Self::setup(
+            settings,
+            sub.id,
+            &sub.grading_tags,
+            submitted,
+            runner_id,
+            |source_dir: &str| {
+                // A way to check out a specific commit, without the cloning the whole history
+                let gitcmd_settings = SyscommandSettings {
+                    expected_code: Some(0),
+                    ..Default::default()
+                };
+                std::fs::create_dir_all(source_dir)
+                    .map_err(Error::from)
+                    .and_then(|_| {
+                        syscommand_timeout(
+                            &["git", "-C", source_dir, "init"],
+                            gitcmd_settings.to_owned(),
+                        )
+                    })
+                    .and_then(|_| {
+                        syscommand_timeout(
+                            &["git", "-C", source_dir, "remote", "add", "origin", ssh_url],
+                            gitcmd_settings.to_owned(),
+                        )
+                    })
+                    .and_then(|_| {
+                        syscommand_timeout(
+                            &[
+                                "git", "-C", source_dir, "fetch", "--depth", "1", "origin", commit,
+                            ],
+                            gitcmd_settings.to_owned(),
+                        )
+                    })
+                    .and_then(|_| {
+                        syscommand_timeout(
+                            &["git", "-C", source_dir, "checkout", "FETCH_HEAD"],
+                            gitcmd_settings.to_owned(),
+                        )
+                    })
+                    .map(|_| ())
+                    .inspect_err(|e| log::error!("Error cloning repository from {}: {e}", ssh_url))
+            },
+        )
+    }
+
+    /// Creates a new handle for grading a local directory, without any
+    /// submission registered in the database. The contents of `local_dir` are
+    /// copied into the workspace, such that the directory itself is never
+    /// modified. The `grading_tags` are separated by `;`, in the same way as
+    /// for submissions in the database.
+    ///
+    /// The submission ID of the returned handle is always 0, and the solution
+    /// is considered to be submitted when the handle is created.
+    pub fn new_local(
+        settings: &Settings,
+        local_dir: &str,
+        grading_tags: &str,
         runner_id: i32,
+    ) -> Result<Self, Report> {
+        Self::setup(
+            settings,
+            0,
+            grading_tags,
+            SystemTime::now(),
+            runner_id,
+            |source_dir: &str| {
+                dircpy::copy_dir(local_dir, source_dir)
+                    .map_err(Error::from)
+                    .inspect_err(|e| {
+                        log::error!("Error copying local directory {}: {e}", local_dir)
+                    })
+            },
+        )
+    }
+
+    /// Common setup of a handle. The `fetch_source` function is responsible
+    /// for populating the source directory that is passed to it.
+    fn setup(
+        settings: &Settings,
+        submission_id: i64,
+        grading_tags: &str,
+        submitted: SystemTime,
+        runner_id: i32,
+        fetch_source: impl FnOnce(&str) -> Result<(), Error>,
     ) -> Result<Self, Report> {
         // Convenient for reporting internal errors
         fn internal_error_report() -> Report {
@@ -77,8 +175,6 @@ impl SubmissionRunnerHandle {
             })
         }
 
-        let sub = subinfo.get_submission();
-
         let tests = Tests::load(&settings.runner.test_config).map_err(|e| {
             log::error!("Could not load test configuration: {e}");
             internal_error_report()
@@ -132,9 +228,28 @@ impl SubmissionRunnerHandle {
             internal_error_report()
         })?;
 
+        let podman_image_id = podman::image_id(&settings.runner.podman_image).map_err(|e| {
+            log::error!(
+                "Could not get the ID of image {}: {e}",
+                settings.runner.podman_image
+            );
+            internal_error_report()
+        })?;
+
+        let reference_cache_dir =
+            path_absolute_join(&settings.runner.workspace_dir, "reference_cache")
+                .and_then(|cache_root| {
+                    test_grader::reference_cache_dir(&cache_root, &podman_image_id)
+                })
+                .map_err(|e| {
+                    log::error!("Could not set up reference_cache_dir: {e}");
+                    internal_error_report()
+                })?;
+
         // Step 2: Set up the container
         let container = ContainerInfo {
             podman_image: settings.runner.podman_image.clone(),
+            podman_image_id: podman_image_id,
             podman_container_name: format!("id2202_runner{}", runner_id),
             podman_network_name: format!("{}{}", settings.runner.podman_network_prefix, runner_id),
             internal_build_dir: "/root/graded_solution".to_string(),
@@ -142,15 +257,13 @@ impl SubmissionRunnerHandle {
             mount_tests: settings.runner.mount_tests.clone(),
             external_solution: solution_dir,
             external_tests: tests_dir,
+            reference_cache_dir: reference_cache_dir,
         };
 
         // Step 3: Collect the tags to grade
-        log::debug!(
-            "Collecting grading tag information from {}",
-            sub.grading_tags
-        );
+        log::debug!("Collecting grading tag information from {}", grading_tags);
         let mut tag_runners: BTreeMap<String, TagRunner> = BTreeMap::new();
-        for t in sub.grading_tags.split(";") {
+        for t in grading_tags.split(";") {
             match tests.tag_groups.get(t) {
                 Some(vec_tag) => {
                     for tag in vec_tag {
@@ -159,8 +272,13 @@ impl SubmissionRunnerHandle {
                                 runner.derived_from.insert(t.to_owned());
                             }
                             None => {
-                                let mut runner =
-                                    TagRunner::new(tag, &container, &tests.default, &source_dir);
+                                let mut runner = TagRunner::new(
+                                    tag,
+                                    &container,
+                                    &tests.default,
+                                    &source_dir,
+                                    submitted,
+                                );
                                 runner.derived_from.insert(t.to_owned());
                                 tag_runners.insert(tag.name.clone(), runner);
                             }
@@ -195,52 +313,7 @@ impl SubmissionRunnerHandle {
         // After this point we need to make sure that the workspace_dir is
         // deleted when the TestRunnerHandle is dropped.
 
-        let (ssh_url, commit) = subinfo.ssh_url_and_commit();
-
-        // A way to check out a specific commit, without the cloning the whole history
-        let gitcmd_settings = SyscommandSettings {
-            expected_code: Some(0),
-            ..Default::default()
-        };
-        std::fs::create_dir_all(&source_dir)
-            .map_err(Error::from)
-            .and_then(|_| {
-                syscommand_timeout(
-                    &["git", "-C", &source_dir, "init"],
-                    gitcmd_settings.to_owned(),
-                )
-            })
-            .and_then(|_| {
-                syscommand_timeout(
-                    &["git", "-C", &source_dir, "remote", "add", "origin", ssh_url],
-                    gitcmd_settings.to_owned(),
-                )
-            })
-            .and_then(|_| {
-                syscommand_timeout(
-                    &[
-                        "git",
-                        "-C",
-                        &source_dir,
-                        "fetch",
-                        "--depth",
-                        "1",
-                        "origin",
-                        commit,
-                    ],
-                    gitcmd_settings.to_owned(),
-                )
-            })
-            .and_then(|_| {
-                syscommand_timeout(
-                    &["git", "-C", &source_dir, "checkout", "FETCH_HEAD"],
-                    gitcmd_settings.to_owned(),
-                )
-            })
-            .map_err(|e| {
-                log::error!("Error cloning repository from {}: {e}", ssh_url);
-                internal_error_report()
-            })?;
+        fetch_source(&source_dir).map_err(|_| internal_error_report())?;
 
         let deadline_time = SystemTime::now()
             .checked_add(Duration::from_secs(tests.default.timeout_total.into()))
@@ -254,7 +327,7 @@ impl SubmissionRunnerHandle {
 
         Ok(SubmissionRunnerHandle {
             workspace: workspace_dir,
-            submission_id: sub.id,
+            submission_id: submission_id,
             source_dir: source_dir,
             next_tag_index: 0,
             tag_runners: tag_runners.into_values().collect(),
@@ -342,8 +415,11 @@ impl SubmissionRunnerHandle {
             }
         } else {
             let prev_count = tag_runner.collected_reports;
+            let reports_remaining = self
+                .tests_max_details
+                .saturating_sub(self.tests_collected_details);
 
-            if !tag_runner.run_test(self.tests_collected_details < self.tests_max_details)? {
+            if !tag_runner.run_test(reports_remaining)? {
                 log::info!(
                     "Finished running test cases for tag \"{}\", proceeding to next tag",
                     tag_runner.tag_name
@@ -351,9 +427,7 @@ impl SubmissionRunnerHandle {
                 self.next_tag_index += 1;
             }
 
-            if tag_runner.collected_reports > prev_count {
-                self.tests_collected_details += 1;
-            }
+            self.tests_collected_details += tag_runner.collected_reports - prev_count;
         }
         if let Some(ssc) = tag_runner.experienced_bad_behavior() {
             self.status_code = ssc;
@@ -362,6 +436,74 @@ impl SubmissionRunnerHandle {
         Ok(())
     }
 
+    /// Returns the current progress of grading this submission.
+    pub fn progress(&self) -> SubmissionProgress {
+        let current = if self.is_finished() {
+            None
+        } else {
+            self.tag_runners.get(self.next_tag_index)
+        };
+        SubmissionProgress {
+            current_tag: current.map(|tr| tr.tag_name.clone()),
+            current_group: current
+                .and_then(|tr| tr.current_group())
+                .map(|g| g.to_string()),
+            tags: self
+                .tag_runners
+                .iter()
+                .enumerate()
+                .map(|(i, tr)| tr.progress(i < self.next_tag_index || self.is_finished()))
+                .collect(),
+        }
+    }
+
+    /// Returns the number of tags that have finished grading, which are the
+    /// first tags of the submission.
+    pub fn finished_tags(&self) -> usize {
+        if self.is_finished() {
+            self.tag_runners.len()
+        } else {
+            self.next_tag_index
+        }
+    }
+
+    /// Returns the report of the tag with index `tag_index`.
+    pub fn tag_report(&self, tag_index: usize) -> Option<Report> {
+        self.tag_runners
+            .get(tag_index)
+            .map(|tr| Report::TagGrading(tr.results_report()))
+    }
+
+    /// Returns a report of the tags that have finished grading so far, while
+    /// the submission is still being graded.
+    pub fn partial_report(&self) -> Report {
+        let finished = self.finished_tags();
+        let mut reports = vec![Report::Submission(ReportSubmission {
+            premature_exit_reason: None,
+            max_shown_details: Some(self.tests_max_details),
+            tag_reports: self.tag_runners[..finished]
+                .iter()
+                .map(|tr| tr.results_report())
+                .collect(),
+        })];
+        if finished < self.tag_runners.len() {
+            reports.push(Report::Message(ReportMessage {
+                msg: format!(
+                    "Still grading: {}",
+                    self.tag_runners[finished..]
+                        .iter()
+                        .map(|tr| tr.tag_name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            }));
+        }
+        Report::Wrapper(ReportWrapper {
+            title: Some("Grading in progress".to_string()),
+            reports: reports,
+        })
+    }
+
     /// Compiles the report of submission results, and sets the status of the
     /// submission if it is still considered as running.
     ///
@@ -376,6 +518,15 @@ impl SubmissionRunnerHandle {
                 self.status_code = SubmissionStatusCode::Success;
             } else if tag_reports.iter().any(|tr| tr.build_failure.is_some()) {
                 self.status_code = SubmissionStatusCode::BuildError;
+            } else if tag_reports.iter().all(|tr| {
+                tr.ok
+                    || tr
+                        .late_submission
+                        .as_ref()
+                        .is_some_and(|late| !late.accepted)
+            }) {
+                // The only tags that did not pass were submitted too late
+                self.status_code = SubmissionStatusCode::DeadlinePassed;
             } else {
                 self.status_code = SubmissionStatusCode::TestCasesFailed;
             }
@@ -0,0 +1,89 @@
+/// Progress of a submission that is being graded, published by the runner
+/// while it steps through the test cases.
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct SubmissionProgress {
+    /// The tag that is currently being graded, or `None` when all tags are
+    /// done.
+    pub current_tag: Option<String>,
+
+    /// Title of the test group that the next test case belongs to.
+    pub current_group: Option<String>,
+
+    /// Progress of each tag, in the order that they are graded.
+    pub tags: Vec<TagProgress>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct TagProgress {
+    pub tag_name: String,
+
+    /// Whether the solution has been built for this tag.
+    pub built: bool,
+
+    /// Whether grading of this tag has finished, either by running all of its
+    /// test cases or by being interrupted.
+    pub finished: bool,
+
+    pub tests_total: usize,
+    pub tests_run: usize,
+    pub tests_passed: usize,
+}
+
+impl SubmissionProgress {
+    /// Returns the percentage of the test cases that have been run, where a
+    /// finished tag counts as if all of its test cases were run.
+    pub fn percent(&self) -> u32 {
+        let (done, total) = self.tags.iter().fold((0, 0), |(done, total), tp| {
+            let tag_done = if tp.finished {
+                tp.tests_total
+            } else {
+                tp.tests_run.min(tp.tests_total)
+            };
+            (done + tag_done, total + tp.tests_total)
+        });
+        (100 * done).checked_div(total).unwrap_or(0) as u32
+    }
+
+    /// Number of test cases that have failed so far.
+    pub fn tests_failed(&self) -> usize {
+        self.tags
+            .iter()
+            .map(|tp| tp.tests_run.saturating_sub(tp.tests_passed))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asserting::prelude::*;
+
+    #[test]
+    fn test_progress_percent() {
+        let tag = |finished: bool, tests_total: usize, tests_run: usize, tests_passed: usize| {
+            TagProgress {
+                tag_name: "hello".to_string(),
+                built: true,
+                finished: finished,
+                tests_total: tests_total,
+                tests_run: tests_run,
+                tests_passed: tests_passed,
+            }
+        };
+        let progress = |tags: Vec<TagProgress>| SubmissionProgress {
+            current_tag: None,
+            current_group: None,
+            tags: tags,
+        };
+
+        assert_that!(progress(vec![]).percent()).is_equal_to(0);
+        assert_that!(progress(vec![tag(false, 4, 1, 1)]).percent()).is_equal_to(25);
+        assert_that!(progress(vec![tag(true, 4, 1, 0), tag(false, 4, 2, 1)]).percent())
+            .is_equal_to(75);
+        assert_that!(progress(vec![tag(true, 4, 1, 0), tag(false, 4, 2, 1)]).tests_failed())
+            .is_equal_to(2);
+    }
+}
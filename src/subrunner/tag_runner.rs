@@ -0,0 +1,1361 @@
+/// Functionality for running the test suite contained within a single tag.
+/// This contains the functionality for building the project, as well as for
+/// iterating over the test cases.
+///
+use std::{
+    collections::BTreeSet,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    config::{
+        tests::{LatePolicy, ResourceLimits},
+        Tag, TagBuildConfig, Test, TestDefault, TestGroup, Testkind,
+    },
+    db::models::SubmissionStatusCode,
+    error::{Error, ErrorKind, SyscommandError},
+    podman,
+    reporting::{
+        DetailsBuildFailure, DetailsLateSubmission, DetailsTagGradingGroup, DetailsTagScore,
+        DetailsTestUsage, MIMETypeInfo, ReportTagGrading,
+    },
+    utils::{self, path_absolute_join, syscommand_timeout, SyscommandSettings},
+};
+use chrono::{DateTime, Utc};
+use num_traits::ToPrimitive;
+
+use crate::subrunner::{
+    container::ContainerInfo,
+    progress::TagProgress,
+    test_grader::{FailureCause, GradingResult, ResourceLimit},
+};
+
+#[derive(Debug, Clone)]
+pub enum BuildResult {
+    BuildOk,
+    BuildTimeout {
+        timeout: u32,
+        captured_stdout: Option<String>,
+        captured_stderr: Option<String>,
+    },
+    BuildSourceNotFound {
+        expected_dir: String,
+    },
+    BuildOutputLimitExceeded {
+        limit: usize,
+    },
+    BuildProhibitedFiles {
+        /// A list of found files in the build dir which are prohibited.
+        found_files: Vec<MIMETypeInfo>,
+    },
+    BuildFailed {
+        message: Option<String>,
+        code: Option<i32>,
+        captured_stdout: Option<String>,
+        captured_stderr: Option<String>,
+    },
+}
+
+/// The runner for a grading tag. This spawns a podman container, builds the
+/// project inside the container, and proceeds to run every test case defined
+/// for this tag.
+#[derive(Debug, Clone)]
+pub struct TagRunner {
+    /// Information about the container that the tag runner will use to build
+    /// the project and grade the tests container within this tag.
+    pub container: ContainerInfo,
+
+    /// The name of this tag, e.g. `hello` for the `#hello` tag.
+    pub tag_name: String,
+
+    /// Tag groups that this tag was derived from, e.g. `hello` from the
+    /// `#hello-all` tag group.
+    pub derived_from: BTreeSet<String>,
+
+    /// Build configuration for this specific tag.
+    pub build_conf: TagBuildConfig,
+
+    /// Test case defaults, useful for things such as timeout
+    pub test_default: TestDefault,
+
+    /// Maximum number of test cases to grade concurrently inside the
+    /// container.
+    pub concurrency: usize,
+
+    /// Resource limits applied to the container.
+    pub limits: ResourceLimits,
+
+    /// Whether a score is computed for this tag.
+    pub scored: bool,
+
+    /// Fraction of the maximum score that is needed for the tag to pass. If
+    /// `None`, every test case has to pass.
+    pub pass_threshold: Option<f64>,
+
+    /// Deadline and late-submission policy of this tag.
+    pub late_policy: LatePolicy,
+
+    /// When the graded solution was submitted, which is compared against the
+    /// deadline of the tag.
+    pub submitted: SystemTime,
+
+    /// Resource limit counters of the container, as last observed.
+    resource_events: podman::ResourceEvents,
+
+    /// Iterators for each respective test group contained within this tag.
+    toplevel_iterator: TestGroupIterator,
+
+    /// Result of the build process
+    build_result: Option<BuildResult>,
+
+    /// Where the source files used to grade this solution is located on the
+    /// host system. This directory should be considered read-only, and only
+    /// ever copied/read from.
+    source_dir: String,
+
+    /// Number of failed test cases
+    testfail_count: usize,
+
+    /// The result from a test case that means that the entire grading process
+    /// should be interrupted.
+    bad_test_behavior: Option<FailureCause>,
+
+    /// Number of reports that have been collected for test cases
+    pub collected_reports: usize,
+}
+
+impl TagRunner {
+    /// Creates a new tag runner from a tag specification, for a solution that
+    /// was submitted at `submitted`.
+    pub fn new(
+        tag: &Tag,
+        container: &ContainerInfo,
+        test_default: &TestDefault,
+        source_dir: &String,
+        submitted: SystemTime,
+    ) -> Self {
+        TagRunner {
+            tag_name: tag.name.to_owned(),
+            container: container.to_owned(),
+            build_conf: tag.build.to_owned(),
+            test_default: test_default.to_owned(),
+            concurrency: tag.concurrency,
+            limits: tag.limits.to_owned(),
+            scored: tag.is_scored(),
+            pass_threshold: tag.pass_threshold,
+            late_policy: tag.late_policy.to_owned(),
+            submitted: submitted,
+            resource_events: podman::ResourceEvents::default(),
+            derived_from: BTreeSet::new(),
+
+            toplevel_iterator: TestGroupIterator::from_groups(
+                format!("top-level for tag \"{}\"", tag.name),
+                tag.test_groups
+                    .iter()
+                    .map(|tg| TestGroupIterator::new(tg))
+                    .collect(),
+            ),
+
+            build_result: None,
+            source_dir: source_dir.clone(),
+            testfail_count: 0,
+            bad_test_behavior: None,
+            collected_reports: 0,
+        }
+    }
+
+    /// Returns true if the solution for this project has been built.
+    /// Irregardless of whether it was successfully built or not.
+    pub fn has_built(&self) -> bool {
+        self.build_result.is_some()
+    }
+
+    /// Returns the progress of grading this tag. The tag is considered
+    /// `finished` by the caller, since only the caller knows whether grading
+    /// has moved on to the next tag.
+    pub fn progress(&self, finished: bool) -> TagProgress {
+        let (tests_total, tests_run, tests_passed) = self.toplevel_iterator.counts();
+        TagProgress {
+            tag_name: self.tag_name.clone(),
+            built: self.has_built(),
+            finished: finished,
+            tests_total: tests_total,
+            tests_run: tests_run,
+            tests_passed: tests_passed,
+        }
+    }
+
+    /// Returns the title of the test group that the next test case to run
+    /// belongs to, if any.
+    pub fn current_group(&self) -> Option<&str> {
+        self.toplevel_iterator.current_title()
+    }
+
+    /// Returns true if a build process has been attempted. This is useful for
+    /// checking whether a build was rejected prematurely.
+    pub fn attempted_build(&self) -> bool {
+        // Note: using an exhaustive match here for the sake of correctness
+        match &self.build_result {
+            None => false,
+            Some(BuildResult::BuildSourceNotFound { .. }) => false,
+            Some(BuildResult::BuildProhibitedFiles { .. }) => false,
+            Some(BuildResult::BuildOk) => true,
+            Some(BuildResult::BuildFailed { .. }) => true,
+            Some(BuildResult::BuildTimeout { .. }) => true,
+            Some(BuildResult::BuildOutputLimitExceeded { .. }) => true,
+        }
+    }
+
+    /// Returns a submission status code IF the tag runner has experienced some
+    /// bad behavior and the entire grading process should stop.
+    pub fn experienced_bad_behavior(&self) -> Option<SubmissionStatusCode> {
+        use SubmissionStatusCode as SSC;
+
+        match &self.build_result {
+            Some(BuildResult::BuildTimeout { .. }) => Some(SSC::BuildTimedOut),
+            Some(BuildResult::BuildOutputLimitExceeded { .. }) => Some(SSC::OutputLimitExceeded),
+            None
+            | Some(BuildResult::BuildSourceNotFound { .. })
+            | Some(BuildResult::BuildProhibitedFiles { .. })
+            | Some(BuildResult::BuildOk) => None,
+            Some(BuildResult::BuildFailed { .. }) => match self.bad_test_behavior {
+                Some(FailureCause::OutputMismatch)
+                | Some(FailureCause::ThresholdExceeded)
+                | Some(FailureCause::ResourceLimitExceeded(_)) => Some(SSC::TestCasesFailed),
+                Some(FailureCause::Timeout(_)) => Some(SSC::TestCasesTimedOut),
+                Some(FailureCause::OutputLimitExceeded { .. }) => Some(SSC::OutputLimitExceeded),
+                None => None,
+            },
+        }
+    }
+
+    /// Compiles a report on the tests within this tag group.
+    pub fn results_report(&self) -> ReportTagGrading {
+        let build_failure = match &self.build_result {
+            None => Some(DetailsBuildFailure { msg: "Never attempted to build the project.".to_string(), ..DetailsBuildFailure::default() }),
+            Some(BuildResult::BuildOk) => None, // ok
+            Some(BuildResult::BuildSourceNotFound { expected_dir }) => {
+                Some(DetailsBuildFailure {
+                    msg: "Could not build the project.".to_string(),
+                    srcdir: Some(expected_dir.clone()),
+                    missing_source_directory: true,
+                    ..DetailsBuildFailure::default()
+                })
+            }
+            Some(BuildResult::BuildProhibitedFiles { found_files }) => {
+                Some(DetailsBuildFailure {
+                    msg: "Build failed due to unexpected non-text files in your solution."
+                        .to_string(),
+                    srcdir: Some(self.build_conf.srcdir.clone()),
+                    prohibited_mimetype_files: found_files.clone(),
+                    suffix_message: Some("Please remove these files from your solution directory and make sure that your .gitignore is properly configured.".to_string()),
+                    ..DetailsBuildFailure::default()
+                })
+            }
+            Some(BuildResult::BuildFailed {
+                message,
+                code,
+                captured_stdout,
+                captured_stderr,
+            }) => {
+                let mut desc = "Build process failed.".to_string();
+                if let Some(msg) = message {
+                    desc.push_str(&format!(" {}", msg));
+                }
+                Some(DetailsBuildFailure {
+                    msg: desc,
+                    cmd: Some(self.build_conf.cmd.join(" ")),
+                    srcdir: Some(self.build_conf.srcdir.clone()),
+                    exit_code: code.clone(),
+                    captured_stdout: captured_stdout.clone(),
+                    captured_stderr: captured_stderr.clone(),
+                    ..DetailsBuildFailure::default()
+                })
+            }
+            Some(BuildResult::BuildTimeout {
+                timeout,
+                captured_stdout,
+                captured_stderr,
+            }) => Some(DetailsBuildFailure {
+                msg: format!("Build process timed out after {} seconds.", timeout),
+                cmd: Some(self.build_conf.cmd.join(" ")),
+                srcdir: Some(self.build_conf.srcdir.clone()),
+                captured_stdout: captured_stdout.clone(),
+                captured_stderr: captured_stderr.clone(),
+                ..DetailsBuildFailure::default()
+            }),
+            Some(BuildResult::BuildOutputLimitExceeded { limit }) => {
+                Some(DetailsBuildFailure {
+                    msg: format!(
+                        "Build failed due to exceeding the output limit of {} bytes on standard output or standard error.",
+                        limit
+                    ),
+                    cmd: Some(self.build_conf.cmd.join(" ")),
+                    srcdir: Some(self.build_conf.srcdir.clone()),
+                    ..DetailsBuildFailure::default()
+                })
+            }
+        };
+        let built = build_failure.is_none();
+        let mut all_ok = built;
+        let mut group_results = vec![];
+        let (mut points, mut max_points) = (0.0, 0.0);
+        for sg in &self.toplevel_iterator.subgroup_iterators {
+            let (res, sg_ok) = sg.group_details(self.scored);
+            let (sg_points, sg_max_points) = sg.points();
+            points += sg_points;
+            max_points += sg_max_points;
+            if built {
+                all_ok &= sg_ok;
+                group_results.push(res);
+            }
+        }
+
+        let score = if self.scored {
+            Some(DetailsTagScore {
+                points: if built { points } else { 0.0 },
+                max_points: max_points,
+                pass_threshold: self.pass_threshold,
+            })
+        } else {
+            None
+        };
+        let ok = match (&score, self.pass_threshold) {
+            // Allow for some rounding errors when the points are scaled
+            (Some(score), Some(threshold)) => {
+                built && score.points >= threshold * score.max_points - 1e-9
+            }
+            _ => all_ok,
+        };
+
+        let submitted = DateTime::<Utc>::from(self.submitted);
+        let late_submission = match self.late_policy.deadline {
+            Some(deadline) if self.late_policy.is_late(&submitted) => {
+                let fmt = |dt: DateTime<Utc>| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                Some(DetailsLateSubmission {
+                    deadline: fmt(deadline),
+                    late_until: self.late_policy.late_until.map(fmt),
+                    submitted: fmt(submitted),
+                    accepted: self.late_policy.accepts(&submitted),
+                    penalty: self.late_policy.late_penalty,
+                })
+            }
+            _ => None,
+        };
+        // Submissions after the final deadline are still graded, such that
+        // the feedback is available, but the tag does not pass.
+        let accepted = late_submission.as_ref().is_none_or(|late| late.accepted);
+
+        ReportTagGrading {
+            tag_name: self.tag_name.clone(),
+            derived_from: self.derived_from.iter().cloned().collect(),
+            build_failure: build_failure,
+            ok: ok && accepted,
+            groups: group_results,
+            score: score,
+            late: late_submission.is_some(),
+            late_submission: late_submission,
+        }
+    }
+
+    /// Removes the podman container if it is still running and makes sure that
+    /// the build directory is removed.
+    pub fn cleanup(&mut self) -> Result<(), Error> {
+        if podman::ps_names()?.contains(&self.container.podman_container_name) {
+            log::debug!(
+                "Removing the container used for grading \"{}\"",
+                self.tag_name
+            );
+            podman::force_rm(&self.container.podman_container_name)?;
+        }
+
+        if std::fs::exists(&self.container.external_solution)? {
+            log::debug!(
+                "Removing the build directory used for grading \"{}\"",
+                self.tag_name
+            );
+            std::fs::remove_dir_all(&self.container.external_solution)?;
+        }
+
+        Ok(())
+    }
+
+    /// Build the solution for this grading tag. This must be performed before
+    /// the solution can be graded. Returns Ok(`true`) if the project was built
+    /// successfully, Ok(`false`) if there was an issue building the project.
+    /// `Err` is only returned if an internal error occurred on the autograder
+    /// side, and the grading process must be interrupted.
+    ///
+    /// This function also spawns the podman container used to grade all the
+    /// tests inside this tag. This container remains active for the duration
+    /// of the tag grading procedure.
+    pub fn build(&mut self) -> Result<bool, Error> {
+        if self.build_result.is_some() {
+            return Error::err_runtime(format!(
+                "Attempted to build project twice for tag \"{}\"",
+                &self.tag_name
+            ));
+        }
+
+        log::info!(
+            "Building project for tag \"{}\" (src: {})",
+            self.tag_name,
+            self.build_conf.srcdir
+        );
+
+        let running_containers = podman::ps_names()?;
+        if running_containers.contains(&self.container.podman_container_name) {
+            log::warn!("Removing dangling image from previous run");
+            podman::force_rm(&self.container.podman_container_name)?;
+        }
+
+        if std::fs::exists(&self.container.external_solution)? {
+            // Remove the old build dir
+            std::fs::remove_dir_all(&self.container.external_solution)?;
+        }
+        if std::fs::exists(&self.container.external_tests)? {
+            // Remove the input files of the previous tag
+            std::fs::remove_dir_all(&self.container.external_tests)?;
+        }
+        // Ensure that the test directory exists outside the container
+        std::fs::create_dir_all(&self.container.external_tests)?;
+
+        // Copy the solution directory to the <workspace>/build
+        let solution_dir: String = path_absolute_join(&self.source_dir, &self.build_conf.srcdir)?;
+
+        if !std::fs::exists(&solution_dir)? {
+            self.build_result.replace(BuildResult::BuildSourceNotFound {
+                expected_dir: self.build_conf.srcdir.to_owned(),
+            });
+            return Ok(false);
+        }
+
+        dircpy::copy_dir(&solution_dir, &self.container.external_solution)?;
+
+        // Check for forbidden binary files inside the solution directory
+        // (Path, mime output)
+        let mut forbidden_files: Vec<MIMETypeInfo> = vec![];
+        fn recur_scandir(
+            forbidden_files: &mut Vec<MIMETypeInfo>,
+            allowed_files: &Vec<String>,
+            allowed_mimetypes: &Vec<String>,
+            dir: PathBuf,
+            dir_prefix: String,
+        ) -> Result<(), Error> {
+            for entry in std::fs::read_dir(dir)? {
+                let entry = entry?;
+                let entry_name = entry
+                    .file_name()
+                    .into_string()
+                    .map_err(|oss| Error::parse_type("utf-8 filename", format!("{oss:?}")))?;
+                let path = format!("{}{}", dir_prefix, entry_name);
+                if allowed_files.contains(&path) {
+                    log::info!("Found allowed binary file: {path}");
+                    continue;
+                }
+                if entry.file_type()?.is_dir() {
+                    recur_scandir(
+                        forbidden_files,
+                        allowed_files,
+                        allowed_mimetypes,
+                        entry.path(),
+                        format!("{}{}/", dir_prefix, entry_name),
+                    )?;
+                } else {
+                    // Check mime-type of this file
+                    let mimetype = utils::mimetype(&entry.path())?;
+                    if allowed_mimetypes
+                        .iter()
+                        .any(|prefix| mimetype.starts_with(prefix))
+                    {
+                        log::info!(
+                        "Found allowed binary file: {path} (due to allowed MIME type {mimetype})"
+                    );
+                        continue;
+                    }
+                    if !mimetype.starts_with("text/") {
+                        log::error!("Found forbidden file: {:?}", entry.path());
+                        forbidden_files.push(MIMETypeInfo {
+                            path: path,
+                            mime_identified: mimetype,
+                            ..Default::default()
+                        });
+                    }
+                }
+            }
+            Ok(())
+        }
+        if self.build_conf.prohibit_binary_files {
+            log::debug!("Checking for prohibited files.");
+            recur_scandir(
+                &mut forbidden_files,
+                &self.build_conf.allowed_binary_files,
+                &self.build_conf.allowed_binary_mimetypes,
+                PathBuf::from(&self.container.external_solution),
+                "".to_string(),
+            )
+            .inspect_err(|e| log::error!("Error when scanning for prohibited files: {e}"))?;
+        }
+        if forbidden_files.len() > 0 {
+            self.build_result
+                .replace(BuildResult::BuildProhibitedFiles {
+                    found_files: forbidden_files,
+                });
+            return Ok(false);
+        }
+
+        log::debug!("Starting podman container");
+        podman::start_container(&podman::ContainerOptions {
+            image: self.container.podman_image.to_owned(),
+            container_name: self.container.podman_container_name.to_owned(),
+            network_name: self.container.podman_network_name.to_owned(),
+            mounts: vec![
+                (
+                    self.container.external_solution.to_owned(),
+                    self.container.mount_solution.to_owned(),
+                    "ro,z".to_string(),
+                ),
+                (
+                    self.container.external_tests.to_owned(),
+                    self.container.mount_tests.to_owned(),
+                    "ro,z".to_string(),
+                ),
+            ],
+            tmpfs_mounts: match &self.limits.disk {
+                Some(size) => vec![
+                    (
+                        self.container.internal_build_dir.to_owned(),
+                        size.to_owned(),
+                    ),
+                    ("/tmp".to_string(), size.to_owned()),
+                ],
+                None => vec![],
+            },
+            memory: self.limits.memory.to_owned(),
+            cpus: self.limits.cpus,
+            pids_limit: self.limits.pids,
+        })?;
+
+        // Wait for the container to start
+        let mut start_attempts = 0;
+        let mut container_started = false;
+        while !container_started {
+            start_attempts += 1;
+            if start_attempts > 10 {
+                return Error::err_runtime("container would not start after 10 attempts");
+            }
+            for ps_output in podman::ps()?.iter() {
+                if ps_output
+                    .names
+                    .contains(&self.container.podman_container_name)
+                    && ps_output.state == "running"
+                {
+                    container_started = true;
+                }
+            }
+            if !container_started {
+                std::thread::sleep(Duration::from_millis(500));
+            }
+        }
+
+        // Double-check that the target repo doesn't exist. With a disk limit,
+        // it is instead an empty tmpfs mount.
+        if self.limits.disk.is_none() {
+            podman::exec(
+                &self.container.podman_container_name,
+                &["test", "!", "-d", &self.container.internal_build_dir],
+            )?;
+        }
+
+        // Now copy the solution to the root repository
+        podman::exec(
+            &self.container.podman_container_name,
+            &[
+                "cp",
+                "-rT",
+                &self.container.mount_solution,
+                &self.container.internal_build_dir,
+            ],
+        )?;
+
+        let mut build_cmd: Vec<&str> = vec![
+            "podman",
+            "exec",
+            "-w",
+            &self.container.internal_build_dir,
+            &self.container.podman_container_name,
+        ];
+        build_cmd.extend(self.build_conf.cmd.iter().map(String::as_str));
+
+        log::info!("Starting build {build_cmd:?}");
+
+        match syscommand_timeout(
+            build_cmd.as_slice(),
+            SyscommandSettings {
+                max_stdout_length: Some(self.test_default.max_output),
+                max_stderr_length: Some(self.test_default.max_output),
+                timeout: Duration::from_secs(self.build_conf.timeout.into()),
+                ..Default::default()
+            },
+        ) {
+            Ok(output) => {
+                if output.code == 0 {
+                    self.build_result.replace(BuildResult::BuildOk);
+                } else {
+                    let message = self
+                        .check_resource_limits()
+                        .map(|limit| format!("The build process {}", self.limit_message(limit)));
+                    self.build_result.replace(BuildResult::BuildFailed {
+                        message: message,
+                        code: Some(output.code),
+                        captured_stdout: Some(output.stdout),
+                        captured_stderr: Some(output.stderr),
+                    });
+                    return Ok(false);
+                }
+            }
+            Err(Error {
+                kind:
+                    ErrorKind::Syscommand(SyscommandError {
+                        timeout: Some(_),
+                        stdout,
+                        stderr,
+                        ..
+                    }),
+                ..
+            }) => {
+                self.build_result.replace(BuildResult::BuildTimeout {
+                    timeout: self.build_conf.timeout,
+                    captured_stdout: stdout,
+                    captured_stderr: stderr,
+                });
+                return Ok(false);
+            }
+            Err(Error {
+                kind:
+                    ErrorKind::Syscommand(SyscommandError {
+                        output_limit_exceeded: Some(limit),
+                        ..
+                    }),
+                ..
+            }) => {
+                self.build_result
+                    .replace(BuildResult::BuildOutputLimitExceeded { limit: limit });
+                return Ok(false);
+            }
+            Err(e) => {
+                log::error!("Error running build command: {e}");
+                return Err(e);
+            }
+        }
+        // Any limits hit by the build itself should not be attributed to the
+        // test cases.
+        self.check_resource_limits();
+
+        log::info!("Build finished. Disconnecting network from container.");
+
+        // Now disconnect the container from the network
+        syscommand_timeout(
+            &[
+                "podman",
+                "network",
+                "disconnect",
+                &self.container.podman_network_name,
+                &self.container.podman_container_name,
+            ],
+            SyscommandSettings {
+                expected_code: Some(0),
+                ..Default::default()
+            },
+        )?;
+
+        log::info!("Proceeding to run test cases.");
+
+        // If the build was successful, we set up the iterator to point at the first test case
+        self.toplevel_iterator.next();
+
+        Ok(true)
+    }
+
+    /// Runs the next batch of test cases. Returns `true` if there are more
+    /// test cases to run. Returns `false` if we have run the final test case.
+    ///
+    /// Up to `concurrency` consecutive "run", "gen_asm_and_run", "checker",
+    /// and "reference" test cases are graded at the same time, each in its
+    /// own slot. The results are still processed in the order that the tests
+    /// are defined, such that the outcome is the same as when grading the
+    /// tests one at a time.
+    ///
+    /// At most `reports_remaining` failure reports will be collected from
+    /// this batch. The reports are kept for the first failing tests in order.
+    pub fn run_test(&mut self, reports_remaining: usize) -> Result<bool, Error> {
+        // First validate the solution is built
+        match &self.build_result {
+            Some(BuildResult::BuildOk) => {} // OK
+            Some(_) => {
+                return Error::err_runtime(format!(
+                    "Attempted to run a test case for tag \"{}\" following a failed build process",
+                    self.tag_name
+                ));
+            }
+            None => {
+                return Error::err_runtime(format!(
+                    "Attempted to run a test case for tag \"{}\" without first building the project",
+                    self.tag_name
+                ));
+            }
+        }
+
+        if self.toplevel_iterator.peek().is_none() {
+            return Error::err_runtime(format!(
+                "Attempted to run a test case for tag \"{}\", but no test could be found",
+                self.tag_name
+            ));
+        }
+
+        let include_report = reports_remaining > 0;
+
+        let mut upcoming: Vec<&Test> = vec![];
+        self.toplevel_iterator
+            .upcoming(self.concurrency.max(1), &mut upcoming);
+
+        // Only run, gen_asm_and_run, checker, and reference test cases are
+        // graded concurrently. A batch is cut off at the first test case of
+        // any other kind, which also keeps benchmarks from competing with
+        // other test cases.
+        let batch_len = match upcoming.iter().position(|t| {
+            !matches!(
+                t.kind,
+                Testkind::Run(_)
+                    | Testkind::GenASMAndRun(_)
+                    | Testkind::Checker(_)
+                    | Testkind::Reference(_)
+            )
+        }) {
+            Some(0) => 1,
+            Some(i) => i,
+            None => upcoming.len(),
+        };
+        let batch = &upcoming[..batch_len];
+
+        let grade = |slot: usize, test: &Test| -> Result<GradingResult, Error> {
+            match &test.kind {
+                Testkind::Run(conf) => {
+                    use crate::subrunner::test_grader::Run;
+                    Run::grade_from_testkind(
+                        conf,
+                        &self.test_default,
+                        &self.container,
+                        slot,
+                        include_report,
+                    )
+                }
+                Testkind::GenASMAndRun(conf) => {
+                    use crate::subrunner::test_grader::GenASMAndRun;
+                    GenASMAndRun::grade_from_testkind(
+                        conf,
+                        &self.test_default,
+                        &self.container,
+                        slot,
+                        include_report,
+                    )
+                }
+                Testkind::GenASMAndBenchmark(conf) => {
+                    use crate::subrunner::test_grader::GenASMAndRun;
+                    GenASMAndRun::grade_benchmark_from_testkind(
+                        conf,
+                        &self.test_default,
+                        &self.container,
+                        slot,
+                        include_report,
+                    )
+                }
+                Testkind::Checker(conf) => {
+                    use crate::subrunner::test_grader::Checker;
+                    Checker::grade_from_testkind(
+                        conf,
+                        &self.test_default,
+                        &self.container,
+                        slot,
+                        include_report,
+                    )
+                }
+                Testkind::Reference(conf) => {
+                    use crate::subrunner::test_grader::Reference;
+                    Reference::grade_from_testkind(
+                        conf,
+                        &self.test_default,
+                        &self.container,
+                        slot,
+                        include_report,
+                    )
+                }
+                Testkind::CheckFileExists(conf) => {
+                    use crate::subrunner::test_grader::CheckFileExists;
+                    CheckFileExists::grade_from_testkind(
+                        conf,
+                        &self.test_default,
+                        &self.container,
+                        include_report,
+                    )
+                }
+            }
+        };
+
+        let results: Vec<Result<GradingResult, Error>> = if batch.len() == 1 {
+            vec![grade(0, batch[0])]
+        } else {
+            log::debug!(
+                "Grading {} test cases concurrently for tag \"{}\"",
+                batch.len(),
+                self.tag_name
+            );
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = batch
+                    .iter()
+                    .enumerate()
+                    .map(|(slot, test)| scope.spawn(move || grade(slot, test)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|h| {
+                        h.join()
+                            .unwrap_or_else(|_| Error::err_runtime("test grading thread panicked"))
+                    })
+                    .collect()
+            })
+        };
+
+        // Input files of this batch must not be visible to later test cases
+        self.remove_test_files();
+
+        // The counters of the container only tell that a resource limit was
+        // hit during this batch, not by which test case. The limit is thus
+        // only attributed to a test case if it is the only one that failed.
+        let limit_hit = self.check_resource_limits().and_then(|limit| {
+            let attributed = limit_attribution(&results);
+            if attributed.is_none() {
+                log::info!(
+                    "Could not attribute the {:?} limit to a single test case (tag \"{}\")",
+                    limit,
+                    self.tag_name
+                );
+            }
+            attributed.map(|i| (i, limit))
+        });
+
+        // Process the results in order, as if they were graded one at a time.
+        let mut batch_reports = 0;
+        for (i, result) in results.into_iter().enumerate() {
+            let mut result = result?;
+            match &mut result {
+                GradingResult::Success { .. } => {} // ok
+                GradingResult::Failure { cause, report, .. } => {
+                    self.testfail_count += 1;
+                    let attributed_limit = limit_hit
+                        .and_then(|(attributed, limit)| (attributed == i).then_some(limit));
+                    if let Some(limit) = attributed_limit {
+                        *cause = FailureCause::ResourceLimitExceeded(limit);
+                        if let Some(r) = report {
+                            r.additional_failure_causes
+                                .push(format!("The test case {}", self.limit_message(limit)));
+                        }
+                    }
+                    if report.is_some() {
+                        if batch_reports < reports_remaining {
+                            batch_reports += 1;
+                            self.collected_reports += 1;
+                        } else {
+                            // Report limit already reached by an earlier test
+                            // in this batch.
+                            report.take();
+                        }
+                    }
+                    match cause {
+                        FailureCause::OutputMismatch | FailureCause::ThresholdExceeded => {}
+                        FailureCause::Timeout(d) => {
+                            log::debug!("Test timed out after {} seconds", d.as_secs());
+                            self.bad_test_behavior.replace(cause.clone());
+                        }
+                        FailureCause::OutputLimitExceeded { limit } => {
+                            log::debug!("Test output exceeded {} bytes", limit);
+                            self.bad_test_behavior.replace(cause.clone());
+                        }
+                        FailureCause::ResourceLimitExceeded(limit) => {
+                            log::debug!("Test exceeded the {:?} limit", limit);
+                            self.bad_test_behavior.replace(cause.clone());
+                        }
+                    }
+                }
+            }
+            self.toplevel_iterator.add_result(result)?;
+
+            // Progress to the next test case. Results from the remainder of
+            // the batch are discarded if grading has to be interrupted.
+            if self.bad_test_behavior.is_some() || !self.toplevel_iterator.next() {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Removes the files that were shared with the container by the test
+    /// cases that have been graded so far.
+    fn remove_test_files(&self) {
+        let entries = match std::fs::read_dir(&self.container.external_tests) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::error!(
+                    "Could not list test files in {}: {e}",
+                    self.container.external_tests
+                );
+                return;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            std::fs::remove_file(&path).unwrap_or_else(|e| {
+                log::error!("Could not remove test file \"{}\": {e}", path.display())
+            });
+        }
+    }
+
+    /// Checks whether a memory or process limit has been hit since the last
+    /// check. Does nothing if neither of those limits are set.
+    fn check_resource_limits(&mut self) -> Option<ResourceLimit> {
+        if self.limits.memory.is_none() && self.limits.pids.is_none() {
+            return None;
+        }
+
+        let events = match podman::resource_events(&self.container.podman_container_name) {
+            Ok(events) => events,
+            Err(e) => {
+                log::warn!("Could not read resource limit counters of the container: {e}");
+                return None;
+            }
+        };
+        let previous = std::mem::replace(&mut self.resource_events, events);
+
+        if events.oom_kill > previous.oom_kill {
+            log::info!(
+                "Process killed due to the memory limit (tag \"{}\")",
+                self.tag_name
+            );
+            Some(ResourceLimit::Memory)
+        } else if events.pids_max > previous.pids_max {
+            log::info!("Process limit reached (tag \"{}\")", self.tag_name);
+            Some(ResourceLimit::Pids)
+        } else {
+            None
+        }
+    }
+
+    /// Description of how a limit was exceeded, to be prefixed by the subject
+    /// that exceeded the limit.
+    fn limit_message(&self, limit: ResourceLimit) -> String {
+        match limit {
+            ResourceLimit::Memory => format!(
+                "was killed after exceeding the memory limit of {}.",
+                self.limits.memory.as_deref().unwrap_or("unknown")
+            ),
+            ResourceLimit::Pids => format!(
+                "exceeded the limit of {} processes.",
+                self.limits
+                    .pids
+                    .map(|p| p.to_string())
+                    .unwrap_or("unknown".to_string())
+            ),
+        }
+    }
+}
+
+/// Returns the index of the test case in a batch that a resource limit hit
+/// during the batch is attributed to. This is only the case if it is the only
+/// test case in the batch that failed, and its output did not match.
+fn limit_attribution(results: &[Result<GradingResult, Error>]) -> Option<usize> {
+    let mut failed = results
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| !matches!(r, Ok(GradingResult::Success { .. })));
+    match (failed.next(), failed.next()) {
+        (
+            Some((
+                i,
+                Ok(GradingResult::Failure {
+                    cause: FailureCause::OutputMismatch,
+                    ..
+                }),
+            )),
+            None,
+        ) => Some(i),
+        _ => None,
+    }
+}
+
+/// Iterator for running the tests in a test group and all tests in the
+/// contained subgroups.
+///
+/// It will first run any tests contained inside the subgroups. After that has
+/// finished, it will run any tests contained directly inside this test group
+/// as well.
+///
+/// Before a test can be run, `next()` has to be called first.
+#[derive(Debug, Clone)]
+struct TestGroupIterator {
+    /// Metadata from the testgroup
+    pub title: String,
+
+    subgroup_iterators: Vec<TestGroupIterator>,
+    next_subgroup: usize,
+
+    /// This is an isize, so -1 means that we have not yet checked the first
+    /// test.
+    next_test_idx: isize,
+
+    tests: Vec<Test>,
+    results: Vec<GradingResult>,
+
+    /// Total points of this group, see `TestGroup::weight`.
+    weight: Option<f64>,
+}
+
+impl TestGroupIterator {
+    /// Creates a new iterator from a test group
+    fn new(tg: &TestGroup) -> Self {
+        TestGroupIterator {
+            title: tg.title.to_owned(),
+            subgroup_iterators: tg.subgroups.iter().map(|sg| Self::new(sg)).collect(),
+            next_subgroup: 0,
+            next_test_idx: -1,
+            tests: tg.tests.to_owned(),
+            results: vec![],
+            weight: tg.weight,
+        }
+    }
+
+    fn from_groups(title: String, groups: Vec<TestGroupIterator>) -> Self {
+        TestGroupIterator {
+            title: title,
+            subgroup_iterators: groups,
+            next_subgroup: 0,
+            next_test_idx: -1,
+            tests: vec![],
+            results: vec![],
+            weight: None,
+        }
+    }
+
+    /// Returns the next test to run. Returns None if there is not a next test
+    /// to run or if `TestGroupIterator::next()` has not yet been invoked.
+    fn peek(&self) -> Option<&Test> {
+        if let Some(Some(t_opt)) = self
+            .subgroup_iterators
+            .get(self.next_subgroup)
+            .map(|sg| sg.peek())
+        {
+            return Some(t_opt);
+        }
+
+        // If we have not yet called next()
+        if self.next_test_idx < 0 {
+            return None;
+        }
+
+        self.next_test_idx
+            .to_usize()
+            .and_then(|i| self.tests.get(i))
+    }
+
+    /// Collects up to `n` tests into `out`, starting with the test that would
+    /// be returned by `peek()` and continuing in the order that `next()` would
+    /// progress through them.
+    fn upcoming<'a>(&'a self, n: usize, out: &mut Vec<&'a Test>) {
+        for sg in self.subgroup_iterators.iter().skip(self.next_subgroup) {
+            if out.len() >= n {
+                return;
+            }
+            sg.upcoming(n, out);
+        }
+
+        let start = self.next_test_idx.max(0).to_usize().unwrap_or(0);
+        for t in self.tests.iter().skip(start) {
+            if out.len() >= n {
+                return;
+            }
+            out.push(t);
+        }
+    }
+
+    /// Progresses to the next test case. Returns `true` if there is a new test
+    /// to run. Returns `false` if we are at the end and there are no more
+    /// tests to run for this tag group.
+    fn next(&mut self) -> bool {
+        while self.next_subgroup < self.subgroup_iterators.len() {
+            let subgroup = self.subgroup_iterators.get_mut(self.next_subgroup).unwrap();
+            if subgroup.next() {
+                return true;
+            }
+            self.next_subgroup += 1;
+        }
+
+        // After the last subgroup has finished, next_test_idx should be -1, so
+        // then we increment it to 0 to signal the start of the first test.
+
+        if self.next_test_idx < self.tests.len().to_isize().unwrap_or(isize::MAX) {
+            self.next_test_idx += 1;
+            return self.next_test_idx < self.tests.len().to_isize().unwrap_or(isize::MAX);
+        }
+
+        return false;
+    }
+
+    /// Returns the number of test cases in this group including its
+    /// subgroups, as `(total, run, passed)`.
+    fn counts(&self) -> (usize, usize, usize) {
+        let passed = self
+            .results
+            .iter()
+            .filter(|r| matches!(r, GradingResult::Success { .. }))
+            .count();
+        self.subgroup_iterators.iter().fold(
+            (self.tests.len(), self.results.len(), passed),
+            |(total, run, passed), sg| {
+                let (sg_total, sg_run, sg_passed) = sg.counts();
+                (total + sg_total, run + sg_run, passed + sg_passed)
+            },
+        )
+    }
+
+    /// Returns the title of the innermost group that contains the next test
+    /// to run, or `None` if there is no next test.
+    fn current_title(&self) -> Option<&str> {
+        if let Some(title) = self
+            .subgroup_iterators
+            .get(self.next_subgroup)
+            .and_then(|sg| sg.current_title())
+        {
+            return Some(title);
+        }
+        self.peek().map(|_| self.title.as_str())
+    }
+
+    /// Adds the test result from a run
+    fn add_result(&mut self, res: GradingResult) -> Result<(), Error> {
+        if let Some(sg) = self.subgroup_iterators.get_mut(self.next_subgroup) {
+            return sg.add_result(res);
+        }
+        if self.results.len().to_isize().unwrap_or(isize::MAX) != self.next_test_idx {
+            return Error::err_runtime(format!(
+                    "Internal error: Adding result to the wrong test case. At test {}, added result to {}.",
+                    self.next_test_idx,
+                    self.results.len()
+                ));
+        }
+        self.results.push(res);
+        Ok(())
+    }
+
+    /// Returns the points awarded to this group and the points that it is
+    /// worth, including its subgroups. Test cases that were not run are not
+    /// awarded any points.
+    fn points(&self) -> (f64, f64) {
+        let (mut points, mut max_points) = (0.0, 0.0);
+        for (i, t) in self.tests.iter().enumerate() {
+            let test_points = t.points.unwrap_or(1.0);
+            max_points += test_points;
+            if let Some(GradingResult::Success { .. }) = self.results.get(i) {
+                points += test_points;
+            }
+        }
+        for sg in &self.subgroup_iterators {
+            let (sg_points, sg_max_points) = sg.points();
+            points += sg_points;
+            max_points += sg_max_points;
+        }
+
+        match self.weight {
+            Some(weight) if max_points > 0.0 => (weight * points / max_points, weight),
+            _ => (points, max_points),
+        }
+    }
+
+    /// Compiles the details necessary for this tag grading group, as well as
+    /// indicating whether everything was successful or not. Points are only
+    /// included if `scored` is set.
+    fn group_details(&self, scored: bool) -> (DetailsTagGradingGroup, bool) {
+        let mut all_ok = true;
+        let mut sg_details = vec![];
+        for sg in &self.subgroup_iterators {
+            let (sg_d, sg_ok) = sg.group_details(scored);
+            all_ok &= sg_ok;
+            sg_details.push(sg_d);
+        }
+        let mut details = DetailsTagGradingGroup {
+            group_title: self.title.clone(),
+            subgroups: sg_details,
+            local_tests: self.tests.len(),
+            tests_run: self.results.len(),
+            tests_passed: self
+                .results
+                .iter()
+                .filter(|r| match r {
+                    GradingResult::Success { .. } => true,
+                    _ => false,
+                })
+                .count(),
+            test_details: self
+                .results
+                .iter()
+                .filter_map(|r| match r {
+                    GradingResult::Failure { report, .. } => report.clone().map(|b| *b),
+                    _ => None,
+                })
+                .collect(),
+            test_usage: self
+                .tests
+                .iter()
+                .zip(self.results.iter())
+                .filter_map(|(t, r)| {
+                    r.usage().map(|u| DetailsTestUsage {
+                        test_name: t.name.clone(),
+                        timeout: u.timeout,
+                        wall_time_ms: u.wall_time.as_millis().to_u64().unwrap_or(u64::MAX),
+                        cpu_time_ms: u.cpu_time.and_then(|d| d.as_millis().to_u64()),
+                        peak_rss_kb: u.peak_rss_kb,
+                    })
+                })
+                .collect(),
+            points: None,
+            max_points: None,
+        };
+        if scored {
+            let (points, max_points) = self.points();
+            details.points = Some(points);
+            details.max_points = Some(max_points);
+        }
+        all_ok &= details.local_tests == details.tests_passed;
+        (details, all_ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::TestkindCheckFileExists;
+    use asserting::prelude::*;
+
+    fn test(name: &str) -> Test {
+        Test {
+            name: name.to_string(),
+            description: None,
+            timeout: 1,
+            kind: Testkind::CheckFileExists(TestkindCheckFileExists {
+                path: String::new(),
+                mimetype_prefix: String::new(),
+                mimetype_prefix_ignore: true,
+            }),
+            points: None,
+        }
+    }
+
+    fn group(title: &str, tests: &[&str], subgroups: Vec<TestGroup>) -> TestGroup {
+        TestGroup {
+            title: title.to_string(),
+            description: None,
+            tests: tests.iter().map(|name| test(name)).collect(),
+            subgroups: subgroups,
+            weight: None,
+        }
+    }
+
+    fn upcoming_names(it: &TestGroupIterator, n: usize) -> Vec<String> {
+        let mut upcoming = vec![];
+        it.upcoming(n, &mut upcoming);
+        upcoming.iter().map(|t| t.name.clone()).collect()
+    }
+
+    #[test]
+    fn test_upcoming() {
+        // Subgroups are graded before the tests of their parent group
+        let groups = [
+            group("a", &["a1"], vec![group("b", &["b1", "b2"], vec![])]),
+            group("c", &["c1", "c2"], vec![]),
+        ];
+        let mut it = TestGroupIterator::from_groups(
+            "tag".to_string(),
+            groups.iter().map(TestGroupIterator::new).collect(),
+        );
+
+        assert_that!(it.next()).is_true();
+        assert_that!(upcoming_names(&it, 3)).is_equal_to(vec![
+            "b1".to_string(),
+            "b2".to_string(),
+            "a1".to_string(),
+        ]);
+        assert_that!(upcoming_names(&it, 10))
+            .is_equal_to(["b1", "b2", "a1", "c1", "c2"].map(String::from).to_vec());
+        // Looking ahead does not advance the iterator
+        assert_that!(it.peek().map(|t| t.name.as_str())).is_equal_to(Some("b1"));
+
+        // Across the boundary between two top-level groups
+        assert_that!(it.next()).is_true();
+        assert_that!(it.next()).is_true();
+        assert_that!(it.peek().map(|t| t.name.as_str())).is_equal_to(Some("a1"));
+        assert_that!(upcoming_names(&it, 2)).is_equal_to(vec!["a1".to_string(), "c1".to_string()]);
+        assert_that!(it.peek().map(|t| t.name.as_str())).is_equal_to(Some("a1"));
+
+        assert_that!(it.next()).is_true();
+        assert_that!(it.next()).is_true();
+        assert_that!(upcoming_names(&it, 2)).is_equal_to(vec!["c2".to_string()]);
+        assert_that!(it.next()).is_false();
+        assert_that!(upcoming_names(&it, 2).is_empty()).is_true();
+    }
+
+    #[test]
+    fn test_limit_attribution() {
+        let success = || {
+            Ok(GradingResult::Success {
+                captured_stdout: String::new(),
+                usage: None,
+            })
+        };
+        let failure = |cause: FailureCause| {
+            Ok(GradingResult::Failure {
+                cause: cause,
+                report: None,
+                usage: None,
+            })
+        };
+
+        assert_that!(limit_attribution(&[success(), success()])).is_equal_to(None);
+        assert_that!(limit_attribution(&[
+            success(),
+            failure(FailureCause::OutputMismatch),
+            success()
+        ]))
+        .is_equal_to(Some(1));
+
+        // Only one of the failed test cases hit the limit, but it can not be
+        // told which one, so neither is relabelled.
+        assert_that!(limit_attribution(&[
+            failure(FailureCause::OutputMismatch),
+            success(),
+            failure(FailureCause::OutputMismatch),
+        ]))
+        .is_equal_to(None);
+
+        // A test case that failed for another reason keeps its cause
+        assert_that!(limit_attribution(&[
+            failure(FailureCause::Timeout(Duration::from_secs(1))),
+            success()
+        ]))
+        .is_equal_to(None);
+        assert_that!(limit_attribution(&[
+            Error::err_runtime("failed"),
+            failure(FailureCause::OutputMismatch)
+        ]))
+        .is_equal_to(None);
+    }
+}
@@ -0,0 +1,2358 @@
+/// This file contains the functionality used to grade a test case.
+use std::{
+    io::Read,
+    io::Write,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::{
+        tests::{
+            NumericTolerance, OutputMatch, TestkindCheckFileExists, TestkindChecker,
+            TestkindGenASMAndBenchmark, TestkindGenASMAndRun, TestkindReference, TestkindRun,
+        },
+        TestDefault,
+    },
+    error::{Error, ErrorKind, SyscommandError},
+    podman,
+    reporting::{
+        diff::OutputDiff, DetailsTestFailure, MIMETypeInfo, MismatchInfo, SourceFileInfo,
+        ThresholdInfo,
+    },
+    utils::{self, path_absolute_join, syscommand_timeout, SyscommandOutput, SyscommandSettings},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::subrunner::container::ContainerInfo;
+
+/// Cause of a failure
+#[derive(Debug, Clone)]
+pub enum FailureCause {
+    OutputMismatch,
+    Timeout(Duration),
+    OutputLimitExceeded {
+        limit: usize,
+    },
+    /// A resource limit of the container was hit while running the test case,
+    /// e.g. a process was killed for exceeding the memory limit.
+    ResourceLimitExceeded(ResourceLimit),
+    /// The output was correct, but a benchmarked measurement exceeded its
+    /// threshold or could not be measured.
+    ThresholdExceeded,
+}
+
+/// A container resource limit that can be detected as exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceLimit {
+    Memory,
+    Pids,
+}
+
+/// Treats stdout and stderr to the format that we expect.
+fn treat_output(s: &str, trim: bool, remove_whitespace: bool) -> Result<String, Error> {
+    if remove_whitespace {
+        String::from_utf8(
+            s.as_bytes()
+                .iter()
+                .filter_map(|c| {
+                    if c.is_ascii_whitespace() {
+                        None
+                    } else {
+                        Some(c.to_owned())
+                    }
+                })
+                .collect(),
+        )
+        .map_err(|e| Error::runtime("error removing whitespace").with_cause(Box::new(e)))
+    } else if trim {
+        Ok(s.trim_ascii().to_string())
+    } else {
+        Ok(s.to_string())
+    }
+}
+
+/// Validates the reference (the program output) against the possible
+/// alternatives. If alternatives is None, then the check is skipped.
+///
+/// The alternatives are only treated like the reference when they are plain
+/// text, as treating a regex or glob pattern could change its meaning.
+fn validate_alternatives(
+    reference: &str,
+    alternatives: &[String],
+    trim: bool,
+    remove_whitespace: bool,
+    mode: OutputMatch,
+    tolerance: &NumericTolerance,
+) -> Result<Option<MismatchInfo<String>>, Error> {
+    // Empty alternatives means that we ignore this check
+    if alternatives.is_empty() {
+        return Ok(None);
+    };
+
+    let treated_reference = treat_output(reference, trim, remove_whitespace)?;
+    let mut found_match = false;
+    for alt in alternatives {
+        found_match |= match mode {
+            OutputMatch::Exact | OutputMatch::Contains | OutputMatch::Numeric => mode
+                .is_match_with_tolerance(
+                    &treated_reference,
+                    &treat_output(&alt, trim, remove_whitespace)?,
+                    tolerance,
+                ),
+            OutputMatch::Regex | OutputMatch::Glob => mode.is_match(&treated_reference, alt),
+        };
+        if found_match {
+            break;
+        }
+    }
+    if !found_match {
+        let mut msgs = vec![];
+        if let Some(description) = mode.description() {
+            msgs.push(description.to_string());
+        }
+        if mode == OutputMatch::Numeric {
+            msgs.push(tolerance.description());
+        }
+        if remove_whitespace {
+            msgs.push("Whitespaces are ignored.".to_string());
+        } else if trim {
+            msgs.push("Leading and trailing whitespaces are ignored.".to_string());
+        };
+        // A line-level diff is only meaningful when the alternatives are the
+        // expected output itself, with the lines still intact.
+        let diff = if mode == OutputMatch::Exact && !remove_whitespace {
+            let treated_alternatives = alternatives
+                .iter()
+                .map(|alt| treat_output(alt, trim, remove_whitespace))
+                .collect::<Result<Vec<String>, Error>>()?;
+            OutputDiff::closest(&treated_reference, &treated_alternatives)
+        } else {
+            None
+        };
+        Ok(Some(MismatchInfo {
+            received: reference.to_string(),
+            allowed_alternatives: alternatives.to_owned(),
+            msgs: msgs,
+            diff: diff,
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Validates the reference code (the exit code of the program) against the
+/// allowed alternative. This is the same as for `validate_alternatives`, but
+/// for `i32`.
+fn validate_alternatives_i32(reference: i32, alternatives: &[i32]) -> Option<MismatchInfo<i32>> {
+    // Empty alternatives means that we ignore this check
+    if alternatives.is_empty() {
+        return None;
+    };
+
+    if alternatives.iter().any(|c| reference == *c) {
+        None
+    } else {
+        Some(MismatchInfo {
+            received: reference,
+            allowed_alternatives: alternatives.to_vec(),
+            msgs: vec![],
+            diff: None,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum GradingResult {
+    Success {
+        /// This is the captured stdout from the run binary. It will only be
+        /// populated if the run configuration explicitly states that standard
+        /// output should be captured. This is typically when output needs to
+        /// be forwarded to a later stage.
+        captured_stdout: String,
+        /// Resources used when running the solution, if it was run.
+        usage: Option<ResourceUsage>,
+    },
+    Failure {
+        /// Cause of the failure
+        cause: FailureCause,
+        /// Provided error report if `include_report` is set to `true` when calling `grade()`.
+        report: Option<Box<DetailsTestFailure>>,
+        /// Resources used when running the solution, if it was run.
+        usage: Option<ResourceUsage>,
+    },
+}
+
+impl GradingResult {
+    /// Returns the resources used when running the solution, if recorded.
+    pub fn usage(&self) -> Option<&ResourceUsage> {
+        match self {
+            GradingResult::Success { usage, .. } => usage.as_ref(),
+            GradingResult::Failure { usage, .. } => usage.as_ref(),
+        }
+    }
+
+    /// Replaces the recorded resource usage of this result.
+    fn with_usage(mut self, new_usage: Option<ResourceUsage>) -> Self {
+        match &mut self {
+            GradingResult::Success { usage, .. } => *usage = new_usage,
+            GradingResult::Failure { usage, .. } => *usage = new_usage,
+        }
+        self
+    }
+}
+
+/// Resources used by the solution while running a test case.
+#[derive(Debug, Clone, Copy)]
+pub struct ResourceUsage {
+    /// Wall time, measured from outside of the container
+    pub wall_time: Duration,
+    /// User and system CPU time of the solution
+    pub cpu_time: Option<Duration>,
+    /// Peak resident set size in kilobytes
+    pub peak_rss_kb: Option<u64>,
+    /// Timeout that applied to the run, in seconds
+    pub timeout: u32,
+}
+
+/// Shell wrapper used to measure the resources used by the solution inside
+/// the container. The first argument is the file to write the measurements
+/// to, followed by the command to run. GNU time is used if it is available,
+/// since it also reports the peak RSS. Otherwise, the CPU time of the child
+/// processes is written by the `times` builtin.
+const USAGE_WRAPPER: &str = r#"u="$1"; shift
+if true 2>/dev/null > "$u" && /usr/bin/time -f '' -o /dev/null true >/dev/null 2>&1; then
+    exec /usr/bin/time -f 'usage %U %S %M' -o "$u" "$@"
+fi
+"$@"
+c=$?
+command times 2>/dev/null > "$u"
+exit $c"#;
+
+/// Parses the measurements written by `USAGE_WRAPPER`, returning the CPU
+/// time and the peak RSS in kilobytes.
+fn parse_usage(content: &str) -> (Option<Duration>, Option<u64>) {
+    // GNU time may write a line about the exit status before the format line
+    if let Some(line) = content.lines().find_map(|l| l.strip_prefix("usage ")) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if let [user, sys, rss] = fields.as_slice() {
+            let cpu = match (user.parse::<f64>(), sys.parse::<f64>()) {
+                (Ok(u), Ok(s)) => Duration::try_from_secs_f64(u + s).ok(),
+                _ => None,
+            };
+            return (cpu, rss.parse().ok());
+        }
+        return (None, None);
+    }
+
+    // Output from `times`, where the second line is the user and system time
+    // of the child processes, e.g. "0m0.012s 0m0.004s".
+    let parse_times = |t: &str| -> Option<f64> {
+        let (min, sec) = t.strip_suffix('s')?.split_once('m')?;
+        Some(min.parse::<f64>().ok()? * 60.0 + sec.parse::<f64>().ok()?)
+    };
+    let cpu = content.lines().nth(1).and_then(|l| {
+        let (user, sys) = l.trim().split_once(' ')?;
+        Duration::try_from_secs_f64(parse_times(user)? + parse_times(sys.trim())?).ok()
+    });
+    (cpu, None)
+}
+
+// .---------------------------------------------------------------------.
+// |  _____         _   _    _           _     _ _ ____             _ _  |
+// | |_   _|__  ___| |_| | _(_)_ __   __| |_  ( | )  _ \ _   _ _ __( | ) |
+// |   | |/ _ \/ __| __| |/ / | '_ \ / _` (_)  V V| |_) | | | | '_ \V V  |
+// |   | |  __/\__ \ |_|   <| | | | | (_| |_      |  _ <| |_| | | | |    |
+// |   |_|\___||___/\__|_|\_\_|_| |_|\__,_(_)     |_| \_\\__,_|_| |_|    |
+// '---------------------------------------------------------------------'
+
+/// Configuration for testkind "run". This runs the provided `bin` with
+/// arguments and input files, and validates the output.
+///
+/// Example:
+///  - `bin` = `"myprog"`
+///  - `cmdargs` = `["--bar", "foo"]`
+///  - `infile_paths` = `["/home/user/test1.txt", "/srv/data/test2.txt"]`
+/// ```sh
+/// ./myprog --bar foo /mnt/testfiles/test0_<hash>.in /mnt/testfiles/test1_<hash>.in
+/// ```
+#[derive(Debug, Clone)]
+pub struct Run<'a> {
+    /// Information about the container to run inside
+    pub container: &'a ContainerInfo,
+    /// Index of the concurrent grading slot that this test case occupies.
+    /// Files written in the container are named after the slot, such that
+    /// test cases graded at the same time do not overwrite each other.
+    pub slot: usize,
+    /// Name of the binary to run
+    pub bin: &'a str,
+    /// Arguments to pass to the binary
+    pub cmdargs: &'a [String],
+    /// Paths to files to provide as input. The paths should be as seen from
+    /// outside the container.
+    pub infile_paths: &'a [String],
+    /// Text to pass through standard input. `None` means that nothing is
+    /// passed to standard input.
+    pub stdin: Option<&'a str>,
+    /// Whether or not we should capture standard output
+    pub capture_stdout: bool,
+    /// Maximum output in bytes
+    pub max_output: usize,
+    /// Timeout limit in seconds
+    pub timeout: u32,
+    /// Allowed return codes
+    pub code_allowed_values: &'a [i32],
+    /// Allowed standard output values. An empty vector means that stdout is ignored
+    pub stdout_allowed_values: &'a [String],
+    pub stdout_trim: bool,
+    pub stdout_rm_whitespace: bool,
+    pub stdout_match: OutputMatch,
+    /// Allowed standard error values. An empty vector means that stdout is ignored
+    pub stderr_allowed_values: &'a [String],
+    pub stderr_trim: bool,
+    pub stderr_rm_whitespace: bool,
+    pub stderr_match: OutputMatch,
+    /// Tolerance of numbers in outputs matched with `OutputMatch::Numeric`
+    pub tolerance: NumericTolerance,
+}
+
+impl<'a> Run<'a> {
+    /// Instantiate this test case from a testkind and then grade it.
+    pub fn grade_from_testkind(
+        kind: &TestkindRun,
+        test_default: &TestDefault,
+        container: &ContainerInfo,
+        slot: usize,
+        include_report: bool,
+    ) -> Result<GradingResult, Error> {
+        Run {
+            container: container,
+            slot: slot,
+            bin: &kind.bin,
+            cmdargs: &kind.args,
+            infile_paths: &kind.input_files,
+            stdin: if kind.stdin_ignore {
+                None
+            } else {
+                Some(kind.stdin.as_str())
+            },
+            capture_stdout: false,
+            max_output: test_default.max_output,
+            timeout: test_default.timeout_test,
+            code_allowed_values: &kind.code,
+            stdout_allowed_values: &kind.stdout,
+            stdout_trim: kind.stdout_trim,
+            stdout_rm_whitespace: kind.stdout_strip_whitespace,
+            stdout_match: kind.stdout_match,
+            stderr_allowed_values: &kind.stderr,
+            stderr_trim: kind.stderr_trim,
+            stderr_rm_whitespace: kind.stderr_strip_whitespace,
+            stderr_match: kind.stderr_match,
+            tolerance: kind.tolerance(),
+        }
+        .grade(include_report)
+    }
+
+    /// Common function to run the compiled solution for different test kinds,
+    /// performing checks such that the output is what would be expected as
+    /// well.
+    pub fn grade(&self, include_report: bool) -> Result<GradingResult, Error> {
+        let (res, usage) = self.execute()?;
+        match res {
+            Ok(output) => self.grade_output(output, usage, include_report),
+            Err(e) => self.execution_failure(e, usage, include_report),
+        }
+    }
+
+    /// Checks the output of a finished run against the expected values.
+    fn grade_output(
+        &self,
+        output: SyscommandOutput,
+        usage: ResourceUsage,
+        include_report: bool,
+    ) -> Result<GradingResult, Error> {
+        // Check the expected statuses (if we are checking the return code)
+        let code_mismatch = validate_alternatives_i32(output.code, self.code_allowed_values);
+        let stdout_mismatch = validate_alternatives(
+            &output.stdout,
+            self.stdout_allowed_values,
+            self.stdout_trim,
+            self.stdout_rm_whitespace,
+            self.stdout_match,
+            &self.tolerance,
+        )?;
+        let stderr_mismatch = validate_alternatives(
+            &output.stderr,
+            self.stderr_allowed_values,
+            self.stderr_trim,
+            self.stderr_rm_whitespace,
+            self.stderr_match,
+            &self.tolerance,
+        )?;
+
+        match (&code_mismatch, &stdout_mismatch, &stderr_mismatch) {
+            (None, None, None) => {
+                return Ok(GradingResult::Success {
+                    captured_stdout: if self.capture_stdout {
+                        output.stdout
+                    } else {
+                        "".to_string()
+                    },
+                    usage: Some(usage),
+                });
+            }
+            _ => {
+                let report = if include_report {
+                    Some(Box::new(DetailsTestFailure {
+                        code_captured: if code_mismatch.is_none() {
+                            Some(output.code)
+                        } else {
+                            None
+                        },
+                        code_mismatch: code_mismatch,
+                        stdout_captured: if stdout_mismatch.is_none() {
+                            Some(output.stdout)
+                        } else {
+                            None
+                        },
+                        stdout_mismatch: stdout_mismatch,
+                        stderr_captured: if stderr_mismatch.is_none() {
+                            Some(output.stderr)
+                        } else {
+                            None
+                        },
+                        stderr_mismatch: stderr_mismatch,
+                        ..self.base_report()?
+                    }))
+                } else {
+                    None
+                };
+
+                return Ok(GradingResult::Failure {
+                    cause: FailureCause::OutputMismatch,
+                    report: report,
+                    usage: Some(usage),
+                });
+            }
+        }
+    }
+
+    /// Runs the binary inside the container, returning its output together
+    /// with the resources that it used. Failing to run the binary, such as
+    /// timing out, is returned in the inner result.
+    fn execute(&self) -> Result<(Result<SyscommandOutput, Error>, ResourceUsage), Error> {
+        let executable = format!("./{}", self.bin);
+        let mut test_cmd: Vec<String> = vec![
+            "podman".into(),
+            "exec".into(),
+            "-w".into(),
+            self.container.internal_build_dir.clone(),
+        ];
+        if self.stdin.is_some() {
+            // This is needed for podman to capture stdin
+            test_cmd.push("-i".into());
+        }
+        test_cmd.push(self.container.podman_container_name.clone());
+        test_cmd.extend([
+            "sh".into(),
+            "-c".into(),
+            USAGE_WRAPPER.into(),
+            "sh".into(),
+            self.usage_file(),
+        ]);
+        test_cmd.push(executable.clone());
+        test_cmd.extend_from_slice(self.cmdargs);
+        for (i, infile) in self.infile_paths.iter().enumerate() {
+            let infile_name = input_file_name(i, infile)?;
+            let hostfile = path_absolute_join(&self.container.external_tests, &infile_name)?;
+            if !std::fs::exists(&hostfile)? {
+                // Copy file to the external_tests dir under a temporary name
+                // first, such that a test case graded in another slot never
+                // sees a partially written file. The copies are removed by
+                // the tag runner once the batch of test cases is graded, as
+                // test cases in the same batch may share them.
+                let tmpfile = path_absolute_join(
+                    &self.container.external_tests,
+                    format!("slot{}_{infile_name}.tmp", self.slot),
+                )?;
+                std::fs::copy(infile, &tmpfile).inspect_err(|e| {
+                    log::error!("Could not copy input file {} to {}: {e}", infile, tmpfile)
+                })?;
+                std::fs::rename(&tmpfile, &hostfile)?;
+            }
+            // Add the internal file path to the command
+            test_cmd.push(path_absolute_join(
+                &self.container.mount_tests,
+                &infile_name,
+            )?);
+        }
+
+        let start = Instant::now();
+        let res = syscommand_timeout(
+            test_cmd.as_slice(),
+            SyscommandSettings {
+                stdin: self.stdin.map(String::from),
+                max_stdout_length: Some(self.max_output),
+                max_stderr_length: Some(self.max_output),
+                timeout: Duration::from_secs(self.timeout.into()),
+                ..Default::default()
+            },
+        );
+        let usage = self.resource_usage(start.elapsed(), res.is_ok());
+
+        Ok((res, usage))
+    }
+
+    /// Turns an error from running the binary into a failed test case if the
+    /// error is caused by the binary, e.g. by timing out.
+    fn execution_failure(
+        &self,
+        e: Error,
+        usage: ResourceUsage,
+        include_report: bool,
+    ) -> Result<GradingResult, Error> {
+        match e {
+            Error {
+                kind:
+                    ErrorKind::Syscommand(SyscommandError {
+                        timeout: Some(duration),
+                        stdout,
+                        stderr,
+                        ..
+                    }),
+                ..
+            } => Ok(GradingResult::Failure {
+                cause: FailureCause::Timeout(duration),
+                report: if include_report {
+                    Some(Box::new(DetailsTestFailure {
+                        additional_failure_causes: vec![format!(
+                            "Timed out after {} seconds.",
+                            duration.as_secs(),
+                        )],
+                        stdout_captured: stdout,
+                        stderr_captured: stderr,
+                        ..self.base_report()?
+                    }))
+                } else {
+                    None
+                },
+                usage: Some(usage),
+            }),
+            Error {
+                kind:
+                    ErrorKind::Syscommand(SyscommandError {
+                        output_limit_exceeded: Some(limit),
+                        ..
+                    }),
+                ..
+            } => Ok(GradingResult::Failure {
+                cause: FailureCause::OutputLimitExceeded { limit: limit },
+                report: if include_report {
+                    Some(Box::new(DetailsTestFailure {
+                        additional_failure_causes: vec![format!(
+                            "Output stream exceeded {} bytes.",
+                            limit
+                        )],
+                        ..self.base_report()?
+                    }))
+                } else {
+                    None
+                },
+                usage: Some(usage),
+            }),
+            e => {
+                log::error!("Unknown error happened when running test case in a container: {e}");
+                Err(e)
+            }
+        }
+    }
+
+    /// Path inside the container where the measured resource usage for this
+    /// slot is written.
+    fn usage_file(&self) -> String {
+        format!("/tmp/slot{}.usage", self.slot)
+    }
+
+    /// Collects the resources used by the last run in this slot. The
+    /// measurements from inside the container are only read if the run
+    /// finished, since they are never written if the run was interrupted.
+    fn resource_usage(&self, wall_time: Duration, finished: bool) -> ResourceUsage {
+        let (cpu_time, peak_rss_kb) = if finished {
+            match syscommand_timeout(
+                &[
+                    "podman",
+                    "exec",
+                    &self.container.podman_container_name,
+                    "cat",
+                    &self.usage_file(),
+                ],
+                SyscommandSettings {
+                    expected_code: Some(0),
+                    timeout: Duration::from_secs(10),
+                    ..Default::default()
+                },
+            ) {
+                Ok(output) => parse_usage(&output.stdout),
+                Err(e) => {
+                    log::debug!("Could not read resource usage of slot {}: {e}", self.slot);
+                    (None, None)
+                }
+            }
+        } else {
+            (None, None)
+        };
+
+        ResourceUsage {
+            wall_time: wall_time,
+            cpu_time: cpu_time,
+            peak_rss_kb: peak_rss_kb,
+            timeout: self.timeout,
+        }
+    }
+
+    /// Generates a template failure report with the basic information present,
+    /// including the executed command, standard input, and any of the input
+    /// files.
+    fn base_report(&self) -> Result<DetailsTestFailure, Error> {
+        let mut cmdvec = vec![format!("./{}", self.bin)];
+        cmdvec.extend_from_slice(self.cmdargs);
+
+        let mut infile_contents = vec![];
+        for (i, strpath) in self.infile_paths.iter().enumerate() {
+            let path = Path::new(strpath);
+
+            let content = std::fs::File::open(path)
+                .and_then(|mut f| {
+                    let mut buf = String::new();
+                    f.read_to_string(&mut buf)?;
+                    Ok(buf)
+                })
+                .inspect_err(|e| {
+                    log::error!("Could not read input file when creating error report: {e}")
+                })?;
+
+            if self.infile_paths.len() > 1 {
+                cmdvec.push(format!("INPUT_FILE{}", i + 1));
+            } else {
+                cmdvec.push("INPUT_FILE".to_string());
+            }
+            infile_contents.push(SourceFileInfo {
+                content: content,
+                extension: path
+                    .extension()
+                    .and_then(|ex| ex.to_str())
+                    .map(String::from),
+            });
+        }
+        Ok(DetailsTestFailure {
+            command: Some(cmdvec.join(" ")),
+            stdin_contents: self.stdin.map(|s| SourceFileInfo {
+                content: s.to_string(),
+                ..Default::default()
+            }),
+            input_file_contents: infile_contents,
+            ..Default::default()
+        })
+    }
+}
+
+// .----------------------------------------------------------------.
+// |  _____         _   _    _           _      ____                |
+// | |_   _|__  ___| |_| | _(_)_ __   __| |_   / ___| ___ _ __      |
+// |   | |/ _ \/ __| __| |/ / | '_ \ / _` (_) | |  _ / _ \ '_ \     |
+// |   | |  __/\__ \ |_|   <| | | | | (_| |_  | |_| |  __/ | | |    |
+// |   |_|\___||___/\__|_|\_\_|_| |_|\__,_(_)_ \____|\___|_| |_|    |
+// |    / \  / ___||  \/  |   __ _ _ __   __| | |  _ \ _   _ _ __   |
+// |   / _ \ \___ \| |\/| |  / _` | '_ \ / _` | | |_) | | | | '_ \  |
+// |  / ___ \ ___) | |  | | | (_| | | | | (_| | |  _ <| |_| | | | | |
+// | /_/   \_\____/|_|  |_|  \__,_|_| |_|\__,_| |_| \_\\__,_|_| |_| |
+// '----------------------------------------------------------------'
+
+/// Configuration for testkind "gen_asm_and_run". This runs the provided `bin`
+/// with arguments and input files, which should generate some assembly on
+/// standard output. This is then compiled, and the compiled binary is then
+/// graded.
+#[derive(Debug, Clone)]
+pub struct GenASMAndRun<'a> {
+    /// Information about the container to run inside
+    pub container: &'a ContainerInfo,
+    /// Index of the concurrent grading slot that this test case occupies.
+    pub slot: usize,
+    /// Name of the binary to run
+    pub bin: &'a str,
+    /// Arguments to pass to the binary
+    pub cmdargs: &'a [String],
+    /// Paths to files to provide as input. The paths should be as seen from
+    /// outside the container.
+    pub infile_paths: &'a [String],
+    /// Text to pass through standard input. `None` means that nothing is
+    /// passed to standard input.
+    pub stdin: Option<&'a str>,
+    /// Maximum output in bytes
+    pub max_output: usize,
+    /// Timeout limit in seconds
+    pub timeout: u32,
+    /// Allowed return codes when running binary generating the assembly code.
+    pub code_allowed_values: &'a [i32],
+    /// Allowed standard error values when running binary generating the
+    /// assembly code. An empty list means that stderr is ignored.
+    pub stderr_allowed_values: &'a [String],
+    pub stderr_trim: bool,
+    pub stderr_rm_whitespace: bool,
+    pub stderr_match: OutputMatch,
+
+    /// Command to run the assembler with
+    pub assemble_cmd: &'a [String],
+    /// Allowed return codes when running the assembler
+    pub assemble_code_allowed_values: &'a [i32],
+
+    /// Command to run the compiler with
+    pub compile_cmd: &'a [String],
+    /// Allowed return codes when running the compiler
+    pub compile_code_allowed_values: &'a [i32],
+
+    /// Command to run the compiled binary with
+    pub run_cmd: &'a [String],
+    /// Text to pass through standard input when running the compiled binary.
+    /// `None` means that nothing is passed to standard input.
+    pub run_stdin: Option<&'a str>,
+    /// Allowed return codes when running the compiled binary
+    pub run_code_allowed_values: &'a [i32],
+    /// Allowed standard output values when running the compiled binary. An
+    /// empty list means that stdout is ignored.
+    pub run_stdout_allowed_values: &'a [String],
+    pub run_stdout_trim: bool,
+    pub run_stdout_rm_whitespace: bool,
+    pub run_stdout_match: OutputMatch,
+    /// Allowed standard error values when running the compiled binary. An
+    /// empty list means that stderr is ignored.
+    pub run_stderr_allowed_values: &'a [String],
+    pub run_stderr_trim: bool,
+    pub run_stderr_rm_whitespace: bool,
+    pub run_stderr_match: OutputMatch,
+    /// Tolerance of numbers in outputs matched with `OutputMatch::Numeric`
+    pub tolerance: NumericTolerance,
+
+    /// Performance thresholds to check after the compiled binary has produced
+    /// the expected output. `None` means that it is only run once.
+    pub benchmark: Option<Benchmark<'a>>,
+}
+
+/// Benchmark configuration for testkind "gen_asm_and_benchmark".
+#[derive(Debug, Clone)]
+pub struct Benchmark<'a> {
+    /// Number of times to run the compiled binary
+    pub runs: u32,
+    /// Maximum median runtime in milliseconds, 0 if not checked
+    pub max_time_ms: u64,
+    /// Maximum median instruction count, 0 if not checked
+    pub max_instructions: u64,
+    /// Command prefix used to count instructions, where <COUNT_FILE> is
+    /// replaced by the file to write the count to.
+    pub instructions_cmd: &'a [String],
+}
+
+/// Script that runs the command given after the first two arguments several
+/// times inside the container. The first argument is the number of runs, and
+/// the second argument is what to print for each run: the runtime in
+/// microseconds for "time", or the instruction count from `count.out` for
+/// "instructions". Standard input is saved and replayed for each run. Exits
+/// with code 2 if the instruction counter did not write `count.out`.
+const BENCHMARK_SCRIPT: &str = r#"n="$1"; m="$2"; shift 2
+cat > bench.in
+i=0
+while [ "$i" -lt "$n" ]; do
+    if [ "$m" = time ]; then
+        s=$(date +%s%N)
+        "$@" < bench.in > /dev/null 2>&1
+        e=$(date +%s%N)
+        echo "$(( (e - s) / 1000 ))"
+    else
+        rm -f count.out
+        "$@" < bench.in > /dev/null 2>&1
+        [ -f count.out ] || exit 2
+        while IFS=, read -r c _; do
+            case "$c" in ''|'#'*) ;; *) echo "$c" ;; esac
+        done < count.out
+    fi
+    i=$((i + 1))
+done"#;
+
+/// Returns the median of the samples, rounding down if there is an even
+/// number of samples.
+fn median(samples: &[u64]) -> u64 {
+    let mut sorted = samples.to_vec();
+    sorted.sort_unstable();
+    let mid = sorted.len() / 2;
+    if sorted.is_empty() {
+        0
+    } else if sorted.len().is_multiple_of(2) {
+        sorted[mid - 1] / 2 + sorted[mid] / 2 + (sorted[mid - 1] % 2 + sorted[mid] % 2) / 2
+    } else {
+        sorted[mid]
+    }
+}
+
+/// Parses the `runs` samples printed by the benchmark script. Samples that
+/// are missing or not a number, such as `<not counted>` from `perf` if the
+/// binary exited before it could be counted, give the reason to report.
+fn parse_benchmark_samples(stdout: &str, runs: u32, measurement: &str) -> Result<Vec<u64>, String> {
+    let mut samples = vec![];
+    for line in stdout.lines() {
+        match line.trim().parse::<u64>() {
+            Ok(sample) => samples.push(sample),
+            Err(_) => {
+                return Err(format!(
+                    "The {measurement} of the compiled assembly could not be measured (got \"{}\"). It might have crashed or exited early.",
+                    line.trim()
+                ))
+            }
+        }
+    }
+    if samples.len() != runs as usize {
+        return Err(format!(
+            "The {measurement} of the compiled assembly was only measured for {} of {runs} runs. It might have crashed or exited early.",
+            samples.len()
+        ));
+    }
+    Ok(samples)
+}
+
+impl<'a> GenASMAndRun<'a> {
+    /// The input used when piggy-backing on the existing run-grader.
+    fn run_input(&self) -> Run<'a> {
+        Run {
+            container: self.container,
+            slot: self.slot,
+            bin: self.bin,
+            cmdargs: self.cmdargs,
+            infile_paths: self.infile_paths,
+            stdin: self.stdin,
+            capture_stdout: true,
+            max_output: self.max_output,
+            timeout: self.timeout,
+            code_allowed_values: self.code_allowed_values,
+            stdout_allowed_values: &[],
+            stdout_trim: false,
+            stdout_rm_whitespace: false,
+            stdout_match: OutputMatch::Exact,
+            stderr_allowed_values: self.stderr_allowed_values,
+            stderr_trim: self.stderr_trim,
+            stderr_rm_whitespace: self.stderr_rm_whitespace,
+            stderr_match: self.stderr_match,
+            tolerance: self.tolerance,
+        }
+    }
+
+    /// Instantiate this test case from a testkind and then grade it.
+    pub fn grade_from_testkind(
+        kind: &TestkindGenASMAndRun,
+        test_default: &TestDefault,
+        container: &ContainerInfo,
+        slot: usize,
+        include_report: bool,
+    ) -> Result<GradingResult, Error> {
+        GenASMAndRun::from_testkind(kind, test_default, container, slot).grade(include_report)
+    }
+
+    /// Instantiate a "gen_asm_and_benchmark" test case and then grade it.
+    pub fn grade_benchmark_from_testkind(
+        kind: &TestkindGenASMAndBenchmark,
+        test_default: &TestDefault,
+        container: &ContainerInfo,
+        slot: usize,
+        include_report: bool,
+    ) -> Result<GradingResult, Error> {
+        GenASMAndRun {
+            benchmark: Some(Benchmark {
+                runs: kind.benchmark_runs,
+                max_time_ms: kind.benchmark_max_time_ms,
+                max_instructions: kind.benchmark_max_instructions,
+                instructions_cmd: &kind.benchmark_instructions_cmd,
+            }),
+            ..GenASMAndRun::from_testkind(&kind.gen_asm_and_run, test_default, container, slot)
+        }
+        .grade(include_report)
+    }
+
+    fn from_testkind(
+        kind: &'a TestkindGenASMAndRun,
+        test_default: &TestDefault,
+        container: &'a ContainerInfo,
+        slot: usize,
+    ) -> Self {
+        GenASMAndRun {
+            container: container,
+            slot: slot,
+            bin: &kind.bin,
+            cmdargs: &kind.args,
+            infile_paths: &kind.input_files,
+            stdin: if kind.stdin_ignore {
+                None
+            } else {
+                Some(kind.stdin.as_str())
+            },
+            max_output: test_default.max_output,
+            timeout: test_default.timeout_test,
+            code_allowed_values: &kind.code,
+            stderr_allowed_values: &kind.stderr,
+            stderr_trim: kind.stderr_trim,
+            stderr_rm_whitespace: kind.stderr_strip_whitespace,
+            stderr_match: kind.stderr_match,
+            assemble_cmd: &kind.assemble_cmd,
+            assemble_code_allowed_values: &kind.assemble_code,
+            compile_cmd: &kind.compile_cmd,
+            compile_code_allowed_values: &kind.compile_code,
+            run_cmd: &kind.run_cmd,
+            run_stdin: if kind.run_stdin_ignore {
+                None
+            } else {
+                Some(kind.run_stdin.as_str())
+            },
+            run_code_allowed_values: &kind.run_code,
+            run_stdout_allowed_values: &kind.run_stdout,
+            run_stdout_trim: kind.run_stdout_trim,
+            run_stdout_rm_whitespace: kind.run_stdout_strip_whitespace,
+            run_stdout_match: kind.run_stdout_match,
+            run_stderr_allowed_values: &kind.run_stderr,
+            run_stderr_trim: kind.run_stderr_trim,
+            run_stderr_rm_whitespace: kind.run_stderr_strip_whitespace,
+            run_stderr_match: kind.run_stderr_match,
+            tolerance: kind.tolerance(),
+            benchmark: None,
+        }
+    }
+
+    /// Common function to run the compiled solution for different test kinds,
+    /// performing checks such that the output is what would be expected as
+    /// well.
+    pub fn grade(&self, include_report: bool) -> Result<GradingResult, Error> {
+        // Reuse the run infrastructure to generate the output assembly.
+        let (generated_assembly, usage) = match self.run_input().grade(include_report)? {
+            GradingResult::Success {
+                captured_stdout,
+                usage,
+            } => (captured_stdout, usage),
+            fail_res @ GradingResult::Failure { .. } => {
+                return Ok(fail_res);
+            }
+        };
+
+        // The recorded usage is that of the solution generating the assembly,
+        // not of the later stages running the generated program.
+        self.grade_generated(include_report, generated_assembly)
+            .map(|res| res.with_usage(usage))
+    }
+
+    /// Assembles, compiles, and runs the assembly program generated by the
+    /// solution.
+    fn grade_generated(
+        &self,
+        include_report: bool,
+        generated_assembly: String,
+    ) -> Result<GradingResult, Error> {
+        // Set up the grading dir for this slot and write the asm program there
+        let grading_dir = format!("/tmp/grading/slot{}", self.slot);
+        let grading_asm = format!("{grading_dir}/gen.asm");
+        let asm_name = format!("slot{}_gen.asm", self.slot);
+        let hostpath_asm = path_absolute_join(&self.container.external_tests, &asm_name)?;
+        let containerpath_asm = path_absolute_join(&self.container.mount_tests, &asm_name)?;
+
+        // Open the file in a separate scope to ensure that it is closed
+        {
+            let mut asm_f = std::fs::File::create(&hostpath_asm)
+                .inspect_err(|e| log::error!("Cannot create ASM file {hostpath_asm}: {e}"))?;
+            asm_f.write_all(generated_assembly.as_bytes())?;
+            asm_f.flush()?;
+        }
+
+        syscommand_timeout(
+            &[
+                "podman",
+                "exec",
+                &self.container.podman_container_name,
+                "bash",
+                "-c",
+                &format!("rm -rf \"{grading_dir}\" && mkdir -p \"{grading_dir}\" && cp \"{containerpath_asm}\" \"{grading_asm}\""),
+            ],
+            SyscommandSettings {
+                expected_code: Some(0),
+                ..Default::default()
+            },
+        )?;
+        std::fs::remove_file(&hostpath_asm)
+            .inspect_err(|e| log::error!("Error removing ASM file {hostpath_asm}: {e}"))?;
+
+        // Now write the generated assembly program to a path
+        // (set up the ASM command separately to make sure that we
+        // replace the template <ASM_FILE> with the true filename.)
+        let mut asm_cmd: Vec<&str> = vec![
+            "podman",
+            "exec",
+            "-w",
+            &grading_dir,
+            &self.container.podman_container_name,
+        ];
+        asm_cmd.extend(self.assemble_cmd.iter().map(|s| {
+            if s == "<ASM_FILE>" {
+                grading_asm.as_str()
+            } else {
+                s.as_str()
+            }
+        }));
+
+        match self.intermediate_grading(
+            include_report,
+            &asm_cmd,
+            self.assemble_code_allowed_values,
+            &generated_assembly,
+            "assembling the generated assembly program",
+        )? {
+            GradingResult::Success { .. } => {} // ok
+            fail_res @ GradingResult::Failure { .. } => {
+                return Ok(fail_res);
+            }
+        }
+
+        // Now run the compilation step
+        let mut compile_cmd: Vec<&str> = vec![
+            "podman",
+            "exec",
+            "-w",
+            &grading_dir,
+            &self.container.podman_container_name,
+        ];
+        compile_cmd.extend(self.compile_cmd.iter().map(String::as_str));
+
+        match self.intermediate_grading(
+            include_report,
+            &compile_cmd,
+            self.compile_code_allowed_values,
+            &generated_assembly,
+            "compiling the generated assembly program",
+        )? {
+            GradingResult::Success { .. } => {} // ok
+            fail_res @ GradingResult::Failure { .. } => {
+                return Ok(fail_res);
+            }
+        }
+
+        // Finally run the compiled binary and check the output
+        let mut run_cmd: Vec<&str> = vec!["podman", "exec", "-w", &grading_dir];
+        if self.run_stdin.is_some() {
+            run_cmd.push("-i");
+        }
+        run_cmd.push(&self.container.podman_container_name);
+        run_cmd.extend(self.run_cmd.iter().map(String::as_str));
+
+        match syscommand_timeout(
+            run_cmd.as_slice(),
+            SyscommandSettings {
+                stdin: self.run_stdin.map(String::from),
+                max_stdout_length: Some(self.max_output),
+                max_stderr_length: Some(self.max_output),
+                timeout: Duration::from_secs(self.timeout.into()),
+                ..Default::default()
+            },
+        ) {
+            Ok(output) => {
+                // Check the expected statuses (if we are checking the return code)
+                let code_mismatch =
+                    validate_alternatives_i32(output.code, self.run_code_allowed_values);
+                let stdout_mismatch = validate_alternatives(
+                    &output.stdout,
+                    self.run_stdout_allowed_values,
+                    self.run_stdout_trim,
+                    self.run_stdout_rm_whitespace,
+                    self.run_stdout_match,
+                    &self.tolerance,
+                )?;
+                let stderr_mismatch = validate_alternatives(
+                    &output.stderr,
+                    self.run_stderr_allowed_values,
+                    self.run_stderr_trim,
+                    self.run_stderr_rm_whitespace,
+                    self.run_stderr_match,
+                    &self.tolerance,
+                )?;
+
+                match (&code_mismatch, &stdout_mismatch, &stderr_mismatch) {
+                    (None, None, None) => {
+                        if let Some(bench) = &self.benchmark {
+                            return self.grade_benchmark(
+                                include_report,
+                                bench,
+                                &grading_dir,
+                                &generated_assembly,
+                            );
+                        }
+                        return Ok(GradingResult::Success {
+                            captured_stdout: "".to_string(),
+                            usage: None,
+                        });
+                    }
+                    _ => {
+                        let report = if include_report {
+                            Some(Box::new(DetailsTestFailure {
+                                code_captured: if code_mismatch.is_none() {
+                                    Some(output.code)
+                                } else {
+                                    None
+                                },
+                                code_mismatch: code_mismatch,
+                                stdout_captured: if stdout_mismatch.is_none() {
+                                    Some(output.stdout)
+                                } else {
+                                    None
+                                },
+                                stdout_mismatch: stdout_mismatch,
+                                stderr_captured: if stderr_mismatch.is_none() {
+                                    Some(output.stderr)
+                                } else {
+                                    None
+                                },
+                                stderr_mismatch: stderr_mismatch,
+                                ..self.base_report(&generated_assembly)?
+                            }))
+                        } else {
+                            None
+                        };
+
+                        return Ok(GradingResult::Failure {
+                            cause: FailureCause::OutputMismatch,
+                            report: report,
+                            usage: None,
+                        });
+                    }
+                }
+            }
+            Err(Error {
+                kind:
+                    ErrorKind::Syscommand(SyscommandError {
+                        timeout: Some(duration),
+                        stdout,
+                        stderr,
+                        ..
+                    }),
+                ..
+            }) => {
+                return Ok(GradingResult::Failure {
+                    cause: FailureCause::Timeout(duration),
+                    report: if include_report {
+                        Some(Box::new(DetailsTestFailure {
+                            additional_failure_causes: vec![format!(
+                                "Timed out after {} seconds when running the compiled assembly.",
+                                duration.as_secs(),
+                            )],
+                            stdout_captured: stdout,
+                            stderr_captured: stderr,
+                            ..self.base_report(&generated_assembly)?
+                        }))
+                    } else {
+                        None
+                    },
+                    usage: None,
+                });
+            }
+            Err(Error {
+                kind:
+                    ErrorKind::Syscommand(SyscommandError {
+                        output_limit_exceeded: Some(limit),
+                        ..
+                    }),
+                ..
+            }) => {
+                return Ok(GradingResult::Failure {
+                    cause: FailureCause::OutputLimitExceeded { limit: limit },
+                    report: if include_report {
+                        Some(Box::new(DetailsTestFailure {
+                            additional_failure_causes: vec![format!(
+                                "Output stream exceeded {} bytes when running the compiled assembly.",
+                                limit
+                            )],
+                            ..self.base_report(&generated_assembly)?
+                        }))
+                    } else {
+                        None
+                    },
+                    usage: None,
+                });
+            }
+            Err(e) => {
+                log::error!("Unknown error happened when running test case in a container: {e}");
+                return Err(e);
+            }
+        }
+    }
+
+    /// Benchmarks the compiled binary, which has already been checked to
+    /// produce the expected output, against the configured thresholds.
+    fn grade_benchmark(
+        &self,
+        include_report: bool,
+        bench: &Benchmark,
+        grading_dir: &str,
+        generated_assembly: &str,
+    ) -> Result<GradingResult, Error> {
+        let mut exceeded = vec![];
+
+        if bench.max_time_ms > 0 {
+            let samples = match self.benchmark_samples(bench.runs, grading_dir, "time", &[]) {
+                Ok(Ok(samples)) => samples,
+                Ok(Err(reason)) => {
+                    return self.unmeasured_failure(include_report, reason, generated_assembly)
+                }
+                Err(e) => return self.benchmark_error(include_report, e, generated_assembly),
+            };
+            let received = median(&samples);
+            log::debug!("Median runtime of the compiled binary: {received} us");
+            if received > bench.max_time_ms.saturating_mul(1000) {
+                let to_ms = |us: u64| us as f64 / 1000.0;
+                exceeded.push(ThresholdInfo {
+                    metric: "runtime".to_string(),
+                    unit: "ms".to_string(),
+                    received: to_ms(received),
+                    threshold: bench.max_time_ms as f64,
+                    samples: samples.into_iter().map(to_ms).collect(),
+                });
+            }
+        }
+
+        if bench.max_instructions > 0 {
+            let prefix: Vec<&str> = bench
+                .instructions_cmd
+                .iter()
+                .map(|s| {
+                    if s == "<COUNT_FILE>" {
+                        "count.out"
+                    } else {
+                        s.as_str()
+                    }
+                })
+                .collect();
+            let samples =
+                match self.benchmark_samples(bench.runs, grading_dir, "instructions", &prefix) {
+                    Ok(Ok(samples)) => samples,
+                    Ok(Err(reason)) => {
+                        return self.unmeasured_failure(include_report, reason, generated_assembly)
+                    }
+                    Err(e) => return self.benchmark_error(include_report, e, generated_assembly),
+                };
+            let received = median(&samples);
+            log::debug!("Median instruction count of the compiled binary: {received}");
+            if received > bench.max_instructions {
+                exceeded.push(ThresholdInfo {
+                    metric: "instruction count".to_string(),
+                    unit: "instructions".to_string(),
+                    received: received as f64,
+                    threshold: bench.max_instructions as f64,
+                    samples: samples.into_iter().map(|c| c as f64).collect(),
+                });
+            }
+        }
+
+        if exceeded.is_empty() {
+            return Ok(GradingResult::Success {
+                captured_stdout: "".to_string(),
+                usage: None,
+            });
+        }
+
+        Ok(GradingResult::Failure {
+            cause: FailureCause::ThresholdExceeded,
+            report: if include_report {
+                Some(Box::new(DetailsTestFailure {
+                    threshold_exceeded: exceeded,
+                    ..self.base_report(generated_assembly)?
+                }))
+            } else {
+                None
+            },
+            usage: None,
+        })
+    }
+
+    /// Runs the compiled binary `runs` times with the benchmark script,
+    /// returning one sample per run. The command in `prefix` is placed
+    /// before the run command. Samples that could not be measured because of
+    /// the binary, e.g. as it crashed before it could be counted, are
+    /// returned in the inner result with the reason to report.
+    fn benchmark_samples(
+        &self,
+        runs: u32,
+        grading_dir: &str,
+        metric: &str,
+        prefix: &[&str],
+    ) -> Result<Result<Vec<u64>, String>, Error> {
+        let runs_str = runs.to_string();
+        let mut cmd: Vec<&str> = vec!["podman", "exec", "-w", grading_dir];
+        if self.run_stdin.is_some() {
+            cmd.push("-i");
+        }
+        cmd.push(&self.container.podman_container_name);
+        cmd.extend(["sh", "-c", BENCHMARK_SCRIPT, "sh", &runs_str, metric]);
+        cmd.extend_from_slice(prefix);
+        cmd.extend(self.run_cmd.iter().map(String::as_str));
+
+        let output = syscommand_timeout(
+            cmd.as_slice(),
+            SyscommandSettings {
+                stdin: self.run_stdin.map(String::from),
+                expected_code: Some(0),
+                max_stdout_length: Some(self.max_output),
+                timeout: Duration::from_secs(u64::from(self.timeout) * u64::from(runs)),
+                ..Default::default()
+            },
+        )?;
+
+        let measurement = if metric == "time" {
+            "runtime"
+        } else {
+            "instruction count"
+        };
+        Ok(parse_benchmark_samples(&output.stdout, runs, measurement))
+    }
+
+    /// Fails the test case as its benchmark could not be measured, for the
+    /// given reason.
+    fn unmeasured_failure(
+        &self,
+        include_report: bool,
+        reason: String,
+        generated_assembly: &str,
+    ) -> Result<GradingResult, Error> {
+        Ok(GradingResult::Failure {
+            cause: FailureCause::ThresholdExceeded,
+            report: if include_report {
+                Some(Box::new(DetailsTestFailure {
+                    additional_failure_causes: vec![reason],
+                    ..self.base_report(generated_assembly)?
+                }))
+            } else {
+                None
+            },
+            usage: None,
+        })
+    }
+
+    /// Converts an error from benchmarking into a grading result. Timeouts
+    /// fail the test case, while any other error is a problem with the
+    /// benchmark setup and is propagated.
+    fn benchmark_error(
+        &self,
+        include_report: bool,
+        e: Error,
+        generated_assembly: &str,
+    ) -> Result<GradingResult, Error> {
+        match e {
+            Error {
+                kind:
+                    ErrorKind::Syscommand(SyscommandError {
+                        timeout: Some(duration),
+                        ..
+                    }),
+                ..
+            } => Ok(GradingResult::Failure {
+                cause: FailureCause::Timeout(duration),
+                report: if include_report {
+                    Some(Box::new(DetailsTestFailure {
+                        additional_failure_causes: vec![format!(
+                            "Timed out after {} seconds when benchmarking the compiled assembly.",
+                            duration.as_secs(),
+                        )],
+                        ..self.base_report(generated_assembly)?
+                    }))
+                } else {
+                    None
+                },
+                usage: None,
+            }),
+            e => {
+                log::error!("Could not benchmark the compiled assembly: {e}");
+                Err(e)
+            }
+        }
+    }
+
+    /// Grading of the intermediate assemble and compile steps. This just runs
+    /// an intermediate step command, checking that the output result codes
+    /// match what is to be expected.
+    fn intermediate_grading(
+        &self,
+        include_report: bool,
+        cmd: &[&str],
+        allowed_codes: &[i32],
+        generated_assembly: &str,
+        stage_description: &str,
+    ) -> Result<GradingResult, Error> {
+        match syscommand_timeout(
+            cmd,
+            SyscommandSettings {
+                expected_code: None,
+                max_stdout_length: Some(self.max_output),
+                max_stderr_length: Some(self.max_output),
+                timeout: Duration::from_secs(self.timeout.into()),
+                ..Default::default()
+            },
+        ) {
+            Ok(output) => {
+                if let Some(mm) = validate_alternatives_i32(output.code, allowed_codes) {
+                    Ok(GradingResult::Failure {
+                        cause: FailureCause::OutputMismatch,
+                        report: if include_report {
+                            Some(Box::new(DetailsTestFailure {
+                                code_mismatch: Some(mm),
+                                additional_failure_causes: vec![format!(
+                                    "Error when {}.",
+                                    stage_description
+                                )],
+                                stdout_captured: Some(output.stdout),
+                                stderr_captured: Some(output.stderr),
+                                ..self.base_report(&generated_assembly)?
+                            }))
+                        } else {
+                            None
+                        },
+                        usage: None,
+                    })
+                } else {
+                    Ok(GradingResult::Success {
+                        captured_stdout: "".to_string(),
+                        usage: None,
+                    })
+                }
+            }
+            Err(Error {
+                kind:
+                    ErrorKind::Syscommand(SyscommandError {
+                        timeout: Some(duration),
+                        stdout,
+                        stderr,
+                        ..
+                    }),
+                ..
+            }) => Ok(GradingResult::Failure {
+                cause: FailureCause::Timeout(duration),
+                report: if include_report {
+                    Some(Box::new(DetailsTestFailure {
+                        additional_failure_causes: vec![format!(
+                            "Timed out after {} seconds when {}.",
+                            duration.as_secs(),
+                            stage_description,
+                        )],
+                        stdout_captured: stdout,
+                        stderr_captured: stderr,
+                        ..self.base_report(&generated_assembly)?
+                    }))
+                } else {
+                    None
+                },
+                usage: None,
+            }),
+            Err(Error {
+                kind:
+                    ErrorKind::Syscommand(SyscommandError {
+                        output_limit_exceeded: Some(limit),
+                        ..
+                    }),
+                ..
+            }) => Ok(GradingResult::Failure {
+                cause: FailureCause::OutputLimitExceeded { limit: limit },
+                report: if include_report {
+                    Some(Box::new(DetailsTestFailure {
+                        additional_failure_causes: vec![format!(
+                            "Output stream exceeded {} bytes when {}.",
+                            limit, stage_description,
+                        )],
+                        ..self.base_report(&generated_assembly)?
+                    }))
+                } else {
+                    None
+                },
+                usage: None,
+            }),
+            Err(e) => {
+                log::error!("Unknown error happened when running test case in a container: {e}");
+                Err(e)
+            }
+        }
+    }
+
+    /// Generates a template failure report with the basic information present,
+    /// including the executed command, standard input, and any of the input
+    /// files.
+    fn base_report(&self, generated_assembly: &str) -> Result<DetailsTestFailure, Error> {
+        Ok(DetailsTestFailure {
+            additional_files: vec![(
+                "Generated Assembly".to_string(),
+                SourceFileInfo {
+                    content: generated_assembly.to_string(),
+                    extension: Some("asm".to_string()),
+                },
+            )],
+            ..self.run_input().base_report()?
+        })
+    }
+}
+
+// .-----------------------------------------------------------------------.
+// |  _____         _   _    _           _      ____ _               _     |
+// | |_   _|__  ___| |_| | _(_)_ __   __| |_   / ___| |__   ___  ___| | __ |
+// |   | |/ _ \/ __| __| |/ / | '_ \ / _` (_) | |   | '_ \ / _ \/ __| |/ / |
+// |   | |  __/\__ \ |_|   <| | | | | (_| |_  | |___| | | |  __/ (__|   <  |
+// |   |_|\___||___/\__|_|\_\_|_| |_|\__,_(_)  \____|_| |_|\___|\___|_|\_\ |
+// |  _____ _ _        _____      _     _                                  |
+// | |  ___(_) | ___  | ____|_  _(_)___| |_ ___                            |
+// | | |_  | | |/ _ \ |  _| \ \/ / / __| __/ __|                           |
+// | |  _| | | |  __/ | |___ >  <| \__ \ |_\__ \                           |
+// | |_|   |_|_|\___| |_____/_/\_\_|___/\__|___/                           |
+// '-----------------------------------------------------------------------'
+
+/// Configuration for testkind "check_file_exists". Just checks that the
+/// specified file exists, and optionally some properties of that file.
+pub struct CheckFileExists<'a> {
+    /// Information about the container to run inside
+    pub container: &'a ContainerInfo,
+    /// The path inside the solution directory where the file can be found
+    pub path: &'a str,
+    /// The mimetype that the file should have
+    pub mimetype_prefix: Option<&'a str>,
+}
+
+impl<'a> CheckFileExists<'a> {
+    /// Instantiate this test case from a testkind and then grade it.
+    pub fn grade_from_testkind(
+        kind: &TestkindCheckFileExists,
+        _test_default: &TestDefault,
+        container: &ContainerInfo,
+        include_report: bool,
+    ) -> Result<GradingResult, Error> {
+        CheckFileExists {
+            container: container,
+            path: &kind.path,
+            mimetype_prefix: if kind.mimetype_prefix_ignore {
+                None
+            } else {
+                Some(&kind.mimetype_prefix)
+            },
+        }
+        .grade(include_report)
+    }
+
+    /// Grades this test case.
+    pub fn grade(&self, include_report: bool) -> Result<GradingResult, Error> {
+        let check_path = path_absolute_join(&self.container.external_solution, &self.path)?;
+
+        if !std::fs::exists(&check_path)? {
+            return Ok(GradingResult::Failure {
+                cause: FailureCause::OutputMismatch,
+                report: if include_report {
+                    Some(Box::new(DetailsTestFailure {
+                        additional_failure_causes: vec!["File not found.".to_string()],
+                        ..self.base_report()?
+                    }))
+                } else {
+                    None
+                },
+                usage: None,
+            });
+        }
+
+        if let Some(check_prefix) = self.mimetype_prefix {
+            let ident_mimetype = utils::mimetype(&check_path)
+                .inspect_err(|e| log::error!("Could not check file {check_path}: {e}"))?;
+            if !ident_mimetype.starts_with(&check_prefix) {
+                return Ok(GradingResult::Failure {
+                    cause: FailureCause::OutputMismatch,
+                    report: if include_report {
+                        Some(Box::new(DetailsTestFailure {
+                            additional_failure_causes: vec!["Invalid MIME-type.".to_string()],
+                            checked_files: vec![], // resetting this to not duplicate info
+                            mimetype_mismatch_files: vec![MIMETypeInfo {
+                                path: self.path.to_string(),
+                                mime_identified: ident_mimetype,
+                                mime_expected: Some(check_prefix.to_string()),
+                            }],
+                            ..self.base_report()?
+                        }))
+                    } else {
+                        None
+                    },
+                    usage: None,
+                });
+            }
+        }
+
+        Ok(GradingResult::Success {
+            captured_stdout: "".to_string(),
+            usage: None,
+        })
+    }
+
+    /// Generates a template failure report with the basic information present.
+    fn base_report(&self) -> Result<DetailsTestFailure, Error> {
+        Ok(DetailsTestFailure {
+            checked_files: vec![self.path.to_string()],
+            ..Default::default()
+        })
+    }
+}
+
+// .-----------------------------------------------------------------------------------.
+// |  _____         _   _    _           _      ____ _               _                 |
+// | |_   _|__  ___| |_| | _(_)_ __   __| |_   / ___| |__   ___  ___| | __  ___  _ __  |
+// |   | |/ _ \/ __| __| |/ / | '_ \ / _` (_) | |   | '_ \ / _ \/ __| |/ / / _ \| '__| |
+// |   | |  __/\__ \ |_|   <| | | | | (_| |_  | |___| | | |  __/ (__|   < |  __/| |    |
+// |   |_|\___||___/\__|_|\_\_|_| |_|\__,_(_)  \____|_| |_|\___|\___|_|\_\ \___||_|    |
+// '-----------------------------------------------------------------------------------'
+
+/// Configuration for testkind "checker". This runs the provided `bin` like
+/// testkind "run", after which a checker program provided by the course staff
+/// decides whether the output is correct. The checker is run outside of the
+/// container, as it is trusted.
+#[derive(Debug, Clone)]
+pub struct Checker<'a> {
+    /// The run of the binary, which has no expected outputs of its own
+    pub run: Run<'a>,
+    /// Path to the checker executable, as seen from outside the container
+    pub checker: &'a str,
+    /// Arguments to pass to the checker, with templates such as
+    /// <STDOUT_FILE> replaced by the corresponding file
+    pub checker_args: &'a [String],
+    /// Expected data passed to the checker
+    pub expected: &'a str,
+}
+
+impl<'a> Checker<'a> {
+    /// Instantiate this test case from a testkind and then grade it.
+    pub fn grade_from_testkind(
+        kind: &TestkindChecker,
+        test_default: &TestDefault,
+        container: &ContainerInfo,
+        slot: usize,
+        include_report: bool,
+    ) -> Result<GradingResult, Error> {
+        Checker {
+            run: Run {
+                container: container,
+                slot: slot,
+                bin: &kind.bin,
+                cmdargs: &kind.args,
+                infile_paths: &kind.input_files,
+                stdin: if kind.stdin_ignore {
+                    None
+                } else {
+                    Some(kind.stdin.as_str())
+                },
+                capture_stdout: false,
+                max_output: test_default.max_output,
+                timeout: test_default.timeout_test,
+                code_allowed_values: &[],
+                stdout_allowed_values: &[],
+                stdout_trim: false,
+                stdout_rm_whitespace: false,
+                stdout_match: OutputMatch::Exact,
+                stderr_allowed_values: &[],
+                stderr_trim: false,
+                stderr_rm_whitespace: false,
+                stderr_match: OutputMatch::Exact,
+                tolerance: NumericTolerance::default(),
+            },
+            checker: &kind.checker,
+            checker_args: &kind.checker_args,
+            expected: &kind.expected,
+        }
+        .grade(include_report)
+    }
+
+    /// Runs the binary inside the container and then checks its output with
+    /// the checker.
+    pub fn grade(&self, include_report: bool) -> Result<GradingResult, Error> {
+        let (res, usage) = self.run.execute()?;
+        let output = match res {
+            Ok(output) => output,
+            Err(e) => return self.run.execution_failure(e, usage, include_report),
+        };
+
+        match self.check(&output)? {
+            None => Ok(GradingResult::Success {
+                captured_stdout: "".to_string(),
+                usage: Some(usage),
+            }),
+            Some(causes) => {
+                let report = if include_report {
+                    Some(Box::new(DetailsTestFailure {
+                        additional_failure_causes: causes,
+                        code_captured: Some(output.code),
+                        stdout_captured: Some(output.stdout),
+                        stderr_captured: Some(output.stderr),
+                        ..self.run.base_report()?
+                    }))
+                } else {
+                    None
+                };
+                Ok(GradingResult::Failure {
+                    cause: FailureCause::OutputMismatch,
+                    report: report,
+                    usage: Some(usage),
+                })
+            }
+        }
+    }
+
+    /// Runs the checker on the output of the binary. Returns `None` if the
+    /// output was accepted, or the reasons given by the checker if it was
+    /// rejected.
+    fn check(&self, output: &SyscommandOutput) -> Result<Option<Vec<String>>, Error> {
+        // The files are kept outside of the directories mounted in the
+        // container, such that the expected data is never visible to the
+        // solutions being graded.
+        let data_dir = tempfile::Builder::new().prefix("checker").tempdir()?;
+        let write_file = |name: &str, content: &str| -> Result<String, Error> {
+            let path = path_absolute_join(data_dir.path(), name)?;
+            std::fs::write(&path, content)
+                .inspect_err(|e| log::error!("Could not write checker file {path}: {e}"))?;
+            Ok(path)
+        };
+        let stdin_file = write_file("stdin", self.run.stdin.unwrap_or(""))?;
+        let stdout_file = write_file("stdout", &output.stdout)?;
+        let stderr_file = write_file("stderr", &output.stderr)?;
+        let expected_file = write_file("expected", self.expected)?;
+        let code = output.code.to_string();
+
+        let mut checker_cmd: Vec<&str> = vec![self.checker];
+        for arg in self.checker_args {
+            match arg.as_str() {
+                "<STDIN_FILE>" => checker_cmd.push(&stdin_file),
+                "<STDOUT_FILE>" => checker_cmd.push(&stdout_file),
+                "<STDERR_FILE>" => checker_cmd.push(&stderr_file),
+                "<EXPECTED_FILE>" => checker_cmd.push(&expected_file),
+                "<CODE>" => checker_cmd.push(&code),
+                "<INPUT_FILES>" => {
+                    checker_cmd.extend(self.run.infile_paths.iter().map(String::as_str))
+                }
+                _ => checker_cmd.push(arg),
+            }
+        }
+
+        let checked = syscommand_timeout(
+            &checker_cmd,
+            SyscommandSettings {
+                max_stdout_length: Some(self.run.max_output),
+                max_stderr_length: Some(self.run.max_output),
+                timeout: Duration::from_secs(self.run.timeout.into()),
+                ..Default::default()
+            },
+        )
+        .inspect_err(|e| log::error!("Could not run checker {}: {e}", self.checker))?;
+
+        match checked.code {
+            0 => Ok(None),
+            1 => {
+                let causes: Vec<String> = checked
+                    .stdout
+                    .lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(String::from)
+                    .collect();
+                if causes.is_empty() {
+                    Ok(Some(vec![
+                        "The output was rejected by the checker.".to_string()
+                    ]))
+                } else {
+                    Ok(Some(causes))
+                }
+            }
+            code => Error::err_runtime(format!(
+                "checker {} failed with exit code {code}: {}",
+                self.checker,
+                checked.stderr.trim()
+            )),
+        }
+    }
+}
+
+// .-----------------------------------------------------------------------------------------------.
+// |  _____         _   _    _           _     ____         __                                     |
+// | |_   _|__  ___| |_| | _(_)_ __   __| |_  |  _ \  ___  / _|  ___  _ __  ___  _ __    ___  ___  |
+// |   | |/ _ \/ __| __| |/ / | '_ \ / _` (_) | |_) |/ _ \| |_  / _ \| '__|/ _ \| '_ \  / __|/ _ \ |
+// |   | |  __/\__ \ |_|   <| | | | | (_| |_  |  _ <|  __/|  _||  __/| |  |  __/| | | || (__|  __/ |
+// |   |_|\___||___/\__|_|\_\_|_| |_|\__,_(_) |_| \_\\___||_|   \___||_|   \___||_| |_| \___|\___| |
+// '-----------------------------------------------------------------------------------------------'
+
+/// Configuration for testkind "reference". This runs the provided `bin` like
+/// testkind "run", where the expected outputs are those of a reference
+/// solution provided by the course staff. The reference is run in a separate
+/// container, as the container of the graded solution can not be trusted.
+#[derive(Debug, Clone)]
+pub struct Reference<'a> {
+    /// The run of the binary, whose expected outputs are filled in from the
+    /// reference
+    pub run: Run<'a>,
+    /// Path to the reference executable, as seen from outside the container
+    pub reference: &'a str,
+    pub code_ignore: bool,
+    pub stdout_ignore: bool,
+    pub stderr_ignore: bool,
+}
+
+/// Outputs of a reference solution, which are cached between graded
+/// solutions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ReferenceOutput {
+    code: i32,
+    stdout: String,
+    stderr: String,
+}
+
+impl<'a> Reference<'a> {
+    /// Instantiate this test case from a testkind and then grade it.
+    pub fn grade_from_testkind(
+        kind: &TestkindReference,
+        test_default: &TestDefault,
+        container: &ContainerInfo,
+        slot: usize,
+        include_report: bool,
+    ) -> Result<GradingResult, Error> {
+        Reference {
+            run: Run {
+                container: container,
+                slot: slot,
+                bin: &kind.bin,
+                cmdargs: &kind.args,
+                infile_paths: &kind.input_files,
+                stdin: if kind.stdin_ignore {
+                    None
+                } else {
+                    Some(kind.stdin.as_str())
+                },
+                capture_stdout: false,
+                max_output: test_default.max_output,
+                timeout: test_default.timeout_test,
+                code_allowed_values: &[],
+                stdout_allowed_values: &[],
+                stdout_trim: kind.stdout_trim,
+                stdout_rm_whitespace: kind.stdout_strip_whitespace,
+                stdout_match: kind.stdout_match,
+                stderr_allowed_values: &[],
+                stderr_trim: kind.stderr_trim,
+                stderr_rm_whitespace: kind.stderr_strip_whitespace,
+                stderr_match: kind.stderr_match,
+                tolerance: kind.tolerance(),
+            },
+            reference: &kind.reference,
+            code_ignore: kind.code_ignore,
+            stdout_ignore: kind.stdout_ignore,
+            stderr_ignore: kind.stderr_ignore,
+        }
+        .grade(include_report)
+    }
+
+    /// Runs the binary inside the container and then compares its output
+    /// with that of the reference.
+    pub fn grade(&self, include_report: bool) -> Result<GradingResult, Error> {
+        let (res, usage) = self.run.execute()?;
+        let output = match res {
+            Ok(output) => output,
+            Err(e) => return self.run.execution_failure(e, usage, include_report),
+        };
+
+        let expected = self.expected_output()?;
+        let code_allowed_values = if self.code_ignore {
+            vec![]
+        } else {
+            vec![expected.code]
+        };
+        let stdout_allowed_values = if self.stdout_ignore {
+            vec![]
+        } else {
+            vec![expected.stdout]
+        };
+        let stderr_allowed_values = if self.stderr_ignore {
+            vec![]
+        } else {
+            vec![expected.stderr]
+        };
+        Run {
+            code_allowed_values: &code_allowed_values,
+            stdout_allowed_values: &stdout_allowed_values,
+            stderr_allowed_values: &stderr_allowed_values,
+            ..self.run.clone()
+        }
+        .grade_output(output, usage, include_report)
+    }
+
+    /// Returns the outputs of the reference, which are only computed if they
+    /// are not already in the cache.
+    fn expected_output(&self) -> Result<ReferenceOutput, Error> {
+        let cache_dir = &self.run.container.reference_cache_dir;
+        let cache_file = path_absolute_join(cache_dir, format!("{}.json", self.cache_key()?))?;
+        match std::fs::read_to_string(&cache_file) {
+            Ok(content) => match serde_json::from_str(&content) {
+                Ok(cached) => return Ok(cached),
+                Err(e) => log::warn!("Ignoring invalid cached reference output {cache_file}: {e}"),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!("Could not read cached reference output {cache_file}: {e}"),
+        }
+
+        let expected = self.run_reference()?;
+
+        // The output is written to a temporary file first, such that runners
+        // caching the same output at once never read a partial file.
+        std::fs::create_dir_all(cache_dir)?;
+        let mut tmp = tempfile::NamedTempFile::new_in(cache_dir)?;
+        serde_json::to_writer(&mut tmp, &expected)
+            .map_err(|e| Error::auto_msg("could not serialize reference output", e))?;
+        tmp.persist(&cache_file).map_err(|e| e.error)?;
+
+        Ok(expected)
+    }
+
+    /// Hash identifying the outputs of the reference, covering everything
+    /// that is passed to it. Each field is prefixed with its length, and each
+    /// list with its number of elements, such that different inputs never
+    /// hash the same data.
+    fn cache_key(&self) -> Result<String, Error> {
+        let mut hasher = Sha256::new();
+        let mut field = |data: &[u8]| {
+            hasher.update((data.len() as u64).to_le_bytes());
+            hasher.update(data);
+        };
+        field(self.run.container.podman_image_id.as_bytes());
+        field(
+            &std::fs::read(self.reference)
+                .inspect_err(|e| log::error!("Could not read reference {}: {e}", self.reference))?,
+        );
+        field(self.run.bin.as_bytes());
+        field(&(self.run.cmdargs.len() as u64).to_le_bytes());
+        for arg in self.run.cmdargs {
+            field(arg.as_bytes());
+        }
+        field(self.run.stdin.unwrap_or("").as_bytes());
+        field(&(self.run.infile_paths.len() as u64).to_le_bytes());
+        for infile in self.run.infile_paths {
+            field(
+                &std::fs::read(infile)
+                    .inspect_err(|e| log::error!("Could not read input file {infile}: {e}"))?,
+            );
+        }
+        Ok(hex::encode(hasher.finalize()))
+    }
+
+    /// Runs the reference in a new container of the same image ID, without any
+    /// network access. The reference is placed where the binary would be,
+    /// such that it is run the same way.
+    fn run_reference(&self) -> Result<ReferenceOutput, Error> {
+        let container_name = format!(
+            "{}_reference{}",
+            self.run.container.podman_container_name, self.run.slot
+        );
+        let reference_path =
+            path_absolute_join(&self.run.container.internal_build_dir, self.run.bin)?;
+        let mut cmd: Vec<String> = vec![
+            "podman".into(),
+            "run".into(),
+            "--rm".into(),
+            "-i".into(),
+            "--name".into(),
+            container_name.clone(),
+            "--network".into(),
+            "none".into(),
+            "-v".into(),
+            format!("{}:{reference_path}:ro,z", self.reference),
+        ];
+        let mut infiles = vec![];
+        for (i, infile) in self.run.infile_paths.iter().enumerate() {
+            let containerfile =
+                path_absolute_join(&self.run.container.mount_tests, input_file_name(i, infile)?)?;
+            cmd.push("-v".into());
+            cmd.push(format!("{infile}:{containerfile}:ro,z"));
+            infiles.push(containerfile);
+        }
+        cmd.extend([
+            "-w".into(),
+            self.run.container.internal_build_dir.clone(),
+            self.run.container.podman_image_id.clone(),
+            format!("./{}", self.run.bin),
+        ]);
+        cmd.extend_from_slice(self.run.cmdargs);
+        cmd.extend(infiles);
+
+        let output = syscommand_timeout(
+            cmd.as_slice(),
+            SyscommandSettings {
+                stdin: Some(self.run.stdin.unwrap_or("").to_string()),
+                max_stdout_length: Some(self.run.max_output),
+                max_stderr_length: Some(self.run.max_output),
+                timeout: Duration::from_secs(self.run.timeout.into()),
+                ..Default::default()
+            },
+        )
+        .inspect_err(|e| {
+            log::error!("Could not run reference {}: {e}", self.reference);
+            // The container is left behind if podman was interrupted
+            podman::force_rm(&container_name).unwrap_or_else(|e| {
+                log::error!("Could not remove reference container {container_name}: {e}")
+            });
+        })?;
+
+        Ok(ReferenceOutput {
+            code: output.code,
+            stdout: output.stdout,
+            stderr: output.stderr,
+        })
+    }
+}
+
+/// Name of the `i`th input file of a test case inside the tests directory of
+/// the container. The name only depends on the index and the contents of the
+/// file, such that the binary and the reference see the same path in every
+/// slot, and test cases graded at the same time never overwrite each other's
+/// files with different contents.
+fn input_file_name(i: usize, infile: &str) -> Result<String, Error> {
+    let contents = std::fs::read(infile)
+        .inspect_err(|e| log::error!("Could not read input file {infile}: {e}"))?;
+    Ok(format!(
+        "test{i}_{}.in",
+        &hex::encode(Sha256::digest(contents))[..16]
+    ))
+}
+
+/// Returns the directory in `cache_root` where the outputs of reference
+/// solutions run in the image with ID `image_id` are cached. The outputs
+/// cached for any other image are removed, as they are never used again.
+pub fn reference_cache_dir(cache_root: &str, image_id: &str) -> Result<String, Error> {
+    match std::fs::read_dir(cache_root) {
+        Ok(entries) => {
+            for entry in entries {
+                let entry = entry?;
+                if entry.file_name() != image_id {
+                    log::info!(
+                        "Removing cached reference outputs {}",
+                        entry.path().display()
+                    );
+                    std::fs::remove_dir_all(entry.path()).unwrap_or_else(|e| {
+                        log::warn!(
+                            "Could not remove cached reference outputs {}: {e}",
+                            entry.path().display()
+                        )
+                    });
+                }
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    path_absolute_join(cache_root, image_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asserting::prelude::*;
+
+    #[test]
+    fn test_parse_usage() {
+        let gnu_time = "Command exited with non-zero status 1\nusage 1.25 0.50 20480\n";
+        assert_that!(parse_usage(gnu_time))
+            .is_equal_to((Some(Duration::from_millis(1750)), Some(20480)));
+
+        let times = "0m0.000s 0m0.001s\n1m2.500s 0m0.250s\n";
+        assert_that!(parse_usage(times)).is_equal_to((Some(Duration::from_millis(62750)), None));
+
+        assert_that!(parse_usage("")).is_equal_to((None, None));
+        assert_that!(parse_usage("usage garbage\n")).is_equal_to((None, None));
+    }
+
+    #[test]
+    fn test_median() {
+        assert_that!(median(&[])).is_equal_to(0);
+        assert_that!(median(&[7])).is_equal_to(7);
+        assert_that!(median(&[30, 10, 20])).is_equal_to(20);
+        assert_that!(median(&[40, 10, 30, 20])).is_equal_to(25);
+        assert_that!(median(&[u64::MAX, u64::MAX])).is_equal_to(u64::MAX);
+    }
+
+    #[test]
+    fn test_parse_benchmark_samples() {
+        assert_that!(parse_benchmark_samples("120\n 80\n100\n", 3, "runtime"))
+            .is_equal_to(Ok(vec![120, 80, 100]));
+        assert_that!(parse_benchmark_samples(
+            "120\n<not counted>\n",
+            2,
+            "instruction count"
+        ))
+        .is_err()
+        .extracting(|r| r.unwrap_err())
+        .contains("(got \"<not counted>\")");
+        assert_that!(parse_benchmark_samples("120\n", 2, "instruction count"))
+            .is_err()
+            .extracting(|r| r.unwrap_err())
+            .contains("only measured for 1 of 2 runs");
+    }
+
+    #[test]
+    fn test_benchmark_script_instructions() {
+        let dir = tempfile::tempdir().unwrap();
+        let bench = |counter: &str| {
+            std::process::Command::new("sh")
+                .current_dir(dir.path())
+                .args(["-c", BENCHMARK_SCRIPT, "sh", "2", "instructions"])
+                .args(["sh", "-c", counter])
+                .stdin(std::process::Stdio::null())
+                .output()
+                .unwrap()
+        };
+
+        let counted = bench("echo '# comment' > count.out; echo '1234,,instructions' >> count.out");
+        assert_that!(counted.status.code()).is_equal_to(Some(0));
+        assert_that!(String::from_utf8_lossy(&counted.stdout).to_string())
+            .is_equal_to("1234\n1234\n".to_string());
+
+        // The binary exited before it could be counted
+        let not_counted = bench("echo '<not counted>,,instructions' > count.out");
+        assert_that!(not_counted.status.code()).is_equal_to(Some(0));
+        assert_that!(parse_benchmark_samples(
+            &String::from_utf8_lossy(&not_counted.stdout),
+            2,
+            "instruction count"
+        ))
+        .is_err();
+
+        // The counter itself failed, and the count from before is not reused
+        let missing = bench("exit 127");
+        assert_that!(missing.status.code()).is_equal_to(Some(2));
+    }
+
+    #[test]
+    fn test_validate_alternatives_modes() {
+        let validate = |output: &str, alternative: &str, mode: OutputMatch| {
+            let tolerance = NumericTolerance {
+                absolute: 1e-6,
+                relative: 0.0,
+            };
+            validate_alternatives(
+                output,
+                &[alternative.to_string()],
+                true,
+                false,
+                mode,
+                &tolerance,
+            )
+            .unwrap()
+        };
+
+        assert_that!(validate(" Hello, World!\n", "Hello, World!", OutputMatch::Exact).is_none())
+            .is_true();
+        assert_that!(validate("at 0x7ffd1234: oops\n", "oops", OutputMatch::Contains).is_none())
+            .is_true();
+        assert_that!(
+            validate("took 12.5 ms\n", r"took \d+(\.\d+)? ms", OutputMatch::Regex).is_none()
+        )
+        .is_true();
+        assert_that!(validate("error: line 3\n", "error: line ?", OutputMatch::Glob).is_none())
+            .is_true();
+        assert_that!(
+            validate("pi = 3.1415927\n", "pi = 3.14159265", OutputMatch::Numeric).is_none()
+        )
+        .is_true();
+        assert_that!(validate("pi = 3.1416\n", "pi = 3.14159265", OutputMatch::Numeric).is_some())
+            .is_true();
+
+        // The mode is explained when the output does not match
+        let mismatch = validate("took 12.5 ms, done", r"took \d+ ms", OutputMatch::Regex);
+        assert_that!(mismatch.as_ref().map(|mm| mm.msgs.clone())).is_equal_to(Some(vec![
+            OutputMatch::Regex.description().unwrap().to_string(),
+            "Leading and trailing whitespaces are ignored.".to_string(),
+        ]));
+        assert_that!(mismatch.and_then(|mm| mm.diff)).is_equal_to(None);
+
+        // Exact mismatches carry a diff against the treated output
+        let mismatch = validate("a\nb\nc\n", "a\nx\nc", OutputMatch::Exact).unwrap();
+        let hunks = mismatch.diff.unwrap().hunks;
+        assert_that!(hunks.len()).is_equal_to(1);
+        assert_that!(hunks[0].expected_len).is_equal_to(3);
+    }
+
+    /// A run of `bin` in `container` that expects nothing, for test cases
+    /// that only set the fields they need.
+    fn test_run<'a>(container: &'a ContainerInfo, bin: &'a str) -> Run<'a> {
+        Run {
+            container: container,
+            slot: 0,
+            bin: bin,
+            cmdargs: &[],
+            infile_paths: &[],
+            stdin: None,
+            capture_stdout: false,
+            max_output: 1024,
+            timeout: 10,
+            code_allowed_values: &[],
+            stdout_allowed_values: &[],
+            stdout_trim: false,
+            stdout_rm_whitespace: false,
+            stdout_match: OutputMatch::Exact,
+            stderr_allowed_values: &[],
+            stderr_trim: false,
+            stderr_rm_whitespace: false,
+            stderr_match: OutputMatch::Exact,
+            tolerance: NumericTolerance::default(),
+        }
+    }
+
+    /// Writes an executable shell script to `path`.
+    fn write_script(path: &str, content: &str) {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::write(path, content).unwrap();
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_input_file_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = |name: &str, content: &str| {
+            let path = path_absolute_join(dir.path(), name).unwrap();
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+        let a = file("a.in", "1 2 3\n");
+        let b = file("b.in", "1 2 3\n");
+        let c = file("c.in", "3 2 1\n");
+
+        let name = input_file_name(0, &a).unwrap();
+        assert_that!(&name).starts_with("test0_").ends_with(".in");
+        assert_that!(input_file_name(0, &b).unwrap()).is_equal_to(name.clone());
+        assert_that!(input_file_name(1, &a).unwrap()).is_not_equal_to(name.clone());
+        assert_that!(input_file_name(0, &c).unwrap()).is_not_equal_to(name);
+        assert_that!(input_file_name(0, "/nonexistent/file.in")).is_err();
+    }
+
+    #[test]
+    fn test_checker_check() {
+        let container = ContainerInfo::default();
+        let dir = tempfile::tempdir().unwrap();
+        let checker = path_absolute_join(dir.path(), "checker.sh").unwrap();
+        // Accepts the output if it is the expected data in reverse order, and
+        // fails on an unexpected exit code. Only shell builtins are used.
+        write_script(
+            &checker,
+            "#!/bin/sh\n\
+             [ \"$1\" = 0 ] || exit 2\n\
+             reversed=''\n\
+             while IFS= read -r line; do reversed=\"$line\n$reversed\"; done < \"$2\"\n\
+             expected=''\n\
+             while IFS= read -r line; do expected=\"$expected$line\n\"; done < \"$3\"\n\
+             [ \"$reversed\" = \"$expected\" ] && exit 0\n\
+             IFS= read -r input < \"$4\"\n\
+             echo \"Not reversed.\"; echo; echo \"  Input: $input\"\n\
+             exit 1\n",
+        );
+
+        let checker_args: Vec<String> =
+            ["<CODE>", "<STDOUT_FILE>", "<EXPECTED_FILE>", "<STDIN_FILE>"]
+                .map(String::from)
+                .to_vec();
+        let grader = Checker {
+            run: Run {
+                stdin: Some("a b"),
+                ..test_run(&container, "reverse")
+            },
+            checker: &checker,
+            checker_args: &checker_args,
+            expected: "b\na\n",
+        };
+        let output = |code: i32, stdout: &str| SyscommandOutput {
+            code: code,
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        };
+
+        assert_that!(grader.check(&output(0, "a\nb\n")).unwrap()).is_equal_to(None);
+        assert_that!(grader.check(&output(0, "b\na\n")).unwrap()).is_equal_to(Some(vec![
+            "Not reversed.".to_string(),
+            "Input: a b".to_string(),
+        ]));
+        assert_that!(grader.check(&output(3, "a\nb\n"))).is_err();
+    }
+
+    #[test]
+    fn test_reference_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let reference = path_absolute_join(dir.path(), "reference.sh").unwrap();
+        std::fs::write(&reference, "#!/bin/sh\necho 42\n").unwrap();
+        let container = ContainerInfo {
+            podman_image_id: "0123456789ab".to_string(),
+            reference_cache_dir: path_absolute_join(dir.path(), "cache").unwrap(),
+            ..Default::default()
+        };
+        let args = vec!["--answer".to_string()];
+        let grader = |stdin: Option<&'static str>| Reference {
+            run: Run {
+                cmdargs: &args,
+                stdin: stdin,
+                stdout_trim: true,
+                stderr_trim: true,
+                ..test_run(&container, "answer")
+            },
+            reference: &reference,
+            code_ignore: false,
+            stdout_ignore: false,
+            stderr_ignore: true,
+        };
+
+        // The key only depends on what is passed to the reference
+        let key = grader(Some("1 2")).cache_key().unwrap();
+        assert_that!(grader(Some("1 2")).cache_key().unwrap()).is_equal_to(key.clone());
+        assert_that!(grader(Some("1 3")).cache_key().unwrap()).is_not_equal_to(key.clone());
+        assert_that!(grader(None).cache_key().unwrap())
+            .is_equal_to(grader(Some("")).cache_key().unwrap());
+        std::fs::write(&reference, "#!/bin/sh\necho 43\n").unwrap();
+        assert_that!(grader(Some("1 2")).cache_key().unwrap()).is_not_equal_to(key.clone());
+        std::fs::write(&reference, "#!/bin/sh\necho 42\n").unwrap();
+        let rebuilt = ContainerInfo {
+            podman_image_id: "ba9876543210".to_string(),
+            ..container.clone()
+        };
+        let mut rebuilt_grader = grader(Some("1 2"));
+        rebuilt_grader.run.container = &rebuilt;
+        assert_that!(rebuilt_grader.cache_key().unwrap()).is_not_equal_to(key.clone());
+
+        // Cached outputs are used without running the reference
+        let cached = ReferenceOutput {
+            code: 0,
+            stdout: "42\n".to_string(),
+            stderr: String::new(),
+        };
+        std::fs::create_dir_all(&container.reference_cache_dir).unwrap();
+        std::fs::write(
+            path_absolute_join(&container.reference_cache_dir, format!("{key}.json")).unwrap(),
+            serde_json::to_string(&cached).unwrap(),
+        )
+        .unwrap();
+        assert_that!(grader(Some("1 2")).expected_output().unwrap()).is_equal_to(cached);
+    }
+
+    #[test]
+    fn test_reference_cache_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = path_absolute_join(dir.path(), "reference_cache").unwrap();
+        let old = path_absolute_join(&root, "0123456789ab").unwrap();
+        std::fs::create_dir_all(&old).unwrap();
+        std::fs::write(path_absolute_join(&old, "key.json").unwrap(), "{}").unwrap();
+        let current = path_absolute_join(&root, "ba9876543210").unwrap();
+        std::fs::create_dir_all(&current).unwrap();
+        std::fs::write(path_absolute_join(&current, "key.json").unwrap(), "{}").unwrap();
+
+        // Only the outputs cached for the current image are kept
+        assert_that!(reference_cache_dir(&root, "ba9876543210").unwrap())
+            .is_equal_to(current.clone());
+        assert_that!(Path::new(&old).exists()).is_false();
+        assert_that!(Path::new(&current).join("key.json").exists()).is_true();
+
+        let missing = path_absolute_join(dir.path(), "missing").unwrap();
+        assert_that!(reference_cache_dir(&missing, "ba9876543210")).is_ok();
+    }
+}
@@ -11,6 +11,9 @@ use std::{
 use subprocess::{Exec, ExitStatus, Job, Redirection};
 use tempfile;
 
+#[cfg(test)]
+pub mod local_http;
+
 /// Trims single newlines from the input string, returning a new string with
 /// single newlines converted to spaces.
 pub fn single_linefeed_to_space<S: AsRef<str>>(s: S) -> String {
@@ -106,6 +109,22 @@ pub fn systemtime_to_fsfriendly_utc_string(systime: &SystemTime) -> Option<Strin
         .map(|dt| dt.format("%Y-%m-%d_%H%M%S.%6f").to_string())
 }
 
+/// Parses a date and time in RFC 3339 format, e.g.
+/// `2025-01-31T23:59:00+01:00`, or a date `YYYY-mm-dd` that is interpreted as
+/// midnight in UTC time.
+pub fn parse_utc_datetime(s: &str) -> Result<SystemTime, Error> {
+    let parsed = match DateTime::parse_from_rfc3339(s) {
+        Ok(dt) => Some(dt.to_utc()),
+        Err(_) => chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .ok()
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+            .map(|dt| dt.and_utc()),
+    };
+    parsed
+        .map(SystemTime::from)
+        .ok_or_else(|| Error::parse_type("date and time".to_string(), s.to_string()))
+}
+
 /// Returns a string containing the mimetype for the file located at the
 /// specified path.
 pub fn mimetype<P: AsRef<Path>>(path: P) -> Result<String, Error> {
@@ -422,6 +441,16 @@ mod tests {
             .is_equal_to("foo bar\n\n\nbabar");
     }
 
+    #[test]
+    fn test_parse_utc_datetime() {
+        let expected = UNIX_EPOCH + std::time::Duration::from_secs(1738367940);
+        assert_that!(parse_utc_datetime("2025-01-31T23:59:00Z")).has_value(expected);
+        assert_that!(parse_utc_datetime("2025-02-01T00:59:00+01:00")).has_value(expected);
+        assert_that!(parse_utc_datetime("2025-01-31"))
+            .has_value(UNIX_EPOCH + std::time::Duration::from_secs(1738281600));
+        assert_that!(parse_utc_datetime("31/01/2025")).is_err();
+    }
+
     #[test]
     fn test_mimetype() {
         {
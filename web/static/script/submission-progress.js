@@ -0,0 +1,53 @@
+/*
+ * Live progress of a submission that is being graded. The progress is
+ * streamed as server-sent events, and the page is reloaded to show the report
+ * once the submission has finished.
+ */
+
+(() => {
+  "use strict";
+
+  const container = document.getElementById("submission-progress");
+  if (!container || !window.EventSource) {
+    return;
+  }
+
+  const label = document.getElementById("submission-progress-label");
+  const tests = document.getElementById("submission-progress-tests");
+  const bar = document.getElementById("submission-progress-bar");
+  const progressbar = bar.parentElement;
+
+  const update = (data) => {
+    const progress = data.progress;
+    if (progress && progress.current_tag) {
+      label.textContent = progress.current_group
+        ? `Grading ${progress.current_tag}: ${progress.current_group}`
+        : `Grading ${progress.current_tag}`;
+    } else {
+      label.textContent = data.status;
+    }
+
+    if (progress) {
+      const run = progress.tags.reduce((n, tag) => n + tag.tests_run, 0);
+      const passed = progress.tags.reduce((n, tag) => n + tag.tests_passed, 0);
+      tests.textContent = `${passed} passed, ${data.tests_failed} failed, ${run} run`;
+    }
+
+    bar.style.width = `${data.percent}%`;
+    bar.textContent = `${data.percent}%`;
+    progressbar.setAttribute("aria-valuenow", data.percent);
+  };
+
+  const events = new EventSource(container.dataset.eventsUrl);
+  events.addEventListener("progress", (e) => update(JSON.parse(e.data)));
+  events.addEventListener("finished", () => {
+    events.close();
+    window.location.reload();
+  });
+  events.onerror = () => {
+    // The browser reconnects by itself, unless the request was rejected
+    if (events.readyState === EventSource.CLOSED) {
+      label.textContent = "Live progress is unavailable, reload the page to see the latest status.";
+    }
+  };
+})();